@@ -0,0 +1,528 @@
+//! 访问控制规则引擎
+//!
+//! 规则按主体（任意客户端、IP 或 CIDR 网段、设备身份、设备分组）、路径（glob 模式）
+//! 和操作匹配请求，效果为允许或拒绝。规则按优先级数值从小到大排序，
+//! 同一优先级中拒绝规则先于允许规则，再按规则 ID 排序；第一条匹配的规则决定结果，
+//! 没有规则匹配时拒绝访问。
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::gateway::interfaces::IpCidr;
+use crate::gateway::tauri_api::AccessRule;
+
+/// 未指定优先级时规则的默认优先级
+pub const DEFAULT_RULE_PRIORITY: i32 = 100;
+
+/// 匹配任意操作的权限
+const ANY_OPERATION: &str = "*";
+
+/// 规则的默认优先级，供反序列化缺省值使用
+pub fn default_rule_priority() -> i32 {
+    DEFAULT_RULE_PRIORITY
+}
+
+/// 规则效果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleEffect {
+    /// 允许访问
+    #[default]
+    Allow,
+    /// 拒绝访问
+    Deny,
+}
+
+/// 规则主体
+///
+/// 字符串形式：`*` 表示任意客户端，`192.168.1.10` 或 `192.168.1.0/24` 表示地址或网段，
+/// `device:<网关 ID>` 表示设备身份，`group:<分组名>` 表示设备分组。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSubject {
+    /// 任意客户端
+    Any,
+    /// IP 地址或 CIDR 网段
+    Network(IpCidr),
+    /// 设备身份（网关 ID）
+    Device(Uuid),
+    /// 设备分组
+    Group(String),
+}
+
+impl RuleSubject {
+    /// 检查请求方是否属于该主体
+    ///
+    /// # 参数
+    ///
+    /// * `request` - 访问请求
+    /// * `groups` - 设备分组，以分组名为键
+    pub fn matches(&self, request: &AccessRequest, groups: &DashMap<String, Vec<Uuid>>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Network(cidr) => request.client_ip.is_some_and(|ip| cidr.contains(&ip)),
+            Self::Device(device_id) => request.device_id == Some(*device_id),
+            Self::Group(name) => request.device_id.is_some_and(|device_id| {
+                groups
+                    .get(name)
+                    .is_some_and(|members| members.contains(&device_id))
+            }),
+        }
+    }
+}
+
+impl FromStr for RuleSubject {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value == "*" {
+            return Ok(Self::Any);
+        }
+        if let Some(device_id) = value.strip_prefix("device:") {
+            let device_id = Uuid::parse_str(device_id.trim())
+                .map_err(|_| anyhow!("无效的设备 ID: {device_id}"))?;
+            return Ok(Self::Device(device_id));
+        }
+        if let Some(name) = value.strip_prefix("group:") {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(anyhow!("分组名称不能为空"));
+            }
+            return Ok(Self::Group(name.to_string()));
+        }
+        value
+            .parse::<IpCidr>()
+            .map(Self::Network)
+            .map_err(|_| anyhow!("无效的规则主体: {value}"))
+    }
+}
+
+impl fmt::Display for RuleSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "*"),
+            Self::Network(cidr) => write!(f, "{cidr}"),
+            Self::Device(device_id) => write!(f, "device:{device_id}"),
+            Self::Group(name) => write!(f, "group:{name}"),
+        }
+    }
+}
+
+/// 设备分组
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessGroup {
+    /// 分组名称
+    pub name: String,
+    /// 成员网关 ID
+    pub members: Vec<Uuid>,
+}
+
+/// 访问请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRequest {
+    /// 客户端 IP
+    pub client_ip: Option<IpAddr>,
+    /// 客户端设备身份（网关 ID），未经认证时为空
+    pub device_id: Option<Uuid>,
+    /// 请求的路径
+    pub path: String,
+    /// 操作类型
+    pub operation: String,
+}
+
+/// 访问决策
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessDecision {
+    /// 是否允许访问
+    pub allowed: bool,
+    /// 做出决定的规则 ID，没有规则匹配时为空
+    pub rule_id: Option<String>,
+    /// 做出决定的规则名称
+    pub rule_name: Option<String>,
+    /// 做出决定的规则效果
+    pub effect: Option<RuleEffect>,
+    /// 决策原因
+    pub reason: String,
+}
+
+impl AccessDecision {
+    /// 不经规则直接拒绝的决策
+    pub fn denied(reason: impl Into<String>) -> Self {
+        Self {
+            allowed: false,
+            rule_id: None,
+            rule_name: None,
+            effect: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// 验证规则格式
+///
+/// # 参数
+///
+/// * `rule` - 访问控制规则
+///
+/// # 返回值
+///
+/// 主体无法解析、没有路径或没有操作时返回错误
+pub fn validate_rule(rule: &AccessRule) -> Result<()> {
+    rule.client.parse::<RuleSubject>()?;
+    if rule.allowed_paths.iter().all(|path| path.trim().is_empty()) {
+        return Err(anyhow!("规则 '{}' 至少需要一个路径模式", rule.name));
+    }
+    if rule.permissions.is_empty() {
+        return Err(anyhow!("规则 '{}' 至少需要一个操作", rule.name));
+    }
+    Ok(())
+}
+
+/// 按求值顺序排序规则
+///
+/// 优先级数值小的先求值；同一优先级中拒绝规则在前，再按规则 ID 排序，保证顺序确定。
+pub fn sort_rules(rules: &mut [AccessRule]) {
+    rules.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then_with(|| (b.effect == RuleEffect::Deny).cmp(&(a.effect == RuleEffect::Deny)))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+/// 按第一条匹配的规则决定访问
+///
+/// # 参数
+///
+/// * `rules` - 已按 [`sort_rules`] 排序的规则
+/// * `request` - 访问请求
+/// * `groups` - 设备分组
+///
+/// # 返回值
+///
+/// 访问决策
+pub fn evaluate(
+    rules: &[AccessRule],
+    request: &AccessRequest,
+    groups: &DashMap<String, Vec<Uuid>>,
+) -> AccessDecision {
+    let path = normalize_request_path(&request.path);
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let Ok(subject) = rule.client.parse::<RuleSubject>() else {
+            continue;
+        };
+        if !subject.matches(request, groups) {
+            continue;
+        }
+        if !rule
+            .permissions
+            .iter()
+            .any(|permission| permission == ANY_OPERATION || *permission == request.operation)
+        {
+            continue;
+        }
+        let Some(pattern) = rule
+            .allowed_paths
+            .iter()
+            .find(|pattern| path_matches(pattern, &path))
+        else {
+            continue;
+        };
+
+        let allowed = rule.effect == RuleEffect::Allow;
+        return AccessDecision {
+            allowed,
+            rule_id: Some(rule.id.clone()),
+            rule_name: Some(rule.name.clone()),
+            effect: Some(rule.effect),
+            reason: format!(
+                "规则 '{}'（优先级 {}）按主体 {subject} 和路径 {pattern} {}",
+                rule.name,
+                rule.priority,
+                if allowed { "允许" } else { "拒绝" }
+            ),
+        };
+    }
+
+    AccessDecision::denied("没有匹配的规则，默认拒绝")
+}
+
+/// 检查路径是否匹配规则中的路径模式
+///
+/// 不含通配符的模式匹配该路径本身及其下的所有路径；
+/// 含通配符的模式按 [`glob_match`] 匹配整个路径。
+///
+/// # 参数
+///
+/// * `pattern` - 路径模式
+/// * `path` - 规范化后的请求路径
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim().replace('\\', "/");
+    if pattern.is_empty() {
+        return false;
+    }
+    if pattern.contains(['*', '?']) {
+        return glob_match(&pattern, path);
+    }
+
+    let base = pattern.trim_end_matches('/');
+    base.is_empty() || path == base || path.starts_with(&format!("{base}/"))
+}
+
+/// glob 模式匹配
+///
+/// `*` 匹配单级路径中的任意字符，`?` 匹配单个非 `/` 字符，
+/// `**` 匹配任意多级路径，`**/` 还可以匹配零级目录。
+///
+/// # 参数
+///
+/// * `pattern` - glob 模式
+/// * `text` - 要匹配的路径
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    #[derive(Clone, Copy)]
+    enum Token {
+        Literal(char),
+        One,
+        Star,
+        DoubleStar,
+        DoubleStarSlash,
+    }
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    tokens.push(Token::DoubleStarSlash);
+                    i += 3;
+                } else {
+                    tokens.push(Token::DoubleStar);
+                    i += 2;
+                }
+            }
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::One);
+                i += 1;
+            }
+            c => {
+                tokens.push(Token::Literal(c));
+                i += 1;
+            }
+        }
+    }
+
+    // matched[t][x] 表示 tokens[t..] 能否匹配 text[x..]
+    let text: Vec<char> = text.chars().collect();
+    let (token_count, text_len) = (tokens.len(), text.len());
+    let mut matched = vec![vec![false; text_len + 1]; token_count + 1];
+    matched[token_count][text_len] = true;
+
+    for t in (0..token_count).rev() {
+        for x in (0..=text_len).rev() {
+            let current = text.get(x).copied();
+            matched[t][x] = match tokens[t] {
+                Token::Literal(c) => current == Some(c) && matched[t + 1][x + 1],
+                Token::One => current.is_some_and(|c| c != '/') && matched[t + 1][x + 1],
+                Token::Star => {
+                    matched[t + 1][x] || (current.is_some_and(|c| c != '/') && matched[t][x + 1])
+                }
+                Token::DoubleStar => matched[t + 1][x] || (current.is_some() && matched[t][x + 1]),
+                Token::DoubleStarSlash => {
+                    matched[t + 1][x]
+                        || (current == Some('/') && matched[t + 1][x + 1])
+                        || (current.is_some() && matched[t][x + 1])
+                }
+            };
+        }
+    }
+
+    matched[0][0]
+}
+
+/// 规范化请求路径
+///
+/// 统一分隔符并按字面移除 `.` 和 `..`，避免 `/public/../private` 之类的路径绕过规则。
+fn normalize_request_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let joined = segments.join("/");
+    if path.starts_with('/') {
+        format!("/{joined}")
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        id: &str,
+        client: &str,
+        paths: &[&str],
+        effect: RuleEffect,
+        priority: i32,
+    ) -> AccessRule {
+        AccessRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            client: client.to_string(),
+            allowed_paths: paths.iter().map(|path| path.to_string()).collect(),
+            permissions: vec!["read".to_string()],
+            enabled: true,
+            effect,
+            priority,
+        }
+    }
+
+    fn request(ip: &str, device_id: Option<Uuid>, path: &str) -> AccessRequest {
+        AccessRequest {
+            client_ip: ip.parse().ok(),
+            device_id,
+            path: path.to_string(),
+            operation: "read".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_glob_and_prefix_path_matching() {
+        assert!(glob_match("/share/*.txt", "/share/a.txt"));
+        assert!(!glob_match("/share/*.txt", "/share/sub/a.txt"));
+        assert!(glob_match("/share/**/*.txt", "/share/a.txt"));
+        assert!(glob_match("/share/**/*.txt", "/share/x/y/a.txt"));
+        assert!(glob_match("/share/**", "/share/x/y"));
+        assert!(glob_match("/share/?.md", "/share/a.md"));
+        assert!(!glob_match("/share/?.md", "/share/ab.md"));
+
+        assert!(path_matches("/test", "/test/file.txt"));
+        assert!(path_matches("/test/", "/test"));
+        assert!(!path_matches("/test", "/testing/file.txt"));
+        assert!(path_matches("/", "/anything"));
+        assert_eq!(
+            normalize_request_path("/public/../private/./a"),
+            "/private/a"
+        );
+    }
+
+    #[test]
+    fn test_subjects_and_first_match_priority() {
+        let device = Uuid::new_v4();
+        let groups = DashMap::new();
+        groups.insert("family".to_string(), vec![device]);
+
+        let mut rules = vec![
+            rule(
+                "allow-lan",
+                "192.168.1.0/24",
+                &["/share"],
+                RuleEffect::Allow,
+                100,
+            ),
+            rule(
+                "deny-secret",
+                "*",
+                &["/share/**/secret*"],
+                RuleEffect::Deny,
+                10,
+            ),
+            rule(
+                "allow-family",
+                "group:family",
+                &["/photos"],
+                RuleEffect::Allow,
+                100,
+            ),
+            rule(
+                "deny-lan-photos",
+                "192.168.1.0/24",
+                &["/photos"],
+                RuleEffect::Deny,
+                100,
+            ),
+        ];
+        sort_rules(&mut rules);
+        let order: Vec<&str> = rules.iter().map(|rule| rule.id.as_str()).collect();
+        assert_eq!(
+            order,
+            [
+                "deny-secret",
+                "deny-lan-photos",
+                "allow-family",
+                "allow-lan"
+            ]
+        );
+
+        let decision = evaluate(
+            &rules,
+            &request("192.168.1.7", None, "/share/a.txt"),
+            &groups,
+        );
+        assert!(decision.allowed);
+        assert_eq!(decision.rule_id.as_deref(), Some("allow-lan"));
+
+        // 更高优先级的拒绝规则先匹配，路径中的 .. 不能绕过
+        let decision = evaluate(
+            &rules,
+            &request("192.168.1.7", None, "/share/x/../docs/secret.txt"),
+            &groups,
+        );
+        assert!(!decision.allowed);
+        assert_eq!(decision.rule_id.as_deref(), Some("deny-secret"));
+
+        // 同一优先级中拒绝规则优先
+        let decision = evaluate(
+            &rules,
+            &request("192.168.1.7", Some(device), "/photos/1.jpg"),
+            &groups,
+        );
+        assert_eq!(decision.rule_id.as_deref(), Some("deny-lan-photos"));
+        let decision = evaluate(
+            &rules,
+            &request("10.0.0.2", Some(device), "/photos/1.jpg"),
+            &groups,
+        );
+        assert!(decision.allowed);
+        assert_eq!(decision.rule_id.as_deref(), Some("allow-family"));
+
+        // 没有规则匹配时默认拒绝
+        let decision = evaluate(&rules, &request("10.0.0.2", None, "/photos/1.jpg"), &groups);
+        assert!(!decision.allowed);
+        assert!(decision.rule_id.is_none());
+
+        assert!(validate_rule(&rule(
+            "bad",
+            "device:not-a-uuid",
+            &["/"],
+            RuleEffect::Allow,
+            0
+        ))
+        .is_err());
+        assert_eq!(
+            format!("device:{device}").parse::<RuleSubject>().unwrap(),
+            RuleSubject::Device(device)
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//! 审计日志模块
+//!
+//! 以 JSON Lines 追加记录安全相关事件：授权决策、文件读取、文件传输、规则变更、
+//! 配对与吊销以及会话断开。每条记录都包含前一条记录的哈希，修改、删除或插入
+//! 任何一条记录都会使哈希链断裂，可通过 [`AuditLog::verify`] 检出。
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use uuid::Uuid;
+
+/// 哈希链起点使用的前序哈希
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 同一进程内按路径共享的审计日志，保证每个文件只有一个写入者
+static SHARED_LOGS: once_cell::sync::Lazy<Mutex<HashMap<PathBuf, Weak<AuditLog>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 审计事件类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    /// 远程操作授权决策
+    Authorization,
+    /// 文件读取
+    FileRead,
+    /// 文件传输请求
+    Transfer,
+    /// 访问规则与分组变更
+    RuleChange,
+    /// 设备配对与解除配对
+    Pairing,
+    /// 证书吊销
+    Revocation,
+    /// 会话断开
+    Session,
+}
+
+/// 审计事件结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// 操作被允许或已接受
+    Allowed,
+    /// 操作被拒绝
+    Denied,
+    /// 操作成功完成
+    Success,
+    /// 操作执行失败
+    Failure,
+}
+
+/// 待记录的审计事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// 事件类别
+    pub category: AuditCategory,
+    /// 事件结果
+    pub outcome: AuditOutcome,
+    /// 对端网关 ID，本地操作时为空
+    pub peer_id: Option<Uuid>,
+    /// 对端地址，本地操作时为空
+    pub peer_address: Option<String>,
+    /// 动作名称
+    pub action: String,
+    /// 操作对象（路径、规则 ID 等）
+    pub target: Option<String>,
+    /// 详细说明
+    pub detail: String,
+}
+
+impl AuditEvent {
+    /// 创建审计事件
+    ///
+    /// # 参数
+    ///
+    /// * `category` - 事件类别
+    /// * `outcome` - 事件结果
+    /// * `action` - 动作名称
+    pub fn new(category: AuditCategory, outcome: AuditOutcome, action: impl Into<String>) -> Self {
+        Self {
+            category,
+            outcome,
+            peer_id: None,
+            peer_address: None,
+            action: action.into(),
+            target: None,
+            detail: String::new(),
+        }
+    }
+
+    /// 设置对端网关 ID
+    pub fn peer(mut self, peer_id: Uuid) -> Self {
+        self.peer_id = Some(peer_id);
+        self
+    }
+
+    /// 设置对端地址
+    pub fn address(mut self, address: impl ToString) -> Self {
+        self.peer_address = Some(address.to_string());
+        self
+    }
+
+    /// 设置操作对象
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// 设置详细说明
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = detail.into();
+        self
+    }
+}
+
+/// 审计日志记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 序号，从 0 开始连续递增
+    pub sequence: u64,
+    /// 记录时间
+    pub timestamp: DateTime<Utc>,
+    /// 事件内容
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// 前一条记录的哈希
+    pub prev_hash: String,
+    /// 本条记录的哈希（SHA-256，小写十六进制）
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// 计算记录哈希，覆盖除 `hash` 以外的全部字段
+    fn compute_hash(
+        sequence: u64,
+        timestamp: &DateTime<Utc>,
+        event: &AuditEvent,
+        prev_hash: &str,
+    ) -> Result<String> {
+        let payload = serde_json::to_vec(&(sequence, timestamp, event, prev_hash))
+            .context("序列化审计记录失败")?;
+        Ok(Sha256::digest(&payload)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+}
+
+/// 审计日志查询条件，所有条件同时满足的记录才会返回
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    /// 事件类别，为空时不限制
+    pub categories: Vec<AuditCategory>,
+    /// 事件结果
+    pub outcome: Option<AuditOutcome>,
+    /// 对端网关 ID 或地址
+    pub peer: Option<String>,
+    /// 起始时间（含）
+    pub since: Option<DateTime<Utc>>,
+    /// 截止时间（含）
+    pub until: Option<DateTime<Utc>>,
+    /// 在动作、操作对象和详细说明中查找的关键词
+    pub keyword: Option<String>,
+    /// 最多返回的记录数，超出时保留最新的记录
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    /// 检查记录是否满足查询条件
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let event = &entry.event;
+        if !self.categories.is_empty() && !self.categories.contains(&event.category) {
+            return false;
+        }
+        if self.outcome.is_some_and(|outcome| outcome != event.outcome) {
+            return false;
+        }
+        if let Some(peer) = &self.peer {
+            let peer_matches = event.peer_id.is_some_and(|id| id.to_string() == *peer)
+                || event.peer_address.as_ref().is_some_and(|address| {
+                    address == peer || address.starts_with(&format!("{peer}:"))
+                });
+            if !peer_matches {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|since| entry.timestamp < since)
+            || self.until.is_some_and(|until| entry.timestamp > until)
+        {
+            return false;
+        }
+        if let Some(keyword) = &self.keyword {
+            let keyword = keyword.to_lowercase();
+            let found = [
+                Some(&event.action),
+                event.target.as_ref(),
+                Some(&event.detail),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&keyword));
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 哈希链校验结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditVerification {
+    /// 哈希链是否完整
+    pub valid: bool,
+    /// 已校验的记录数
+    pub entries: u64,
+    /// 第一条校验失败的行号（从 1 开始）
+    pub broken_at_line: Option<u64>,
+    /// 校验失败原因
+    pub reason: Option<String>,
+}
+
+/// 写入状态
+#[derive(Debug)]
+struct AuditState {
+    /// 追加写入的文件
+    file: File,
+    /// 最后一条记录的哈希
+    last_hash: String,
+    /// 下一条记录的序号
+    next_sequence: u64,
+}
+
+/// 审计日志
+#[derive(Debug)]
+pub struct AuditLog {
+    /// 日志文件路径
+    path: PathBuf,
+    /// 写入状态，追加时加锁保证哈希链按顺序延伸
+    state: Mutex<AuditState>,
+}
+
+impl AuditLog {
+    /// 打开审计日志，文件不存在时创建
+    ///
+    /// 已有记录时从最后一条有效记录继续哈希链。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 日志文件路径
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("创建审计日志目录失败: {}", parent.display()))?;
+        }
+
+        let (last_hash, next_sequence) = match Self::read_entries(&path)?.last() {
+            Some(entry) => (entry.hash.clone(), entry.sequence + 1),
+            None => (GENESIS_HASH.to_string(), 0),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("打开审计日志失败: {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            state: Mutex::new(AuditState {
+                file,
+                last_hash,
+                next_sequence,
+            }),
+        })
+    }
+
+    /// 打开同一进程内共享的审计日志
+    ///
+    /// 同一路径只会打开一次，多个组件写入同一文件时共用一条哈希链。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 日志文件路径
+    pub fn shared(path: impl Into<PathBuf>) -> Result<Arc<Self>> {
+        let path = path.into();
+        let key = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .and_then(|parent| fs::canonicalize(parent).ok())
+            .zip(path.file_name())
+            .map(|(parent, name)| parent.join(name))
+            .unwrap_or_else(|| path.clone());
+
+        let mut logs = SHARED_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(log) = logs.get(&key).and_then(Weak::upgrade) {
+            return Ok(log);
+        }
+        let log = Arc::new(Self::open(path)?);
+        logs.retain(|_, log| log.strong_count() > 0);
+        logs.insert(key, Arc::downgrade(&log));
+        Ok(log)
+    }
+
+    /// 获取日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加审计记录
+    ///
+    /// # 参数
+    ///
+    /// * `event` - 审计事件
+    ///
+    /// # 返回值
+    ///
+    /// 写入的记录
+    pub fn append(&self, event: AuditEvent) -> Result<AuditEntry> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let timestamp = Utc::now();
+        let hash =
+            AuditEntry::compute_hash(state.next_sequence, &timestamp, &event, &state.last_hash)?;
+        let entry = AuditEntry {
+            sequence: state.next_sequence,
+            timestamp,
+            event,
+            prev_hash: state.last_hash.clone(),
+            hash,
+        };
+
+        let mut line = serde_json::to_vec(&entry).context("序列化审计记录失败")?;
+        line.push(b'\n');
+        state.file.write_all(&line).context("写入审计日志失败")?;
+        state.file.flush()?;
+
+        state.last_hash = entry.hash.clone();
+        state.next_sequence += 1;
+        Ok(entry)
+    }
+
+    /// 记录审计事件，写入失败只记录警告，不影响调用方的操作
+    ///
+    /// # 参数
+    ///
+    /// * `event` - 审计事件
+    pub fn record(&self, event: AuditEvent) {
+        if let Err(e) = self.append(event) {
+            warn!("写入审计日志失败: {e}");
+        }
+    }
+
+    /// 查询审计记录
+    ///
+    /// # 参数
+    ///
+    /// * `filter` - 查询条件
+    ///
+    /// # 返回值
+    ///
+    /// 按时间顺序排列的匹配记录
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let mut entries: Vec<AuditEntry> = Self::read_entries(&self.path)?
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        if let Some(limit) = filter.limit {
+            let skip = entries.len().saturating_sub(limit);
+            entries.drain(..skip);
+        }
+        Ok(entries)
+    }
+
+    /// 以 JSON Lines 格式导出审计记录
+    ///
+    /// # 参数
+    ///
+    /// * `filter` - 查询条件
+    /// * `output_path` - 导出文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 导出的记录数
+    pub fn export_jsonl(&self, filter: &AuditFilter, output_path: &Path) -> Result<usize> {
+        let entries = self.query(filter)?;
+        let mut output = Vec::new();
+        for entry in &entries {
+            serde_json::to_writer(&mut output, entry).context("序列化审计记录失败")?;
+            output.push(b'\n');
+        }
+        fs::write(output_path, output)
+            .with_context(|| format!("写入导出文件失败: {}", output_path.display()))?;
+        Ok(entries.len())
+    }
+
+    /// 校验哈希链
+    ///
+    /// 逐行检查序号连续、前序哈希衔接以及记录哈希与内容一致。
+    ///
+    /// # 返回值
+    ///
+    /// 校验结果
+    pub fn verify(&self) -> Result<AuditVerification> {
+        let _state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let reader = BufReader::new(
+            File::open(&self.path)
+                .with_context(|| format!("打开审计日志失败: {}", self.path.display()))?,
+        );
+
+        let mut expected_prev = GENESIS_HASH.to_string();
+        let mut expected_sequence = 0u64;
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index as u64 + 1;
+            let broken = |reason: String| AuditVerification {
+                valid: false,
+                entries: expected_sequence,
+                broken_at_line: Some(line_number),
+                reason: Some(reason),
+            };
+
+            let line = line?;
+            let entry: AuditEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) => return Ok(broken(format!("记录格式无效: {e}"))),
+            };
+            if entry.sequence != expected_sequence {
+                return Ok(broken(format!(
+                    "序号不连续: 期望 {expected_sequence}，实际 {}",
+                    entry.sequence
+                )));
+            }
+            if entry.prev_hash != expected_prev {
+                return Ok(broken("前序哈希与上一条记录不一致".to_string()));
+            }
+            let hash = AuditEntry::compute_hash(
+                entry.sequence,
+                &entry.timestamp,
+                &entry.event,
+                &entry.prev_hash,
+            )?;
+            if hash != entry.hash {
+                return Ok(broken("记录内容与哈希不一致".to_string()));
+            }
+
+            expected_prev = entry.hash;
+            expected_sequence += 1;
+        }
+
+        Ok(AuditVerification {
+            valid: true,
+            entries: expected_sequence,
+            broken_at_line: None,
+            reason: None,
+        })
+    }
+
+    /// 读取日志文件中的全部记录，跳过无法解析的行
+    fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(anyhow!("打开审计日志失败: {}: {e}", path.display()));
+            }
+        };
+
+        let mut entries = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("审计日志第 {} 行无法解析: {e}", index + 1),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_query_and_verify() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("audit.jsonl");
+        let peer_id = Uuid::new_v4();
+
+        let log = AuditLog::open(&path).unwrap();
+        log.append(
+            AuditEvent::new(AuditCategory::Authorization, AuditOutcome::Denied, "read")
+                .peer(peer_id)
+                .address("192.168.1.20:55556")
+                .target("/srv/share/secret.txt")
+                .detail("命中拒绝规则"),
+        )
+        .unwrap();
+        log.append(AuditEvent::new(
+            AuditCategory::RuleChange,
+            AuditOutcome::Success,
+            "add_rule",
+        ))
+        .unwrap();
+        drop(log);
+
+        // 重新打开后继续哈希链
+        let log = AuditLog::open(&path).unwrap();
+        let entry = log
+            .append(
+                AuditEvent::new(AuditCategory::FileRead, AuditOutcome::Success, "read")
+                    .peer(peer_id),
+            )
+            .unwrap();
+        assert_eq!(entry.sequence, 2);
+
+        let verification = log.verify().unwrap();
+        assert!(verification.valid);
+        assert_eq!(verification.entries, 3);
+
+        let filter = AuditFilter {
+            peer: Some("192.168.1.20".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&filter).unwrap().len(), 1);
+
+        let filter = AuditFilter {
+            peer: Some(peer_id.to_string()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let entries = log.query(&filter).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].event.category, AuditCategory::FileRead);
+
+        let export_path = temp_dir.path().join("export.jsonl");
+        let filter = AuditFilter {
+            categories: vec![AuditCategory::Authorization, AuditCategory::RuleChange],
+            ..Default::default()
+        };
+        assert_eq!(log.export_jsonl(&filter, &export_path).unwrap(), 2);
+        assert_eq!(fs::read_to_string(&export_path).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_tampering_breaks_chain() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("audit.jsonl");
+
+        let log = AuditLog::open(&path).unwrap();
+        for action in ["pair", "unpair", "revoke"] {
+            log.append(AuditEvent::new(
+                AuditCategory::Pairing,
+                AuditOutcome::Success,
+                action,
+            ))
+            .unwrap();
+        }
+
+        // 篡改第二条记录的内容
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replacen("\"unpair\"", "\"pair\"", 1)).unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.broken_at_line, Some(2));
+
+        // 删除第二条记录
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.broken_at_line, Some(2));
+    }
+}
@@ -0,0 +1,457 @@
+//! 远程操作授权模块
+//!
+//! 其他网关发起的搜索、列目录、读取和文件传输请求都经由此处统一授权：
+//! 依次核对对端的签名身份与配对状态、吊销状态、挂载点的只读标志、
+//! 搜索令牌和访问规则，拒绝时给出带稳定错误码的原因。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock as StdRwLock};
+use uuid::Uuid;
+
+use crate::gateway::access_control::AccessDecision;
+use crate::gateway::audit::{AuditCategory, AuditEvent, AuditLog, AuditOutcome};
+use crate::gateway::mount::MountManager;
+use crate::gateway::pairing::PairingManager;
+use crate::gateway::protocol::WdicMessage;
+use crate::gateway::security::SecurityManager;
+use crate::gateway::tls::TlsManager;
+
+/// 错误代码：对端未配对，或请求未由其配对时固定的证书签名
+pub const AUTHZ_ERROR_UNAUTHENTICATED: u32 = 1401;
+/// 错误代码：访问规则拒绝了该操作
+pub const AUTHZ_ERROR_FORBIDDEN: u32 = 1403;
+/// 错误代码：请求的路径不在任何挂载点内
+pub const AUTHZ_ERROR_NOT_SHARED: u32 = 1404;
+/// 错误代码：写入操作指向只读挂载点
+pub const AUTHZ_ERROR_READ_ONLY: u32 = 1405;
+/// 错误代码：对端的证书已被吊销
+pub const AUTHZ_ERROR_REVOKED: u32 = 1410;
+/// 错误代码：搜索令牌签名无效、过期、已吊销、不属于请求者或未覆盖请求的路径与操作
+pub const AUTHZ_ERROR_INVALID_TOKEN: u32 = 1498;
+
+/// 远程操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteOperation {
+    /// 目录搜索
+    Search,
+    /// 列出目录
+    List,
+    /// 读取文件
+    Read,
+    /// 文件传输请求（向本网关写入文件）
+    Transfer,
+}
+
+impl RemoteOperation {
+    /// 获取操作名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Search => "search",
+            Self::List => "list",
+            Self::Read => "read",
+            Self::Transfer => "transfer",
+        }
+    }
+
+    /// 获取操作对应的访问规则权限
+    pub fn permission(&self) -> &'static str {
+        if self.is_write() {
+            "write"
+        } else {
+            "read"
+        }
+    }
+
+    /// 是否为写入操作
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::Transfer)
+    }
+}
+
+impl fmt::Display for RemoteOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 远程操作请求
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteRequest<'a> {
+    /// 请求者声明的网关 ID
+    pub peer_id: Uuid,
+    /// 经签名信封确认的发送者，未签名消息为 `None`
+    pub signer: Option<Uuid>,
+    /// 请求的来源地址
+    pub sender: SocketAddr,
+    /// 操作类型
+    pub operation: RemoteOperation,
+    /// 请求的本地路径，搜索请求没有路径
+    pub path: Option<&'a str>,
+    /// 请求者出示的签名搜索令牌
+    pub search_token: Option<&'a str>,
+}
+
+/// 授权拒绝
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthzDenial {
+    /// 稳定的错误代码
+    pub code: u32,
+    /// 拒绝原因
+    pub reason: String,
+}
+
+impl AuthzDenial {
+    /// 创建授权拒绝
+    pub fn new(code: u32, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    /// 转换为协议错误消息
+    pub fn to_message(&self) -> WdicMessage {
+        WdicMessage::error(self.code, self.reason.clone())
+    }
+}
+
+impl fmt::Display for AuthzDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.reason, self.code)
+    }
+}
+
+/// 远程操作授权器
+///
+/// 授权顺序：
+///
+/// 1. 对端必须是已配对设备，且请求由其配对时固定的证书签名；证书被吊销的对端一律拒绝
+/// 2. 写入操作不能指向只读挂载点
+/// 3. 出示的搜索令牌必须由本网关签发且签名有效、未过期、未吊销，
+///    授予请求者本身，并覆盖请求的路径与权限
+/// 4. 显式拒绝规则优先；其次是允许规则或有效的搜索令牌；
+///    未配置任何访问规则时，已配对设备可执行只读操作
+///
+/// 每次授权决策都会写入审计日志。
+#[derive(Debug)]
+pub struct Authorizer {
+    /// 设备配对管理器
+    pairing: Arc<PairingManager>,
+    /// TLS 管理器，用于查询证书吊销状态
+    tls_manager: Arc<TlsManager>,
+    /// 挂载管理器，提供只读标志和搜索令牌
+    mount_manager: Arc<MountManager>,
+    /// 安全管理器，提供访问规则，可替换为前端共享的实例
+    security_manager: StdRwLock<Arc<SecurityManager>>,
+    /// 审计日志
+    audit_log: Arc<AuditLog>,
+}
+
+impl Authorizer {
+    /// 创建授权器
+    ///
+    /// # 参数
+    ///
+    /// * `pairing` - 设备配对管理器
+    /// * `tls_manager` - TLS 管理器
+    /// * `mount_manager` - 挂载管理器
+    /// * `security_manager` - 安全管理器
+    /// * `audit_log` - 审计日志
+    pub fn new(
+        pairing: Arc<PairingManager>,
+        tls_manager: Arc<TlsManager>,
+        mount_manager: Arc<MountManager>,
+        security_manager: Arc<SecurityManager>,
+        audit_log: Arc<AuditLog>,
+    ) -> Self {
+        Self {
+            pairing,
+            tls_manager,
+            mount_manager,
+            security_manager: StdRwLock::new(security_manager),
+            audit_log,
+        }
+    }
+
+    /// 替换安全管理器，使访问规则与前端管理的规则保持一致
+    pub fn set_security_manager(&self, security_manager: Arc<SecurityManager>) {
+        *self
+            .security_manager
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = security_manager;
+    }
+
+    /// 获取当前使用的安全管理器
+    pub fn security_manager(&self) -> Arc<SecurityManager> {
+        Arc::clone(
+            &self
+                .security_manager
+                .read()
+                .unwrap_or_else(|e| e.into_inner()),
+        )
+    }
+
+    /// 授权远程操作
+    ///
+    /// # 参数
+    ///
+    /// * `request` - 远程操作请求
+    ///
+    /// # 返回值
+    ///
+    /// 授权通过时返回 `Ok(())`，否则返回带错误代码的拒绝原因
+    pub fn authorize(&self, request: &RemoteRequest<'_>) -> Result<(), AuthzDenial> {
+        let result = self.check(request);
+
+        let (outcome, detail) = match &result {
+            Ok(()) => (AuditOutcome::Allowed, String::new()),
+            Err(denial) => (AuditOutcome::Denied, denial.to_string()),
+        };
+        let mut event = AuditEvent::new(
+            AuditCategory::Authorization,
+            outcome,
+            request.operation.as_str(),
+        )
+        .peer(request.peer_id)
+        .address(request.sender)
+        .detail(detail);
+        if let Some(path) = request.path {
+            event = event.target(path);
+        }
+        self.audit_log.record(event);
+
+        result
+    }
+
+    /// 授权远程操作，不写入审计日志
+    fn check(&self, request: &RemoteRequest<'_>) -> Result<(), AuthzDenial> {
+        let security_manager = self.security_manager();
+        self.authenticate(&security_manager, request)?;
+
+        if request.operation.is_write() {
+            self.check_writable(request.path)?;
+        }
+
+        let token_granted = match request.search_token {
+            Some(token) => {
+                self.mount_manager
+                    .check_search_token(
+                        token,
+                        request.peer_id,
+                        request.path,
+                        request.operation.permission(),
+                    )
+                    .map_err(|e| AuthzDenial::new(AUTHZ_ERROR_INVALID_TOKEN, e.to_string()))?;
+                true
+            }
+            None => false,
+        };
+
+        // 搜索请求没有具体路径，结果逐条经 `filter_paths` 过滤
+        let Some(path) = request.path else {
+            return Ok(());
+        };
+
+        // 对端身份已由 `authenticate` 按签名确认，设备规则按该身份匹配
+        let decision = security_manager.explain_access(
+            &request.sender.ip().to_string(),
+            Some(request.peer_id),
+            path,
+            request.operation.permission(),
+        );
+
+        resolve(
+            decision,
+            token_granted,
+            security_manager.has_access_rules(),
+            request.operation,
+        )
+    }
+
+    /// 过滤对端无权读取的路径
+    ///
+    /// 用于搜索和列目录结果，对端只能看到自己有权读取的条目。
+    ///
+    /// # 参数
+    ///
+    /// * `request` - 已通过授权的远程操作请求
+    /// * `paths` - 候选路径
+    ///
+    /// # 返回值
+    ///
+    /// 对端有权读取的路径
+    pub fn filter_paths<I>(&self, request: &RemoteRequest<'_>, paths: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        paths
+            .into_iter()
+            .filter(|path| {
+                let item = RemoteRequest {
+                    operation: RemoteOperation::Read,
+                    path: Some(path),
+                    ..*request
+                };
+                self.check(&item).is_ok()
+            })
+            .collect()
+    }
+
+    /// 核对对端身份：已配对、未吊销，且请求由其签名
+    ///
+    /// 请求中的网关 ID 由发送者自报，因此要求签名信封的签名者与之一致。
+    /// 已配对设备的签名由配对时固定的证书验证，来源地址可以伪造，不作为身份依据。
+    fn authenticate(
+        &self,
+        security_manager: &SecurityManager,
+        request: &RemoteRequest<'_>,
+    ) -> Result<(), AuthzDenial> {
+        let peer_id = request.peer_id;
+        let sender = request.sender;
+        if self.tls_manager.is_gateway_revoked(&peer_id)
+            || security_manager.is_revoked(&peer_id.to_string())
+            || security_manager.is_revoked(&sender.ip().to_string())
+        {
+            return Err(AuthzDenial::new(
+                AUTHZ_ERROR_REVOKED,
+                format!("网关 {peer_id} 的证书已被吊销"),
+            ));
+        }
+
+        if !self.pairing.is_trusted(&peer_id) {
+            return Err(AuthzDenial::new(
+                AUTHZ_ERROR_UNAUTHENTICATED,
+                "未配对的设备无法访问共享目录",
+            ));
+        }
+
+        check_signer(peer_id, request.signer)
+    }
+
+    /// 写入操作必须指向可写挂载点，未指定路径时至少需要一个可写挂载点
+    fn check_writable(&self, path: Option<&str>) -> Result<(), AuthzDenial> {
+        let writable = match path.map(Path::new).filter(|path| path.is_absolute()) {
+            Some(path) => match self.mount_manager.mount_containing(path) {
+                Some(mount) => !mount.read_only,
+                None => {
+                    return Err(AuthzDenial::new(
+                        AUTHZ_ERROR_NOT_SHARED,
+                        format!("路径不在任何挂载点内: {}", path.display()),
+                    ))
+                }
+            },
+            None => self.mount_manager.has_writable_mount(),
+        };
+
+        if writable {
+            Ok(())
+        } else {
+            Err(AuthzDenial::new(
+                AUTHZ_ERROR_READ_ONLY,
+                "挂载点为只读，拒绝写入",
+            ))
+        }
+    }
+}
+
+/// 要求请求由其声明的网关签名
+fn check_signer(peer_id: Uuid, signer: Option<Uuid>) -> Result<(), AuthzDenial> {
+    match signer {
+        Some(signer) if signer == peer_id => Ok(()),
+        Some(signer) => Err(AuthzDenial::new(
+            AUTHZ_ERROR_UNAUTHENTICATED,
+            format!("请求声明的网关 {peer_id} 与签名者 {signer} 不一致"),
+        )),
+        None => Err(AuthzDenial::new(
+            AUTHZ_ERROR_UNAUTHENTICATED,
+            "未签名的请求无法访问共享目录",
+        )),
+    }
+}
+
+/// 综合访问规则决策与搜索令牌得出授权结果
+fn resolve(
+    decision: AccessDecision,
+    token_granted: bool,
+    has_rules: bool,
+    operation: RemoteOperation,
+) -> Result<(), AuthzDenial> {
+    if decision.allowed {
+        return Ok(());
+    }
+    // 显式拒绝规则优先于搜索令牌
+    if decision.rule_id.is_some() {
+        return Err(AuthzDenial::new(AUTHZ_ERROR_FORBIDDEN, decision.reason));
+    }
+    if token_granted || (!has_rules && !operation.is_write()) {
+        return Ok(());
+    }
+    Err(AuthzDenial::new(AUTHZ_ERROR_FORBIDDEN, decision.reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::access_control::RuleEffect;
+
+    fn rule_decision(allowed: bool) -> AccessDecision {
+        AccessDecision {
+            allowed,
+            rule_id: Some("rule".to_string()),
+            rule_name: Some("规则".to_string()),
+            effect: Some(if allowed {
+                RuleEffect::Allow
+            } else {
+                RuleEffect::Deny
+            }),
+            reason: "命中规则".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        // 规则允许
+        assert!(resolve(rule_decision(true), false, true, RemoteOperation::Read).is_ok());
+
+        // 显式拒绝规则优先于搜索令牌
+        let denial = resolve(rule_decision(false), true, true, RemoteOperation::Read).unwrap_err();
+        assert_eq!(denial.code, AUTHZ_ERROR_FORBIDDEN);
+
+        // 没有规则命中时，有效的搜索令牌可以授权
+        let unmatched = AccessDecision::denied("没有匹配的规则，默认拒绝");
+        assert!(resolve(unmatched.clone(), true, true, RemoteOperation::Read).is_ok());
+        assert!(resolve(unmatched.clone(), false, true, RemoteOperation::Read).is_err());
+
+        // 未配置规则时只放行只读操作
+        assert!(resolve(unmatched.clone(), false, false, RemoteOperation::Search).is_ok());
+        let denial = resolve(unmatched, false, false, RemoteOperation::Transfer).unwrap_err();
+        assert_eq!(denial.code, AUTHZ_ERROR_FORBIDDEN);
+    }
+
+    #[test]
+    fn test_check_signer() {
+        let peer_id = Uuid::new_v4();
+        assert!(check_signer(peer_id, Some(peer_id)).is_ok());
+
+        let denial = check_signer(peer_id, Some(Uuid::new_v4())).unwrap_err();
+        assert_eq!(denial.code, AUTHZ_ERROR_UNAUTHENTICATED);
+        let denial = check_signer(peer_id, None).unwrap_err();
+        assert_eq!(denial.code, AUTHZ_ERROR_UNAUTHENTICATED);
+    }
+
+    #[test]
+    fn test_operation_permissions() {
+        assert_eq!(RemoteOperation::Search.permission(), "read");
+        assert_eq!(RemoteOperation::List.permission(), "read");
+        assert_eq!(RemoteOperation::Read.permission(), "read");
+        assert_eq!(RemoteOperation::Transfer.permission(), "write");
+        assert_eq!(
+            AuthzDenial::new(AUTHZ_ERROR_READ_ONLY, "只读").to_message(),
+            WdicMessage::error(AUTHZ_ERROR_READ_ONLY, "只读".to_string())
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! 证书有效期监控模块
+//!
+//! 定期检查 TLS 管理器缓存中的证书，在到期前按配置的阈值发出警告，
+//! 并在服务端和客户端证书即将到期时由本地 CA 自动重新签发。
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::gateway::tls::TlsManager;
+
+/// 证书事件通道容量
+const CERTIFICATE_EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// 自动重新签发的终端证书
+const ROTATED_CERTIFICATES: [&str; 2] = ["server", "client"];
+
+/// 证书轮换通知签名的域分隔前缀
+const ROTATION_SIGNATURE_CONTEXT: &[u8] = b"WDIC-CERT-ROTATION";
+
+/// 每天的秒数
+const SECONDS_PER_DAY: i64 = 24 * 3600;
+
+/// 证书事件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CertificateEvent {
+    /// 证书即将到期
+    Expiring {
+        /// 证书名称
+        name: String,
+        /// 证书指纹
+        fingerprint: String,
+        /// 到期时间
+        not_after: DateTime<Utc>,
+        /// 剩余天数
+        days_remaining: i64,
+    },
+    /// 证书已过期
+    Expired {
+        /// 证书名称
+        name: String,
+        /// 证书指纹
+        fingerprint: String,
+        /// 到期时间
+        not_after: DateTime<Utc>,
+    },
+    /// 服务端和客户端证书已重新签发
+    Rotated {
+        /// 新证书指纹
+        fingerprints: Vec<String>,
+    },
+    /// 重新签发失败
+    RotationFailed {
+        /// 错误信息
+        error: String,
+    },
+}
+
+/// 证书有效期监控器
+#[derive(Debug)]
+pub struct CertificateMonitor {
+    /// TLS 管理器
+    tls_manager: Arc<TlsManager>,
+    /// 警告阈值（剩余天数，降序）
+    warning_days: Vec<u32>,
+    /// 剩余天数低于该值时重新签发终端证书
+    renew_before_days: u32,
+    /// 每个证书指纹已警告过的最小阈值，避免重复警告
+    warned: DashMap<String, i64>,
+    /// 事件发送器
+    event_sender: broadcast::Sender<CertificateEvent>,
+}
+
+impl CertificateMonitor {
+    /// 创建新的证书监控器
+    ///
+    /// # 参数
+    ///
+    /// * `tls_manager` - TLS 管理器
+    /// * `warning_days` - 警告阈值（剩余天数）
+    /// * `renew_before_days` - 剩余天数低于该值时重新签发终端证书
+    pub fn new(tls_manager: Arc<TlsManager>, warning_days: &[u32], renew_before_days: u32) -> Self {
+        let mut warning_days = warning_days.to_vec();
+        warning_days.sort_unstable_by(|a, b| b.cmp(a));
+        warning_days.dedup();
+        let (event_sender, _) = broadcast::channel(CERTIFICATE_EVENT_CHANNEL_CAPACITY);
+
+        Self {
+            tls_manager,
+            warning_days,
+            renew_before_days,
+            warned: DashMap::new(),
+            event_sender,
+        }
+    }
+
+    /// 订阅证书事件
+    pub fn subscribe(&self) -> broadcast::Receiver<CertificateEvent> {
+        self.event_sender.subscribe()
+    }
+
+    /// 检查所有证书
+    ///
+    /// 服务端和客户端证书剩余天数低于重新签发阈值时一起重新签发；
+    /// 其他证书（以及签发失败时的终端证书）按阈值发出警告，每个阈值只警告一次。
+    ///
+    /// # 返回值
+    ///
+    /// 本次检查产生的事件
+    pub fn check(&self) -> Vec<CertificateEvent> {
+        let now = SystemTime::now();
+        let mut events = Vec::new();
+        let mut needs_rotation = false;
+        let mut expiring = Vec::new();
+
+        for name in self.tls_manager.certificate_names() {
+            let info = match self.tls_manager.certificate_info(&name) {
+                Ok(Some(info)) => info,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("无法解析证书 '{name}': {e}");
+                    continue;
+                }
+            };
+
+            let remaining = match info.not_after.duration_since(now) {
+                Ok(duration) => duration.as_secs() as i64,
+                Err(e) => -(e.duration().as_secs() as i64),
+            };
+            if ROTATED_CERTIFICATES.contains(&name.as_str())
+                && remaining < i64::from(self.renew_before_days) * SECONDS_PER_DAY
+            {
+                needs_rotation = true;
+            }
+            expiring.push((name, info.fingerprint, info.not_after, remaining));
+        }
+
+        let rotated = needs_rotation && self.rotate(&mut events);
+        for (name, fingerprint, not_after, remaining) in expiring {
+            if rotated && ROTATED_CERTIFICATES.contains(&name.as_str()) {
+                continue;
+            }
+            if let Some(event) = self.warning_for(name, fingerprint, not_after, remaining) {
+                events.push(event);
+            }
+        }
+
+        for event in &events {
+            // 没有订阅者时发送失败是正常的
+            let _ = self.event_sender.send(event.clone());
+        }
+        events
+    }
+
+    /// 重新签发服务端和客户端证书
+    fn rotate(&self, events: &mut Vec<CertificateEvent>) -> bool {
+        match self.tls_manager.rotate_leaf_certificates() {
+            Ok(fingerprints) => {
+                info!("证书即将到期，已自动重新签发");
+                events.push(CertificateEvent::Rotated { fingerprints });
+                true
+            }
+            Err(e) => {
+                error!("自动重新签发证书失败: {e}");
+                events.push(CertificateEvent::RotationFailed {
+                    error: e.to_string(),
+                });
+                false
+            }
+        }
+    }
+
+    /// 根据剩余时间生成警告事件，同一阈值只警告一次
+    fn warning_for(
+        &self,
+        name: String,
+        fingerprint: String,
+        not_after: SystemTime,
+        remaining: i64,
+    ) -> Option<CertificateEvent> {
+        let not_after = DateTime::<Utc>::from(not_after);
+        if remaining <= 0 {
+            if self
+                .warned
+                .get(&fingerprint)
+                .is_some_and(|level| *level <= 0)
+            {
+                return None;
+            }
+            error!("证书 '{name}' 已于 {not_after} 过期");
+            self.warned.insert(fingerprint.clone(), 0);
+            return Some(CertificateEvent::Expired {
+                name,
+                fingerprint,
+                not_after,
+            });
+        }
+
+        let days_remaining = remaining / SECONDS_PER_DAY;
+        let threshold = self
+            .warning_days
+            .iter()
+            .map(|days| i64::from(*days))
+            .filter(|days| days_remaining < *days)
+            .min()?;
+        if self
+            .warned
+            .get(&fingerprint)
+            .is_some_and(|level| *level <= threshold)
+        {
+            return None;
+        }
+
+        warn!("证书 '{name}' 将在 {days_remaining} 天后（{not_after}）到期");
+        self.warned.insert(fingerprint.clone(), threshold);
+        Some(CertificateEvent::Expiring {
+            name,
+            fingerprint,
+            not_after,
+            days_remaining,
+        })
+    }
+
+    /// 证书轮换通知的签名内容
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送者网关 ID
+    /// * `fingerprints` - 新证书指纹
+    /// * `timestamp` - 通知时间
+    pub fn rotation_payload(
+        sender_id: &Uuid,
+        fingerprints: &[String],
+        timestamp: &DateTime<Utc>,
+    ) -> Vec<u8> {
+        let mut payload = ROTATION_SIGNATURE_CONTEXT.to_vec();
+        payload.extend_from_slice(sender_id.as_bytes());
+        payload.extend_from_slice(&timestamp.timestamp_millis().to_be_bytes());
+        for fingerprint in fingerprints {
+            payload.push(0);
+            payload.extend_from_slice(fingerprint.as_bytes());
+        }
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::tls::{MtlsConfig, VerifyMode};
+    use rcgen::{CertificateParams, KeyPair};
+    use tempfile::tempdir;
+
+    fn tls_manager_in(dir: &std::path::Path) -> anyhow::Result<Arc<TlsManager>> {
+        let config = MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            verify_mode: VerifyMode::Strict,
+            ..Default::default()
+        };
+        Ok(Arc::new(TlsManager::new(config)?))
+    }
+
+    #[test]
+    fn test_expiry_warnings_fire_once_per_threshold() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let tls_manager = tls_manager_in(temp_dir.path())?;
+
+        let mut params = CertificateParams::new(vec!["short.local".to_string()])?;
+        let now = ::time::OffsetDateTime::now_utc();
+        params.not_before = now - ::time::Duration::days(1);
+        params.not_after = now + ::time::Duration::days(3);
+        let key = KeyPair::generate()?;
+        let cert = params.self_signed(&key)?;
+        tls_manager.install_certificate("short", cert.pem(), key.serialize_pem());
+
+        let monitor = CertificateMonitor::new(Arc::clone(&tls_manager), &[30, 7, 1], 14);
+        let events = monitor.check();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            CertificateEvent::Expiring { name, days_remaining: 2, .. } if name == "short"
+        ));
+
+        // 同一阈值不会重复警告，新签发的 365 天证书也不会触发警告
+        assert!(monitor.check().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaf_certificates_rotate_before_expiry() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let tls_manager = tls_manager_in(temp_dir.path())?;
+        let old_server = tls_manager.certificate_info("server")?.unwrap().fingerprint;
+
+        // 重新签发阈值大于证书有效期，立即触发轮换
+        let monitor = CertificateMonitor::new(Arc::clone(&tls_manager), &[30], 400);
+        let mut receiver = monitor.subscribe();
+        let events = monitor.check();
+        let Some(CertificateEvent::Rotated { fingerprints }) = events.first() else {
+            panic!("期望证书被重新签发: {events:?}");
+        };
+        assert_eq!(receiver.try_recv()?, events[0]);
+
+        let new_server = tls_manager.certificate_info("server")?.unwrap().fingerprint;
+        assert_ne!(new_server, old_server);
+        assert_eq!(fingerprints[0], new_server);
+
+        // 新证书写入文件并通过本地 CA 的链验证
+        let server_pem = std::fs::read(temp_dir.path().join("server.crt"))?;
+        assert!(tls_manager.verify_certificate(&server_pem)?);
+        assert_eq!(tls_manager.get_certificate("server"), Some(server_pem));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotation_payload_signature() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let tls_manager = tls_manager_in(temp_dir.path())?;
+        let ca_pem = tls_manager.ca_certificate_pem().unwrap();
+
+        let sender_id = Uuid::new_v4();
+        let timestamp = Utc::now();
+        let fingerprints = vec!["aa".to_string(), "bb".to_string()];
+        let payload = CertificateMonitor::rotation_payload(&sender_id, &fingerprints, &timestamp);
+        let signature = tls_manager.sign_with_ca_key(&payload)?;
+        assert!(TlsManager::verify_ca_signature(
+            &ca_pem, &payload, &signature
+        )?);
+
+        // 篡改指纹后签名无效
+        let forged =
+            CertificateMonitor::rotation_payload(&sender_id, &fingerprints[..1], &timestamp);
+        assert!(!TlsManager::verify_ca_signature(
+            &ca_pem, &forged, &signature
+        )?);
+
+        Ok(())
+    }
+}
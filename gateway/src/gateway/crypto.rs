@@ -0,0 +1,268 @@
+//! 端到端加密模块
+//!
+//! 基于 X25519 密钥协商、HKDF-SHA256 密钥派生和 ChaCha20-Poly1305 AEAD，
+//! 为经过中继等不受信任路径转发的消息提供端到端的保密性和完整性。
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// X25519 公钥长度（字节）
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// 会话密钥长度（字节）
+pub const SESSION_KEY_LEN: usize = 32;
+
+/// X25519 密钥协商密钥对
+///
+/// 每个网关持有一个静态密钥对，公钥随注册表条目发布，
+/// 任意两个网关可据此独立派生出相同的会话密钥。
+pub struct AgreementKeyPair {
+    /// 私钥
+    secret: StaticSecret,
+    /// 公钥
+    public: PublicKey,
+}
+
+impl AgreementKeyPair {
+    /// 生成新的随机密钥对
+    ///
+    /// # 返回值
+    ///
+    /// 密钥对实例
+    pub fn generate() -> Result<Self> {
+        let mut secret_bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut secret_bytes)
+            .map_err(|_| anyhow!("生成 X25519 私钥失败"))?;
+        Ok(Self::from_secret_bytes(secret_bytes))
+    }
+
+    /// 从私钥字节恢复密钥对
+    ///
+    /// # 参数
+    ///
+    /// * `secret_bytes` - 32 字节私钥
+    ///
+    /// # 返回值
+    ///
+    /// 密钥对实例
+    pub fn from_secret_bytes(secret_bytes: [u8; 32]) -> Self {
+        let secret = StaticSecret::from(secret_bytes);
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// 获取公钥字节
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        self.public.to_bytes()
+    }
+
+    /// 获取 Base64 编码的公钥
+    pub fn public_key_base64(&self) -> String {
+        BASE64.encode(self.public.as_bytes())
+    }
+
+    /// 与对端公钥协商会话密钥
+    ///
+    /// 双方公钥按字节序排列后作为 HKDF 盐值，保证两端派生结果一致。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_public` - 对端公钥
+    /// * `context` - 密钥用途标签，不同用途派生出互不相关的密钥
+    ///
+    /// # 返回值
+    ///
+    /// 会话密钥，对端公钥为低阶点时返回错误
+    pub fn derive_session_key(
+        &self,
+        peer_public: &[u8; PUBLIC_KEY_LEN],
+        context: &[u8],
+    ) -> Result<SessionKey> {
+        let shared = self.secret.diffie_hellman(&PublicKey::from(*peer_public));
+        if !shared.was_contributory() {
+            return Err(anyhow!("对端公钥无效"));
+        }
+
+        let local_public = self.public_key();
+        let (first, second) = if local_public <= *peer_public {
+            (local_public, *peer_public)
+        } else {
+            (*peer_public, local_public)
+        };
+        let mut salt = Vec::with_capacity(PUBLIC_KEY_LEN * 2);
+        salt.extend_from_slice(&first);
+        salt.extend_from_slice(&second);
+
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &salt).extract(shared.as_bytes());
+        let info = [context];
+        let okm = prk
+            .expand(&info, &CHACHA20_POLY1305)
+            .map_err(|_| anyhow!("会话密钥派生失败"))?;
+
+        Ok(SessionKey {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+        })
+    }
+}
+
+impl std::fmt::Debug for AgreementKeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgreementKeyPair")
+            .field("public", &self.public_key_base64())
+            .finish_non_exhaustive()
+    }
+}
+
+/// 解码 Base64 编码的 X25519 公钥
+///
+/// # 参数
+///
+/// * `encoded` - Base64 编码的公钥
+///
+/// # 返回值
+///
+/// 32 字节公钥
+pub fn decode_public_key(encoded: &str) -> Result<[u8; PUBLIC_KEY_LEN]> {
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| anyhow!("公钥 Base64 解码失败: {e}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("公钥长度必须为 {PUBLIC_KEY_LEN} 字节"))
+}
+
+/// AEAD 会话密钥
+///
+/// 密文格式为 `nonce(12 字节) || 密文 || 认证标签(16 字节)`，
+/// 每次加密使用随机 nonce。
+pub struct SessionKey {
+    key: LessSafeKey,
+}
+
+impl SessionKey {
+    /// 从原始密钥字节创建会话密钥
+    ///
+    /// # 参数
+    ///
+    /// * `key` - 32 字节 ChaCha20-Poly1305 密钥
+    ///
+    /// # 返回值
+    ///
+    /// 会话密钥
+    pub fn from_bytes(key: &[u8; SESSION_KEY_LEN]) -> Result<Self> {
+        let key = UnboundKey::new(&CHACHA20_POLY1305, key).map_err(|_| anyhow!("会话密钥无效"))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// 加密并认证数据
+    ///
+    /// # 参数
+    ///
+    /// * `plaintext` - 明文
+    /// * `aad` - 附加认证数据，不加密但参与认证
+    ///
+    /// # 返回值
+    ///
+    /// 密文
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce_bytes)
+            .map_err(|_| anyhow!("生成 nonce 失败"))?;
+
+        let mut in_out = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce_bytes),
+                Aad::from(aad),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("加密失败"))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_LEN + in_out.len());
+        sealed.extend_from_slice(&nonce_bytes);
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+
+    /// 验证并解密数据
+    ///
+    /// # 参数
+    ///
+    /// * `sealed` - 由 [`SessionKey::seal`] 生成的密文
+    /// * `aad` - 加密时使用的附加认证数据
+    ///
+    /// # 返回值
+    ///
+    /// 明文，密文被篡改或密钥不匹配时返回错误
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("密文长度不足"));
+        }
+
+        let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| anyhow!("nonce 格式无效"))?;
+
+        let mut in_out = ciphertext.to_vec();
+        let plaintext_len = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut in_out)
+            .map_err(|_| anyhow!("解密失败：密文被篡改或密钥不匹配"))?
+            .len();
+        in_out.truncate(plaintext_len);
+        Ok(in_out)
+    }
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionKey").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_key_agreement_and_roundtrip() {
+        let alice = AgreementKeyPair::generate().unwrap();
+        let bob = AgreementKeyPair::generate().unwrap();
+        let mallory = AgreementKeyPair::generate().unwrap();
+
+        let alice_key = alice
+            .derive_session_key(&bob.public_key(), b"test")
+            .unwrap();
+        let bob_key = bob
+            .derive_session_key(&alice.public_key(), b"test")
+            .unwrap();
+
+        let plaintext = b"secret directory listing";
+        let sealed = alice_key.seal(plaintext, b"aad").unwrap();
+        assert!(!sealed.windows(plaintext.len()).any(|w| w == plaintext));
+        assert_eq!(bob_key.open(&sealed, b"aad").unwrap(), plaintext);
+
+        // 错误的附加数据、第三方密钥或不同用途标签都无法解密
+        assert!(bob_key.open(&sealed, b"other").is_err());
+        let mallory_key = mallory
+            .derive_session_key(&alice.public_key(), b"test")
+            .unwrap();
+        assert!(mallory_key.open(&sealed, b"aad").is_err());
+        let other_context = bob
+            .derive_session_key(&alice.public_key(), b"other")
+            .unwrap();
+        assert!(other_context.open(&sealed, b"aad").is_err());
+
+        let decoded = decode_public_key(&alice.public_key_base64()).unwrap();
+        assert_eq!(decoded, alice.public_key());
+        assert!(decode_public_key("AAAA").is_err());
+    }
+}
@@ -0,0 +1,523 @@
+//! 消息签名信封模块
+//!
+//! 发现与控制消息（`WdicMessage`）和 UDP 令牌在 UDP 上以 JSON 明文传输，
+//! 局域网内任何人都可以伪造。本模块用网关的身份密钥（本地 CA 私钥）为消息签名，
+//! 信封携带发送者 ID、时间戳、随机 nonce 和发送者的 CA 证书。
+//!
+//! 接收方按以下顺序确认发送者的公钥：
+//! 1. 已配对设备固定的 CA 证书；
+//! 2. 首次见到该网关时记住的 CA 证书（首次使用信任）。
+//!
+//! 时间戳超出重放窗口、或窗口内重复出现的 nonce 会被拒绝；
+//! 未签名消息按 [`MessageSigningPolicy`] 决定接受或丢弃。
+
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::gateway::pairing::PairingManager;
+use crate::gateway::tls::TlsManager;
+
+/// 信封签名的域分隔前缀
+const ENVELOPE_SIGNATURE_CONTEXT: &[u8] = b"WDIC-ENVELOPE";
+
+/// nonce 长度（字节）
+pub const ENVELOPE_NONCE_LEN: usize = 16;
+
+/// 默认重放窗口（秒）
+pub const DEFAULT_REPLAY_WINDOW: u64 = 60;
+
+/// 每个发送者在重放窗口内最多记录的 nonce 数量，超出后拒绝该发送者的新消息
+const MAX_NONCES_PER_SENDER: usize = 10_000;
+
+/// 消息签名策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageSigningPolicy {
+    /// 不签名也不验证，信封只被拆开
+    Disabled,
+    /// 签名发出的消息，验证签名消息，仍接受未签名消息（用于与旧版本网关混合部署）
+    Permissive,
+    /// 签名发出的消息，丢弃未签名或签名无效的消息
+    #[default]
+    Required,
+}
+
+/// 签名信封
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    /// 发送者网关 ID
+    pub sender_id: Uuid,
+    /// 签名时间
+    pub timestamp: DateTime<Utc>,
+    /// 随机 nonce（Base64）
+    pub nonce: String,
+    /// 发送者 CA 证书（PEM）
+    pub certificate: String,
+    /// 被签名的消息（Base64）
+    pub payload: String,
+    /// CA 私钥对信封内容的签名（Base64）
+    pub signature: String,
+}
+
+impl SignedEnvelope {
+    /// 生成签名内容
+    ///
+    /// 签名覆盖发送者、时间戳、nonce 和消息本身，证书由指纹比对单独约束。
+    fn signing_payload(
+        sender_id: &Uuid,
+        timestamp: &DateTime<Utc>,
+        nonce: &[u8],
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut data = Vec::with_capacity(
+            ENVELOPE_SIGNATURE_CONTEXT.len() + 16 + 8 + nonce.len() + payload.len(),
+        );
+        data.extend_from_slice(ENVELOPE_SIGNATURE_CONTEXT);
+        data.extend_from_slice(sender_id.as_bytes());
+        data.extend_from_slice(&timestamp.timestamp_millis().to_be_bytes());
+        data.extend_from_slice(nonce);
+        data.extend_from_slice(payload);
+        data
+    }
+}
+
+/// 拆开信封后的消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenedMessage<'a> {
+    /// 经签名确认的发送者，未签名消息为 `None`
+    pub signer: Option<Uuid>,
+    /// 消息内容
+    pub payload: Cow<'a, [u8]>,
+}
+
+/// 本网关的签名身份
+#[derive(Debug)]
+struct SigningIdentity {
+    /// 本网关 ID
+    local_id: Uuid,
+    /// 持有 CA 私钥的 TLS 管理器
+    tls_manager: Arc<TlsManager>,
+    /// 本地 CA 证书（PEM）
+    certificate: String,
+    /// 提供固定公钥的配对管理器
+    pairing: Arc<PairingManager>,
+}
+
+/// 每个发送者在重放窗口内见过的 nonce 及其时间戳
+type SeenNonces = HashMap<[u8; ENVELOPE_NONCE_LEN], DateTime<Utc>>;
+
+/// 消息签名与验证器
+///
+/// 网络管理器和 UDP 广播管理器共享同一个实例，
+/// 因此首次使用信任记住的公钥和重放窗口对两条通道都有效。
+#[derive(Debug)]
+pub struct MessageAuthenticator {
+    /// 签名策略
+    policy: MessageSigningPolicy,
+    /// 重放窗口
+    replay_window: chrono::Duration,
+    /// 签名身份，未配置时不签名
+    identity: Option<SigningIdentity>,
+    /// 首次使用信任记住的 CA 证书指纹，以网关 ID 为键
+    known_keys: DashMap<Uuid, String>,
+    /// 重放窗口内见过的 nonce 及其时间戳，以发送者为键
+    nonces: Mutex<HashMap<Uuid, SeenNonces>>,
+}
+
+impl MessageAuthenticator {
+    /// 创建不签名也不验证的验证器
+    ///
+    /// 网关配置签名身份之前，网络管理器使用它收发未签名消息。
+    pub fn disabled() -> Self {
+        Self {
+            policy: MessageSigningPolicy::Disabled,
+            replay_window: chrono::Duration::seconds(DEFAULT_REPLAY_WINDOW as i64),
+            identity: None,
+            known_keys: DashMap::new(),
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 创建消息签名与验证器
+    ///
+    /// # 参数
+    ///
+    /// * `local_id` - 本网关 ID
+    /// * `tls_manager` - 持有本地 CA 私钥的 TLS 管理器
+    /// * `pairing` - 配对管理器，已配对设备的 CA 证书作为固定公钥
+    /// * `policy` - 签名策略
+    /// * `replay_window` - 重放窗口（秒）
+    ///
+    /// # 返回值
+    ///
+    /// 验证器实例，本地 CA 证书缺失时返回错误
+    pub fn new(
+        local_id: Uuid,
+        tls_manager: Arc<TlsManager>,
+        pairing: Arc<PairingManager>,
+        policy: MessageSigningPolicy,
+        replay_window: u64,
+    ) -> Result<Self> {
+        let certificate = tls_manager
+            .ca_certificate_pem()
+            .ok_or_else(|| anyhow!("未找到本地 CA 证书，无法签名消息"))?;
+
+        // 任何人都不能以本网关的身份发送消息
+        let known_keys = DashMap::new();
+        known_keys.insert(local_id, tls_manager.ca_fingerprint()?);
+
+        Ok(Self {
+            policy,
+            replay_window: chrono::Duration::seconds(replay_window as i64),
+            identity: Some(SigningIdentity {
+                local_id,
+                tls_manager,
+                certificate,
+                pairing,
+            }),
+            known_keys,
+            nonces: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 获取签名策略
+    pub fn policy(&self) -> MessageSigningPolicy {
+        self.policy
+    }
+
+    /// 为消息签名并装入信封
+    ///
+    /// 策略为 `Disabled` 或未配置签名身份时原样返回消息。
+    ///
+    /// # 参数
+    ///
+    /// * `payload` - 序列化后的消息
+    ///
+    /// # 返回值
+    ///
+    /// 要发送的数据
+    pub fn seal(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let Some(identity) = self.signing_identity() else {
+            return Ok(payload.to_vec());
+        };
+
+        let mut nonce = [0u8; ENVELOPE_NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("生成 nonce 失败"))?;
+        let timestamp = Utc::now();
+        let data = SignedEnvelope::signing_payload(&identity.local_id, &timestamp, &nonce, payload);
+        let signature = identity.tls_manager.sign_with_ca_key(&data)?;
+
+        let envelope = SignedEnvelope {
+            sender_id: identity.local_id,
+            timestamp,
+            nonce: BASE64_STANDARD.encode(nonce),
+            certificate: identity.certificate.clone(),
+            payload: BASE64_STANDARD.encode(payload),
+            signature: BASE64_STANDARD.encode(signature),
+        };
+        serde_json::to_vec(&envelope).map_err(|e| anyhow!("序列化签名信封失败: {e}"))
+    }
+
+    /// 拆开信封并验证签名
+    ///
+    /// 不是信封的数据原样返回，由调用方解析后再经 [`Self::authorize`] 按策略决定是否接受。
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 收到的数据
+    ///
+    /// # 返回值
+    ///
+    /// 消息内容及签名者；签名无效、公钥与已知公钥不符、超出重放窗口或 nonce 重复时返回错误
+    pub fn open<'a>(&self, data: &'a [u8]) -> Result<OpenedMessage<'a>> {
+        let Ok(envelope) = serde_json::from_slice::<SignedEnvelope>(data) else {
+            return Ok(OpenedMessage {
+                signer: None,
+                payload: Cow::Borrowed(data),
+            });
+        };
+
+        let payload = BASE64_STANDARD
+            .decode(&envelope.payload)
+            .map_err(|e| anyhow!("信封消息编码无效: {e}"))?;
+        if self.policy == MessageSigningPolicy::Disabled {
+            return Ok(OpenedMessage {
+                signer: None,
+                payload: Cow::Owned(payload),
+            });
+        }
+
+        let sender_id = envelope.sender_id;
+        let nonce: [u8; ENVELOPE_NONCE_LEN] = BASE64_STANDARD
+            .decode(&envelope.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(|| anyhow!("信封 nonce 无效"))?;
+        let now = Utc::now();
+        if (now - envelope.timestamp).abs() > self.replay_window {
+            return Err(anyhow!(
+                "网关 {sender_id} 的消息时间戳 {} 超出重放窗口",
+                envelope.timestamp
+            ));
+        }
+
+        let fingerprint = TlsManager::ca_pem_fingerprint(&envelope.certificate)?;
+        self.check_sender_key(&sender_id, &fingerprint)?;
+
+        let signature = BASE64_STANDARD
+            .decode(&envelope.signature)
+            .map_err(|e| anyhow!("信封签名编码无效: {e}"))?;
+        let data =
+            SignedEnvelope::signing_payload(&sender_id, &envelope.timestamp, &nonce, &payload);
+        if !TlsManager::verify_ca_signature(&envelope.certificate, &data, &signature)? {
+            return Err(anyhow!("网关 {sender_id} 的消息签名无效"));
+        }
+
+        // 签名通过后才记录 nonce 和公钥，伪造的消息不会占用重放窗口
+        self.record_nonce(sender_id, nonce, envelope.timestamp, now)?;
+        self.known_keys.entry(sender_id).or_insert(fingerprint);
+
+        Ok(OpenedMessage {
+            signer: Some(sender_id),
+            payload: Cow::Owned(payload),
+        })
+    }
+
+    /// 按策略决定是否接受拆开后的消息
+    ///
+    /// # 参数
+    ///
+    /// * `signer` - 经签名确认的发送者
+    /// * `claimed` - 消息内容声明的发送者，经中继转发等不要求与签名者一致的消息为 `None`
+    ///
+    /// # 返回值
+    ///
+    /// 未签名消息在 `Required` 策略下、或声明的发送者与签名者不一致时返回错误
+    pub fn authorize(&self, signer: Option<Uuid>, claimed: Option<Uuid>) -> Result<()> {
+        match (signer, claimed) {
+            (None, _) if self.policy == MessageSigningPolicy::Required => {
+                Err(anyhow!("签名策略要求签名，丢弃未签名的消息"))
+            }
+            (Some(signer), Some(claimed)) if signer != claimed => Err(anyhow!(
+                "消息声明的发送者 {claimed} 与签名者 {signer} 不一致"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 忘记首次使用信任记住的公钥
+    ///
+    /// 网关重置身份后，需要在确认其新证书前调用。已配对设备的固定公钥不受影响。
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    pub fn forget(&self, gateway_id: &Uuid) {
+        if self
+            .signing_identity()
+            .is_some_and(|identity| identity.local_id == *gateway_id)
+        {
+            return;
+        }
+        self.known_keys.remove(gateway_id);
+        self.nonces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(gateway_id);
+    }
+
+    /// 当前用于签名的身份，策略为 `Disabled` 时为 `None`
+    fn signing_identity(&self) -> Option<&SigningIdentity> {
+        self.identity
+            .as_ref()
+            .filter(|_| self.policy != MessageSigningPolicy::Disabled)
+    }
+
+    /// 检查信封中的证书是否与发送者已知或固定的公钥一致
+    fn check_sender_key(&self, sender_id: &Uuid, fingerprint: &str) -> Result<()> {
+        let pinned = self
+            .identity
+            .as_ref()
+            .and_then(|identity| identity.pairing.device(sender_id))
+            .map(|device| device.fingerprint);
+        let expected = pinned.or_else(|| self.known_keys.get(sender_id).map(|key| key.clone()));
+        match expected {
+            Some(expected) if !expected.eq_ignore_ascii_case(fingerprint) => Err(anyhow!(
+                "网关 {sender_id} 的消息由未知密钥签名（证书指纹 {fingerprint}）"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 在滑动窗口中记录 nonce
+    fn record_nonce(
+        &self,
+        sender_id: Uuid,
+        nonce: [u8; ENVELOPE_NONCE_LEN],
+        timestamp: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut nonces = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        let seen = nonces.entry(sender_id).or_default();
+
+        // 时间戳滑出窗口的 nonce 不会再通过时间戳检查，可以安全丢弃
+        let oldest = now - self.replay_window;
+        seen.retain(|_, seen_at| *seen_at >= oldest);
+
+        if seen.contains_key(&nonce) {
+            return Err(anyhow!("网关 {sender_id} 的消息 nonce 重复，疑似重放"));
+        }
+        if seen.len() >= MAX_NONCES_PER_SENDER {
+            return Err(anyhow!("网关 {sender_id} 在重放窗口内发送的消息过多"));
+        }
+        seen.insert(nonce, timestamp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::pairing::TrustedDevice;
+    use crate::gateway::tls::MtlsConfig;
+    use tempfile::tempdir;
+
+    /// 在临时目录中创建网关的签名身份
+    fn authenticator_in(
+        dir: &std::path::Path,
+        local_id: Uuid,
+        policy: MessageSigningPolicy,
+    ) -> Result<MessageAuthenticator> {
+        let tls_manager = Arc::new(TlsManager::new(MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            trusted_ca_dir: dir.join("trusted"),
+            ..Default::default()
+        })?);
+        let pairing = Arc::new(PairingManager::new(
+            dir.join("trusted_devices.json"),
+            tls_manager.ca_fingerprint()?,
+        ));
+        MessageAuthenticator::new(
+            local_id,
+            tls_manager,
+            pairing,
+            policy,
+            DEFAULT_REPLAY_WINDOW,
+        )
+    }
+
+    #[test]
+    fn test_signed_message_round_trip_and_replay() -> Result<()> {
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let (id_a, id_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let a = authenticator_in(dir_a.path(), id_a, MessageSigningPolicy::Required)?;
+        let b = authenticator_in(dir_b.path(), id_b, MessageSigningPolicy::Required)?;
+
+        let sealed = a.seal(b"{\"UnregisterRequest\":{}}")?;
+        let opened = b.open(&sealed)?;
+        assert_eq!(opened.signer, Some(id_a));
+        assert_eq!(opened.payload.as_ref(), b"{\"UnregisterRequest\":{}}");
+        b.authorize(opened.signer, Some(id_a))?;
+
+        // 签名者不能冒充其他网关，同一信封不能重放
+        assert!(b.authorize(opened.signer, Some(id_b)).is_err());
+        assert!(b.open(&sealed).is_err());
+
+        // 未签名消息按策略处理
+        let plain = b.open(b"{}")?;
+        assert_eq!(plain.signer, None);
+        assert!(b.authorize(plain.signer, Some(id_a)).is_err());
+        let permissive = authenticator_in(dir_b.path(), id_b, MessageSigningPolicy::Permissive)?;
+        permissive.authorize(None, Some(id_a))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_or_impersonating_messages_are_rejected() -> Result<()> {
+        let (dir_a, dir_b, dir_c) = (tempdir()?, tempdir()?, tempdir()?);
+        let (id_a, id_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let a = authenticator_in(dir_a.path(), id_a, MessageSigningPolicy::Required)?;
+        let b = authenticator_in(dir_b.path(), id_b, MessageSigningPolicy::Required)?;
+
+        // 篡改消息内容后签名失效
+        let mut envelope: SignedEnvelope = serde_json::from_slice(&a.seal(b"hello")?)?;
+        envelope.payload = BASE64_STANDARD.encode(b"goodbye");
+        assert!(b.open(&serde_json::to_vec(&envelope)?).is_err());
+
+        // 首次见到 A 后记住其公钥，另一把密钥以 A 的身份签名会被拒绝
+        b.open(&a.seal(b"hello")?)?;
+        let impostor = authenticator_in(dir_c.path(), id_a, MessageSigningPolicy::Required)?;
+        assert!(b.open(&impostor.seal(b"hello")?).is_err());
+
+        // 以 B 自己的身份发送的消息同样被拒绝
+        let spoof_b = authenticator_in(dir_c.path(), id_b, MessageSigningPolicy::Required)?;
+        assert!(b.open(&spoof_b.seal(b"hello")?).is_err());
+
+        // 超出重放窗口的消息被拒绝
+        let mut stale: SignedEnvelope = serde_json::from_slice(&a.seal(b"hello")?)?;
+        stale.timestamp -= chrono::Duration::seconds(DEFAULT_REPLAY_WINDOW as i64 * 2);
+        assert!(b.open(&serde_json::to_vec(&stale)?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_key_overrides_first_use() -> Result<()> {
+        let (dir_a, dir_b, dir_c) = (tempdir()?, tempdir()?, tempdir()?);
+        let (id_a, id_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let a = authenticator_in(dir_a.path(), id_a, MessageSigningPolicy::Required)?;
+        let impostor = authenticator_in(dir_c.path(), id_a, MessageSigningPolicy::Required)?;
+
+        // B 已与 A 配对，即使首先收到冒充者的消息也不会接受
+        let b_tls = Arc::new(TlsManager::new(MtlsConfig {
+            ca_cert_path: dir_b.path().join("ca.crt"),
+            server_cert_path: dir_b.path().join("server.crt"),
+            server_key_path: dir_b.path().join("server.key"),
+            client_cert_path: dir_b.path().join("client.crt"),
+            client_key_path: dir_b.path().join("client.key"),
+            trusted_ca_dir: dir_b.path().join("trusted"),
+            ..Default::default()
+        })?);
+        let pairing = Arc::new(PairingManager::new(
+            dir_b.path().join("trusted_devices.json"),
+            b_tls.ca_fingerprint()?,
+        ));
+        let a_certificate = std::fs::read_to_string(dir_a.path().join("ca.crt"))?;
+        pairing.restore(vec![TrustedDevice {
+            gateway_id: id_a,
+            name: "网关 A".to_string(),
+            fingerprint: TlsManager::ca_pem_fingerprint(&a_certificate)?,
+            certificate: a_certificate,
+            paired_at: Utc::now(),
+            leaf_fingerprints: Vec::new(),
+            rotated_at: None,
+            agreement_key: None,
+        }])?;
+        let b = MessageAuthenticator::new(
+            id_b,
+            b_tls,
+            pairing,
+            MessageSigningPolicy::Required,
+            DEFAULT_REPLAY_WINDOW,
+        )?;
+
+        assert!(b.open(&impostor.seal(b"hello")?).is_err());
+        assert_eq!(b.open(&a.seal(b"hello")?)?.signer, Some(id_a));
+
+        Ok(())
+    }
+}
@@ -5,24 +5,91 @@
 //! 增强版本：支持 TLS 1.3 mTLS、zstd 压缩、缓存系统和 IPv6/IPv4 双栈。
 
 use anyhow::{anyhow, Context, Result};
+use base64::prelude::*;
 use chrono::Utc;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tokio::time::{interval, sleep, Duration};
 use uuid::Uuid;
 
-use crate::gateway::{RegistryEntry, UdpBroadcastEvent, UdpToken};
+use crate::gateway::{RegistryEntry, TauriDirectoryEntry, UdpBroadcastEvent, UdpToken};
 use crate::gateway::protocol::WdicMessage;
-use crate::gateway::{MtlsConfig, Registry, UdpBroadcastManager, MountManager};
+use crate::gateway::{
+    MountManager, MountPoint, MountRules, MountStatus, MtlsConfig, Registry, UdpBroadcastManager,
+};
+use crate::gateway::audit::{AuditCategory, AuditEvent, AuditLog, AuditOutcome};
+use crate::gateway::authz::{AuthzDenial, Authorizer, RemoteOperation, RemoteRequest};
 use crate::gateway::cache::GatewayCache;
+use crate::gateway::cert_monitor::{CertificateEvent, CertificateMonitor};
 use crate::gateway::compression::{CompressionConfig, CompressionManager};
+use crate::gateway::crypto::AgreementKeyPair;
+use crate::gateway::envelope::{MessageAuthenticator, MessageSigningPolicy, DEFAULT_REPLAY_WINDOW};
+use crate::gateway::interfaces::{InterfaceChange, InterfacePolicy, DEFAULT_DENY_INTERFACES};
+use crate::gateway::heartbeat::{HeartbeatConfig, HeartbeatScheduler, PeerLiveness};
+use crate::gateway::nat::{HolePunchConfig, HolePunchStats, PathOutcome, RendezvousService};
 use crate::gateway::network::{NetworkEvent, NetworkManager};
+use crate::gateway::peer_store::PeerStore;
 use crate::gateway::performance::PerformanceMonitor;
-use crate::gateway::tls::TlsManager;
+use crate::gateway::protocol::WdicProtocol;
+use crate::gateway::rate_limit::{InboundRateLimiter, RateLimitConfig};
+use crate::gateway::search_token::{SearchTokenSigner, SEARCH_TOKEN_KEY_LABEL};
+use crate::gateway::relay::{
+    self, PeerRoute, RelayConfig, RelayDecision, RelayManager, RelayStats, TrustedKeys,
+};
+use crate::gateway::pairing::{PairingManager, PairingSession, TrustedDevice};
+use crate::gateway::quic::QuicSecurity;
+use crate::gateway::security::{SecureFileReader, SecurityManager};
+use crate::gateway::share::{ShareAccess, ShareManager};
+use crate::gateway::token_cipher::TokenCipher;
+use crate::gateway::tls::{
+    CertificateIdentity, NegotiatedTls, PeerCertificate, Revocation, TlsManager,
+};
+use crate::gateway::transport::DatagramTransport;
+
+/// 吊销事件通道容量
+const REVOCATION_EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// 设备吊销事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationEvent {
+    /// 吊销记录
+    pub revocation: Revocation,
+    /// 被吊销设备已知的 IP 地址
+    pub addresses: Vec<IpAddr>,
+}
+
+/// 会话事件通道容量
+const SESSION_EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// 会话流量和活跃时间的同步间隔（秒）
+const SESSION_SYNC_INTERVAL: u64 = 5;
+
+/// QUIC 会话事件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SessionEvent {
+    /// 握手完成，协商参数符合 TLS 策略且对端通过认证
+    Established {
+        /// 远程地址
+        remote_addr: SocketAddr,
+        /// 对端证书声明的网关 ID
+        gateway_id: Option<Uuid>,
+        /// 对端证书指纹
+        fingerprint: Option<String>,
+        /// 握手协商出的 TLS 参数
+        negotiated: NegotiatedTls,
+    },
+    /// 握手参数不符合 TLS 策略或对端认证失败，连接已关闭
+    Rejected {
+        /// 远程地址
+        remote_addr: SocketAddr,
+        /// 拒绝原因
+        error: String,
+    },
+}
 
 /// 网关配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +102,10 @@ pub struct GatewayConfig {
     pub broadcast_interval: u64,
     /// 心跳间隔（秒）
     pub heartbeat_interval: u64,
+    /// 连续丢失多少次心跳后将网关标记为可疑
+    pub heartbeat_suspect_after: u32,
+    /// 连续丢失多少次心跳后将网关标记为死亡并移除
+    pub heartbeat_dead_after: u32,
     /// 连接超时时间（秒）
     pub connection_timeout: i64,
     /// 注册表清理间隔（秒）
@@ -57,6 +128,62 @@ pub struct GatewayConfig {
     pub save_directory: PathBuf,
     /// TLS 配置
     pub tls_config: MtlsConfig,
+    /// 已知网关持久化文件路径
+    pub peer_store_path: PathBuf,
+    /// 已知网关最长保留时间（秒），超过该时间未被确认的网关将被淘汰
+    pub peer_max_age: i64,
+    /// 为无法直接通信的网关提供中继转发
+    pub enable_relay: bool,
+    /// 中继总带宽上限（字节/秒）
+    pub relay_max_bandwidth: u64,
+    /// 单个源-目标网关对的中继带宽上限（字节/秒）
+    pub relay_max_session_bandwidth: u64,
+    /// 经中继通信时自动尝试经会合网关 UDP 打洞建立直连
+    pub enable_hole_punching: bool,
+    /// 打洞失败后对同一网关再次尝试前的冷却时间（秒）
+    pub hole_punch_cooldown: u64,
+    /// 允许用于发现和监听的接口名称，支持以 `*` 结尾的前缀匹配，为空时不限制
+    pub allowed_interfaces: Vec<String>,
+    /// 排除的接口名称，默认排除常见 VPN 与隧道接口
+    pub denied_interfaces: Vec<String>,
+    /// 允许用于发现和监听的网段（CIDR），为空时不限制；同时限制接受的来源地址
+    pub allowed_cidrs: Vec<String>,
+    /// 排除的网段（CIDR）
+    pub denied_cidrs: Vec<String>,
+    /// 允许在公网地址的接口上广播发现消息
+    pub broadcast_on_public_interfaces: bool,
+    /// 网络接口变化检测间隔（秒）
+    pub interface_scan_interval: u64,
+    /// 受信任设备（已配对网关）持久化文件路径
+    pub trusted_devices_path: PathBuf,
+    /// 证书有效期检查间隔（秒）
+    pub cert_check_interval: u64,
+    /// 证书到期警告阈值（剩余天数），每个阈值只警告一次
+    pub cert_warning_days: Vec<u32>,
+    /// 服务端和客户端证书剩余天数低于该值时由本地 CA 自动重新签发
+    pub cert_renew_before_days: u32,
+    /// 发现与控制消息的签名策略
+    pub message_signing: MessageSigningPolicy,
+    /// 签名消息的重放窗口（秒），时间戳偏差超过该值的消息会被拒绝
+    pub message_replay_window: u64,
+    /// 加密发给已配对网关的 UDP 令牌（文件响应、搜索结果等）
+    pub encrypt_udp_tokens: bool,
+    /// 使用群组密钥加密广播的 UDP 令牌（目录搜索、信息消息），只有已配对网关能读取
+    pub encrypt_broadcast_tokens: bool,
+    /// 审计日志文件路径（JSON Lines，哈希链）
+    pub audit_log_path: PathBuf,
+    /// 会话被强制断开后拒绝对端重连的冷却期（秒），为 0 时不阻止重连
+    pub session_cooldown: u64,
+    /// 入站消息的限速与封禁配置
+    pub rate_limits: RateLimitConfig,
+    /// 搜索令牌吊销列表文件路径
+    pub search_token_revocations_path: PathBuf,
+    /// 挂载点定义持久化文件路径
+    pub mounts_path: PathBuf,
+    /// 目录索引文件保存目录
+    pub index_directory: PathBuf,
+    /// 保存的目录索引在启动时可直接加载的最长时间（秒），超过后重新生成
+    pub index_max_age: u64,
 }
 
 impl Default for GatewayConfig {
@@ -66,6 +193,8 @@ impl Default for GatewayConfig {
             port: 55555,
             broadcast_interval: 30,
             heartbeat_interval: 60,
+            heartbeat_suspect_after: 2,
+            heartbeat_dead_after: 4,
             connection_timeout: 300,
             registry_cleanup_interval: 120,
             enable_ipv6: true,
@@ -77,6 +206,34 @@ impl Default for GatewayConfig {
             cache_cleanup_interval: 300,        // 5 分钟
             save_directory: PathBuf::from("./downloads"),
             tls_config: MtlsConfig::default(),
+            peer_store_path: PathBuf::from("./peers.json"),
+            peer_max_age: 7 * 24 * 3600,         // 7 天
+            enable_relay: false,
+            relay_max_bandwidth: 1024 * 1024,         // 1 MB/s
+            relay_max_session_bandwidth: 256 * 1024,  // 256 KB/s
+            enable_hole_punching: true,
+            hole_punch_cooldown: 60,
+            allowed_interfaces: Vec::new(),
+            denied_interfaces: DEFAULT_DENY_INTERFACES.iter().map(|s| s.to_string()).collect(),
+            allowed_cidrs: Vec::new(),
+            denied_cidrs: Vec::new(),
+            broadcast_on_public_interfaces: false,
+            interface_scan_interval: 10,
+            trusted_devices_path: PathBuf::from("./trusted_devices.json"),
+            cert_check_interval: 3600,
+            cert_warning_days: vec![30, 7, 1],
+            cert_renew_before_days: 14,
+            message_signing: MessageSigningPolicy::default(),
+            message_replay_window: DEFAULT_REPLAY_WINDOW,
+            encrypt_udp_tokens: true,
+            encrypt_broadcast_tokens: false,
+            audit_log_path: PathBuf::from("./audit.jsonl"),
+            session_cooldown: 300,
+            rate_limits: RateLimitConfig::default(),
+            search_token_revocations_path: PathBuf::from("./search_token_revocations.json"),
+            mounts_path: PathBuf::from("./mounts.json"),
+            index_directory: PathBuf::from("./indices"),
+            index_max_age: 24 * 3600,                 // 1 天
         }
     }
 }
@@ -102,6 +259,14 @@ impl GatewayConfig {
             return Err(anyhow!("心跳间隔不能为 0"));
         }
 
+        if self.heartbeat_suspect_after == 0 {
+            return Err(anyhow!("可疑心跳阈值不能为 0"));
+        }
+
+        if self.heartbeat_dead_after <= self.heartbeat_suspect_after {
+            return Err(anyhow!("死亡心跳阈值必须大于可疑心跳阈值"));
+        }
+
         if self.connection_timeout <= 0 {
             return Err(anyhow!("连接超时时间必须大于 0"));
         }
@@ -120,8 +285,133 @@ impl GatewayConfig {
             return Err(anyhow!("缓存目录路径不能为空"));
         }
 
+        // 验证已知网关持久化配置
+        if self.peer_store_path.to_string_lossy().is_empty() {
+            return Err(anyhow!("已知网关文件路径不能为空"));
+        }
+
+        if self.peer_max_age <= 0 {
+            return Err(anyhow!("已知网关保留时间必须大于 0"));
+        }
+
+        // 验证中继配置
+        if self.enable_relay {
+            if self.relay_max_bandwidth == 0 || self.relay_max_session_bandwidth == 0 {
+                return Err(anyhow!("中继带宽上限不能为 0"));
+            }
+
+            if self.relay_max_session_bandwidth > self.relay_max_bandwidth {
+                return Err(anyhow!("单个会话的中继带宽上限不能超过总带宽上限"));
+            }
+        }
+
+        if self.enable_hole_punching && self.hole_punch_cooldown == 0 {
+            return Err(anyhow!("打洞冷却时间不能为 0"));
+        }
+
+        // 验证接口策略
+        self.interface_policy()?;
+
+        // 验证限速配置
+        self.rate_limits.validate()?;
+
+        if self.interface_scan_interval == 0 {
+            return Err(anyhow!("接口检测间隔不能为 0"));
+        }
+
+        if self.trusted_devices_path.to_string_lossy().is_empty() {
+            return Err(anyhow!("受信任设备文件路径不能为空"));
+        }
+
+        if self.search_token_revocations_path.to_string_lossy().is_empty() {
+            return Err(anyhow!("搜索令牌吊销列表路径不能为空"));
+        }
+
+        if self.mounts_path.to_string_lossy().is_empty() {
+            return Err(anyhow!("挂载点文件路径不能为空"));
+        }
+
+        if self.index_directory.to_string_lossy().is_empty() {
+            return Err(anyhow!("目录索引保存目录不能为空"));
+        }
+
+        if self.cert_check_interval == 0 {
+            return Err(anyhow!("证书检查间隔不能为 0"));
+        }
+
+        if self.cert_renew_before_days == 0 {
+            return Err(anyhow!("证书重新签发阈值不能为 0"));
+        }
+
+        if self.message_replay_window == 0 {
+            return Err(anyhow!("重放窗口不能为 0"));
+        }
+
+        if self.encrypt_broadcast_tokens && !self.encrypt_udp_tokens {
+            return Err(anyhow!("加密广播令牌需要同时启用 UDP 令牌加密"));
+        }
+
+        if self.audit_log_path.to_string_lossy().is_empty() {
+            return Err(anyhow!("审计日志文件路径不能为空"));
+        }
+
+        // 验证 TLS 版本与密码套件策略
+        self.tls_config.validate_policy()?;
+
         Ok(())
     }
+
+    /// 根据配置构建网络接口策略
+    ///
+    /// # 返回值
+    ///
+    /// 接口策略，网段格式无效时返回错误
+    pub fn interface_policy(&self) -> Result<InterfacePolicy> {
+        InterfacePolicy::new(
+            &self.allowed_interfaces,
+            &self.denied_interfaces,
+            &self.allowed_cidrs,
+            &self.denied_cidrs,
+            self.broadcast_on_public_interfaces,
+        )
+    }
+}
+
+#[cfg(test)]
+impl GatewayConfig {
+    /// 创建测试配置，所有持久化文件都位于指定目录下
+    ///
+    /// # 参数
+    ///
+    /// * `dir` - 存放持久化文件的目录，通常为测试持有的临时目录
+    /// * `name` - 网关名称
+    ///
+    /// # 返回值
+    ///
+    /// 测试配置
+    pub(crate) fn for_test(dir: &std::path::Path, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            cache_dir: dir.join("cache"),
+            save_directory: dir.join("downloads"),
+            tls_config: MtlsConfig {
+                ca_cert_path: dir.join("certs/ca.crt"),
+                server_cert_path: dir.join("certs/server.crt"),
+                server_key_path: dir.join("certs/server.key"),
+                client_cert_path: dir.join("certs/client.crt"),
+                client_key_path: dir.join("certs/client.key"),
+                trusted_ca_dir: dir.join("certs/trusted"),
+                ..Default::default()
+            },
+            peer_store_path: dir.join("peers.json"),
+            trusted_devices_path: dir.join("trusted_devices.json"),
+            audit_log_path: dir.join("audit.jsonl"),
+            search_token_revocations_path: dir.join("search_token_revocations.json"),
+            mounts_path: dir.join("mounts.json"),
+            index_directory: dir.join("indices"),
+            ..Default::default()
+        }
+    }
 }
 
 /// WDIC 网关
@@ -150,6 +440,36 @@ pub struct Gateway {
     compression_manager: Arc<CompressionManager>,
     /// 挂载管理器
     mount_manager: Arc<MountManager>,
+    /// 文件分享管理器
+    share_manager: Arc<ShareManager>,
+    /// 远程操作授权器
+    authorizer: Arc<Authorizer>,
+    /// 审计日志
+    audit_log: Arc<AuditLog>,
+    /// 已知网关持久化存储
+    peer_store: Arc<PeerStore>,
+    /// 心跳调度器
+    heartbeat_scheduler: Arc<HeartbeatScheduler>,
+    /// 端到端加密密钥对
+    agreement_key: Arc<AgreementKeyPair>,
+    /// 中继管理器（路径选择与中继转发）
+    relay_manager: Arc<RelayManager>,
+    /// 会合服务（启用中继时为其他网关协调 UDP 打洞）
+    rendezvous: Arc<RendezvousService>,
+    /// 设备配对管理器
+    pairing: Arc<PairingManager>,
+    /// 中继端到端加密使用的可信公钥
+    trusted_keys: Arc<TrustedKeys>,
+    /// 证书有效期监控器
+    cert_monitor: Arc<CertificateMonitor>,
+    /// 设备吊销事件发送器
+    revocation_sender: broadcast::Sender<RevocationEvent>,
+    /// 会话事件发送器
+    session_sender: broadcast::Sender<SessionEvent>,
+    /// 会话被强制断开的网关及冷却期截止时间
+    peer_cooldowns: Arc<dashmap::DashMap<Uuid, std::time::Instant>>,
+    /// 入站消息限速器，两个 UDP 监听器共用
+    rate_limiter: Arc<InboundRateLimiter>,
     /// 运行状态
     running: Arc<Mutex<bool>>,
 }
@@ -199,17 +519,64 @@ impl Gateway {
             SocketAddr::from(([0, 0, 0, 0], port))
         };
 
+        // 配置了接口白名单时只绑定到符合策略的接口地址
+        let interface_policy = config.interface_policy()?;
+        let bind_addr = NetworkManager::select_bind_address(local_addr, &interface_policy);
+
         info!(
             "创建网关，启用 IPv6 双栈: {}, 监听地址: {}",
-            config.enable_ipv6, local_addr
+            config.enable_ipv6, bind_addr
         );
 
         // 创建网络管理器（QUIC 协议）
-        let network_manager = Arc::new(NetworkManager::new(local_addr)?);
+        let mut network_manager = NetworkManager::new(bind_addr)?;
+        if interface_policy.is_restricted() {
+            network_manager.enable_auto_rebind(local_addr);
+        }
+
+        Self::with_network_manager(config, network_manager).await
+    }
+
+    /// 使用指定的数据报传输层创建网关实例
+    ///
+    /// 网关的 WDIC 消息全部经由该传输层收发，可用于在进程内通过
+    /// [`MemoryNetwork`](crate::gateway::transport::MemoryNetwork) 组建受控的测试网络。
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 网关配置
+    /// * `transport` - 数据报传输层
+    ///
+    /// # 返回值
+    ///
+    /// 网关实例
+    pub async fn with_transport(
+        config: GatewayConfig,
+        transport: Arc<dyn DatagramTransport>,
+    ) -> Result<Self> {
+        let local_addr = transport.local_addr()?;
+        let network_manager = NetworkManager::with_transport(local_addr, transport)?;
+
+        Self::with_network_manager(config, network_manager).await
+    }
+
+    /// 基于已创建的网络管理器组装网关
+    async fn with_network_manager(
+        config: GatewayConfig,
+        mut network_manager: NetworkManager,
+    ) -> Result<Self> {
+        network_manager.set_hole_punch_config(HolePunchConfig {
+            retry_cooldown: Duration::from_secs(config.hole_punch_cooldown),
+            ..Default::default()
+        });
+        let interface_policy = config.interface_policy()?;
+        network_manager.set_interface_policy(interface_policy.clone());
+        let network_manager = Arc::new(network_manager);
         let actual_addr = network_manager.local_addr();
 
         // 创建 UDP 广播管理器（UDP 协议）
-        // 使用固定端口 55556 以便跨进程发现和连接
+        // 使用固定端口 55556 以便跨进程发现和连接，测试环境中使用 0 以避免冲突
+        let udp_port = if cfg!(test) { 0 } else { 55556 };
         let udp_addr_ip = if config.enable_ipv6 {
             IpAddr::V6(Ipv6Addr::UNSPECIFIED)
         } else {
@@ -217,7 +584,10 @@ impl Gateway {
         };
 
         // 使用新的固定端口UDP管理器
-        let udp_addr = SocketAddr::new(udp_addr_ip, 55556);
+        let udp_addr = NetworkManager::select_bind_address(
+            SocketAddr::new(udp_addr_ip, udp_port),
+            &interface_policy,
+        );
         let udp_broadcast_manager: Arc<UdpBroadcastManager> = match UdpBroadcastManager::new(udp_addr) {
             Ok(mgr) => {
                 info!("UDP 广播管理器成功绑定到地址 {}", udp_addr);
@@ -229,9 +599,38 @@ impl Gateway {
             }
         };
 
+        // 令牌广播同样遵循接口策略
+        Self::sync_udp_broadcast_addresses(&network_manager, &udp_broadcast_manager).await;
+
         // 创建注册表 (lock-free)
         let registry = Arc::new(Registry::new(config.name.clone(), actual_addr));
 
+        // 生成端到端加密密钥对，公钥随注册表条目发布
+        let agreement_key = Arc::new(AgreementKeyPair::generate()?);
+        registry.set_local_public_key(agreement_key.public_key_base64());
+
+        // 加载已知网关并以未验证状态恢复
+        let peer_store_path = config.peer_store_path.clone();
+        let mut peer_store = match PeerStore::load(peer_store_path.clone(), config.peer_max_age) {
+            Ok(store) => store,
+            Err(e) => {
+                warn!("加载已知网关失败，将从空列表开始: {e}");
+                PeerStore::new(peer_store_path, config.peer_max_age)
+            }
+        };
+        match peer_store.local_id() {
+            Some(local_id) => registry.set_local_id(local_id),
+            None => peer_store.set_local_id(registry.local_entry().id),
+        }
+        let restored_gateways = peer_store.restore_into(&registry);
+        let restored_nodes = {
+            let mut nodes = network_manager.discovered_nodes.write().await;
+            peer_store.restore_discovered_nodes(&mut nodes)
+        };
+        if restored_gateways > 0 || restored_nodes > 0 {
+            info!("恢复了 {restored_gateways} 个已知网关和 {restored_nodes} 个发现节点，等待重新探测");
+        }
+
         // 创建性能监控器
         let performance_monitor = Arc::new(PerformanceMonitor::new());
 
@@ -242,8 +641,127 @@ impl Gateway {
             config.max_cache_size,
         )?));
 
-        // 创建 TLS 管理器
-        let tls_manager = Arc::new(TlsManager::new(config.tls_config.clone())?);
+        // 创建 TLS 管理器，证书的主题名称和 SAN 由网关身份与监听接口派生
+        let interface_ips: Vec<IpAddr> = network_manager
+            .permitted_interfaces()
+            .await
+            .into_iter()
+            .map(|interface| interface.ip)
+            .collect();
+        let certificate_identity = CertificateIdentity::for_gateway(
+            &config.name,
+            registry.local_entry().id,
+            &interface_ips,
+        );
+        let tls_manager = Arc::new(TlsManager::with_identity(
+            config.tls_config.clone(),
+            certificate_identity,
+        )?);
+        // TLS 策略无法在 QUIC 上落实时拒绝启动，避免以与配置不符的参数运行
+        tls_manager.check_quic_policy()?;
+        network_manager.set_quic_security(QuicSecurity::new(
+            Arc::clone(&tls_manager),
+            config.enable_mtls,
+        ));
+
+        // 加载已配对的设备，并确保它们的 CA 证书仍在受信任目录中
+        let trusted_devices_path = config.trusted_devices_path.clone();
+        let local_fingerprint = tls_manager.ca_fingerprint()?;
+        let pairing = match PairingManager::load(
+            trusted_devices_path.clone(),
+            local_fingerprint.clone(),
+        ) {
+            Ok(pairing) => pairing,
+            Err(e) => {
+                warn!("加载受信任设备失败，将从空列表开始: {e}");
+                PairingManager::new(trusted_devices_path, local_fingerprint)
+            }
+        };
+        let pairing = Arc::new(pairing.with_agreement_key(agreement_key.public_key_base64()));
+        for device in pairing.devices() {
+            let name = device.gateway_id.to_string();
+            if let Err(e) = tls_manager.trust_ca(&name, &device.certificate) {
+                warn!("恢复受信任设备 '{}' 的证书失败: {e}", device.name);
+            }
+        }
+
+        // 发现与控制消息使用本地 CA 密钥签名，已配对设备按固定指纹验证
+        let authenticator = Arc::new(MessageAuthenticator::new(
+            registry.local_entry().id,
+            Arc::clone(&tls_manager),
+            Arc::clone(&pairing),
+            config.message_signing,
+            config.message_replay_window,
+        )?);
+        network_manager.set_message_authenticator(Arc::clone(&authenticator));
+        udp_broadcast_manager.set_message_authenticator(authenticator);
+
+        // 入站消息按来源地址和网关身份限速，超限过多的来源被临时封禁
+        let rate_limiter = Arc::new(InboundRateLimiter::new(config.rate_limits.clone()));
+        network_manager.set_rate_limiter(Arc::clone(&rate_limiter));
+        udp_broadcast_manager.set_rate_limiter(Arc::clone(&rate_limiter));
+        performance_monitor.set_rate_limiter(Arc::clone(&rate_limiter));
+
+        // 与已配对网关之间的令牌使用注册表中发布的密钥协商公钥加密
+        if config.encrypt_udp_tokens {
+            udp_broadcast_manager.set_token_cipher(Arc::new(TokenCipher::new(
+                Arc::clone(&registry),
+                Arc::clone(&pairing),
+                Arc::clone(&agreement_key),
+                config.encrypt_broadcast_tokens,
+            )?));
+        }
+
+        // 安全相关事件写入哈希链审计日志
+        let audit_log = AuditLog::shared(config.audit_log_path.clone())?;
+
+        // 挂载点与目录索引统一由挂载管理器持有，重启后恢复，路径已消失的挂载点被标记为缺失
+        let (mounts_path, index_directory) =
+            (config.mounts_path.clone(), config.index_directory.clone());
+        let mount_manager = match MountManager::load(
+            mounts_path.clone(),
+            index_directory.clone(),
+            config.index_max_age,
+        ) {
+            Ok(manager) => manager,
+            Err(e) => {
+                warn!("加载挂载点失败，将从空列表开始: {e}");
+                MountManager::with_store(mounts_path, index_directory, config.index_max_age)
+            }
+        };
+        let mount_manager = Arc::new(mount_manager);
+
+        // 搜索令牌使用从 CA 私钥派生的密钥签名，重启后已签发的令牌仍然有效
+        let revocations_path = config.search_token_revocations_path.clone();
+        let token_key = tls_manager.derive_key(SEARCH_TOKEN_KEY_LABEL)?;
+        let local_id = registry.local_entry().id;
+        let token_signer =
+            match SearchTokenSigner::load(revocations_path.clone(), local_id, &token_key) {
+                Ok(signer) => signer,
+                Err(e) => {
+                    warn!("加载搜索令牌吊销列表失败，将从空列表开始: {e}");
+                    SearchTokenSigner::new(local_id, &token_key, Some(revocations_path))
+                }
+            };
+        mount_manager.set_token_signer(Arc::new(token_signer));
+
+        // 其他网关发起的搜索、列目录、读取和传输请求统一经授权器检查
+        let security_manager = Arc::new(SecurityManager::new().await?);
+        security_manager.set_audit_log(Arc::clone(&audit_log));
+        let authorizer = Arc::new(Authorizer::new(
+            Arc::clone(&pairing),
+            Arc::clone(&tls_manager),
+            Arc::clone(&mount_manager),
+            security_manager,
+            Arc::clone(&audit_log),
+        ));
+        let share_manager = Arc::new(ShareManager::new(local_id));
+
+        let cert_monitor = Arc::new(CertificateMonitor::new(
+            Arc::clone(&tls_manager),
+            &config.cert_warning_days,
+            config.cert_renew_before_days,
+        ));
 
         // 创建压缩管理器
         let compression_config = CompressionConfig {
@@ -276,6 +794,34 @@ impl Gateway {
             info!("启用 zstd 数据压缩");
         }
 
+        // 创建心跳调度器
+        let heartbeat_scheduler = Arc::new(HeartbeatScheduler::new(HeartbeatConfig {
+            suspect_after_missed: config.heartbeat_suspect_after,
+            dead_after_missed: config.heartbeat_dead_after,
+        }));
+
+        // 创建中继管理器，直连确认在三个心跳周期内有效
+        let relay_manager = Arc::new(RelayManager::new(RelayConfig {
+            enabled: config.enable_relay,
+            max_bandwidth: config.relay_max_bandwidth,
+            max_session_bandwidth: config.relay_max_session_bandwidth,
+            direct_timeout: Duration::from_secs(config.heartbeat_interval * 3),
+        }));
+        if config.enable_relay {
+            info!(
+                "启用中继转发，总带宽上限 {} 字节/秒，单会话上限 {} 字节/秒",
+                config.relay_max_bandwidth, config.relay_max_session_bandwidth
+            );
+        }
+
+        // 端点记录与网关条目同样在连接超时后失效
+        let rendezvous = Arc::new(RendezvousService::new(Duration::from_secs(
+            config.connection_timeout as u64,
+        )));
+
+        let (revocation_sender, _) = broadcast::channel(REVOCATION_EVENT_CHANNEL_CAPACITY);
+        let (session_sender, _) = broadcast::channel(SESSION_EVENT_CHANNEL_CAPACITY);
+
         Ok(Self {
             config,
             registry,
@@ -285,7 +831,22 @@ impl Gateway {
             cache,
             tls_manager,
             compression_manager,
-            mount_manager: Arc::new(MountManager::new()),
+            mount_manager,
+            share_manager,
+            authorizer,
+            audit_log,
+            peer_store: Arc::new(peer_store),
+            heartbeat_scheduler,
+            agreement_key,
+            relay_manager,
+            rendezvous,
+            trusted_keys: Arc::new(TrustedKeys::new(Arc::clone(&pairing))),
+            pairing,
+            cert_monitor,
+            revocation_sender,
+            session_sender,
+            peer_cooldowns: Arc::new(dashmap::DashMap::new()),
+            rate_limiter,
             running: Arc::new(Mutex::new(false)),
         })
     }
@@ -305,6 +866,21 @@ impl Gateway {
         &self.mount_manager
     }
 
+    /// 获取文件分享管理器
+    pub fn share_manager(&self) -> &Arc<ShareManager> {
+        &self.share_manager
+    }
+
+    /// 获取远程操作授权器
+    pub fn authorizer(&self) -> &Arc<Authorizer> {
+        &self.authorizer
+    }
+
+    /// 获取审计日志
+    pub fn audit_log(&self) -> &Arc<AuditLog> {
+        &self.audit_log
+    }
+
     /// 获取UDP广播地址
     pub fn udp_local_addr(&self) -> SocketAddr {
         self.udp_broadcast_manager.local_addr()
@@ -358,6 +934,11 @@ impl Gateway {
         &self.performance_monitor
     }
 
+    /// 获取入站消息限速器
+    pub fn rate_limiter(&self) -> &Arc<InboundRateLimiter> {
+        &self.rate_limiter
+    }
+
     /// 获取TLS管理器
     pub fn tls_manager(&self) -> &Arc<TlsManager> {
         &self.tls_manager
@@ -368,6 +949,132 @@ impl Gateway {
         &self.compression_manager
     }
 
+    /// 获取已知网关持久化存储
+    pub fn peer_store(&self) -> &Arc<PeerStore> {
+        &self.peer_store
+    }
+
+    /// 获取心跳调度器
+    pub fn heartbeat_scheduler(&self) -> &Arc<HeartbeatScheduler> {
+        &self.heartbeat_scheduler
+    }
+
+    /// 获取中继管理器
+    pub fn relay_manager(&self) -> &Arc<RelayManager> {
+        &self.relay_manager
+    }
+
+    /// 获取设备配对管理器
+    pub fn pairing(&self) -> &Arc<PairingManager> {
+        &self.pairing
+    }
+
+    /// 获取证书有效期监控器
+    pub fn cert_monitor(&self) -> &Arc<CertificateMonitor> {
+        &self.cert_monitor
+    }
+
+    /// 订阅设备吊销事件
+    pub fn subscribe_revocations(&self) -> broadcast::Receiver<RevocationEvent> {
+        self.revocation_sender.subscribe()
+    }
+
+    /// 订阅 QUIC 会话事件
+    pub fn subscribe_sessions(&self) -> broadcast::Receiver<SessionEvent> {
+        self.session_sender.subscribe()
+    }
+
+    /// 强制断开会话
+    ///
+    /// 关闭会话对应的连接，并在配置的冷却期内拒绝该对端重连。
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    ///
+    /// # 返回值
+    ///
+    /// 被断开的会话
+    pub async fn disconnect_session(
+        &self,
+        session_id: &str,
+    ) -> Result<crate::gateway::tauri_api::ActiveSession> {
+        let session = self
+            .authorizer
+            .security_manager()
+            .disconnect_session(session_id)
+            .await?;
+        let cooldown = Duration::from_secs(self.config.session_cooldown);
+
+        if let Some(remote_addr) = session.remote_addr {
+            self.network_manager
+                .disconnect_with_cooldown(remote_addr, cooldown)
+                .await;
+        }
+        if let Some(peer_id) = session
+            .user_id
+            .as_deref()
+            .and_then(|user_id| Uuid::parse_str(user_id).ok())
+        {
+            if !cooldown.is_zero() {
+                self.peer_cooldowns
+                    .insert(peer_id, std::time::Instant::now() + cooldown);
+            }
+            self.registry.remove(&peer_id);
+        }
+
+        info!(
+            "已断开会话 {session_id}，{} 秒内拒绝对端重连",
+            self.config.session_cooldown
+        );
+        Ok(session)
+    }
+
+    /// 检查网关是否处于会话断开后的冷却期，过期的冷却记录随之移除
+    fn peer_in_cooldown(&self, peer_id: &Uuid) -> bool {
+        let Some(until) = self.peer_cooldowns.get(peer_id).map(|entry| *entry) else {
+            return false;
+        };
+        if std::time::Instant::now() < until {
+            return true;
+        }
+        self.peer_cooldowns.remove(peer_id);
+        false
+    }
+
+    /// 为完成握手或注册的对端打开会话
+    fn open_session(
+        &self,
+        remote_addr: SocketAddr,
+        peer_id: Option<Uuid>,
+        fingerprint: Option<String>,
+        tls: Option<NegotiatedTls>,
+    ) {
+        let security_manager = self.authorizer.security_manager();
+        match security_manager.open_session(remote_addr, peer_id, fingerprint, tls) {
+            Ok((session, true)) => {
+                info!("为 {remote_addr} 打开会话 {}", session.session_id);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("无法为 {remote_addr} 打开会话: {e}"),
+        }
+    }
+
+    /// 获取证书吊销列表
+    pub fn revocations(&self) -> Vec<Revocation> {
+        self.tls_manager.revocations()
+    }
+
+    /// 获取 UDP 打洞统计
+    pub fn hole_punch_stats(&self) -> HolePunchStats {
+        self.network_manager.hole_punch_stats()
+    }
+
+    /// 获取中继转发统计
+    pub fn relay_stats(&self) -> RelayStats {
+        self.relay_manager.stats()
+    }
+
     /// 启动网关
     ///
     /// 开始监听网络消息、定期广播和维护注册表。
@@ -404,7 +1111,24 @@ impl Gateway {
         // 启动初始广播
         self.initial_broadcast().await?;
 
+        // 重新探测从磁盘恢复的已知网关
+        self.probe_known_peers().await;
+
         // 启动定期任务
+        self.spawn_background_tasks();
+
+        // 主事件循环
+        self.event_loop(&mut event_receiver, &mut udp_event_receiver)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 启动后台定期任务
+    ///
+    /// 包括定期广播、心跳、注册表清理和缓存清理任务，
+    /// 所有任务在运行状态变为 false 后退出。
+    pub fn spawn_background_tasks(&self) {
         let registry_clone = Arc::clone(&self.registry);
         let network_clone = Arc::clone(&self.network_manager);
         let udp_clone = Arc::clone(&self.udp_broadcast_manager);
@@ -427,11 +1151,20 @@ impl Gateway {
 
         // 注册表清理任务
         let registry_cleanup = Arc::clone(&self.registry);
+        let network_cleanup = Arc::clone(&self.network_manager);
+        let peer_store_cleanup = Arc::clone(&self.peer_store);
         let config_cleanup = self.config.clone();
         let running_cleanup = Arc::clone(&self.running);
 
         tokio::spawn(async move {
-            Self::registry_cleanup_task(registry_cleanup, config_cleanup, running_cleanup).await;
+            Self::registry_cleanup_task(
+                registry_cleanup,
+                network_cleanup,
+                peer_store_cleanup,
+                config_cleanup,
+                running_cleanup,
+            )
+            .await;
         });
 
         // 缓存清理任务
@@ -444,11 +1177,102 @@ impl Gateway {
                 .await;
         });
 
-        // 主事件循环
-        self.event_loop(&mut event_receiver, &mut udp_event_receiver)
-            .await?;
+        // 心跳任务
+        let scheduler_heartbeat = Arc::clone(&self.heartbeat_scheduler);
+        let registry_heartbeat = Arc::clone(&self.registry);
+        let network_heartbeat = Arc::clone(&self.network_manager);
+        let relay_heartbeat = Arc::clone(&self.relay_manager);
+        let key_heartbeat = Arc::clone(&self.agreement_key);
+        let keys_heartbeat = Arc::clone(&self.trusted_keys);
+        let monitor_heartbeat = Arc::clone(&self.performance_monitor);
+        let config_heartbeat = self.config.clone();
+        let running_heartbeat = Arc::clone(&self.running);
 
-        Ok(())
+        tokio::spawn(async move {
+            Self::heartbeat_task(
+                scheduler_heartbeat,
+                registry_heartbeat,
+                network_heartbeat,
+                relay_heartbeat,
+                key_heartbeat,
+                keys_heartbeat,
+                monitor_heartbeat,
+                config_heartbeat,
+                running_heartbeat,
+            )
+            .await;
+        });
+
+        // 网络接口监视任务
+        let registry_interfaces = Arc::clone(&self.registry);
+        let network_interfaces = Arc::clone(&self.network_manager);
+        let udp_interfaces = Arc::clone(&self.udp_broadcast_manager);
+        let config_interfaces = self.config.clone();
+        let running_interfaces = Arc::clone(&self.running);
+
+        tokio::spawn(async move {
+            Self::interface_monitor_task(
+                registry_interfaces,
+                network_interfaces,
+                udp_interfaces,
+                config_interfaces,
+                running_interfaces,
+            )
+            .await;
+        });
+
+        // 证书有效期监控任务
+        let monitor_certificates = Arc::clone(&self.cert_monitor);
+        let tls_certificates = Arc::clone(&self.tls_manager);
+        let registry_certificates = Arc::clone(&self.registry);
+        let network_certificates = Arc::clone(&self.network_manager);
+        let pairing_certificates = Arc::clone(&self.pairing);
+        let config_certificates = self.config.clone();
+        let running_certificates = Arc::clone(&self.running);
+
+        tokio::spawn(async move {
+            Self::certificate_monitor_task(
+                monitor_certificates,
+                tls_certificates,
+                registry_certificates,
+                network_certificates,
+                pairing_certificates,
+                config_certificates,
+                running_certificates,
+            )
+            .await;
+        });
+
+        // 会话同步任务
+        let network_sessions = Arc::clone(&self.network_manager);
+        let authorizer_sessions = Arc::clone(&self.authorizer);
+        let running_sessions = Arc::clone(&self.running);
+
+        tokio::spawn(async move {
+            Self::session_sync_task(network_sessions, authorizer_sessions, running_sessions).await;
+        });
+
+        // 中继路由通告任务（仅在启用中继时运行）
+        if self.config.enable_relay {
+            let registry_relay = Arc::clone(&self.registry);
+            let network_relay = Arc::clone(&self.network_manager);
+            let relay_relay = Arc::clone(&self.relay_manager);
+            let scheduler_relay = Arc::clone(&self.heartbeat_scheduler);
+            let config_relay = self.config.clone();
+            let running_relay = Arc::clone(&self.running);
+
+            tokio::spawn(async move {
+                Self::relay_advertise_task(
+                    registry_relay,
+                    network_relay,
+                    relay_relay,
+                    scheduler_relay,
+                    config_relay,
+                    running_relay,
+                )
+                .await;
+            });
+        }
     }
 
     /// 初始广播
@@ -469,105 +1293,1318 @@ impl Gateway {
         Ok(())
     }
 
-    /// 主事件循环
+    /// 重新探测已知网关
     ///
-    /// 处理网络事件和消息。
-    pub async fn event_loop(
-        &self,
-        event_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<NetworkEvent>,
-        udp_event_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<UdpBroadcastEvent>,
-    ) -> Result<()> {
-        info!("进入主事件循环");
+    /// 向从磁盘恢复的未验证网关的最后已知地址直接发送广播消息，
+    /// 对方的广播响应会将条目转为已验证状态。
+    ///
+    /// # 返回值
+    ///
+    /// 成功发送探测的网关数量
+    pub async fn probe_known_peers(&self) -> usize {
+        let unverified = self.registry.unverified_entries();
+        if unverified.is_empty() {
+            return 0;
+        }
 
-        while *self.running.lock().await {
-            tokio::select! {
-                Some(event) = event_receiver.recv() => {
-                    if let Err(e) = self.handle_network_event(event).await {
-                        error!("处理网络事件时出错: {e}");
-                    }
-                }
-                Some(udp_event) = udp_event_receiver.recv() => {
-                    if let Err(e) = self.handle_udp_event(udp_event).await {
-                        error!("处理 UDP 事件时出错: {e}");
-                    }
-                }
-                _ = sleep(Duration::from_millis(100)) => {
-                    // 定期检查运行状态
-                }
+        let probe_message = WdicMessage::broadcast(self.get_local_entry().await);
+        let mut probed = 0;
+        for entry in unverified {
+            match self.network_manager.send_message(&probe_message, entry.address).await {
+                Ok(()) => probed += 1,
+                Err(e) => debug!("探测已知网关 '{}' ({}) 失败: {e}", entry.name, entry.address),
             }
         }
 
-        info!("事件循环退出");
-        Ok(())
+        info!("向 {probed} 个已知网关发送了重新探测");
+        probed
     }
 
-    /// 处理网络事件
-    async fn handle_network_event(&self, event: NetworkEvent) -> Result<()> {
-        match event {
-            NetworkEvent::MessageReceived { message, sender } => {
-                self.handle_message(message, sender).await?;
-            }
-            NetworkEvent::ConnectionEstablished { remote_addr } => {
-                debug!("建立连接: {remote_addr}");
-            }
-            NetworkEvent::ConnectionLost { remote_addr } => {
-                debug!("连接断开: {remote_addr}");
-                // 清理相关的注册表条目
-                self.cleanup_connection_entry(remote_addr).await?;
-            }
-            NetworkEvent::ConnectionFailed { remote_addr, error } => {
+    /// 主动连接指定地址的网关
+    ///
+    /// 向该地址直接发送广播消息，对方的广播响应会将其加入注册表。
+    ///
+    /// # 参数
+    ///
+    /// * `address` - 目标网关地址
+    ///
+    /// # 返回值
+    ///
+    /// 发送结果
+    pub async fn connect_peer(&self, address: SocketAddr) -> Result<()> {
+        let message = WdicMessage::broadcast(self.get_local_entry().await);
+        self.network_manager
+            .send_message(&message, address)
+            .await
+            .with_context(|| format!("连接网关 {address} 失败"))
+    }
+
+    /// 向注册表中的网关发送消息
+    ///
+    /// 根据可达性自动选择直连或经中继转发，经中继时消息以端到端加密的信封发送。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标网关 ID
+    /// * `message` - 要发送的消息
+    ///
+    /// # 返回值
+    ///
+    /// 实际使用的路径
+    pub async fn send_to_peer(&self, peer_id: &Uuid, message: &WdicMessage) -> Result<PeerRoute> {
+        let target = self
+            .registry
+            .get(peer_id)
+            .ok_or_else(|| anyhow!("网关 {peer_id} 不在注册表中"))?;
+
+        Self::send_routed(
+            &self.registry,
+            &self.network_manager,
+            &self.relay_manager,
+            &self.agreement_key,
+            &self.trusted_keys,
+            &target,
+            message,
+        )
+        .await
+    }
+
+    /// 兑现其他网关的分享链接
+    ///
+    /// 分享请求经 UDP 令牌通道发往分享者，结果以同一请求 ID 的 `FileResponse`、
+    /// `DirectoryListResponse` 或 `Error` 令牌返回。
+    ///
+    /// # 参数
+    ///
+    /// * `link` - 分享链接
+    /// * `password` - 访问密码
+    /// * `path` - 文件夹分享中的相对路径
+    ///
+    /// # 返回值
+    ///
+    /// 请求 ID
+    pub async fn redeem_share(
+        &self,
+        link: &str,
+        password: Option<String>,
+        path: Option<String>,
+    ) -> Result<Uuid> {
+        let (gateway_id, code) = ShareManager::parse_link(link)?;
+        let target = self
+            .registry
+            .get(&gateway_id)
+            .ok_or_else(|| anyhow!("网关 {gateway_id} 不在注册表中"))?;
+
+        let request_id = Uuid::new_v4();
+        let token = UdpToken::ShareRequest {
+            requester_id: self.registry.local_entry().id,
+            code,
+            password,
+            path,
+            request_id,
+        };
+        let address =
+            SocketAddr::new(target.address.ip(), self.udp_broadcast_manager.local_addr().port());
+        self.udp_broadcast_manager
+            .send_token_to_peer(&token, address, gateway_id)
+            .await?;
+
+        info!("已向网关 {gateway_id} 发送分享请求 {request_id}");
+        Ok(request_id)
+    }
+
+    /// 建立到指定网关的直连路径
+    ///
+    /// 已有直连路径时直接返回；仅能经中继到达时以中继网关为会合网关尝试 UDP 打洞，
+    /// 打洞失败则继续使用中继路径。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 最终使用的路径
+    pub async fn establish_direct_path(&self, peer_id: &Uuid) -> Result<PathOutcome> {
+        let target = self
+            .registry
+            .get(peer_id)
+            .ok_or_else(|| anyhow!("网关 {peer_id} 不在注册表中"))?;
+
+        let relay_addr = match self.relay_manager.route(&self.registry, &target) {
+            PeerRoute::Direct(address) => return Ok(PathOutcome::Direct(address)),
+            PeerRoute::Relay { relay_addr, .. } => relay_addr,
+        };
+
+        let local_id = self.registry.local_entry().id;
+        match self
+            .network_manager
+            .hole_punch_via(local_id, *peer_id, relay_addr)
+            .await?
+        {
+            Some(address) => {
+                self.adopt_direct_path(*peer_id, address);
+                Ok(PathOutcome::HolePunched(address))
+            }
+            None => Ok(PathOutcome::Relayed { relay_addr }),
+        }
+    }
+
+    /// 启用打洞建立的直连路径
+    ///
+    /// 以打洞确认的来源地址更新注册表，并将该网关标记为直连可达。
+    fn adopt_direct_path(&self, peer_id: Uuid, address: SocketAddr) {
+        if let Some(mut entry) = self.registry.get(&peer_id) {
+            if entry.address != address {
+                info!("网关 '{}' 的直连地址更新为 {address}", entry.name);
+                entry.address = address;
+                self.registry.add_or_update(entry);
+            }
+        }
+        self.relay_manager.mark_direct(peer_id);
+    }
+
+    /// 向注册表中的网关发起配对
+    ///
+    /// 配对消息只经直连路径发送，对端收到后返回自己的 CA 证书，
+    /// 双方据此显示相同的短验证码。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 新建的配对会话
+    pub async fn start_pairing(&self, peer_id: &Uuid) -> Result<PairingSession> {
+        let target = self
+            .registry
+            .get(peer_id)
+            .ok_or_else(|| anyhow!("网关 {peer_id} 不在注册表中"))?;
+        let certificate = self
+            .tls_manager
+            .ca_certificate_pem()
+            .ok_or_else(|| anyhow!("未找到本地 CA 证书"))?;
+
+        let session = self
+            .pairing
+            .begin(target.id, target.name.clone(), target.address);
+        let local_entry = self.get_local_entry().await;
+        let request = WdicMessage::pair_request(session.session_id, local_entry, certificate);
+        if let Err(e) = self.network_manager.send_message(&request, target.address).await {
+            self.pairing.cancel(session.session_id);
+            return Err(e.context(format!("向网关 '{}' 发送配对请求失败", target.name)));
+        }
+
+        Ok(session)
+    }
+
+    /// 确认或拒绝配对
+    ///
+    /// 用户核对双方显示的短验证码一致后确认；双方都确认后对端被固定为受信任设备，
+    /// 其 CA 证书加入受信任目录。
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 配对会话 ID
+    /// * `accepted` - 验证码是否一致
+    ///
+    /// # 返回值
+    ///
+    /// 双方都已确认时新配对的设备
+    pub async fn confirm_pairing(
+        &self,
+        session_id: Uuid,
+        accepted: bool,
+    ) -> Result<Option<TrustedDevice>> {
+        let local_id = self.registry.local_entry().id;
+
+        if !accepted {
+            let session = self
+                .pairing
+                .cancel(session_id)
+                .ok_or_else(|| anyhow!("配对会话不存在: {session_id}"))?;
+            self.audit_pairing(
+                AuditOutcome::Denied,
+                "reject_pairing",
+                session.peer_id,
+                session.peer_address,
+                &session.peer_name,
+            );
+            self.network_manager
+                .send_message(&WdicMessage::pair_reject(session_id, local_id), session.peer_address)
+                .await?;
+            return Ok(None);
+        }
+
+        let (session, device) = self.pairing.confirm(session_id)?;
+        self.network_manager
+            .send_message(&WdicMessage::pair_confirm(session_id, local_id), session.peer_address)
+            .await?;
+
+        if let Some(device) = &device {
+            self.trust_device(device)?;
+            self.audit_pairing(
+                AuditOutcome::Success,
+                "pair",
+                device.gateway_id,
+                session.peer_address,
+                &device.name,
+            );
+        }
+        Ok(device)
+    }
+
+    /// 重命名受信任设备
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    /// * `name` - 新名称
+    ///
+    /// # 返回值
+    ///
+    /// 更新后的受信任设备
+    pub fn rename_trusted_device(&self, gateway_id: &Uuid, name: String) -> Result<TrustedDevice> {
+        self.pairing.rename(gateway_id, name)
+    }
+
+    /// 撤销受信任设备
+    ///
+    /// 撤销后该设备的 CA 证书移出受信任目录，新的 QUIC 握手将不再信任它，
+    /// 本网关的群组密钥随之轮换。
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 被撤销的设备
+    pub fn revoke_trusted_device(&self, gateway_id: &Uuid) -> Result<TrustedDevice> {
+        let device = self.pairing.revoke(gateway_id)?;
+        self.tls_manager.untrust_ca(&gateway_id.to_string())?;
+        self.audit_log.record(
+            AuditEvent::new(AuditCategory::Pairing, AuditOutcome::Success, "unpair")
+                .peer(*gateway_id)
+                .detail(device.name.clone()),
+        );
+
+        // 轮换群组密钥，被移除的设备无法再解密后续广播
+        if let Some(cipher) = self.udp_broadcast_manager.token_cipher() {
+            cipher.forget(gateway_id);
+            cipher.rotate_group_key()?;
+        }
+        Ok(device)
+    }
+
+    /// 吊销设备
+    ///
+    /// 吊销该设备的 CA 与终端证书，解除配对并立即断开它的连接，
+    /// 随后将签名的吊销通知发送给其他已配对的网关，由它们继续传播。
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 被吊销设备的网关 ID
+    /// * `reason` - 吊销原因
+    ///
+    /// # 返回值
+    ///
+    /// 吊销记录
+    pub async fn revoke_device(&self, gateway_id: Uuid, reason: String) -> Result<Revocation> {
+        let mut fingerprints = Vec::new();
+        if let Some(device) = self.pairing.device(&gateway_id) {
+            fingerprints.push(device.fingerprint);
+            fingerprints.extend(device.leaf_fingerprints);
+        }
+        if let Some(certificate) = self
+            .registry
+            .get(&gateway_id)
+            .and_then(|entry| self.network_manager.peer_certificate(&entry.address))
+        {
+            if !fingerprints.contains(&certificate.fingerprint) {
+                fingerprints.push(certificate.fingerprint);
+            }
+        }
+
+        let revocation = Revocation {
+            gateway_id: Some(gateway_id),
+            fingerprints,
+            reason,
+            revoked_at: Utc::now(),
+            revoked_by: self.registry.local_entry().id,
+        };
+        if !self.apply_revocation(&revocation).await? {
+            return Err(anyhow!("网关 {gateway_id} 已被吊销"));
+        }
+        self.gossip_revocation(&revocation, None).await;
+
+        Ok(revocation)
+    }
+
+    /// 应用吊销记录
+    ///
+    /// 写入吊销列表，解除被覆盖设备的配对，断开其 QUIC 连接并从注册表中移除。
+    ///
+    /// # 返回值
+    ///
+    /// 是否为新的吊销记录
+    async fn apply_revocation(&self, revocation: &Revocation) -> Result<bool> {
+        if !self.tls_manager.revoke(revocation.clone())? {
+            return Ok(false);
+        }
+
+        let mut event = AuditEvent::new(AuditCategory::Revocation, AuditOutcome::Success, "revoke")
+            .detail(format!(
+                "由 {} 吊销: {}（{} 个证书指纹）",
+                revocation.revoked_by,
+                revocation.reason,
+                revocation.fingerprints.len()
+            ));
+        if let Some(gateway_id) = revocation.gateway_id {
+            event = event.peer(gateway_id);
+        }
+        self.audit_log.record(event);
+
+        for device in self.pairing.devices() {
+            if revocation.covers(Some(&device.gateway_id), &[device.fingerprint.as_str()]) {
+                self.revoke_trusted_device(&device.gateway_id)?;
+                info!("已吊销设备 '{}'，解除配对", device.name);
+            }
+        }
+
+        let mut addresses: Vec<IpAddr> = self
+            .network_manager
+            .disconnect_revoked()
+            .await
+            .iter()
+            .map(SocketAddr::ip)
+            .collect();
+        if let Some(entry) = revocation
+            .gateway_id
+            .and_then(|gateway_id| self.registry.get(&gateway_id))
+        {
+            self.network_manager.disconnect(entry.address).await;
+            self.registry.remove(&entry.id);
+            self.trusted_keys.forget(&entry.id);
+            if !addresses.contains(&entry.address.ip()) {
+                addresses.push(entry.address.ip());
+            }
+        }
+
+        // 没有订阅者时发送失败是正常的
+        let _ = self.revocation_sender.send(RevocationEvent {
+            revocation: revocation.clone(),
+            addresses,
+        });
+        Ok(true)
+    }
+
+    /// 向已配对的网关发送签名的吊销通知
+    ///
+    /// # 参数
+    ///
+    /// * `revocation` - 吊销记录
+    /// * `exclude` - 不需要再通知的网关（通常是通知的来源）
+    async fn gossip_revocation(&self, revocation: &Revocation, exclude: Option<Uuid>) {
+        let local_id = self.registry.local_entry().id;
+        let signature = match revocation
+            .signing_payload(&local_id)
+            .and_then(|payload| self.tls_manager.sign_with_ca_key(&payload))
+        {
+            Ok(signature) => BASE64_STANDARD.encode(signature),
+            Err(e) => {
+                warn!("签名证书吊销通知失败: {e}");
+                return;
+            }
+        };
+        let message = WdicMessage::revocation_notice(local_id, revocation.clone(), signature);
+
+        for device in self.pairing.devices() {
+            if Some(device.gateway_id) == exclude {
+                continue;
+            }
+            let Some(entry) = self.registry.get(&device.gateway_id) else {
+                debug!("已配对网关 '{}' 当前不可达，跳过吊销通知", device.name);
+                continue;
+            };
+            if let Err(e) = self.network_manager.send_message(&message, entry.address).await {
+                warn!("向网关 '{}' 发送吊销通知失败: {e}", device.name);
+            }
+        }
+    }
+
+    /// 处理证书吊销通知
+    ///
+    /// 只接受已配对网关的通知，签名必须能用配对时固定的 CA 证书验证；
+    /// 新的吊销记录会继续转发给其他已配对的网关。
+    async fn handle_revocation_notice(
+        &self,
+        sender_id: Uuid,
+        revocation: Revocation,
+        signature: &str,
+    ) -> Result<()> {
+        let device = self
+            .pairing
+            .device(&sender_id)
+            .ok_or_else(|| anyhow!("未配对的网关 {sender_id} 发送了证书吊销通知"))?;
+
+        let payload = revocation.signing_payload(&sender_id)?;
+        let signature = BASE64_STANDARD
+            .decode(signature)
+            .context("证书吊销通知签名格式无效")?;
+        if !TlsManager::verify_ca_signature(&device.certificate, &payload, &signature)? {
+            return Err(anyhow!("网关 '{}' 的证书吊销通知签名无效", device.name));
+        }
+
+        if revocation.gateway_id == Some(self.registry.local_entry().id) {
+            warn!("网关 '{}' 通知本网关已被吊销，忽略", device.name);
+            return Ok(());
+        }
+
+        if self.apply_revocation(&revocation).await? {
+            info!(
+                "网关 '{}' 通知吊销设备 {:?}: {}",
+                device.name, revocation.gateway_id, revocation.reason
+            );
+            self.gossip_revocation(&revocation, Some(sender_id)).await;
+        } else {
+            debug!("忽略已知的吊销记录");
+        }
+        Ok(())
+    }
+
+    /// 记录配对事件
+    fn audit_pairing(
+        &self,
+        outcome: AuditOutcome,
+        action: &str,
+        peer_id: Uuid,
+        address: SocketAddr,
+        name: &str,
+    ) {
+        self.audit_log.record(
+            AuditEvent::new(AuditCategory::Pairing, outcome, action)
+                .peer(peer_id)
+                .address(address)
+                .detail(name),
+        );
+    }
+
+    /// 将已配对设备的 CA 证书加入受信任目录
+    fn trust_device(&self, device: &TrustedDevice) -> Result<()> {
+        self.tls_manager
+            .trust_ca(&device.gateway_id.to_string(), &device.certificate)?;
+        Ok(())
+    }
+
+    /// 按路由表发送消息到指定网关
+    async fn send_routed(
+        registry: &Registry,
+        network_manager: &NetworkManager,
+        relay_manager: &RelayManager,
+        agreement_key: &AgreementKeyPair,
+        trusted_keys: &TrustedKeys,
+        target: &RegistryEntry,
+        message: &WdicMessage,
+    ) -> Result<PeerRoute> {
+        let route = relay_manager.route(registry, target);
+        match route {
+            PeerRoute::Direct(address) => {
+                network_manager.send_message(message, address).await?;
+            }
+            PeerRoute::Relay {
+                relay_id,
+                relay_addr,
+            } => {
+                let public_key = trusted_keys.get(&target.id).ok_or_else(|| {
+                    anyhow!("网关 '{}' 没有可信的公钥，无法经中继加密发送", target.name)
+                })?;
+                let envelope = relay::seal_envelope(
+                    agreement_key,
+                    registry.local_entry().id,
+                    target.id,
+                    &public_key,
+                    relay_id,
+                    message,
+                )?;
+                network_manager.send_message(&envelope, relay_addr).await?;
+                debug!(
+                    "经中继 {relay_addr} 向网关 '{}' 发送 {} 消息",
+                    target.name,
+                    message.message_type()
+                );
+            }
+        }
+        Ok(route)
+    }
+
+    /// 将当前注册表和发现节点写入持久化存储
+    async fn persist_known_peers(
+        registry: &Registry,
+        network_manager: &NetworkManager,
+        peer_store: &PeerStore,
+    ) {
+        peer_store.record_registry(registry);
+        {
+            let nodes = network_manager.discovered_nodes.read().await;
+            peer_store.record_discovered_nodes(&nodes);
+        }
+
+        let pruned = peer_store.prune_expired();
+        if pruned > 0 {
+            info!("淘汰了 {pruned} 个长期未出现的已知网关");
+        }
+
+        peer_store.save_or_warn();
+    }
+
+    /// 主事件循环
+    ///
+    /// 处理网络事件和消息。
+    pub async fn event_loop(
+        &self,
+        event_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<NetworkEvent>,
+        udp_event_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<UdpBroadcastEvent>,
+    ) -> Result<()> {
+        info!("进入主事件循环");
+
+        while *self.running.lock().await {
+            tokio::select! {
+                Some(event) = event_receiver.recv() => {
+                    if let Err(e) = self.handle_network_event(event).await {
+                        error!("处理网络事件时出错: {e}");
+                    }
+                }
+                Some(udp_event) = udp_event_receiver.recv() => {
+                    if let Err(e) = self.handle_udp_event(udp_event).await {
+                        error!("处理 UDP 事件时出错: {e}");
+                    }
+                }
+                _ = sleep(Duration::from_millis(100)) => {
+                    // 定期检查运行状态
+                }
+            }
+        }
+
+        info!("事件循环退出");
+        Ok(())
+    }
+
+    /// 处理网络事件
+    async fn handle_network_event(&self, event: NetworkEvent) -> Result<()> {
+        match event {
+            NetworkEvent::MessageReceived {
+                message,
+                sender,
+                signer,
+            } => {
+                self.handle_message(message, sender, signer).await?;
+            }
+            NetworkEvent::ConnectionEstablished { remote_addr } => {
+                debug!("建立连接: {remote_addr}");
+            }
+            NetworkEvent::ConnectionLost { remote_addr } => {
+                debug!("连接断开: {remote_addr}");
+                self.authorizer
+                    .security_manager()
+                    .close_session_by_addr(remote_addr, "连接断开");
+                // 清理相关的注册表条目
+                self.cleanup_connection_entry(remote_addr).await?;
+            }
+            NetworkEvent::ConnectionFailed { remote_addr, error } => {
                 warn!("连接失败: {} - {}", remote_addr, error);
                 // 记录连接失败但不需要清理
             }
-            NetworkEvent::BroadcastSent { message } => {
-                debug!("广播消息发送完成: {}", message.message_type());
+            NetworkEvent::BroadcastSent { message } => {
+                debug!("广播消息发送完成: {}", message.message_type());
+            }
+            NetworkEvent::NetworkError { error } => {
+                warn!("网络错误: {error}");
+            }
+            NetworkEvent::HolePunchSucceeded {
+                session_id,
+                peer_id,
+                address,
+            } => {
+                debug!("打洞会话 {session_id} 建立了到网关 {peer_id} 的直连");
+                self.adopt_direct_path(peer_id, address);
+            }
+            NetworkEvent::HolePunchFailed {
+                session_id,
+                peer_id,
+            } => {
+                info!("与网关 {peer_id} 的打洞会话 {session_id} 失败，继续经中继通信");
+            }
+            NetworkEvent::PeerAuthenticated {
+                remote_addr,
+                certificate,
+                negotiated,
+            } => {
+                info!(
+                    "QUIC 对端 {remote_addr} 握手完成: {}, 可能的密码套件 {}",
+                    negotiated.tls_version.as_str(),
+                    negotiated.permitted_cipher_suites.join(":")
+                );
+                if let Some(certificate) = &certificate {
+                    self.log_peer_certificate(remote_addr, certificate);
+                }
+                self.open_session(
+                    remote_addr,
+                    certificate.as_ref().and_then(|c| c.gateway_id),
+                    certificate.as_ref().map(|c| c.fingerprint.clone()),
+                    Some(negotiated.clone()),
+                );
+                let _ = self.session_sender.send(SessionEvent::Established {
+                    remote_addr,
+                    gateway_id: certificate.as_ref().and_then(|c| c.gateway_id),
+                    fingerprint: certificate.map(|c| c.fingerprint),
+                    negotiated,
+                });
+            }
+            NetworkEvent::PeerAuthenticationFailed { remote_addr, error } => {
+                warn!("QUIC 对端 {remote_addr} 握手被拒绝，已断开: {error}");
+                let _ = self
+                    .session_sender
+                    .send(SessionEvent::Rejected { remote_addr, error });
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录 QUIC 对端证书声明的网关身份
+    fn log_peer_certificate(&self, remote_addr: SocketAddr, certificate: &PeerCertificate) {
+        match certificate.gateway_id {
+            Some(gateway_id) if self.pairing.is_trusted(&gateway_id) => {
+                // 已配对的网关必须出示由配对时固定的 CA 签发的证书
+                let pinned = certificate
+                    .issuer_fingerprint
+                    .as_deref()
+                    .is_some_and(|issuer| self.pairing.matches_pinned(&gateway_id, issuer));
+                if pinned {
+                    info!("QUIC 对端 {remote_addr} 认证为已配对网关 {gateway_id}");
+                } else {
+                    warn!(
+                        "QUIC 对端 {remote_addr} 声明为网关 {gateway_id}，但证书不是由配对时固定的 CA 签发"
+                    );
+                }
+            }
+            Some(gateway_id) => info!(
+                "QUIC 对端 {remote_addr} 认证为网关 {gateway_id}，证书指纹 {}",
+                certificate.fingerprint
+            ),
+            None => warn!(
+                "QUIC 对端 {remote_addr} 的证书 {} 未声明网关身份",
+                certificate.fingerprint
+            ),
+        }
+    }
+
+    /// 处理接收到的消息
+    async fn handle_message(
+        &self,
+        message: WdicMessage,
+        sender: SocketAddr,
+        signer: Option<Uuid>,
+    ) -> Result<()> {
+        debug!("处理来自 {sender} 的 {} 消息", message.message_type());
+
+        if let WdicMessage::RelayEnvelope {
+            source_id,
+            target_id,
+            relay_id,
+            source_public_key,
+            payload,
+        } = message
+        {
+            return self
+                .handle_relay_envelope(
+                    source_id,
+                    target_id,
+                    relay_id,
+                    source_public_key,
+                    payload,
+                    sender,
+                )
+                .await;
+        }
+
+        // 丢弃已吊销网关的消息，直接收到的消息证明与发送者之间存在直连路径
+        if let Some(sender_id) = message.sender_id() {
+            if self.tls_manager.is_gateway_revoked(&sender_id) {
+                debug!("丢弃已吊销网关 {sender_id} 的 {} 消息", message.message_type());
+                return Ok(());
+            }
+            if self.peer_in_cooldown(&sender_id) {
+                debug!("丢弃冷却期内网关 {sender_id} 的 {} 消息", message.message_type());
+                return Ok(());
+            }
+            self.relay_manager.mark_direct(sender_id);
+        }
+
+        // NAT 穿透消息只在直连路径上处理
+        if Self::is_traversal_message(&message) {
+            return self.handle_traversal_message(message, sender).await;
+        }
+
+        // 配对消息同样只在直连路径上处理
+        if Self::is_pairing_message(&message) {
+            if let Err(e) = self.handle_pairing_message(message, sender).await {
+                warn!("处理来自 {sender} 的配对消息失败: {e}");
+            }
+            return Ok(());
+        }
+
+        let message = Self::with_observed_address(message, sender);
+        if let Some(response) = self.process_message(message, sender, signer).await? {
+            self.network_manager
+                .reply_message(&response, sender)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 用观察到的发送地址替换网关自报的地址
+    ///
+    /// 网关自报的可能是未指定地址或 NAT 内部地址，而直接收到的消息来源
+    /// 就是可回复的端点（位于 NAT 之后时为公网映射端点），以此作为后续直连、
+    /// 中继转发和打洞的地址。
+    fn with_observed_address(mut message: WdicMessage, sender: SocketAddr) -> WdicMessage {
+        if let WdicMessage::Broadcast { sender: entry }
+        | WdicMessage::BroadcastResponse { sender: entry, .. }
+        | WdicMessage::RegisterRequest { gateway: entry }
+        | WdicMessage::RelayRoutes { relay: entry, .. } = &mut message
+        {
+            if entry.address != sender {
+                debug!(
+                    "网关 '{}' 自报地址 {} 与观察到的 {sender} 不同",
+                    entry.name, entry.address
+                );
+                entry.address = sender;
+            }
+        }
+        message
+    }
+
+    /// 判断是否为 NAT 穿透消息
+    fn is_traversal_message(message: &WdicMessage) -> bool {
+        matches!(
+            message,
+            WdicMessage::EndpointReport { .. }
+                | WdicMessage::EndpointObserved { .. }
+                | WdicMessage::PunchRequest { .. }
+                | WdicMessage::PunchInstruction { .. }
+                | WdicMessage::PunchProbe { .. }
+                | WdicMessage::PunchAck { .. }
+        )
+    }
+
+    /// 判断是否为配对消息
+    fn is_pairing_message(message: &WdicMessage) -> bool {
+        matches!(
+            message,
+            WdicMessage::PairRequest { .. }
+                | WdicMessage::PairResponse { .. }
+                | WdicMessage::PairConfirm { .. }
+                | WdicMessage::PairReject { .. }
+        )
+    }
+
+    /// 处理配对消息
+    async fn handle_pairing_message(&self, message: WdicMessage, sender: SocketAddr) -> Result<()> {
+        match message {
+            WdicMessage::PairRequest {
+                session_id,
+                sender: sender_entry,
+                certificate,
+            } => {
+                let local_certificate = self
+                    .tls_manager
+                    .ca_certificate_pem()
+                    .ok_or_else(|| anyhow!("未找到本地 CA 证书"))?;
+                self.pairing.accept_request(
+                    session_id,
+                    sender_entry.id,
+                    sender_entry.name,
+                    sender,
+                    certificate,
+                    sender_entry.public_key,
+                )?;
+                let response = WdicMessage::pair_response(
+                    session_id,
+                    self.registry.local_entry().id,
+                    local_certificate,
+                    self.pairing.local_agreement_key().map(str::to_string),
+                );
+                self.network_manager.reply_message(&response, sender).await?;
+            }
+            WdicMessage::PairResponse {
+                session_id,
+                sender_id,
+                certificate,
+                agreement_key,
+            } => {
+                self.pairing
+                    .accept_response(session_id, sender_id, certificate, agreement_key)?;
+            }
+            WdicMessage::PairConfirm {
+                session_id,
+                sender_id,
+            } => {
+                if let Some(device) = self.pairing.remote_confirm(session_id, sender_id)? {
+                    self.trust_device(&device)?;
+                    self.audit_pairing(
+                        AuditOutcome::Success,
+                        "pair",
+                        device.gateway_id,
+                        sender,
+                        &device.name,
+                    );
+                }
             }
-            NetworkEvent::NetworkError { error } => {
-                warn!("网络错误: {error}");
+            WdicMessage::PairReject {
+                session_id,
+                sender_id,
+            } if self
+                .pairing
+                .session(&session_id)
+                .is_some_and(|session| session.peer_id == sender_id) =>
+            {
+                self.pairing.cancel(session_id);
             }
+            _ => {}
         }
         Ok(())
     }
 
-    /// 处理接收到的消息
-    async fn handle_message(&self, message: WdicMessage, sender: SocketAddr) -> Result<()> {
-        debug!("处理来自 {sender} 的 {} 消息", message.message_type());
+    /// 处理 NAT 穿透消息
+    ///
+    /// 启用中继的网关同时担任会合网关，记录端点上报并协调打洞；
+    /// 其余网关只接受来自直连会合网关的观察结果和打洞指令。
+    async fn handle_traversal_message(
+        &self,
+        message: WdicMessage,
+        sender: SocketAddr,
+    ) -> Result<()> {
+        let local_id = self.registry.local_entry().id;
 
         match message {
+            WdicMessage::EndpointReport {
+                sender_id,
+                local_endpoints,
+            } => {
+                if !self.relay_manager.is_enabled() || !self.is_known_at(&sender_id, sender) {
+                    debug!("忽略来自 {sender} 的端点上报");
+                    return Ok(());
+                }
+                self.rendezvous.record(sender_id, local_endpoints);
+                self.network_manager
+                    .reply_message(&WdicMessage::endpoint_observed(local_id, sender), sender)
+                    .await?;
+            }
+            WdicMessage::EndpointObserved {
+                sender_id,
+                observed,
+            } if self.is_known_at(&sender_id, sender) => {
+                self.network_manager.set_public_endpoint(observed);
+            }
+            WdicMessage::PunchRequest {
+                session_id,
+                requester_id,
+                target_id,
+            } => {
+                self.handle_punch_request(session_id, requester_id, target_id, sender)
+                    .await?;
+            }
+            WdicMessage::PunchInstruction {
+                session_id,
+                sender_id,
+                peer_id,
+                candidates,
+            } => {
+                if !self.is_known_at(&sender_id, sender) {
+                    warn!("忽略来自未知会合网关 {sender} 的打洞指令");
+                    return Ok(());
+                }
+                self.network_manager
+                    .begin_hole_punch(session_id, local_id, peer_id, candidates);
+            }
+            WdicMessage::PunchProbe {
+                session_id,
+                sender_id,
+            } => {
+                self.network_manager
+                    .handle_punch_probe(session_id, sender_id, local_id, sender)?;
+            }
+            WdicMessage::PunchAck {
+                session_id,
+                sender_id,
+            } => {
+                self.network_manager
+                    .handle_punch_ack(session_id, sender_id, sender);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 检查网关是否已在注册表中且地址与消息来源一致
+    fn is_known_at(&self, gateway_id: &Uuid, sender: SocketAddr) -> bool {
+        self.registry
+            .get(gateway_id)
+            .is_some_and(|entry| entry.is_verified() && entry.address == sender)
+    }
+
+    /// 作为会合网关处理打洞请求
+    ///
+    /// 请求者与目标都必须与本网关直连，指令同时下发给双方，
+    /// 各自携带对端的候选端点。
+    async fn handle_punch_request(
+        &self,
+        session_id: Uuid,
+        requester_id: Uuid,
+        target_id: Uuid,
+        sender: SocketAddr,
+    ) -> Result<()> {
+        if !self.relay_manager.is_enabled() {
+            let error = WdicMessage::error(
+                relay::RELAY_ERROR_DISABLED,
+                "本网关未提供会合服务".to_string(),
+            );
+            return self.network_manager.reply_message(&error, sender).await;
+        }
+
+        let requester = self
+            .registry
+            .get(&requester_id)
+            .filter(|entry| entry.is_verified() && entry.address == sender);
+        let Some(requester) = requester else {
+            let error = WdicMessage::error(
+                relay::RELAY_ERROR_UNKNOWN_SOURCE,
+                "打洞请求者未注册".to_string(),
+            );
+            return self.network_manager.reply_message(&error, sender).await;
+        };
+
+        let target = self
+            .registry
+            .get(&target_id)
+            .filter(|entry| entry.is_verified() && self.relay_manager.is_direct(&entry.id));
+        let Some(target) = target else {
+            let error = WdicMessage::error(
+                relay::RELAY_ERROR_UNREACHABLE,
+                format!("打洞目标 {target_id} 不可达"),
+            );
+            return self.network_manager.reply_message(&error, sender).await;
+        };
+
+        let local_id = self.registry.local_entry().id;
+        let to_requester = WdicMessage::punch_instruction(
+            session_id,
+            local_id,
+            target.id,
+            self.rendezvous.candidates(&target),
+        );
+        let to_target = WdicMessage::punch_instruction(
+            session_id,
+            local_id,
+            requester.id,
+            self.rendezvous.candidates(&requester),
+        );
+
+        // 先通知目标再回复请求者，使双方的探测尽量同时开始
+        self.network_manager
+            .send_message(&to_target, target.address)
+            .await?;
+        self.network_manager
+            .reply_message(&to_requester, sender)
+            .await?;
+        info!("协调网关 '{}' 与 '{}' 打洞", requester.name, target.name);
+
+        Ok(())
+    }
+
+    /// 执行消息的处理逻辑
+    ///
+    /// 直接收到的消息与经中继解密的内层消息共用该逻辑，
+    /// 返回的响应由调用方沿消息到达的路径送回。
+    async fn process_message(
+        &self,
+        message: WdicMessage,
+        sender: SocketAddr,
+        signer: Option<Uuid>,
+    ) -> Result<Option<WdicMessage>> {
+        let response = match message {
             WdicMessage::Broadcast {
                 sender: sender_entry,
             } => {
-                self.handle_broadcast_message(sender_entry, sender).await?;
+                self.trusted_keys.record_announced(signer, &sender_entry);
+                Some(self.handle_broadcast_message(sender_entry, sender).await)
             }
             WdicMessage::BroadcastResponse {
                 sender: sender_entry,
                 gateways,
             } => {
+                self.trusted_keys.record_announced(signer, &sender_entry);
                 self.handle_broadcast_response(sender_entry, gateways)
                     .await?;
+                None
             }
-            WdicMessage::Heartbeat { sender_id, .. } => {
-                self.handle_heartbeat(sender_id, sender).await?;
+            WdicMessage::Heartbeat {
+                sender_id,
+                sequence,
+                ..
+            } => Some(self.handle_heartbeat(sender_id, sequence, sender).await),
+            WdicMessage::HeartbeatResponse {
+                sender_id,
+                sequence,
+                ..
+            } => {
+                self.handle_heartbeat_response(sender_id, sequence).await;
+                None
             }
             WdicMessage::RegisterRequest { gateway } => {
-                self.handle_register_request(gateway, sender).await?;
+                self.trusted_keys.record_announced(signer, &gateway);
+                Some(self.handle_register_request(gateway).await)
             }
             WdicMessage::QueryGateways { requester_id } => {
-                self.handle_query_gateways(requester_id, sender).await?;
+                Some(self.handle_query_gateways(requester_id).await)
+            }
+            WdicMessage::RelayRoutes { relay, reachable } => {
+                self.handle_relay_routes(relay, reachable).await;
+                None
+            }
+            WdicMessage::Error { code, message } => {
+                warn!("收到来自 {sender} 的错误消息 ({code}): {message}");
+                None
+            }
+            WdicMessage::FileTransferTokenRequest {
+                transfer_id,
+                file_metadata,
+                sender_info,
+            } => {
+                let request = RemoteRequest {
+                    peer_id: sender_info.id,
+                    signer,
+                    sender,
+                    operation: RemoteOperation::Transfer,
+                    path: Some(&file_metadata.filename),
+                    search_token: None,
+                };
+                let result = self.authorizer.authorize(&request);
+                let (outcome, detail) = match &result {
+                    Ok(()) => (
+                        AuditOutcome::Allowed,
+                        format!("{} 字节", file_metadata.file_size),
+                    ),
+                    Err(denial) => (AuditOutcome::Denied, denial.to_string()),
+                };
+                self.audit_log.record(
+                    AuditEvent::new(AuditCategory::Transfer, outcome, "transfer_request")
+                        .peer(sender_info.id)
+                        .address(sender)
+                        .target(file_metadata.filename.clone())
+                        .detail(format!("传输 {transfer_id}: {detail}")),
+                );
+                match result {
+                    Ok(()) => {
+                        info!(
+                            "来自 '{}' 的文件传输请求 {transfer_id} 已通过授权: {}",
+                            sender_info.name, file_metadata.filename
+                        );
+                        None
+                    }
+                    Err(denial) => {
+                        warn!(
+                            "拒绝来自 '{}' 的文件传输请求 {transfer_id}: {denial}",
+                            sender_info.name
+                        );
+                        Some(denial.to_message())
+                    }
+                }
+            }
+            WdicMessage::CertificateRotated {
+                sender_id,
+                fingerprints,
+                timestamp,
+                signature,
+            } => {
+                if let Err(e) =
+                    self.handle_certificate_rotated(sender_id, fingerprints, timestamp, &signature)
+                {
+                    warn!("处理来自 {sender} 的证书轮换通知失败: {e}");
+                }
+                None
+            }
+            WdicMessage::RevocationNotice {
+                sender_id,
+                revocation,
+                signature,
+            } => {
+                if let Err(e) = self
+                    .handle_revocation_notice(sender_id, revocation, &signature)
+                    .await
+                {
+                    warn!("处理来自 {sender} 的证书吊销通知失败: {e}");
+                }
+                None
             }
             _ => {
                 debug!("忽略消息类型: {}", message.message_type());
+                None
+            }
+        };
+
+        Ok(response)
+    }
+
+    /// 处理中继信封
+    ///
+    /// 目标为本网关时解密并处理内层消息，响应沿原中继返回；
+    /// 指定本网关为中继时按可达性和带宽上限转发；其余信封忽略。
+    async fn handle_relay_envelope(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+        relay_id: Uuid,
+        source_public_key: String,
+        payload: Vec<u8>,
+        sender: SocketAddr,
+    ) -> Result<()> {
+        let local_id = self.registry.local_entry().id;
+
+        if target_id == local_id {
+            // 信封公钥必须与源网关的可信公钥一致，防止中继替换公钥
+            let trusted_key = self.trusted_keys.get(&source_id);
+            if trusted_key.as_deref() != Some(source_public_key.as_str()) {
+                warn!("来自 {source_id} 的中继信封公钥未与其可信身份绑定，已丢弃");
+                return Ok(());
+            }
+
+            let inner = relay::open_envelope(
+                &self.agreement_key,
+                source_id,
+                target_id,
+                &source_public_key,
+                &payload,
+            )?;
+            WdicProtocol::new().validate_message(&inner)?;
+            debug!("收到经 {sender} 中继的 {} 消息", inner.message_type());
+
+            // 内层消息由源网关的可信公钥加密，视为由源网关签名
+            if let Some(response) = self.process_message(inner, sender, Some(source_id)).await? {
+                let reply = relay::seal_envelope(
+                    &self.agreement_key,
+                    local_id,
+                    source_id,
+                    &source_public_key,
+                    relay_id,
+                    &response,
+                )?;
+                self.network_manager.send_message(&reply, sender).await?;
+            }
+            return Ok(());
+        }
+
+        if relay_id != local_id {
+            debug!("忽略不经由本网关转发的中继信封: {source_id} -> {target_id}");
+            return Ok(());
+        }
+
+        let decision = self.relay_manager.decide(
+            &self.registry,
+            &self.heartbeat_scheduler,
+            source_id,
+            target_id,
+            sender,
+            payload.len(),
+        );
+        match decision {
+            RelayDecision::Forward(target_addr) => {
+                let envelope = WdicMessage::relay_envelope(
+                    source_id,
+                    target_id,
+                    relay_id,
+                    source_public_key,
+                    payload,
+                );
+                self.network_manager
+                    .send_message(&envelope, target_addr)
+                    .await?;
+                debug!("中继转发信封: {source_id} -> {target_id} ({target_addr})");
+            }
+            RelayDecision::Reject { code, reason } => {
+                warn!("拒绝中继转发 {source_id} -> {target_id}: {reason}");
+                self.network_manager
+                    .reply_message(&WdicMessage::error(code, reason), sender)
+                    .await?;
+            }
+            RelayDecision::RateLimited => {
+                debug!("中继带宽已达上限，丢弃 {source_id} -> {target_id} 的信封");
             }
         }
 
         Ok(())
     }
 
+    /// 处理中继路由通告
+    ///
+    /// 只接受直接收到的通告，将其中的网关加入注册表并记录经该中继的路由，
+    /// 同时向中继上报本地端点，以便之后经其协调打洞。
+    async fn handle_relay_routes(&self, relay: RegistryEntry, reachable: Vec<RegistryEntry>) {
+        if !self.relay_manager.is_direct(&relay.id) {
+            debug!("忽略非直连中继 '{}' 的路由通告", relay.name);
+            return;
+        }
+
+        let local_id = self.registry.local_entry().id;
+        let mut targets = Vec::with_capacity(reachable.len());
+        for mut entry in reachable {
+            if entry.id == local_id {
+                continue;
+            }
+            targets.push(entry.id);
+
+            // 中继通告的公钥仅在与配对时固定的公钥一致时保留
+            let pinned = self.pairing.pinned_agreement_key(&entry.id);
+            if entry.public_key.is_some() && entry.public_key != pinned {
+                debug!(
+                    "中继 '{}' 通告的网关 '{}' 公钥未与配对身份绑定，已忽略",
+                    relay.name, entry.name
+                );
+            }
+            entry.public_key = pinned;
+
+            // 已知网关的地址和公钥不会被中继通告覆盖
+            if self.registry.add_unverified(entry.clone()) {
+                info!("经中继 '{}' 发现新网关: '{}'", relay.name, entry.name);
+            }
+        }
+
+        debug!("中继 '{}' 通告了 {} 个可达网关", relay.name, targets.len());
+        self.relay_manager.learn_routes(relay.id, &targets);
+
+        // 即使关闭了自动打洞也上报端点，显式建立直连时会合网关同样需要它们
+        if !targets.is_empty() {
+            let report =
+                WdicMessage::endpoint_report(local_id, self.network_manager.local_candidates());
+            if let Err(e) = self.network_manager.send_message(&report, relay.address).await {
+                debug!("向会合网关 '{}' 上报端点失败: {e}", relay.name);
+            }
+        }
+    }
+
     /// 处理 UDP 广播事件
     async fn handle_udp_event(&self, event: UdpBroadcastEvent) -> Result<()> {
         match event {
-            UdpBroadcastEvent::TokenReceived { token, sender } => {
-                self.handle_udp_token(token, sender).await?;
+            UdpBroadcastEvent::TokenReceived {
+                token,
+                sender,
+                signer,
+            } => {
+                self.handle_udp_token(token, sender, signer).await?;
             }
             UdpBroadcastEvent::BroadcastSent { token, sent_count } => {
                 debug!(
@@ -582,7 +2619,12 @@ impl Gateway {
     }
 
     /// 处理 UDP 令牌
-    async fn handle_udp_token(&self, token: UdpToken, sender: SocketAddr) -> Result<()> {
+    async fn handle_udp_token(
+        &self,
+        token: UdpToken,
+        sender: SocketAddr,
+        signer: Option<Uuid>,
+    ) -> Result<()> {
         debug!("处理来自 {sender} 的 UDP 令牌: {token:?}");
 
         match token {
@@ -590,8 +2632,17 @@ impl Gateway {
                 searcher_id,
                 keywords,
                 search_id,
+                search_token,
             } => {
-                self.handle_directory_search(searcher_id, keywords, search_id, sender)
+                let request = RemoteRequest {
+                    peer_id: searcher_id,
+                    signer,
+                    sender,
+                    operation: RemoteOperation::Search,
+                    path: None,
+                    search_token: search_token.as_deref(),
+                };
+                self.handle_directory_search(&request, keywords, search_id)
                     .await?;
             }
             UdpToken::DirectorySearchResponse {
@@ -608,10 +2659,71 @@ impl Gateway {
                 requester_id,
                 file_path,
                 request_id,
+                search_token,
             } => {
-                self.handle_file_request(requester_id, file_path, request_id, sender)
+                let request = RemoteRequest {
+                    peer_id: requester_id,
+                    signer,
+                    sender,
+                    operation: RemoteOperation::Read,
+                    path: Some(&file_path),
+                    search_token: search_token.as_deref(),
+                };
+                self.handle_file_request(&request, &file_path, request_id)
                     .await?;
             }
+            UdpToken::DirectoryList {
+                requester_id,
+                path,
+                request_id,
+                search_token,
+            } => {
+                let request = RemoteRequest {
+                    peer_id: requester_id,
+                    signer,
+                    sender,
+                    operation: RemoteOperation::List,
+                    path: Some(&path),
+                    search_token: search_token.as_deref(),
+                };
+                self.handle_directory_list(&request, &path, request_id)
+                    .await?;
+            }
+            UdpToken::ShareRequest {
+                requester_id,
+                code,
+                password,
+                path,
+                request_id,
+            } => {
+                self.handle_share_request(
+                    requester_id,
+                    sender,
+                    &code,
+                    password.as_deref(),
+                    path.as_deref(),
+                    request_id,
+                )
+                .await?;
+            }
+            UdpToken::DirectoryListResponse {
+                responder_id,
+                request_id,
+                entries,
+            } => {
+                info!(
+                    "收到来自 {responder_id} 的列目录响应，请求 ID: {request_id}，{} 个条目",
+                    entries.len()
+                );
+            }
+            UdpToken::Error {
+                responder_id,
+                request_id,
+                code,
+                message,
+            } => {
+                warn!("请求 {request_id} 被 {responder_id} 拒绝 ({code}): {message}");
+            }
             UdpToken::FileResponse {
                 responder_id,
                 request_id,
@@ -646,28 +2758,36 @@ impl Gateway {
                     "收到来自 {tester_id} 的性能测试: 类型={test_type}, 数据大小={data_size} 字节"
                 );
             }
+            UdpToken::GroupKeyRequest { .. } | UdpToken::GroupKey { .. } => {
+                // 群组密钥令牌由 UDP 广播管理器直接处理，不会上报到这里
+                debug!("忽略来自 {sender} 的群组密钥令牌");
+            }
         }
 
         Ok(())
     }
 
     /// 处理目录搜索请求 - 性能优化版本
+    ///
+    /// 只返回请求者有权读取的匹配结果。
     async fn handle_directory_search(
         &self,
-        searcher_id: uuid::Uuid,
+        request: &RemoteRequest<'_>,
         keywords: smallvec::SmallVec<[String; 4]>,
         search_id: uuid::Uuid,
-        sender: SocketAddr,
     ) -> Result<()> {
+        let searcher_id = request.peer_id;
+        if let Err(denial) = self.authorizer.authorize(request) {
+            return self.send_denial(request, search_id, denial).await;
+        }
+
         info!(
             "处理来自 {searcher_id} 的目录搜索请求，关键词: {keywords:?}"
         );
 
-        let matches: smallvec::SmallVec<[String; 8]> = self
-            .udp_broadcast_manager
-            .search_files(&keywords)
-            .await
-            .into();
+        let matches = self.mount_manager.search_files(&keywords);
+        let matches: smallvec::SmallVec<[String; 8]> =
+            self.authorizer.filter_paths(request, matches).into();
 
         let response_token = UdpToken::DirectorySearchResponse {
             responder_id: self.get_local_entry().await.id,
@@ -676,7 +2796,7 @@ impl Gateway {
         };
 
         self.udp_broadcast_manager
-            .send_token_to(&response_token, sender)
+            .send_token_to_peer(&response_token, request.sender, searcher_id)
             .await?;
         Ok(())
     }
@@ -684,14 +2804,37 @@ impl Gateway {
     /// 处理文件请求
     async fn handle_file_request(
         &self,
-        requester_id: uuid::Uuid,
-        file_path: String,
+        request: &RemoteRequest<'_>,
+        file_path: &str,
         request_id: uuid::Uuid,
-        sender: SocketAddr,
     ) -> Result<()> {
+        let requester_id = request.peer_id;
         info!("处理来自 {requester_id} 的文件请求: {file_path}");
 
-        let response_token = match self.udp_broadcast_manager.read_file(&file_path).await {
+        if let Err(denial) = self.authorizer.authorize(request) {
+            return self.send_denial(request, request_id, denial).await;
+        }
+
+        let read_result = self
+            .mount_manager
+            .read_file(file_path)
+            .map(|data| BASE64_STANDARD.encode(data));
+        let audit_event = match &read_result {
+            Ok(file_data) => {
+                AuditEvent::new(AuditCategory::FileRead, AuditOutcome::Success, "read")
+                    .detail(format!("{} 字节（Base64）", file_data.len()))
+            }
+            Err(e) => AuditEvent::new(AuditCategory::FileRead, AuditOutcome::Failure, "read")
+                .detail(e.to_string()),
+        };
+        self.audit_log.record(
+            audit_event
+                .peer(requester_id)
+                .address(request.sender)
+                .target(file_path),
+        );
+
+        let response_token = match read_result {
             Ok(file_data) => UdpToken::FileResponse {
                 responder_id: self.get_local_entry().await.id,
                 request_id,
@@ -707,23 +2850,302 @@ impl Gateway {
         };
 
         self.udp_broadcast_manager
-            .send_token_to(&response_token, sender)
+            .send_token_to_peer(&response_token, request.sender, requester_id)
+            .await?;
+        Ok(())
+    }
+
+    /// 处理列目录请求
+    ///
+    /// 只返回请求者有权读取的条目。
+    async fn handle_directory_list(
+        &self,
+        request: &RemoteRequest<'_>,
+        path: &str,
+        request_id: uuid::Uuid,
+    ) -> Result<()> {
+        let requester_id = request.peer_id;
+        info!("处理来自 {requester_id} 的列目录请求: {path}");
+
+        if let Err(denial) = self.authorizer.authorize(request) {
+            return self.send_denial(request, request_id, denial).await;
+        }
+
+        let Some(mount) = self
+            .mount_manager
+            .mount_containing(std::path::Path::new(path))
+        else {
+            let denial = AuthzDenial::new(
+                crate::gateway::authz::AUTHZ_ERROR_NOT_SHARED,
+                format!("路径不在任何挂载点内: {path}"),
+            );
+            return self.send_denial(request, request_id, denial).await;
+        };
+        let relative_path = std::path::Path::new(path)
+            .strip_prefix(&mount.local_path)
+            .map(|relative| relative.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let entries = self
+            .mount_manager
+            .list_directory(&mount.id, &relative_path)
+            .await?;
+        let allowed = self
+            .authorizer
+            .filter_paths(request, entries.iter().map(|entry| entry.path.clone()));
+        let entries = entries
+            .into_iter()
+            .filter(|entry| allowed.contains(&entry.path))
+            .collect();
+
+        let response_token = UdpToken::DirectoryListResponse {
+            responder_id: self.get_local_entry().await.id,
+            request_id,
+            entries,
+        };
+
+        self.udp_broadcast_manager
+            .send_token_to_peer(&response_token, request.sender, requester_id)
+            .await?;
+        Ok(())
+    }
+
+    /// 处理分享兑现请求
+    ///
+    /// 分享本身即为授权凭据，请求者无需配对，但证书被吊销的网关一律拒绝。
+    /// 被分享的路径必须仍在某个挂载点内。
+    async fn handle_share_request(
+        &self,
+        requester_id: Uuid,
+        sender: SocketAddr,
+        code: &str,
+        password: Option<&str>,
+        path: Option<&str>,
+        request_id: Uuid,
+    ) -> Result<()> {
+        info!("处理来自 {requester_id} 的分享请求");
+
+        // 分享本身即为授权凭据，不经授权器核对签名身份
+        let request = RemoteRequest {
+            peer_id: requester_id,
+            signer: None,
+            sender,
+            operation: RemoteOperation::Read,
+            path: None,
+            search_token: None,
+        };
+        if self.tls_manager.is_gateway_revoked(&requester_id) {
+            let denial = AuthzDenial::new(
+                crate::gateway::authz::AUTHZ_ERROR_REVOKED,
+                format!("网关 {requester_id} 的证书已被吊销"),
+            );
+            return self.send_denial(&request, request_id, denial).await;
+        }
+
+        let access = self
+            .share_manager
+            .redeem(code, password, path)
+            .and_then(|access| {
+                let target = match &access {
+                    ShareAccess::File { path, .. } => path,
+                    ShareAccess::Directory { path, .. } => path,
+                };
+                if self.mount_manager.is_path_visible(target) {
+                    Ok(access)
+                } else {
+                    Err(anyhow!("被分享的路径已不在任何挂载点内或被挂载规则排除"))
+                }
+            });
+        let access = match access {
+            Ok(access) => access,
+            Err(e) => {
+                self.audit_log.record(
+                    AuditEvent::new(AuditCategory::Authorization, AuditOutcome::Denied, "share")
+                        .peer(requester_id)
+                        .address(sender)
+                        .detail(e.to_string()),
+                );
+                let denial = AuthzDenial::new(
+                    crate::gateway::authz::AUTHZ_ERROR_INVALID_TOKEN,
+                    e.to_string(),
+                );
+                return self.send_denial(&request, request_id, denial).await;
+            }
+        };
+
+        let responder_id = self.get_local_entry().await.id;
+        let response_token = match access {
+            ShareAccess::File { share_id, path } => {
+                let reader = SecureFileReader::new(
+                    path.parent().map(|parent| vec![parent.to_path_buf()]).unwrap_or_default(),
+                    10 * 1024 * 1024, // 10MB 文件大小限制
+                );
+                let read_result = reader
+                    .read_file(&path.to_string_lossy())
+                    .map(|data| BASE64_STANDARD.encode(data));
+                let audit_event = match &read_result {
+                    Ok(file_data) => {
+                        AuditEvent::new(AuditCategory::FileRead, AuditOutcome::Success, "share")
+                            .detail(format!("分享 {share_id}，{} 字节", file_data.len()))
+                    }
+                    Err(e) => {
+                        AuditEvent::new(AuditCategory::FileRead, AuditOutcome::Failure, "share")
+                            .detail(format!("分享 {share_id}: {e}"))
+                    }
+                };
+                self.audit_log.record(
+                    audit_event
+                        .peer(requester_id)
+                        .address(sender)
+                        .target(path.to_string_lossy()),
+                );
+
+                match read_result {
+                    Ok(file_data) => UdpToken::FileResponse {
+                        responder_id,
+                        request_id,
+                        file_data: Some(file_data),
+                        error: None,
+                    },
+                    Err(e) => UdpToken::FileResponse {
+                        responder_id,
+                        request_id,
+                        file_data: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            }
+            ShareAccess::Directory {
+                share_id,
+                root,
+                path,
+            } => {
+                self.audit_log.record(
+                    AuditEvent::new(AuditCategory::Authorization, AuditOutcome::Allowed, "share")
+                        .peer(requester_id)
+                        .address(sender)
+                        .target(path.to_string_lossy())
+                        .detail(format!("分享 {share_id}")),
+                );
+                UdpToken::DirectoryListResponse {
+                    responder_id,
+                    request_id,
+                    entries: self.list_shared_directory(&root, &path)?,
+                }
+            }
+        };
+
+        self.udp_broadcast_manager
+            .send_token_to_peer(&response_token, sender, requester_id)
             .await?;
         Ok(())
     }
 
+    /// 列出分享文件夹的内容，条目路径相对于分享根目录，不暴露本地路径
+    ///
+    /// 被所在挂载点过滤规则排除的条目不会列出。
+    fn list_shared_directory(
+        &self,
+        root: &std::path::Path,
+        path: &std::path::Path,
+    ) -> Result<Vec<TauriDirectoryEntry>> {
+        let filter = self
+            .mount_manager
+            .filter_for(path)
+            .ok_or_else(|| anyhow!("被分享的路径已不在任何挂载点内"))?;
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let full_path = entry.path();
+            let Ok(relative_path) = full_path.strip_prefix(root) else {
+                continue;
+            };
+            if !filter.allows_entry(&full_path, &metadata) {
+                continue;
+            }
+
+            entries.push(TauriDirectoryEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: relative_path.to_string_lossy().to_string(),
+                is_directory: metadata.is_dir(),
+                size: if metadata.is_file() { metadata.len() } else { 0 },
+                modified_time: metadata
+                    .modified()
+                    .map(chrono::DateTime::<Utc>::from)
+                    .unwrap_or_else(|_| Utc::now()),
+                created_time: metadata.created().ok().map(chrono::DateTime::<Utc>::from),
+                file_type: if metadata.is_dir() {
+                    "directory".to_string()
+                } else {
+                    full_path
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "file".to_string())
+                },
+            });
+        }
+
+        entries.sort_by(|a, b| {
+            b.is_directory
+                .cmp(&a.is_directory)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(entries)
+    }
+
+    /// 向请求者返回授权拒绝
+    ///
+    /// 身份未通过验证的请求者不使用点对点密钥，直接以明文回复。
+    async fn send_denial(
+        &self,
+        request: &RemoteRequest<'_>,
+        request_id: uuid::Uuid,
+        denial: AuthzDenial,
+    ) -> Result<()> {
+        warn!(
+            "拒绝网关 {} ({}) 的 {} 请求: {denial}",
+            request.peer_id, request.sender, request.operation
+        );
+
+        let error_token = UdpToken::Error {
+            responder_id: self.get_local_entry().await.id,
+            request_id,
+            code: denial.code,
+            message: denial.reason,
+        };
+        if denial.code == crate::gateway::authz::AUTHZ_ERROR_UNAUTHENTICATED
+            || denial.code == crate::gateway::authz::AUTHZ_ERROR_REVOKED
+        {
+            self.udp_broadcast_manager
+                .send_token_to(&error_token, request.sender)
+                .await
+        } else {
+            self.udp_broadcast_manager
+                .send_token_to_peer(&error_token, request.sender, request.peer_id)
+                .await
+        }
+    }
+
     /// 处理广播消息
+    ///
+    /// # 返回值
+    ///
+    /// 广播响应，包含除发送者外的其他网关信息
     async fn handle_broadcast_message(
         &self,
         sender_entry: RegistryEntry,
         sender_addr: SocketAddr,
-    ) -> Result<()> {
+    ) -> WdicMessage {
         info!("收到来自 '{}' ({sender_addr}) 的广播", sender_entry.name);
 
         // 添加到注册表 (lock-free)
         let is_new = self.registry.add_or_update(sender_entry.clone());
         if is_new {
             info!("新网关 '{}' 加入网络", sender_entry.name);
+
+            self.refresh_relay_routes().await;
         } else {
             debug!("更新现有网关 '{}' 信息", sender_entry.name);
         }
@@ -732,14 +3154,20 @@ impl Gateway {
         let response_gateways = self.registry.entries_except(&sender_entry.id);
 
         let local_entry = self.get_local_entry().await;
-        let response = WdicMessage::broadcast_response(local_entry, response_gateways);
-
-        self.network_manager
-            .reply_message(&response, sender_addr)
-            .await?;
-        debug!("已回复广播响应到 {sender_addr}");
+        WdicMessage::broadcast_response(local_entry, response_gateways)
+    }
 
-        Ok(())
+    /// 新网关加入后立即更新中继路由，无需等待下一次通告
+    async fn refresh_relay_routes(&self) {
+        if self.relay_manager.is_enabled() {
+            Self::advertise_relay_routes(
+                &self.registry,
+                &self.network_manager,
+                &self.relay_manager,
+                &self.heartbeat_scheduler,
+            )
+            .await;
+        }
     }
 
     /// 处理广播响应消息
@@ -754,14 +3182,19 @@ impl Gateway {
             gateways.len()
         );
 
-        // 添加响应者 (lock-free)
+        // 添加响应者 (lock-free)，其条目由信封签名者本人声明
         self.registry.add_or_update(sender_entry);
 
-        // 添加响应中包含的其他网关
-        for gateway in gateways {
-            let is_new = self.registry.add_or_update(gateway.clone());
-            if is_new {
-                info!("发现新网关: '{}'", gateway.name);
+        // 响应中包含的其他网关属于转述，地址和公钥无法确认，只以未验证状态加入，
+        // 已存在的条目不会被覆盖，已吊销的网关不再加入
+        for mut gateway in gateways {
+            if self.tls_manager.is_gateway_revoked(&gateway.id) {
+                continue;
+            }
+            gateway.public_key = None;
+            let name = gateway.name.clone();
+            if self.registry.add_unverified(gateway) {
+                info!("发现新网关: '{name}'");
             }
         }
 
@@ -769,7 +3202,16 @@ impl Gateway {
     }
 
     /// 处理心跳消息
-    async fn handle_heartbeat(&self, sender_id: uuid::Uuid, sender_addr: SocketAddr) -> Result<()> {
+    ///
+    /// # 返回值
+    ///
+    /// 回显探测序号的心跳响应
+    async fn handle_heartbeat(
+        &self,
+        sender_id: uuid::Uuid,
+        sequence: u64,
+        sender_addr: SocketAddr,
+    ) -> WdicMessage {
         debug!("收到来自 {sender_addr} 的心跳");
 
         // 更新注册表中的条目 (lock-free)
@@ -781,23 +3223,50 @@ impl Gateway {
 
         // 回复心跳响应
         let local_entry = self.get_local_entry().await;
-        let response = WdicMessage::heartbeat_response(local_entry.id);
-        self.network_manager
-            .reply_message(&response, sender_addr)
-            .await?;
+        WdicMessage::heartbeat_response(local_entry.id, sequence)
+    }
 
-        Ok(())
+    /// 处理心跳响应
+    ///
+    /// 更新网关的 RTT 估计与存活状态，并将 RTT 计入性能监控。
+    async fn handle_heartbeat_response(&self, sender_id: uuid::Uuid, sequence: u64) {
+        let Some(ack) = self.heartbeat_scheduler.record_ack(&sender_id, sequence) else {
+            debug!("心跳响应 {sender_id}#{sequence} 不对应未完成的探测，不计入 RTT");
+            return;
+        };
+
+        if let Some(entry) = self.registry.get(&sender_id) {
+            self.registry.add_or_update(entry);
+        }
+
+        self.performance_monitor
+            .record_peer_latency(
+                &sender_id.to_string(),
+                ack.rtt_ms,
+                ack.smoothed_rtt_ms,
+                ack.jitter_ms,
+            )
+            .await;
+
+        if let Some(change) = ack.state_change {
+            info!("网关 '{}' 恢复为 {:?}", change.name, change.current);
+        }
     }
 
     /// 处理注册请求
-    async fn handle_register_request(
-        &self,
-        gateway: RegistryEntry,
-        sender_addr: SocketAddr,
-    ) -> Result<()> {
+    ///
+    /// # 返回值
+    ///
+    /// 注册响应
+    async fn handle_register_request(&self, gateway: RegistryEntry) -> WdicMessage {
         info!("收到来自 '{}' 的注册请求", gateway.name);
 
         let is_new = self.registry.add_or_update(gateway.clone());
+        self.open_session(gateway.address, Some(gateway.id), None, None);
+
+        if is_new {
+            self.refresh_relay_routes().await;
+        }
 
         let (success, message) = if is_new {
             (true, format!("网关 '{}' 注册成功", gateway.name))
@@ -806,31 +3275,21 @@ impl Gateway {
         };
 
         let response_gateways = self.registry.entries_except(&gateway.id);
-        let response = WdicMessage::register_response(success, message, response_gateways);
-
-        self.network_manager
-            .reply_message(&response, sender_addr)
-            .await?;
-        Ok(())
+        WdicMessage::register_response(success, message, response_gateways)
     }
 
     /// 处理网关查询请求
-    async fn handle_query_gateways(
-        &self,
-        requester_id: uuid::Uuid,
-        sender_addr: SocketAddr,
-    ) -> Result<()> {
+    ///
+    /// # 返回值
+    ///
+    /// 查询响应
+    async fn handle_query_gateways(&self, requester_id: uuid::Uuid) -> WdicMessage {
         debug!("收到网关查询请求");
 
         let gateways = self.registry.entries_except(&requester_id);
 
         let local_entry = self.get_local_entry().await;
-        let response = WdicMessage::query_response(local_entry.id, gateways);
-
-        self.network_manager
-            .reply_message(&response, sender_addr)
-            .await?;
-        Ok(())
+        WdicMessage::query_response(local_entry.id, gateways)
     }
 
     /// 清理连接相关的注册表条目
@@ -843,6 +3302,34 @@ impl Gateway {
         Ok(())
     }
 
+    /// 会话同步任务
+    ///
+    /// 定期把网络连接的流量、活跃时间和认证身份同步到活跃会话，
+    /// 并关闭连接已超时清理的会话。
+    async fn session_sync_task(
+        network_manager: Arc<NetworkManager>,
+        authorizer: Arc<Authorizer>,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut sync_interval = interval(Duration::from_secs(SESSION_SYNC_INTERVAL));
+
+        while *running.lock().await {
+            sync_interval.tick().await;
+
+            let connections = network_manager.get_active_connections().await;
+            let security_manager = authorizer.security_manager();
+            for connection in &connections {
+                security_manager.sync_session(connection);
+            }
+
+            let live: Vec<SocketAddr> = connections.iter().map(|c| c.remote_addr).collect();
+            let closed = security_manager.close_stale_sessions(&live);
+            if closed > 0 {
+                debug!("关闭了 {closed} 个连接已超时的会话");
+            }
+        }
+    }
+
     /// 广播任务 - 增强版本
     ///
     /// 定期向网络广播自己的存在，并在心跳时广播缓存名称哈希列表。
@@ -921,11 +3408,239 @@ impl Gateway {
         debug!("广播任务退出");
     }
 
+    /// 心跳任务
+    ///
+    /// 按心跳间隔向注册表中的网关发送心跳，并根据未响应次数迁移网关存活状态。
+    #[allow(clippy::too_many_arguments)]
+    async fn heartbeat_task(
+        scheduler: Arc<HeartbeatScheduler>,
+        registry: Arc<Registry>,
+        network_manager: Arc<NetworkManager>,
+        relay_manager: Arc<RelayManager>,
+        agreement_key: Arc<AgreementKeyPair>,
+        trusted_keys: Arc<TrustedKeys>,
+        performance_monitor: Arc<PerformanceMonitor>,
+        config: GatewayConfig,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut heartbeat_interval = interval(Duration::from_secs(config.heartbeat_interval));
+
+        while *running.lock().await {
+            heartbeat_interval.tick().await;
+
+            let (targets, changes) = scheduler.begin_round(&registry);
+
+            for change in &changes {
+                match change.current {
+                    PeerLiveness::Suspect => {
+                        warn!("网关 '{}' ({}) 心跳无响应，标记为可疑", change.name, change.address);
+                    }
+                    PeerLiveness::Dead => {
+                        relay_manager.forget_peer(&change.peer_id);
+                        performance_monitor
+                            .remove_peer_latency(&change.peer_id.to_string())
+                            .await;
+                    }
+                    PeerLiveness::Alive => {}
+                }
+            }
+
+            let local_id = registry.local_entry().id;
+            for (peer_id, address, sequence) in targets {
+                let Some(target) = registry.get(&peer_id) else {
+                    continue;
+                };
+                let heartbeat = WdicMessage::heartbeat(local_id, sequence);
+
+                match Self::send_routed(
+                    &registry,
+                    &network_manager,
+                    &relay_manager,
+                    &agreement_key,
+                    &trusted_keys,
+                    &target,
+                    &heartbeat,
+                )
+                .await
+                {
+                    // 经中继时仍向原地址发送直连探测，直连恢复后自动切回；
+                    // 同时以中继为会合网关尝试打洞，失败后按冷却时间重试
+                    Ok(PeerRoute::Relay { relay_addr, .. }) => {
+                        let _ = network_manager.send_message(&heartbeat, address).await;
+                        if config.enable_hole_punching {
+                            if let Err(e) = network_manager
+                                .request_hole_punch(local_id, peer_id, relay_addr)
+                                .await
+                            {
+                                debug!("请求与网关 {peer_id} 打洞失败: {e}");
+                            }
+                        }
+                    }
+                    Ok(PeerRoute::Direct(_)) => {}
+                    Err(e) => debug!("向网关 {peer_id} ({address}) 发送心跳失败: {e}"),
+                }
+            }
+        }
+
+        debug!("心跳任务退出");
+    }
+
+    /// 中继路由通告任务
+    ///
+    /// 按广播间隔向每个直连可达的网关通告可经本网关转发到达的其他网关。
+    async fn relay_advertise_task(
+        registry: Arc<Registry>,
+        network_manager: Arc<NetworkManager>,
+        relay_manager: Arc<RelayManager>,
+        scheduler: Arc<HeartbeatScheduler>,
+        config: GatewayConfig,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut advertise_interval = interval(Duration::from_secs(config.broadcast_interval));
+
+        while *running.lock().await {
+            advertise_interval.tick().await;
+            Self::advertise_relay_routes(&registry, &network_manager, &relay_manager, &scheduler)
+                .await;
+        }
+
+        debug!("中继路由通告任务退出");
+    }
+
+    /// 向直连可达的网关通告中继路由
+    async fn advertise_relay_routes(
+        registry: &Registry,
+        network_manager: &NetworkManager,
+        relay_manager: &RelayManager,
+        scheduler: &HeartbeatScheduler,
+    ) {
+        let reachable = relay_manager.reachable_peers(registry, scheduler);
+        if reachable.is_empty() {
+            return;
+        }
+
+        let local_entry = registry.local_entry();
+        for peer in &reachable {
+            let others: Vec<RegistryEntry> = reachable
+                .iter()
+                .filter(|entry| entry.id != peer.id)
+                .cloned()
+                .collect();
+            let message = WdicMessage::relay_routes(local_entry.clone(), others);
+            if let Err(e) = network_manager.send_message(&message, peer.address).await {
+                debug!("向网关 '{}' 通告中继路由失败: {e}", peer.name);
+            }
+        }
+    }
+
+    /// 证书有效期监控任务
+    ///
+    /// 定期检查证书，即将到期时发出警告或重新签发，
+    /// 重新签发后向所有已配对的网关发送签名的证书轮换通知。
+    async fn certificate_monitor_task(
+        cert_monitor: Arc<CertificateMonitor>,
+        tls_manager: Arc<TlsManager>,
+        registry: Arc<Registry>,
+        network_manager: Arc<NetworkManager>,
+        pairing: Arc<PairingManager>,
+        config: GatewayConfig,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut check_interval = interval(Duration::from_secs(config.cert_check_interval));
+
+        while *running.lock().await {
+            check_interval.tick().await;
+
+            for event in cert_monitor.check() {
+                if let CertificateEvent::Rotated { fingerprints } = event {
+                    Self::announce_certificate_rotation(
+                        &tls_manager,
+                        &registry,
+                        &network_manager,
+                        &pairing,
+                        fingerprints,
+                    )
+                    .await;
+                }
+            }
+        }
+
+        debug!("证书有效期监控任务退出");
+    }
+
+    /// 向已配对的网关发送签名的证书轮换通知
+    async fn announce_certificate_rotation(
+        tls_manager: &TlsManager,
+        registry: &Registry,
+        network_manager: &NetworkManager,
+        pairing: &PairingManager,
+        fingerprints: Vec<String>,
+    ) {
+        let local_id = registry.local_entry().id;
+        let timestamp = Utc::now();
+        let payload = CertificateMonitor::rotation_payload(&local_id, &fingerprints, &timestamp);
+        let signature = match tls_manager.sign_with_ca_key(&payload) {
+            Ok(signature) => BASE64_STANDARD.encode(signature),
+            Err(e) => {
+                warn!("签名证书轮换通知失败: {e}");
+                return;
+            }
+        };
+        let message =
+            WdicMessage::certificate_rotated(local_id, fingerprints, timestamp, signature);
+
+        for device in pairing.devices() {
+            let Some(entry) = registry.get(&device.gateway_id) else {
+                debug!("已配对网关 '{}' 当前不可达，跳过证书轮换通知", device.name);
+                continue;
+            };
+            if let Err(e) = network_manager.send_message(&message, entry.address).await {
+                warn!("向网关 '{}' 发送证书轮换通知失败: {e}", device.name);
+            }
+        }
+    }
+
+    /// 处理证书轮换通知
+    ///
+    /// 只接受已配对网关的通知，签名必须能用配对时固定的 CA 证书验证。
+    fn handle_certificate_rotated(
+        &self,
+        sender_id: Uuid,
+        fingerprints: Vec<String>,
+        timestamp: chrono::DateTime<Utc>,
+        signature: &str,
+    ) -> Result<()> {
+        let device = self
+            .pairing
+            .device(&sender_id)
+            .ok_or_else(|| anyhow!("未配对的网关 {sender_id} 发送了证书轮换通知"))?;
+
+        let payload = CertificateMonitor::rotation_payload(&sender_id, &fingerprints, &timestamp);
+        let signature = BASE64_STANDARD
+            .decode(signature)
+            .context("证书轮换通知签名格式无效")?;
+        if !TlsManager::verify_ca_signature(&device.certificate, &payload, &signature)? {
+            return Err(anyhow!("网关 '{}' 的证书轮换通知签名无效", device.name));
+        }
+
+        if self
+            .pairing
+            .record_rotation(&sender_id, fingerprints, timestamp)?
+        {
+            info!("已配对网关 '{}' 轮换了证书", device.name);
+        } else {
+            debug!("忽略网关 '{}' 重放的证书轮换通知", device.name);
+        }
+        Ok(())
+    }
+
     /// 注册表清理任务
     ///
-    /// 定期清理过期的注册表条目。
+    /// 定期清理过期的注册表条目，并将已知网关写入持久化存储。
     async fn registry_cleanup_task(
         registry: Arc<Registry>,
+        network_manager: Arc<NetworkManager>,
+        peer_store: Arc<PeerStore>,
         config: GatewayConfig,
         running: Arc<Mutex<bool>>,
     ) {
@@ -934,6 +3649,9 @@ impl Gateway {
         while *running.lock().await {
             cleanup_interval.tick().await;
 
+            // 先记录已验证的网关，再清理过期条目，避免丢失最后一次确认的信息
+            Self::persist_known_peers(&registry, &network_manager, &peer_store).await;
+
             let cleaned_count = registry.cleanup_expired(config.connection_timeout);
 
             if cleaned_count > 0 {
@@ -944,6 +3662,83 @@ impl Gateway {
         debug!("注册表清理任务退出");
     }
 
+    /// 网络接口监视任务
+    ///
+    /// 定期重新扫描本地接口，接口启用、停用或地址变化时更新广播地址，
+    /// 必要时重新绑定监听地址，并立即重新广播本网关。
+    async fn interface_monitor_task(
+        registry: Arc<Registry>,
+        network_manager: Arc<NetworkManager>,
+        udp_broadcast_manager: Arc<UdpBroadcastManager>,
+        config: GatewayConfig,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut scan_interval = interval(Duration::from_secs(config.interface_scan_interval));
+        // 第一次 tick 立即完成，创建网关时已经扫描过
+        scan_interval.tick().await;
+
+        while *running.lock().await {
+            scan_interval.tick().await;
+
+            match network_manager.refresh_interfaces().await {
+                Ok(Some(change)) => {
+                    Self::apply_interface_change(
+                        &registry,
+                        &network_manager,
+                        &udp_broadcast_manager,
+                        &change,
+                    )
+                    .await;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("检测网络接口变化失败: {e}"),
+            }
+        }
+
+        debug!("网络接口监视任务退出");
+    }
+
+    /// 应用网络接口变化
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 注册表
+    /// * `network_manager` - 网络管理器
+    /// * `udp_broadcast_manager` - UDP 广播管理器
+    /// * `change` - 接口变化
+    async fn apply_interface_change(
+        registry: &Registry,
+        network_manager: &NetworkManager,
+        udp_broadcast_manager: &UdpBroadcastManager,
+        change: &InterfaceChange,
+    ) {
+        if let Some(address) = change.rebound {
+            registry.set_local_address(address);
+        }
+        Self::sync_udp_broadcast_addresses(network_manager, udp_broadcast_manager).await;
+
+        let message = WdicMessage::broadcast(registry.local_entry());
+        match network_manager.broadcast_message(&message).await {
+            Ok(sent_count) => info!("接口变化后重新广播到 {sent_count} 个地址"),
+            Err(e) => warn!("接口变化后重新广播失败: {e}"),
+        }
+    }
+
+    /// 将网络管理器按接口策略生成的广播地址同步到 UDP 广播管理器
+    async fn sync_udp_broadcast_addresses(
+        network_manager: &NetworkManager,
+        udp_broadcast_manager: &UdpBroadcastManager,
+    ) {
+        let udp_port = udp_broadcast_manager.local_addr().port();
+        let addresses: Vec<SocketAddr> = network_manager
+            .broadcast_addresses()
+            .await
+            .into_iter()
+            .map(|addr| SocketAddr::new(addr.ip(), udp_port))
+            .collect();
+        udp_broadcast_manager.set_broadcast_addresses(&addresses).await;
+    }
+
     /// 缓存清理任务
     ///
     /// 定期清理过期的缓存条目。
@@ -1014,6 +3809,9 @@ impl Gateway {
             warn!("发送注销广播失败: {e}");
         }
 
+        // 保存已知网关，以便下次启动时立即恢复
+        Self::persist_known_peers(&self.registry, &self.network_manager, &self.peer_store).await;
+
         // 关闭网络管理器
         self.network_manager.shutdown().await?;
 
@@ -1024,7 +3822,9 @@ impl Gateway {
         Ok(())
     }
 
-    /// 挂载目录
+    /// 以只读方式挂载目录
+    ///
+    /// 与 `mount_directory` Tauri 命令使用同一个挂载管理器。
     ///
     /// # 参数
     ///
@@ -1035,7 +3835,19 @@ impl Gateway {
     ///
     /// 挂载结果
     pub async fn mount_directory(&self, name: String, path: String) -> Result<()> {
-        self.udp_broadcast_manager.mount_directory(name, path).await
+        let mount_point = MountPoint {
+            id: Uuid::new_v4().to_string(),
+            local_path: PathBuf::from(path),
+            mount_name: name,
+            read_only: true,
+            mount_time: Utc::now(),
+            file_count: 0,
+            total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
+        };
+        self.mount_manager.mount_directory(mount_point).await?;
+        Ok(())
     }
 
     /// 卸载目录
@@ -1048,7 +3860,14 @@ impl Gateway {
     ///
     /// 是否成功卸载
     pub async fn unmount_directory(&self, name: &str) -> bool {
-        self.udp_broadcast_manager.unmount_directory(name).await
+        match self.mount_manager.mount_by_name(name) {
+            Some(mount_point) => self
+                .mount_manager
+                .unmount_directory(&mount_point.id)
+                .await
+                .is_ok(),
+            None => false,
+        }
     }
 
     /// 获取已挂载目录列表
@@ -1057,7 +3876,13 @@ impl Gateway {
     ///
     /// 挂载点名称列表
     pub async fn get_mounted_directories(&self) -> Vec<String> {
-        self.udp_broadcast_manager.get_mounted_directories().await
+        self.mount_manager
+            .get_mount_points()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mount_point| mount_point.mount_name)
+            .collect()
     }
 
     /// 搜索文件
@@ -1070,7 +3895,7 @@ impl Gateway {
     ///
     /// 匹配的文件路径列表
     pub async fn search_files_locally(&self, keywords: &[String]) -> Vec<String> {
-        self.udp_broadcast_manager.search_files(keywords).await
+        self.mount_manager.search_files(keywords)
     }
 
     /// 向网络广播目录搜索请求
@@ -1094,6 +3919,7 @@ impl Gateway {
             searcher_id: local_entry.id,
             keywords: keywords.into(),
             search_id: uuid::Uuid::new_v4(),
+            search_token: None,
         };
 
         self.udp_broadcast_manager
@@ -1295,10 +4121,22 @@ pub struct QuickPerformanceStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gateway::transport::MemoryNetwork;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// 创建持久化文件位于临时目录下的网关
+    async fn create_test_gateway(dir: &TempDir, name: &str) -> Gateway {
+        Gateway::with_config(GatewayConfig::for_test(dir.path(), name))
+            .await
+            .unwrap()
+    }
 
     #[tokio::test]
     async fn test_gateway_creation() {
-        let gateway = Gateway::new("测试网关".to_string()).await;
+        let temp_dir = TempDir::new().unwrap();
+        let gateway =
+            Gateway::with_config(GatewayConfig::for_test(temp_dir.path(), "测试网关")).await;
         assert!(gateway.is_ok());
 
         let gateway = gateway.unwrap();
@@ -1308,11 +4146,11 @@ mod tests {
 
     #[tokio::test]
     async fn test_gateway_with_config() {
+        let temp_dir = TempDir::new().unwrap();
         let config = GatewayConfig {
-            name: "配置网关".to_string(),
             port: 0, // 让系统分配端口
             broadcast_interval: 10,
-            ..Default::default()
+            ..GatewayConfig::for_test(temp_dir.path(), "配置网关")
         };
 
         let gateway = Gateway::with_config(config).await;
@@ -1325,7 +4163,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_gateway_local_info() {
-        let gateway = Gateway::new("信息网关".to_string()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "信息网关").await;
 
         let local_entry = gateway.get_local_entry().await;
         assert_eq!(local_entry.name, "信息网关");
@@ -1341,16 +4180,140 @@ mod tests {
 
     #[tokio::test]
     async fn test_gateway_stop_before_start() {
-        let gateway = Gateway::new("停止网关".to_string()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "停止网关").await;
 
         // 在未启动的情况下停止应该成功
         let result = gateway.stop().await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_gateway_disconnect_session_blocks_reconnect() {
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "会话网关").await;
+        let peer_addr = SocketAddr::from(([192, 168, 1, 30], 55555));
+
+        // 注册请求自动打开会话
+        let entry = RegistryEntry::new("对端网关".to_string(), peer_addr);
+        let peer_id = entry.id;
+        gateway.handle_register_request(entry).await;
+        let sessions = gateway
+            .authorizer()
+            .security_manager()
+            .get_active_sessions()
+            .await
+            .unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].remote_addr, Some(peer_addr));
+
+        // 断开后地址和网关都进入冷却期
+        gateway
+            .disconnect_session(&sessions[0].session_id)
+            .await
+            .unwrap();
+        assert!(gateway.network_manager().is_in_cooldown(&peer_addr));
+        assert!(gateway.peer_in_cooldown(&peer_id));
+        assert!(gateway.registry.get(&peer_id).is_none());
+        assert!(gateway
+            .disconnect_session(&sessions[0].session_id)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_broadcast_response_lists_third_parties_unverified() {
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "响应网关").await;
+
+        let mut known =
+            RegistryEntry::new("已知网关".to_string(), "192.168.1.40:55555".parse().unwrap());
+        known.public_key = Some("known-key".to_string());
+        gateway.handle_register_request(known.clone()).await;
+
+        let responder =
+            RegistryEntry::new("响应者".to_string(), "192.168.1.41:55555".parse().unwrap());
+        let mut forged_known = known.clone();
+        forged_known.address = "10.0.0.66:55555".parse().unwrap();
+        forged_known.public_key = Some("forged-key".to_string());
+        let mut stranger =
+            RegistryEntry::new("转述网关".to_string(), "192.168.1.42:55555".parse().unwrap());
+        stranger.public_key = Some("stranger-key".to_string());
+
+        gateway
+            .handle_broadcast_response(
+                responder.clone(),
+                vec![forged_known, stranger.clone()],
+            )
+            .await
+            .unwrap();
+
+        // 响应者本人的条目被确认
+        assert!(gateway.registry.get(&responder.id).unwrap().is_verified());
+
+        // 已知网关的地址和公钥不被转述覆盖
+        let known_entry = gateway.registry.get(&known.id).unwrap();
+        assert!(known_entry.is_verified());
+        assert_eq!(known_entry.address, known.address);
+        assert_eq!(known_entry.public_key.as_deref(), Some("known-key"));
+
+        // 新网关以未验证状态加入，且不带公钥
+        let stranger_entry = gateway.registry.get(&stranger.id).unwrap();
+        assert!(!stranger_entry.is_verified());
+        assert!(stranger_entry.public_key.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_forged_broadcast_response_keeps_trusted_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "密钥网关").await;
+
+        let mut known =
+            RegistryEntry::new("已知网关".to_string(), "192.168.1.50:55555".parse().unwrap());
+        known.public_key = Some("known-key".to_string());
+        let broadcast = WdicMessage::Broadcast {
+            sender: known.clone(),
+        };
+        gateway
+            .process_message(broadcast, known.address, Some(known.id))
+            .await
+            .unwrap();
+        assert_eq!(
+            gateway.trusted_keys.get(&known.id).as_deref(),
+            Some("known-key")
+        );
+
+        // 其他网关签名的广播响应转述已知网关的另一个公钥
+        let responder =
+            RegistryEntry::new("伪造者".to_string(), "192.168.1.51:55555".parse().unwrap());
+        let mut forged = known.clone();
+        forged.public_key = Some("forged-key".to_string());
+        let response = WdicMessage::broadcast_response(responder.clone(), vec![forged.clone()]);
+        gateway
+            .process_message(response, responder.address, Some(responder.id))
+            .await
+            .unwrap();
+        assert_eq!(
+            gateway.trusted_keys.get(&known.id).as_deref(),
+            Some("known-key")
+        );
+
+        // 未签名的自我通告同样不能替换公钥
+        let unsigned = WdicMessage::Broadcast { sender: forged };
+        gateway
+            .process_message(unsigned, known.address, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            gateway.trusted_keys.get(&known.id).as_deref(),
+            Some("known-key")
+        );
+    }
+
     #[tokio::test]
     async fn test_gateway_directory_operations() {
-        let gateway = Gateway::new("目录网关".to_string()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "目录网关").await;
 
         // 测试挂载目录（使用当前目录）
         let current_dir = std::env::current_dir().unwrap();
@@ -1378,7 +4341,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_gateway_udp_messaging() {
-        let gateway = Gateway::new("消息网关".to_string()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "消息网关").await;
 
         // 测试广播信息消息
         let result = gateway.broadcast_info_message("测试消息".to_string()).await;
@@ -1393,7 +4357,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_gateway_performance_test() {
-        let gateway = Gateway::new("性能网关".to_string()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let gateway = create_test_gateway(&temp_dir, "性能网关").await;
 
         // 测试性能测试功能
         let result = gateway
@@ -1404,4 +4369,238 @@ mod tests {
         let latency = result.unwrap();
         assert!(latency <= 1000); // 延迟应该在合理范围内（毫秒）
     }
+
+    /// 在内存网络上启动网关
+    async fn start_memory_gateway(
+        network: &MemoryNetwork,
+        dir: &Path,
+        name: &str,
+        address: SocketAddr,
+        enable_relay: bool,
+    ) -> Arc<Gateway> {
+        let config = GatewayConfig {
+            heartbeat_interval: 1,
+            enable_relay,
+            ..GatewayConfig::for_test(&dir.join(name), name)
+        };
+        start_gateway_on(config, network.bind(address).unwrap()).await
+    }
+
+    /// 让两个网关互相记录对方的公钥，相当于此前直接收到过对方签名的通告
+    async fn exchange_announced_keys(a: &Gateway, b: &Gateway) {
+        let (entry_a, entry_b) = (a.get_local_entry().await, b.get_local_entry().await);
+        a.trusted_keys.record_announced(Some(entry_b.id), &entry_b);
+        b.trusted_keys.record_announced(Some(entry_a.id), &entry_a);
+    }
+
+    async fn start_gateway_on(
+        config: GatewayConfig,
+        transport: Arc<dyn DatagramTransport>,
+    ) -> Arc<Gateway> {
+        let gateway = Arc::new(Gateway::with_transport(config, transport).await.unwrap());
+
+        let runner = Arc::clone(&gateway);
+        tokio::spawn(async move {
+            let _ = runner.run().await;
+        });
+        gateway
+    }
+
+    /// 组建 NAT 测试拓扑：公网上的会合网关 B，以及分别位于两个 NAT 之后的 A 和 C
+    ///
+    /// 心跳间隔足够长且关闭自动打洞，使打洞只由测试显式触发。
+    async fn start_nat_topology(
+        network: &MemoryNetwork,
+        dir: &Path,
+        rendezvous_port: u16,
+        block_direct: bool,
+    ) -> (Arc<Gateway>, Arc<Gateway>, Arc<Gateway>, SocketAddr) {
+        let nat_config = |name: &str| GatewayConfig {
+            heartbeat_interval: 60,
+            enable_hole_punching: false,
+            ..GatewayConfig::for_test(&dir.join(name), name)
+        };
+        let addr_b = SocketAddr::from(([198, 51, 100, 1], rendezvous_port));
+        let transport_b = network.bind(addr_b).unwrap();
+        let transport_a = network
+            .bind_behind_nat("10.0.1.2:55555".parse().unwrap(), "203.0.113.1".parse().unwrap())
+            .unwrap();
+        let transport_c = network
+            .bind_behind_nat("10.0.2.2:55555".parse().unwrap(), "203.0.113.2".parse().unwrap())
+            .unwrap();
+        if block_direct {
+            network.block(transport_a.public_addr(), transport_c.public_addr());
+        }
+
+        let rendezvous = start_gateway_on(
+            GatewayConfig {
+                enable_relay: true,
+                ..nat_config("会合网关")
+            },
+            transport_b,
+        )
+        .await;
+        let gateway_a = start_gateway_on(nat_config("网关A"), transport_a).await;
+        let gateway_c = start_gateway_on(nat_config("网关C"), transport_c).await;
+        sleep(Duration::from_millis(200)).await;
+
+        gateway_c.connect_peer(addr_b).await.unwrap();
+        sleep(Duration::from_millis(200)).await;
+        gateway_a.connect_peer(addr_b).await.unwrap();
+        sleep(Duration::from_millis(300)).await;
+
+        (gateway_a, rendezvous, gateway_c, addr_b)
+    }
+
+    #[tokio::test]
+    async fn test_gateway_relay_between_blocked_peers() {
+        let network = MemoryNetwork::new();
+        let temp_dir = TempDir::new().unwrap();
+        let addr_a = SocketAddr::from(([127, 0, 0, 1], 45001));
+        let addr_b = SocketAddr::from(([127, 0, 0, 1], 45002));
+        let addr_c = SocketAddr::from(([127, 0, 0, 1], 45003));
+        network.block(addr_a, addr_c);
+
+        let relay_gateway = start_memory_gateway(&network, temp_dir.path(), "中继网关", addr_b, true).await;
+        let gateway_a = start_memory_gateway(&network, temp_dir.path(), "网关A", addr_a, false).await;
+        let gateway_c = start_memory_gateway(&network, temp_dir.path(), "网关C", addr_c, false).await;
+        exchange_announced_keys(&gateway_a, &gateway_c).await;
+        sleep(Duration::from_millis(200)).await;
+
+        gateway_c.connect_peer(addr_b).await.unwrap();
+        sleep(Duration::from_millis(200)).await;
+        gateway_a.connect_peer(addr_b).await.unwrap();
+
+        let a_id = gateway_a.get_local_entry().await.id;
+        let c_id = gateway_c.get_local_entry().await.id;
+
+        // 等待中继通告路由，并经中继完成一次心跳往返
+        let mut relayed_ack = false;
+        for _ in 0..50 {
+            sleep(Duration::from_millis(100)).await;
+            let acked = gateway_a
+                .heartbeat_scheduler()
+                .get(&c_id)
+                .is_some_and(|health| health.last_rtt_ms.is_some());
+            if acked {
+                relayed_ack = true;
+                break;
+            }
+        }
+        assert!(relayed_ack, "网关A 应当经中继收到网关C 的心跳响应");
+
+        // A 与 C 互相知晓，且 A 到 C 的路径为经中继转发
+        let c_entry = gateway_a.registry().get(&c_id).expect("网关A 应当知晓网关C");
+        assert!(gateway_c.registry().get(&a_id).is_some());
+        assert_eq!(
+            gateway_a.relay_manager().route(gateway_a.registry(), &c_entry),
+            PeerRoute::Relay {
+                relay_id: relay_gateway.get_local_entry().await.id,
+                relay_addr: addr_b,
+            }
+        );
+
+        // 直接流量被传输层丢弃，中继转发了双向的信封
+        assert!(network.dropped_count() > 0);
+        let stats = relay_gateway.relay_stats();
+        assert!(stats.forwarded_messages >= 2);
+        assert!(stats.forwarded_bytes > 0);
+
+        // 未启用中继的网关拒绝转发
+        assert!(!gateway_a.relay_manager().is_enabled());
+
+        for gateway in [&gateway_a, &gateway_c, &relay_gateway] {
+            gateway.stop().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_hole_punch_through_nat() {
+        let network = MemoryNetwork::new();
+        let temp_dir = TempDir::new().unwrap();
+        let (gateway_a, rendezvous, gateway_c, addr_b) =
+            start_nat_topology(&network, temp_dir.path(), 45101, false).await;
+        let c_id = gateway_c.get_local_entry().await.id;
+        let a_id = gateway_a.get_local_entry().await.id;
+
+        // 双方经会合网关得知对方，初始只能经中继通信
+        let c_entry = gateway_a.registry().get(&c_id).expect("网关A 应当知晓网关C");
+        assert!(matches!(
+            gateway_a.relay_manager().route(gateway_a.registry(), &c_entry),
+            PeerRoute::Relay { relay_addr, .. } if relay_addr == addr_b
+        ));
+        assert!(gateway_a.hole_punch_stats().public_endpoint.is_some());
+
+        let outcome = gateway_a.establish_direct_path(&c_id).await.unwrap();
+        let PathOutcome::HolePunched(address) = outcome else {
+            panic!("打洞应当成功，实际结果: {outcome:?}");
+        };
+        assert_eq!(address.ip(), "203.0.113.2".parse::<IpAddr>().unwrap());
+
+        // A 改为直连，C 也在收到确认后切换为直连
+        let c_entry = gateway_a.registry().get(&c_id).unwrap();
+        assert_eq!(
+            gateway_a.relay_manager().route(gateway_a.registry(), &c_entry),
+            PeerRoute::Direct(address)
+        );
+        let mut c_direct = false;
+        for _ in 0..20 {
+            if gateway_c.relay_manager().is_direct(&a_id) {
+                c_direct = true;
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert!(c_direct, "网关C 应当确认到网关A 的直连");
+        assert_eq!(gateway_a.hole_punch_stats().succeeded, 1);
+
+        // 直连路径可以直接收发消息
+        let heartbeat = WdicMessage::heartbeat(a_id, 0);
+        assert_eq!(
+            gateway_a.send_to_peer(&c_id, &heartbeat).await.unwrap(),
+            PeerRoute::Direct(address)
+        );
+
+        for gateway in [&gateway_a, &gateway_c, &rendezvous] {
+            gateway.stop().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_hole_punch_falls_back_to_relay() {
+        let network = MemoryNetwork::new();
+        let temp_dir = TempDir::new().unwrap();
+        let (gateway_a, rendezvous, gateway_c, addr_b) =
+            start_nat_topology(&network, temp_dir.path(), 45102, true).await;
+        let c_id = gateway_c.get_local_entry().await.id;
+        exchange_announced_keys(&gateway_a, &gateway_c).await;
+
+        // 双方之间的直接流量被阻断，打洞失败后继续经中继
+        let outcome = gateway_a.establish_direct_path(&c_id).await.unwrap();
+        assert_eq!(outcome, PathOutcome::Relayed { relay_addr: addr_b });
+        let stats = gateway_a.hole_punch_stats();
+        assert_eq!((stats.attempts, stats.succeeded, stats.failed), (1, 0, 1));
+
+        let c_entry = gateway_a.registry().get(&c_id).unwrap();
+        assert!(matches!(
+            gateway_a.relay_manager().route(gateway_a.registry(), &c_entry),
+            PeerRoute::Relay { .. }
+        ));
+
+        // 冷却期内不会重复发起打洞
+        let outcome = gateway_a.establish_direct_path(&c_id).await.unwrap();
+        assert_eq!(outcome, PathOutcome::Relayed { relay_addr: addr_b });
+        assert_eq!(gateway_a.hole_punch_stats().attempts, 1);
+
+        // 中继路径仍然可用
+        let forwarded = rendezvous.relay_stats().forwarded_messages;
+        let heartbeat = WdicMessage::heartbeat(gateway_a.get_local_entry().await.id, 0);
+        gateway_a.send_to_peer(&c_id, &heartbeat).await.unwrap();
+        sleep(Duration::from_millis(200)).await;
+        assert!(rendezvous.relay_stats().forwarded_messages > forwarded);
+
+        for gateway in [&gateway_a, &gateway_c, &rendezvous] {
+            gateway.stop().await.unwrap();
+        }
+    }
 }
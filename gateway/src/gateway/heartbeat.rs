@@ -0,0 +1,472 @@
+//! 心跳调度模块
+//!
+//! 定期向注册表中的网关发送心跳，测量往返时间（RTT），
+//! 并参考 SWIM 协议将网关在 Alive → Suspect → Dead 状态之间迁移。
+//!
+//! 每次探测都带有递增的序号，只有回显当前未完成探测序号的响应才计入 RTT
+//! （Karn 算法，RFC 6298 第 3 节），迟到或重复的响应不会污染 RTT 估计。
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::gateway::registry::Registry;
+
+/// 状态变更事件通道容量
+const STATE_CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// 网关存活状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PeerLiveness {
+    /// 存活：最近的心跳已得到响应
+    Alive,
+    /// 可疑：连续若干次心跳未得到响应
+    Suspect,
+    /// 死亡：超过阈值仍未响应，将从注册表中移除
+    Dead,
+}
+
+/// 心跳调度配置
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    /// 连续丢失多少次心跳后标记为可疑
+    pub suspect_after_missed: u32,
+    /// 连续丢失多少次心跳后标记为死亡
+    pub dead_after_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            suspect_after_missed: 2,
+            dead_after_missed: 4,
+        }
+    }
+}
+
+/// 单个网关的健康信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerHealth {
+    /// 网关 ID
+    pub peer_id: Uuid,
+    /// 网关名称
+    pub name: String,
+    /// 网关地址
+    pub address: SocketAddr,
+    /// 存活状态
+    pub state: PeerLiveness,
+    /// 最近一次 RTT（毫秒）
+    pub last_rtt_ms: Option<f64>,
+    /// 平滑 RTT（毫秒）
+    pub smoothed_rtt_ms: Option<f64>,
+    /// RTT 抖动（平均偏差，毫秒）
+    pub jitter_ms: Option<f64>,
+    /// 连续丢失的心跳次数
+    pub missed_heartbeats: u32,
+    /// 最近一次收到心跳响应的时间
+    pub last_ack: Option<DateTime<Utc>>,
+    /// 进入当前状态的时间
+    pub state_since: DateTime<Utc>,
+}
+
+/// 网关状态变更事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStateChange {
+    /// 网关 ID
+    pub peer_id: Uuid,
+    /// 网关名称
+    pub name: String,
+    /// 网关地址
+    pub address: SocketAddr,
+    /// 之前的状态
+    pub previous: PeerLiveness,
+    /// 当前状态
+    pub current: PeerLiveness,
+    /// 平滑 RTT（毫秒）
+    pub smoothed_rtt_ms: Option<f64>,
+    /// 变更时间
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 心跳响应处理结果
+#[derive(Debug, Clone)]
+pub struct HeartbeatAck {
+    /// 本次 RTT（毫秒）
+    pub rtt_ms: f64,
+    /// 平滑 RTT（毫秒）
+    pub smoothed_rtt_ms: f64,
+    /// RTT 抖动（毫秒）
+    pub jitter_ms: f64,
+    /// 如果状态发生变化则包含变更事件
+    pub state_change: Option<PeerStateChange>,
+}
+
+/// 尚未得到响应的心跳探测（内部）
+#[derive(Debug, Clone, Copy)]
+struct PendingProbe {
+    /// 探测序号
+    sequence: u64,
+    /// 发送时间
+    sent_at: Instant,
+}
+
+/// 网关跟踪状态（内部）
+#[derive(Debug, Clone)]
+struct TrackedPeer {
+    health: PeerHealth,
+    /// 尚未得到响应的心跳探测
+    pending: Option<PendingProbe>,
+    /// 上一次探测使用的序号
+    last_sequence: u64,
+}
+
+/// 心跳调度器
+///
+/// 调度器本身不执行网络发送，只负责决定每一轮需要探测的网关、
+/// 计算 RTT 和迁移状态；实际发送由网关的心跳任务完成。
+#[derive(Debug)]
+pub struct HeartbeatScheduler {
+    /// 调度配置
+    config: HeartbeatConfig,
+    /// 被跟踪的网关 (lock-free)
+    peers: DashMap<Uuid, TrackedPeer>,
+    /// 状态变更事件发送端
+    state_sender: broadcast::Sender<PeerStateChange>,
+}
+
+impl HeartbeatScheduler {
+    /// 创建新的心跳调度器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 调度配置
+    ///
+    /// # 返回值
+    ///
+    /// 新的调度器实例
+    pub fn new(config: HeartbeatConfig) -> Self {
+        let (state_sender, _) = broadcast::channel(STATE_CHANGE_CHANNEL_CAPACITY);
+        Self {
+            config,
+            peers: DashMap::new(),
+            state_sender,
+        }
+    }
+
+    /// 订阅网关状态变更事件
+    pub fn subscribe(&self) -> broadcast::Receiver<PeerStateChange> {
+        self.state_sender.subscribe()
+    }
+
+    /// 开始新一轮心跳
+    ///
+    /// 与注册表同步被跟踪的网关，统计上一轮未响应的心跳并迁移状态，
+    /// 然后为本轮所有目标分配新的探测序号并标记为等待响应，上一轮未响应的探测随之作废。
+    /// 被判定为死亡的网关会从注册表中移除。
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 网关注册表
+    ///
+    /// # 返回值
+    ///
+    /// 本轮需要发送心跳的目标地址及探测序号列表，以及发生的状态变更
+    pub fn begin_round(
+        &self,
+        registry: &Registry,
+    ) -> (Vec<(Uuid, SocketAddr, u64)>, Vec<PeerStateChange>) {
+        let now = Instant::now();
+        let entries = registry.all_entries();
+
+        // 停止跟踪已不在注册表中的网关
+        self.peers
+            .retain(|id, _| entries.iter().any(|entry| entry.id == *id));
+
+        let mut targets = Vec::with_capacity(entries.len());
+        let mut changes = Vec::new();
+
+        for entry in entries {
+            let mut tracked = self.peers.entry(entry.id).or_insert_with(|| TrackedPeer {
+                health: PeerHealth {
+                    peer_id: entry.id,
+                    name: entry.name.clone(),
+                    address: entry.address,
+                    state: PeerLiveness::Alive,
+                    last_rtt_ms: None,
+                    smoothed_rtt_ms: None,
+                    jitter_ms: None,
+                    missed_heartbeats: 0,
+                    last_ack: None,
+                    state_since: Utc::now(),
+                },
+                pending: None,
+                last_sequence: 0,
+            });
+
+            // 地址或名称可能已通过广播更新
+            tracked.health.name = entry.name.clone();
+            tracked.health.address = entry.address;
+
+            if tracked.pending.is_some() {
+                tracked.health.missed_heartbeats += 1;
+                let next_state = self.state_for_missed(tracked.health.missed_heartbeats);
+                if let Some(change) = Self::transition(&mut tracked.health, next_state) {
+                    changes.push(change);
+                }
+            }
+
+            if tracked.health.state == PeerLiveness::Dead {
+                continue;
+            }
+
+            tracked.last_sequence += 1;
+            let sequence = tracked.last_sequence;
+            tracked.pending = Some(PendingProbe {
+                sequence,
+                sent_at: now,
+            });
+            targets.push((entry.id, entry.address, sequence));
+        }
+
+        // 死亡的网关从注册表和跟踪表中移除
+        for change in &changes {
+            if change.current == PeerLiveness::Dead {
+                registry.remove(&change.peer_id);
+                self.peers.remove(&change.peer_id);
+                info!("网关 '{}' ({}) 心跳超时，已移除", change.name, change.address);
+            }
+        }
+
+        for change in &changes {
+            let _ = self.state_sender.send(change.clone());
+        }
+
+        (targets, changes)
+    }
+
+    /// 处理心跳响应
+    ///
+    /// 响应回显的序号与未完成探测一致时，使用 RFC 6298 的平滑算法更新 RTT 估计，
+    /// 并将网关恢复为存活状态。序号不一致的响应属于已作废或重复的探测，直接忽略；
+    /// 旧版本网关不回显序号（为 0），其响应只用于恢复存活状态，不计入 RTT。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 响应者 ID
+    /// * `sequence` - 响应回显的探测序号
+    ///
+    /// # 返回值
+    ///
+    /// 如果响应对应当前未完成的探测，返回 RTT 统计；否则返回 None
+    pub fn record_ack(&self, peer_id: &Uuid, sequence: u64) -> Option<HeartbeatAck> {
+        let mut tracked = self.peers.get_mut(peer_id)?;
+        let pending = tracked.pending?;
+
+        if sequence == 0 {
+            tracked.pending = None;
+            tracked.health.missed_heartbeats = 0;
+            tracked.health.last_ack = Some(Utc::now());
+            if let Some(change) = Self::transition(&mut tracked.health, PeerLiveness::Alive) {
+                let _ = self.state_sender.send(change);
+            }
+            return None;
+        }
+        if sequence != pending.sequence {
+            debug!(
+                "忽略网关 {peer_id} 的过期心跳响应 #{sequence}（当前探测 #{}）",
+                pending.sequence
+            );
+            return None;
+        }
+
+        tracked.pending = None;
+        let rtt_ms = pending.sent_at.elapsed().as_secs_f64() * 1000.0;
+
+        let health = &mut tracked.health;
+        let (smoothed, jitter) = match (health.smoothed_rtt_ms, health.jitter_ms) {
+            (Some(srtt), Some(rttvar)) => {
+                let rttvar = 0.75 * rttvar + 0.25 * (srtt - rtt_ms).abs();
+                let srtt = 0.875 * srtt + 0.125 * rtt_ms;
+                (srtt, rttvar)
+            }
+            _ => (rtt_ms, rtt_ms / 2.0),
+        };
+
+        health.last_rtt_ms = Some(rtt_ms);
+        health.smoothed_rtt_ms = Some(smoothed);
+        health.jitter_ms = Some(jitter);
+        health.missed_heartbeats = 0;
+        health.last_ack = Some(Utc::now());
+
+        let state_change = Self::transition(health, PeerLiveness::Alive);
+        if let Some(ref change) = state_change {
+            let _ = self.state_sender.send(change.clone());
+        }
+
+        debug!("网关 {peer_id} 心跳 RTT {rtt_ms:.2} ms（平滑 {smoothed:.2} ms，抖动 {jitter:.2} ms）");
+
+        Some(HeartbeatAck {
+            rtt_ms,
+            smoothed_rtt_ms: smoothed,
+            jitter_ms: jitter,
+            state_change,
+        })
+    }
+
+    /// 获取指定网关的健康信息
+    pub fn get(&self, peer_id: &Uuid) -> Option<PeerHealth> {
+        self.peers.get(peer_id).map(|tracked| tracked.health.clone())
+    }
+
+    /// 获取所有被跟踪网关的健康信息
+    pub fn snapshot(&self) -> Vec<PeerHealth> {
+        self.peers
+            .iter()
+            .map(|tracked| tracked.health.clone())
+            .collect()
+    }
+
+    /// 停止跟踪指定网关
+    pub fn remove(&self, peer_id: &Uuid) -> bool {
+        self.peers.remove(peer_id).is_some()
+    }
+
+    /// 根据丢失次数计算目标状态
+    fn state_for_missed(&self, missed: u32) -> PeerLiveness {
+        if missed >= self.config.dead_after_missed {
+            PeerLiveness::Dead
+        } else if missed >= self.config.suspect_after_missed {
+            PeerLiveness::Suspect
+        } else {
+            PeerLiveness::Alive
+        }
+    }
+
+    /// 迁移状态，返回变更事件
+    fn transition(health: &mut PeerHealth, next: PeerLiveness) -> Option<PeerStateChange> {
+        if health.state == next {
+            return None;
+        }
+
+        let change = PeerStateChange {
+            peer_id: health.peer_id,
+            name: health.name.clone(),
+            address: health.address,
+            previous: health.state,
+            current: next,
+            smoothed_rtt_ms: health.smoothed_rtt_ms,
+            timestamp: Utc::now(),
+        };
+        health.state = next;
+        health.state_since = change.timestamp;
+        Some(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::registry::RegistryEntry;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn create_test_address(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), port)
+    }
+
+    #[test]
+    fn test_liveness_transitions() {
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+        let entry = RegistryEntry::new("远程网关".to_string(), create_test_address(55556));
+        registry.add_or_update(entry.clone());
+
+        let scheduler = HeartbeatScheduler::new(HeartbeatConfig {
+            suspect_after_missed: 1,
+            dead_after_missed: 3,
+        });
+        let mut receiver = scheduler.subscribe();
+
+        // 第一轮：开始跟踪，无状态变化
+        let (targets, changes) = scheduler.begin_round(&registry);
+        assert_eq!(targets, vec![(entry.id, entry.address, 1)]);
+        assert!(changes.is_empty());
+
+        // 第二轮：上一轮未响应，进入可疑状态
+        let (targets, changes) = scheduler.begin_round(&registry);
+        assert_eq!(targets[0].2, 2);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].current, PeerLiveness::Suspect);
+        assert_eq!(receiver.try_recv().unwrap().current, PeerLiveness::Suspect);
+
+        // 收到响应后恢复存活
+        let ack = scheduler.record_ack(&entry.id, 2).unwrap();
+        assert!(ack.rtt_ms >= 0.0);
+        assert_eq!(ack.state_change.unwrap().current, PeerLiveness::Alive);
+        assert_eq!(scheduler.get(&entry.id).unwrap().missed_heartbeats, 0);
+
+        // 重复响应不产生新的 RTT 样本
+        assert!(scheduler.record_ack(&entry.id, 2).is_none());
+
+        // 连续丢失直到死亡，并从注册表中移除
+        scheduler.begin_round(&registry);
+        scheduler.begin_round(&registry);
+        scheduler.begin_round(&registry);
+        let (targets, changes) = scheduler.begin_round(&registry);
+        assert!(targets.is_empty());
+        assert_eq!(changes.last().unwrap().current, PeerLiveness::Dead);
+        assert!(registry.get(&entry.id).is_none());
+        assert!(scheduler.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_rtt_smoothing() {
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+        let entry = RegistryEntry::new("远程网关".to_string(), create_test_address(55556));
+        registry.add_or_update(entry.clone());
+
+        let scheduler = HeartbeatScheduler::new(HeartbeatConfig::default());
+
+        scheduler.begin_round(&registry);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let first = scheduler.record_ack(&entry.id, 1).unwrap();
+        assert_eq!(first.smoothed_rtt_ms, first.rtt_ms);
+        assert!(first.state_change.is_none());
+
+        scheduler.begin_round(&registry);
+        let second = scheduler.record_ack(&entry.id, 2).unwrap();
+        let expected = 0.875 * first.smoothed_rtt_ms + 0.125 * second.rtt_ms;
+        assert!((second.smoothed_rtt_ms - expected).abs() < 1e-9);
+        assert!(second.jitter_ms >= 0.0);
+    }
+
+    #[test]
+    fn test_stale_ack_is_not_sampled() {
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+        let entry = RegistryEntry::new("远程网关".to_string(), create_test_address(55556));
+        registry.add_or_update(entry.clone());
+
+        let scheduler = HeartbeatScheduler::new(HeartbeatConfig::default());
+
+        // 第 1 次探测未及时响应，第 2 次探测发出后才收到其响应
+        scheduler.begin_round(&registry);
+        scheduler.begin_round(&registry);
+        assert!(scheduler.record_ack(&entry.id, 1).is_none());
+        assert!(scheduler.get(&entry.id).unwrap().last_rtt_ms.is_none());
+
+        // 当前探测的响应仍可计入 RTT
+        let ack = scheduler.record_ack(&entry.id, 2).unwrap();
+        assert_eq!(scheduler.get(&entry.id).unwrap().last_rtt_ms, Some(ack.rtt_ms));
+
+        // 未回显序号的响应只恢复存活状态
+        scheduler.begin_round(&registry);
+        assert!(scheduler.record_ack(&entry.id, 0).is_none());
+        let health = scheduler.get(&entry.id).unwrap();
+        assert_eq!(health.last_rtt_ms, Some(ack.rtt_ms));
+        assert_eq!(health.missed_heartbeats, 0);
+        assert!(scheduler.record_ack(&entry.id, 3).is_none());
+    }
+}
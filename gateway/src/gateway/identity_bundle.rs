@@ -0,0 +1,844 @@
+//! 网关身份导入导出模块
+//!
+//! 将网关身份、本地 CA、服务端和客户端证书以及受信任设备打包成受密码保护的文件，
+//! 用于备份网关或迁移到新机器。支持两种格式：
+//!
+//! * **PEM 包**：完整备份，包含受信任设备和吊销列表，
+//!   以 PBKDF2-HMAC-SHA256 派生的密钥经 ChaCha20-Poly1305 加密
+//! * **PKCS#12**：可与其他工具互通，只包含证书、私钥和受信任 CA，
+//!   导入时由受信任 CA 恢复受信任设备
+//!
+//! 导入时重新计算并核对所有证书指纹，并检查证书链和私钥是否匹配。
+
+use anyhow::{anyhow, Context, Result};
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+use rcgen::{KeyPair, PublicKeyData};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::Path;
+use uuid::Uuid;
+use x509_parser::prelude::*;
+
+use crate::gateway::gateway::GatewayConfig;
+use crate::gateway::pairing::{PairingManager, TrustedDevice};
+use crate::gateway::peer_store::PeerStore;
+use crate::gateway::tls::{write_private_key_file, MtlsConfig, Revocation, TlsManager};
+
+/// PEM 包格式标识
+const BUNDLE_FORMAT_TAG: &str = "wdic-identity-bundle";
+
+/// PEM 包格式版本
+const BUNDLE_VERSION: u32 = 1;
+
+/// 导出时使用的 PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// 导入时接受的最大 PBKDF2 迭代次数，避免恶意文件耗尽 CPU
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// PBKDF2 盐值长度（字节）
+const SALT_LEN: usize = 16;
+
+/// PKCS#12 中受信任 CA 条目的别名前缀
+///
+/// 别名为 `trusted:<文件名>`，属于受信任设备时为 `trusted:<网关 ID>/<设备名称>`。
+const TRUSTED_CA_ALIAS_PREFIX: &str = "trusted:";
+
+/// 身份包密码的最小长度
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// 身份包格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleFormat {
+    /// 加密的 PEM 包（完整备份）
+    #[default]
+    PemBundle,
+    /// PKCS#12
+    Pkcs12,
+}
+
+/// 身份包中的证书与私钥
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// 证书（PEM）
+    pub certificate: String,
+    /// 私钥（PEM）
+    pub private_key: String,
+    /// 证书指纹（SHA-256）
+    pub fingerprint: String,
+}
+
+/// 身份包中的受信任 CA
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedCaEntry {
+    /// 受信任 CA 的文件名（不含扩展名）
+    pub name: String,
+    /// CA 证书（PEM）
+    pub certificate: String,
+    /// CA 证书指纹（SHA-256）
+    pub fingerprint: String,
+}
+
+/// 网关身份包
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityBundle {
+    /// 网关唯一标识
+    pub gateway_id: Option<Uuid>,
+    /// 网关名称
+    pub gateway_name: String,
+    /// 导出时间
+    pub exported_at: DateTime<Utc>,
+    /// 本地 CA
+    pub ca: BundleEntry,
+    /// 服务端证书
+    pub server: BundleEntry,
+    /// 客户端证书
+    pub client: BundleEntry,
+    /// 受信任 CA
+    pub trusted_cas: Vec<TrustedCaEntry>,
+    /// 受信任设备
+    #[serde(default)]
+    pub trusted_devices: Vec<TrustedDevice>,
+    /// 证书吊销列表
+    #[serde(default)]
+    pub revocations: Vec<Revocation>,
+}
+
+/// 身份包摘要，导出后展示给用户，导入时用于核对
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityBundleSummary {
+    /// 网关唯一标识
+    pub gateway_id: Option<Uuid>,
+    /// 网关名称
+    pub gateway_name: String,
+    /// 本地 CA 指纹
+    pub ca_fingerprint: String,
+    /// 服务端证书指纹
+    pub server_fingerprint: String,
+    /// 客户端证书指纹
+    pub client_fingerprint: String,
+    /// 受信任 CA 数量
+    pub trusted_cas: usize,
+    /// 受信任设备数量
+    pub trusted_devices: usize,
+    /// 导出时间
+    pub exported_at: DateTime<Utc>,
+}
+
+/// 加密的 PEM 包文件
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedBundle {
+    /// 格式标识
+    format: String,
+    /// 格式版本
+    version: u32,
+    /// PBKDF2 迭代次数
+    iterations: u32,
+    /// PBKDF2 盐值（Base64）
+    salt: String,
+    /// `nonce || 密文 || 认证标签`（Base64）
+    ciphertext: String,
+}
+
+impl IdentityBundle {
+    /// 从 TLS 管理器的证书缓存和受信任 CA 目录收集身份
+    ///
+    /// # 参数
+    ///
+    /// * `tls_manager` - TLS 管理器
+    /// * `gateway_id` - 网关唯一标识
+    /// * `gateway_name` - 网关名称
+    /// * `trusted_devices` - 受信任设备
+    ///
+    /// # 返回值
+    ///
+    /// 身份包，CA 私钥缺失时返回错误
+    pub fn collect(
+        tls_manager: &TlsManager,
+        gateway_id: Option<Uuid>,
+        gateway_name: String,
+        trusted_devices: Vec<TrustedDevice>,
+    ) -> Result<Self> {
+        let entry = |name: &str| -> Result<BundleEntry> {
+            let certificate = tls_manager
+                .get_certificate(name)
+                .map(|data| String::from_utf8_lossy(&data).into_owned())
+                .ok_or_else(|| anyhow!("缺少 {name} 证书"))?;
+            let private_key = tls_manager
+                .get_private_key(name)
+                .map(|data| String::from_utf8_lossy(&data).into_owned())
+                .ok_or_else(|| anyhow!("缺少 {name} 私钥"))?;
+            Ok(BundleEntry {
+                fingerprint: pem_fingerprint(&certificate)?,
+                certificate,
+                private_key,
+            })
+        };
+
+        let trusted_cas = tls_manager
+            .trusted_ca_certificates()?
+            .into_iter()
+            .map(|(name, certificate)| {
+                Ok(TrustedCaEntry {
+                    fingerprint: TlsManager::ca_pem_fingerprint(&certificate)?,
+                    name,
+                    certificate,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            gateway_id,
+            gateway_name,
+            exported_at: Utc::now(),
+            ca: entry("ca")?,
+            server: entry("server")?,
+            client: entry("client")?,
+            trusted_cas,
+            trusted_devices,
+            revocations: tls_manager.revocations(),
+        })
+    }
+
+    /// 获取身份包摘要
+    pub fn summary(&self) -> IdentityBundleSummary {
+        IdentityBundleSummary {
+            gateway_id: self.gateway_id,
+            gateway_name: self.gateway_name.clone(),
+            ca_fingerprint: self.ca.fingerprint.clone(),
+            server_fingerprint: self.server.fingerprint.clone(),
+            client_fingerprint: self.client.fingerprint.clone(),
+            trusted_cas: self.trusted_cas.len(),
+            trusted_devices: self.trusted_devices.len(),
+            exported_at: self.exported_at,
+        }
+    }
+
+    /// 核对身份包
+    ///
+    /// 重新计算所有证书指纹并与记录的指纹比较，检查服务端和客户端证书由包内 CA 签发、
+    /// 私钥与证书匹配、证书声明的网关 ID 与身份一致，以及受信任设备与受信任 CA 对应。
+    ///
+    /// # 参数
+    ///
+    /// * `expected_ca_fingerprint` - 用户预期的 CA 指纹，通常来自导出时的摘要
+    pub fn verify(&self, expected_ca_fingerprint: Option<&str>) -> Result<()> {
+        let ca_fingerprint = TlsManager::ca_pem_fingerprint(&self.ca.certificate)?;
+        if ca_fingerprint != self.ca.fingerprint {
+            return Err(anyhow!("CA 证书指纹不匹配"));
+        }
+        if let Some(expected) = expected_ca_fingerprint {
+            let expected = expected.replace(':', "").to_ascii_lowercase();
+            if expected != ca_fingerprint {
+                return Err(anyhow!(
+                    "CA 证书指纹 {ca_fingerprint} 与预期的 {expected} 不一致"
+                ));
+            }
+        }
+        Self::verify_key(&self.ca, "CA")?;
+
+        let ca_der = pem_der(&self.ca.certificate)?;
+        let (_, ca) =
+            X509Certificate::from_der(&ca_der).map_err(|e| anyhow!("CA 证书解析失败: {e}"))?;
+        for (entry, label) in [(&self.server, "服务端"), (&self.client, "客户端")] {
+            if pem_fingerprint(&entry.certificate)? != entry.fingerprint {
+                return Err(anyhow!("{label}证书指纹不匹配"));
+            }
+            let der = pem_der(&entry.certificate)?;
+            let (_, cert) =
+                X509Certificate::from_der(&der).map_err(|e| anyhow!("{label}证书解析失败: {e}"))?;
+            if !TlsManager::is_issued_by(&cert, &ca) {
+                return Err(anyhow!("{label}证书不是由包内 CA 签发"));
+            }
+            if self.gateway_id.is_some()
+                && TlsManager::certificate_gateway_id(&cert) != self.gateway_id
+            {
+                return Err(anyhow!("{label}证书声明的网关 ID 与身份不一致"));
+            }
+            Self::verify_key(entry, label)?;
+        }
+
+        for trusted in &self.trusted_cas {
+            if TlsManager::ca_pem_fingerprint(&trusted.certificate)? != trusted.fingerprint {
+                return Err(anyhow!("受信任 CA '{}' 的指纹不匹配", trusted.name));
+            }
+            if !is_valid_file_name(&trusted.name) {
+                return Err(anyhow!("受信任 CA 名称无效: {}", trusted.name));
+            }
+        }
+        for device in &self.trusted_devices {
+            if TlsManager::ca_pem_fingerprint(&device.certificate)? != device.fingerprint {
+                return Err(anyhow!("受信任设备 '{}' 的证书指纹不匹配", device.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 检查私钥与证书公钥是否匹配
+    fn verify_key(entry: &BundleEntry, label: &str) -> Result<()> {
+        let key = KeyPair::from_pem(&entry.private_key)
+            .map_err(|e| anyhow!("{label}私钥解析失败: {e}"))?;
+        let der = pem_der(&entry.certificate)?;
+        let (_, cert) =
+            X509Certificate::from_der(&der).map_err(|e| anyhow!("{label}证书解析失败: {e}"))?;
+        if key.subject_public_key_info() != cert.public_key().raw {
+            return Err(anyhow!("{label}私钥与证书不匹配"));
+        }
+        Ok(())
+    }
+
+    /// 导出为受密码保护的文件内容
+    ///
+    /// # 参数
+    ///
+    /// * `format` - 身份包格式
+    /// * `password` - 保护密码
+    pub fn export(&self, format: BundleFormat, password: &str) -> Result<Vec<u8>> {
+        if password.chars().count() < MIN_PASSWORD_LEN {
+            return Err(anyhow!("密码至少需要 {MIN_PASSWORD_LEN} 个字符"));
+        }
+
+        match format {
+            BundleFormat::PemBundle => self.seal(password, PBKDF2_ITERATIONS),
+            BundleFormat::Pkcs12 => self.to_pkcs12(password),
+        }
+    }
+
+    /// 解密身份包文件内容
+    ///
+    /// 根据内容自动识别 PEM 包或 PKCS#12 格式。返回的身份包尚未核对，
+    /// 安装前应调用 [`IdentityBundle::verify`]。
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 文件内容
+    /// * `password` - 保护密码
+    pub fn open(data: &[u8], password: &str) -> Result<Self> {
+        match data.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => Self::unseal(data, password),
+            Some(0x30) => Self::from_pkcs12(data, password),
+            _ => Err(anyhow!("无法识别的身份包格式")),
+        }
+    }
+
+    /// 以 PEM 包格式加密
+    fn seal(&self, password: &str, iterations: u32) -> Result<Vec<u8>> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt).map_err(|_| anyhow!("生成盐值失败"))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut nonce)
+            .map_err(|_| anyhow!("生成 nonce 失败"))?;
+
+        let key = derive_key(password, &salt, iterations)?;
+        let mut in_out = serde_json::to_vec(self).context("序列化身份包失败")?;
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(bundle_aad(iterations)),
+            &mut in_out,
+        )
+        .map_err(|_| anyhow!("加密身份包失败"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&in_out);
+        let file = EncryptedBundle {
+            format: BUNDLE_FORMAT_TAG.to_string(),
+            version: BUNDLE_VERSION,
+            iterations,
+            salt: BASE64_STANDARD.encode(salt),
+            ciphertext: BASE64_STANDARD.encode(sealed),
+        };
+        serde_json::to_vec_pretty(&file).context("序列化身份包文件失败")
+    }
+
+    /// 解密 PEM 包
+    fn unseal(data: &[u8], password: &str) -> Result<Self> {
+        let file: EncryptedBundle = serde_json::from_slice(data).context("身份包文件格式无效")?;
+        if file.format != BUNDLE_FORMAT_TAG {
+            return Err(anyhow!("不是 WDIC 身份包: {}", file.format));
+        }
+        if file.version > BUNDLE_VERSION {
+            return Err(anyhow!("不支持的身份包版本: {}", file.version));
+        }
+        if file.iterations > MAX_PBKDF2_ITERATIONS {
+            return Err(anyhow!("身份包的密钥派生迭代次数过大: {}", file.iterations));
+        }
+
+        let salt = BASE64_STANDARD
+            .decode(&file.salt)
+            .context("身份包盐值格式无效")?;
+        let mut sealed = BASE64_STANDARD
+            .decode(&file.ciphertext)
+            .context("身份包密文格式无效")?;
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("身份包密文过短"));
+        }
+        let mut in_out = sealed.split_off(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(&sealed).map_err(|_| anyhow!("身份包 nonce 无效"))?;
+
+        let key = derive_key(password, &salt, file.iterations)?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(bundle_aad(file.iterations)), &mut in_out)
+            .map_err(|_| anyhow!("密码错误或身份包已损坏"))?;
+        serde_json::from_slice(plaintext).context("身份包内容格式无效")
+    }
+
+    /// 以 PKCS#12 格式导出
+    ///
+    /// CA、服务端和客户端证书以私钥条目保存，受信任 CA 以受信任证书条目保存。
+    fn to_pkcs12(&self, password: &str) -> Result<Vec<u8>> {
+        let ca_cert = p12_certificate(&self.ca.certificate)?;
+        let mut keystore = KeyStore::new();
+
+        for (alias, entry) in [
+            ("ca", &self.ca),
+            ("server", &self.server),
+            ("client", &self.client),
+        ] {
+            let key = KeyPair::from_pem(&entry.private_key)
+                .map_err(|e| anyhow!("{alias} 私钥解析失败: {e}"))?;
+            let mut chain = vec![p12_certificate(&entry.certificate)?];
+            if alias != "ca" {
+                chain.push(ca_cert.clone());
+            }
+            let local_key_id = ring::digest::digest(&ring::digest::SHA256, chain[0].as_der());
+            keystore.add_entry(
+                alias,
+                KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+                    key.serialize_der(),
+                    local_key_id.as_ref(),
+                    chain,
+                )),
+            );
+        }
+        for trusted in &self.trusted_cas {
+            let device = self
+                .trusted_devices
+                .iter()
+                .find(|device| device.gateway_id.to_string() == trusted.name);
+            let alias = match device {
+                Some(device) => {
+                    format!("{TRUSTED_CA_ALIAS_PREFIX}{}/{}", trusted.name, device.name)
+                }
+                None => format!("{TRUSTED_CA_ALIAS_PREFIX}{}", trusted.name),
+            };
+            keystore.add_entry(
+                &alias,
+                KeyStoreEntry::Certificate(p12_certificate(&trusted.certificate)?),
+            );
+        }
+
+        keystore
+            .writer(password)
+            .write()
+            .map_err(|e| anyhow!("生成 PKCS#12 文件失败: {e}"))
+    }
+
+    /// 从 PKCS#12 导入
+    ///
+    /// 网关 ID 与名称取自服务端证书；带设备名称的受信任 CA 恢复为受信任设备，
+    /// 配对时间记为导入时间。
+    fn from_pkcs12(data: &[u8], password: &str) -> Result<Self> {
+        let keystore = KeyStore::from_pkcs12(data, password)
+            .map_err(|e| anyhow!("密码错误或 PKCS#12 文件已损坏: {e}"))?;
+
+        let entry = |alias: &str| -> Result<BundleEntry> {
+            let Some(KeyStoreEntry::PrivateKeyChain(chain)) = keystore.entry(alias) else {
+                return Err(anyhow!("PKCS#12 文件缺少 {alias} 私钥条目"));
+            };
+            let leaf = chain
+                .chain()
+                .first()
+                .ok_or_else(|| anyhow!("PKCS#12 文件的 {alias} 条目缺少证书"))?;
+            let key =
+                KeyPair::try_from(chain.key()).map_err(|e| anyhow!("{alias} 私钥解析失败: {e}"))?;
+            Ok(BundleEntry {
+                certificate: der_to_pem(leaf.as_der()),
+                private_key: key.serialize_pem(),
+                fingerprint: TlsManager::fingerprint(leaf.as_der()),
+            })
+        };
+        let (ca, server, client) = (entry("ca")?, entry("server")?, entry("client")?);
+
+        let server_der = pem_der(&server.certificate)?;
+        let (_, server_cert) = X509Certificate::from_der(&server_der)
+            .map_err(|e| anyhow!("服务端证书解析失败: {e}"))?;
+        let gateway_id = TlsManager::certificate_gateway_id(&server_cert);
+        let gateway_name = server_cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|name| name.as_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut trusted_cas = Vec::new();
+        let mut trusted_devices = Vec::new();
+        for (alias, entry) in keystore.entries() {
+            let (Some(label), KeyStoreEntry::Certificate(cert)) =
+                (alias.strip_prefix(TRUSTED_CA_ALIAS_PREFIX), entry)
+            else {
+                continue;
+            };
+            let (name, device_name) = match label.split_once('/') {
+                Some((name, device_name)) => (name, Some(device_name)),
+                None => (label, None),
+            };
+            let certificate = der_to_pem(cert.as_der());
+            let fingerprint = TlsManager::fingerprint(cert.as_der());
+            if let (Some(device_name), Ok(device_id)) = (device_name, Uuid::parse_str(name)) {
+                trusted_devices.push(TrustedDevice {
+                    gateway_id: device_id,
+                    name: device_name.to_string(),
+                    fingerprint: fingerprint.clone(),
+                    certificate: certificate.clone(),
+                    paired_at: Utc::now(),
+                    leaf_fingerprints: Vec::new(),
+                    rotated_at: None,
+                    agreement_key: None,
+                });
+            }
+            trusted_cas.push(TrustedCaEntry {
+                name: name.to_string(),
+                certificate,
+                fingerprint,
+            });
+        }
+
+        Ok(Self {
+            gateway_id,
+            gateway_name,
+            exported_at: Utc::now(),
+            ca,
+            server,
+            client,
+            trusted_cas,
+            trusted_devices,
+            revocations: Vec::new(),
+        })
+    }
+
+    /// 将身份写入网关配置指定的位置
+    ///
+    /// 替换 CA、服务端和客户端证书与私钥，用包内的受信任 CA 替换受信任目录，
+    /// 并恢复受信任设备、吊销列表和网关唯一标识。应在网关停止时调用，
+    /// 下次启动网关时生效。
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 网关配置
+    pub fn install(&self, config: &GatewayConfig) -> Result<()> {
+        self.verify(None)?;
+        let tls_config = &config.tls_config;
+        if let Some(parent) = tls_config.ca_cert_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("创建证书目录失败: {parent:?}"))?;
+        }
+
+        let entries = [
+            (
+                &self.ca,
+                tls_config.ca_cert_path.clone(),
+                tls_config.ca_cert_path.with_extension("key"),
+            ),
+            (
+                &self.server,
+                tls_config.server_cert_path.clone(),
+                tls_config.server_key_path.clone(),
+            ),
+            (
+                &self.client,
+                tls_config.client_cert_path.clone(),
+                tls_config.client_key_path.clone(),
+            ),
+        ];
+        for (entry, cert_path, key_path) in &entries {
+            let key_tmp = key_path.with_extension("key.tmp");
+            write_private_key_file(&key_tmp, entry.private_key.as_bytes())?;
+            let cert_tmp = cert_path.with_extension("crt.tmp");
+            std::fs::write(&cert_tmp, &entry.certificate)
+                .with_context(|| format!("写入证书文件失败: {cert_tmp:?}"))?;
+            std::fs::rename(&key_tmp, key_path)
+                .with_context(|| format!("替换私钥文件失败: {key_path:?}"))?;
+            std::fs::rename(&cert_tmp, cert_path)
+                .with_context(|| format!("替换证书文件失败: {cert_path:?}"))?;
+        }
+
+        Self::replace_trusted_cas(tls_config, &self.trusted_cas)?;
+
+        // 重新加载以确认证书可用，并恢复吊销列表
+        let tls_manager = TlsManager::new(tls_config.clone()).context("加载导入的证书失败")?;
+        if tls_manager.ca_fingerprint()? != self.ca.fingerprint {
+            return Err(anyhow!("导入后的 CA 证书指纹不一致"));
+        }
+        for revocation in &self.revocations {
+            if let Err(e) = tls_manager.revoke(revocation.clone()) {
+                warn!("恢复吊销记录失败: {e}");
+            }
+        }
+
+        PairingManager::new(
+            config.trusted_devices_path.clone(),
+            self.ca.fingerprint.clone(),
+        )
+        .restore(self.trusted_devices.clone())?;
+
+        if let Some(gateway_id) = self.gateway_id {
+            let mut peer_store =
+                PeerStore::load(config.peer_store_path.clone(), config.peer_max_age)
+                    .unwrap_or_else(|e| {
+                        warn!("加载已知网关失败，将从空列表开始: {e}");
+                        PeerStore::new(config.peer_store_path.clone(), config.peer_max_age)
+                    });
+            peer_store.set_local_id(gateway_id);
+            peer_store.save()?;
+        }
+
+        info!(
+            "已导入网关 '{}' 的身份，CA 指纹 {}，{} 个受信任设备",
+            self.gateway_name,
+            self.ca.fingerprint,
+            self.trusted_devices.len()
+        );
+        Ok(())
+    }
+
+    /// 用包内的受信任 CA 替换受信任目录中的证书
+    fn replace_trusted_cas(tls_config: &MtlsConfig, trusted_cas: &[TrustedCaEntry]) -> Result<()> {
+        let dir = &tls_config.trusted_ca_dir;
+        std::fs::create_dir_all(dir).with_context(|| format!("创建受信任 CA 目录失败: {dir:?}"))?;
+
+        for entry in
+            std::fs::read_dir(dir).with_context(|| format!("读取受信任 CA 目录失败: {dir:?}"))?
+        {
+            let path = entry?.path();
+            if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("crt") | Some("pem")
+            ) {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("删除受信任 CA 证书失败: {path:?}"))?;
+            }
+        }
+        for trusted in trusted_cas {
+            let path = dir.join(format!("{}.crt", trusted.name));
+            std::fs::write(&path, &trusted.certificate)
+                .with_context(|| format!("写入受信任 CA 证书失败: {path:?}"))?;
+        }
+        Ok(())
+    }
+
+    /// 写入身份包文件
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 目标文件路径
+    /// * `format` - 身份包格式
+    /// * `password` - 保护密码
+    pub fn export_to_file(&self, path: &Path, format: BundleFormat, password: &str) -> Result<()> {
+        let data = self.export(format, password)?;
+        write_private_key_file(path, &data)
+    }
+}
+
+/// 以 PBKDF2-HMAC-SHA256 由密码派生加密密钥
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey> {
+    let iterations =
+        NonZeroU32::new(iterations).ok_or_else(|| anyhow!("密钥派生迭代次数不能为 0"))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        password.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| anyhow!("创建加密密钥失败"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// 绑定格式、版本和迭代次数的附加认证数据
+fn bundle_aad(iterations: u32) -> Vec<u8> {
+    let mut aad = BUNDLE_FORMAT_TAG.as_bytes().to_vec();
+    aad.extend_from_slice(&BUNDLE_VERSION.to_be_bytes());
+    aad.extend_from_slice(&iterations.to_be_bytes());
+    aad
+}
+
+/// 解码 PEM 证书
+fn pem_der(pem: &str) -> Result<Vec<u8>> {
+    TlsManager::pem_to_der(pem.as_bytes())?.ok_or_else(|| anyhow!("证书不是 PEM 格式"))
+}
+
+/// 计算 PEM 证书的指纹
+fn pem_fingerprint(pem: &str) -> Result<String> {
+    Ok(TlsManager::fingerprint(&pem_der(pem)?))
+}
+
+/// 将 DER 证书编码为 PEM
+fn der_to_pem(der: &[u8]) -> String {
+    let encoded = BASE64_STANDARD.encode(der);
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+/// 将 PEM 证书转换为 PKCS#12 证书
+fn p12_certificate(pem: &str) -> Result<p12_keystore::Certificate> {
+    p12_keystore::Certificate::from_der(&pem_der(pem)?)
+        .map_err(|e| anyhow!("PKCS#12 证书转换失败: {e}"))
+}
+
+/// 受信任 CA 名称只能作为文件名使用，不能包含路径分隔符
+fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::tls::CertificateIdentity;
+    use tempfile::tempdir;
+
+    fn gateway_config_in(dir: &Path) -> GatewayConfig {
+        GatewayConfig {
+            tls_config: MtlsConfig {
+                ca_cert_path: dir.join("certs/ca.crt"),
+                server_cert_path: dir.join("certs/server.crt"),
+                server_key_path: dir.join("certs/server.key"),
+                client_cert_path: dir.join("certs/client.crt"),
+                client_key_path: dir.join("certs/client.key"),
+                trusted_ca_dir: dir.join("certs/trusted"),
+                ..Default::default()
+            },
+            trusted_devices_path: dir.join("trusted_devices.json"),
+            peer_store_path: dir.join("peers.json"),
+            ..Default::default()
+        }
+    }
+
+    fn bundle_in(dir: &Path) -> Result<IdentityBundle> {
+        let config = gateway_config_in(dir);
+        let gateway_id = Uuid::new_v4();
+        let tls_manager = TlsManager::with_identity(
+            config.tls_config.clone(),
+            CertificateIdentity::for_gateway("旧网关", gateway_id, &[]),
+        )?;
+
+        // 与另一个网关配对，使其 CA 进入受信任目录
+        let peer_dir = tempdir()?;
+        let peer_id = Uuid::new_v4();
+        let peer = TlsManager::with_identity(
+            gateway_config_in(peer_dir.path()).tls_config,
+            CertificateIdentity::for_gateway("对端网关", peer_id, &[]),
+        )?;
+        let peer_ca = peer.ca_certificate_pem().unwrap();
+        let fingerprint = tls_manager.trust_ca(&peer_id.to_string(), &peer_ca)?;
+        let device = TrustedDevice {
+            gateway_id: peer_id,
+            name: "客厅 NAS".to_string(),
+            fingerprint,
+            certificate: peer_ca,
+            paired_at: Utc::now(),
+            leaf_fingerprints: Vec::new(),
+            rotated_at: None,
+            agreement_key: None,
+        };
+
+        IdentityBundle::collect(
+            &tls_manager,
+            Some(gateway_id),
+            "旧网关".to_string(),
+            vec![device],
+        )
+    }
+
+    #[test]
+    fn test_pem_bundle_round_trip_and_install() -> Result<()> {
+        let source = tempdir()?;
+        let bundle = bundle_in(source.path())?;
+        bundle.verify(None)?;
+
+        // 测试中降低迭代次数，格式与正式导出一致
+        let sealed = bundle.seal("correct horse", 1000)?;
+        assert!(IdentityBundle::open(&sealed, "wrong password").is_err());
+        let opened = IdentityBundle::open(&sealed, "correct horse")?;
+        assert_eq!(opened, bundle);
+        opened.verify(Some(&bundle.ca.fingerprint.to_uppercase()))?;
+        assert!(opened.verify(Some(&"00".repeat(32))).is_err());
+
+        // 安装到新机器后，证书、受信任设备和网关 ID 都与原网关一致
+        let target = tempdir()?;
+        let config = gateway_config_in(target.path());
+        opened.install(&config)?;
+        let tls_manager = TlsManager::new(config.tls_config.clone())?;
+        assert_eq!(tls_manager.ca_fingerprint()?, bundle.ca.fingerprint);
+        assert_eq!(
+            tls_manager.certificate_info("server")?.unwrap().fingerprint,
+            bundle.server.fingerprint
+        );
+        assert_eq!(tls_manager.trusted_ca_certificates()?.len(), 1);
+        let pairing = PairingManager::load(
+            config.trusted_devices_path.clone(),
+            bundle.ca.fingerprint.clone(),
+        )?;
+        assert_eq!(pairing.devices(), bundle.trusted_devices);
+        let peer_store = PeerStore::load(config.peer_store_path.clone(), config.peer_max_age)?;
+        assert_eq!(peer_store.local_id(), bundle.gateway_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pkcs12_round_trip() -> Result<()> {
+        let source = tempdir()?;
+        let bundle = bundle_in(source.path())?;
+
+        let data = bundle.export(BundleFormat::Pkcs12, "correct horse")?;
+        assert!(IdentityBundle::open(&data, "wrong password").is_err());
+        let opened = IdentityBundle::open(&data, "correct horse")?;
+        opened.verify(Some(&bundle.ca.fingerprint))?;
+
+        assert_eq!(opened.gateway_id, bundle.gateway_id);
+        assert_eq!(opened.ca.fingerprint, bundle.ca.fingerprint);
+        assert_eq!(opened.server.fingerprint, bundle.server.fingerprint);
+        assert_eq!(opened.client.fingerprint, bundle.client.fingerprint);
+        assert_eq!(opened.trusted_cas, bundle.trusted_cas);
+        assert_eq!(opened.trusted_devices.len(), 1);
+        assert_eq!(
+            opened.trusted_devices[0].gateway_id,
+            bundle.trusted_devices[0].gateway_id
+        );
+        assert_eq!(opened.trusted_devices[0].name, "客厅 NAS");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_bundle_is_rejected() -> Result<()> {
+        let source = tempdir()?;
+        let mut bundle = bundle_in(source.path())?;
+        assert!(bundle.export(BundleFormat::PemBundle, "short").is_err());
+
+        // 替换服务端证书后指纹和签发者都对不上
+        let other = bundle_in(tempdir()?.path())?;
+        bundle.server.certificate = other.server.certificate.clone();
+        assert!(bundle.verify(None).is_err());
+        bundle.server.fingerprint = other.server.fingerprint.clone();
+        assert!(bundle.verify(None).is_err());
+
+        Ok(())
+    }
+}
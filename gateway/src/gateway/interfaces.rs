@@ -0,0 +1,424 @@
+//! 网络接口策略模块
+//!
+//! 按接口名称和 CIDR 的白名单、黑名单决定哪些本地接口参与发现广播和监听，
+//! 并检测运行期间接口的启用、停用和地址变化。
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::gateway::network::NetworkManager;
+
+/// 默认排除的接口名称模式（常见 VPN 与隧道接口）
+pub const DEFAULT_DENY_INTERFACES: &[&str] = &["tun*", "tap*", "utun*", "wg*", "ppp*", "ipsec*"];
+
+/// CIDR 网段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    /// 网络地址
+    addr: IpAddr,
+    /// 前缀长度
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// 创建 CIDR 网段
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 网段内任意地址
+    /// * `prefix_len` - 前缀长度
+    ///
+    /// # 返回值
+    ///
+    /// CIDR 网段，前缀长度超出地址位数时返回错误
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self> {
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max_len {
+            return Err(anyhow!(
+                "CIDR 前缀长度 {prefix_len} 超出范围 (最大 {max_len})"
+            ));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// 检查地址是否位于网段内
+    ///
+    /// IPv4 映射的 IPv6 地址按对应的 IPv4 地址匹配。
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(*v6)),
+            IpAddr::V4(_) => *ip,
+        };
+
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = anyhow::Error;
+
+    /// 解析 `地址/前缀长度` 格式的网段，省略前缀长度时表示单个地址
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (addr, prefix_len) = match value.split_once('/') {
+            Some((addr, prefix_len)) => {
+                let prefix_len = prefix_len
+                    .parse::<u8>()
+                    .map_err(|_| anyhow!("无效的 CIDR 前缀长度: {value}"))?;
+                (addr, Some(prefix_len))
+            }
+            None => (value, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| anyhow!("无效的 CIDR 地址: {value}"))?;
+        let prefix_len = prefix_len.unwrap_or(if addr.is_ipv4() { 32 } else { 128 });
+
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// 本地网络接口地址
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LocalInterface {
+    /// 接口名称
+    pub name: String,
+    /// 接口地址
+    pub ip: IpAddr,
+}
+
+/// 两次扫描之间的接口变化
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceChange {
+    /// 新出现的接口地址
+    pub added: Vec<LocalInterface>,
+    /// 已消失的接口地址
+    pub removed: Vec<LocalInterface>,
+    /// 因接口变化重新绑定后的监听地址
+    pub rebound: Option<SocketAddr>,
+}
+
+impl InterfaceChange {
+    /// 比较两次扫描结果
+    ///
+    /// # 参数
+    ///
+    /// * `previous` - 上一次扫描结果
+    /// * `current` - 本次扫描结果
+    ///
+    /// # 返回值
+    ///
+    /// 有变化时返回变化内容
+    pub fn between(previous: &[LocalInterface], current: &[LocalInterface]) -> Option<Self> {
+        let added: Vec<LocalInterface> = current
+            .iter()
+            .filter(|interface| !previous.contains(interface))
+            .cloned()
+            .collect();
+        let removed: Vec<LocalInterface> = previous
+            .iter()
+            .filter(|interface| !current.contains(interface))
+            .cloned()
+            .collect();
+
+        if added.is_empty() && removed.is_empty() {
+            None
+        } else {
+            Some(Self {
+                added,
+                removed,
+                rebound: None,
+            })
+        }
+    }
+}
+
+/// 网络接口策略
+///
+/// 黑名单优先于白名单；白名单为空时不限制。接口名称支持以 `*` 结尾的前缀匹配。
+/// 默认策略不做任何限制。
+#[derive(Debug, Clone)]
+pub struct InterfacePolicy {
+    /// 允许的接口名称模式
+    allow_interfaces: Vec<String>,
+    /// 排除的接口名称模式
+    deny_interfaces: Vec<String>,
+    /// 允许的网段
+    allow_cidrs: Vec<IpCidr>,
+    /// 排除的网段
+    deny_cidrs: Vec<IpCidr>,
+    /// 是否允许在公网地址的接口上广播和监听
+    allow_public: bool,
+}
+
+impl Default for InterfacePolicy {
+    fn default() -> Self {
+        Self {
+            allow_interfaces: Vec::new(),
+            deny_interfaces: Vec::new(),
+            allow_cidrs: Vec::new(),
+            deny_cidrs: Vec::new(),
+            allow_public: true,
+        }
+    }
+}
+
+impl InterfacePolicy {
+    /// 根据配置创建接口策略
+    ///
+    /// # 参数
+    ///
+    /// * `allow_interfaces` - 允许的接口名称模式
+    /// * `deny_interfaces` - 排除的接口名称模式
+    /// * `allow_cidrs` - 允许的网段
+    /// * `deny_cidrs` - 排除的网段
+    /// * `allow_public` - 是否允许公网地址的接口
+    ///
+    /// # 返回值
+    ///
+    /// 接口策略，网段格式无效时返回错误
+    pub fn new(
+        allow_interfaces: &[String],
+        deny_interfaces: &[String],
+        allow_cidrs: &[String],
+        deny_cidrs: &[String],
+        allow_public: bool,
+    ) -> Result<Self> {
+        let parse_cidrs = |values: &[String]| -> Result<Vec<IpCidr>> {
+            values.iter().map(|value| value.parse()).collect()
+        };
+
+        Ok(Self {
+            allow_interfaces: allow_interfaces.to_vec(),
+            deny_interfaces: deny_interfaces.to_vec(),
+            allow_cidrs: parse_cidrs(allow_cidrs)?,
+            deny_cidrs: parse_cidrs(deny_cidrs)?,
+            allow_public,
+        })
+    }
+
+    /// 是否配置了白名单
+    ///
+    /// 配置白名单时网关只绑定到符合策略的接口地址，而不是未指定地址。
+    pub fn is_restricted(&self) -> bool {
+        !self.allow_interfaces.is_empty() || !self.allow_cidrs.is_empty()
+    }
+
+    /// 是否完全不做限制
+    ///
+    /// 只有不做任何限制时才使用会发往所有接口的有限广播等后备地址。
+    pub fn is_permissive(&self) -> bool {
+        self.allow_interfaces.is_empty()
+            && self.deny_interfaces.is_empty()
+            && self.allow_cidrs.is_empty()
+            && self.deny_cidrs.is_empty()
+            && self.allow_public
+    }
+
+    /// 检查本地接口地址是否可用于发现和监听
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 接口名称
+    /// * `ip` - 接口地址
+    pub fn permits_interface(&self, name: &str, ip: &IpAddr) -> bool {
+        if self
+            .deny_interfaces
+            .iter()
+            .any(|pattern| Self::name_matches(pattern, name))
+        {
+            return false;
+        }
+        if self.deny_cidrs.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        if !self.allow_public && !Self::is_private(ip) {
+            return false;
+        }
+        if !self.allow_interfaces.is_empty()
+            && !self
+                .allow_interfaces
+                .iter()
+                .any(|pattern| Self::name_matches(pattern, name))
+        {
+            return false;
+        }
+        if !self.allow_cidrs.is_empty() && !self.allow_cidrs.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        true
+    }
+
+    /// 检查是否接受来自指定地址的数据报
+    ///
+    /// 回环地址总是接受；配置网段白名单时只接受白名单内的来源。
+    ///
+    /// # 参数
+    ///
+    /// * `ip` - 来源地址
+    pub fn permits_peer(&self, ip: &IpAddr) -> bool {
+        if ip.is_loopback() {
+            return true;
+        }
+        if self.deny_cidrs.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        self.allow_cidrs.is_empty() || self.allow_cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// 是否允许使用公网接口
+    pub fn allows_public(&self) -> bool {
+        self.allow_public
+    }
+
+    /// 扫描本机符合策略的非回环接口地址
+    ///
+    /// # 返回值
+    ///
+    /// 按名称和地址排序的接口列表
+    pub fn scan(&self) -> std::io::Result<Vec<LocalInterface>> {
+        let mut interfaces: Vec<LocalInterface> = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|interface| !interface.is_loopback())
+            .filter(|interface| self.permits_interface(&interface.name, &interface.ip()))
+            .map(|interface| LocalInterface {
+                ip: interface.ip(),
+                name: interface.name,
+            })
+            .collect();
+        interfaces.sort();
+        interfaces.dedup();
+        Ok(interfaces)
+    }
+
+    /// 匹配接口名称模式
+    fn name_matches(pattern: &str, name: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => pattern == name,
+        }
+    }
+
+    /// 判断是否为私有或链路本地地址
+    fn is_private(ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(v4) => NetworkManager::is_private_ipv4(*v4) || v4.is_link_local(),
+            IpAddr::V6(v6) => NetworkManager::is_private_ipv6(*v6),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cidr_parse_and_contains() {
+        let cidr: IpCidr = "192.168.1.0/24".parse().unwrap();
+        assert!(cidr.contains(&"192.168.1.42".parse().unwrap()));
+        assert!(!cidr.contains(&"192.168.2.1".parse().unwrap()));
+        assert!(cidr.contains(&"::ffff:192.168.1.7".parse().unwrap()));
+        assert_eq!(cidr.to_string(), "192.168.1.0/24");
+
+        let host: IpCidr = "10.0.0.1".parse().unwrap();
+        assert!(host.contains(&"10.0.0.1".parse().unwrap()));
+        assert!(!host.contains(&"10.0.0.2".parse().unwrap()));
+
+        let v6: IpCidr = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(&"fd12::1".parse().unwrap()));
+        assert!(!v6.contains(&"10.0.0.1".parse().unwrap()));
+
+        assert!("0.0.0.0/0"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(&"8.8.8.8".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("not-an-ip/8".parse::<IpCidr>().is_err());
+    }
+
+    #[test]
+    fn test_interface_policy_rules() {
+        let policy = InterfacePolicy::new(
+            &strings(&["eth*", "wlan0"]),
+            &strings(DEFAULT_DENY_INTERFACES),
+            &[],
+            &strings(&["192.168.99.0/24"]),
+            false,
+        )
+        .unwrap();
+        let lan: IpAddr = "192.168.1.10".parse().unwrap();
+
+        assert!(policy.is_restricted());
+        assert!(policy.permits_interface("eth0", &lan));
+        assert!(policy.permits_interface("wlan0", &lan));
+        assert!(!policy.permits_interface("wlan1", &lan));
+        // 黑名单优先：隧道接口、被排除网段和公网地址均不可用
+        assert!(!policy.permits_interface("tun0", &lan));
+        assert!(!policy.permits_interface("eth1", &"192.168.99.3".parse().unwrap()));
+        assert!(!policy.permits_interface("eth0", &"8.8.8.8".parse().unwrap()));
+
+        // 来源过滤只使用网段规则
+        assert!(policy.permits_peer(&"8.8.8.8".parse().unwrap()));
+        assert!(!policy.permits_peer(&"192.168.99.3".parse().unwrap()));
+        assert!(policy.permits_peer(&"127.0.0.1".parse().unwrap()));
+
+        // 默认策略不做限制
+        let default = InterfacePolicy::default();
+        assert!(!default.is_restricted());
+        assert!(default.is_permissive());
+        assert!(!policy.is_permissive());
+        assert!(default.permits_interface("tun0", &"8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_interface_change_detection() {
+        let eth0 = LocalInterface {
+            name: "eth0".to_string(),
+            ip: "192.168.1.10".parse().unwrap(),
+        };
+        let eth0_new = LocalInterface {
+            name: "eth0".to_string(),
+            ip: "192.168.1.11".parse().unwrap(),
+        };
+
+        assert_eq!(
+            InterfaceChange::between(std::slice::from_ref(&eth0), std::slice::from_ref(&eth0)),
+            None
+        );
+        let change =
+            InterfaceChange::between(std::slice::from_ref(&eth0), std::slice::from_ref(&eth0_new))
+                .unwrap();
+        assert_eq!(change.added, vec![eth0_new]);
+        assert_eq!(change.removed, vec![eth0]);
+    }
+}
@@ -24,7 +24,15 @@ pub fn run() {
             wdic_gateway::tauri_api::mount_directory,
             wdic_gateway::tauri_api::unmount_directory,
             wdic_gateway::tauri_api::get_mount_points,
+            wdic_gateway::tauri_api::revalidate_mounts,
+            wdic_gateway::tauri_api::set_mount_rules,
             wdic_gateway::tauri_api::list_directory,
+            wdic_gateway::tauri_api::list_search_tokens,
+            wdic_gateway::tauri_api::revoke_search_token,
+            wdic_gateway::tauri_api::create_share,
+            wdic_gateway::tauri_api::list_shares,
+            wdic_gateway::tauri_api::revoke_share,
+            wdic_gateway::tauri_api::redeem_share,
             wdic_gateway::tauri_api::create_file_transfer,
             wdic_gateway::tauri_api::get_transfer_status,
             wdic_gateway::tauri_api::cancel_transfer,
@@ -36,6 +44,9 @@ pub fn run() {
             wdic_gateway::tauri_api::get_discovered_nodes,
             wdic_gateway::tauri_api::connect_to_node,
             wdic_gateway::tauri_api::disconnect_from_node,
+            wdic_gateway::tauri_api::get_peer_health,
+            wdic_gateway::tauri_api::get_relay_stats,
+            wdic_gateway::tauri_api::get_hole_punch_stats,
             
             // Performance API
             wdic_gateway::tauri_api::get_performance_report,
@@ -57,8 +68,27 @@ pub fn run() {
             wdic_gateway::tauri_api::remove_access_rule,
             wdic_gateway::tauri_api::get_access_rules,
             wdic_gateway::tauri_api::validate_client_access,
+            wdic_gateway::tauri_api::explain_access,
+            wdic_gateway::tauri_api::set_access_group,
+            wdic_gateway::tauri_api::remove_access_group,
+            wdic_gateway::tauri_api::get_access_groups,
             wdic_gateway::tauri_api::get_active_sessions,
             wdic_gateway::tauri_api::disconnect_session,
+
+            // Pairing API
+            wdic_gateway::tauri_api::list_trusted_devices,
+            wdic_gateway::tauri_api::get_pairing_sessions,
+            wdic_gateway::tauri_api::pair_device,
+            wdic_gateway::tauri_api::confirm_pairing,
+            wdic_gateway::tauri_api::rename_trusted_device,
+            wdic_gateway::tauri_api::revoke_trusted_device,
+            wdic_gateway::tauri_api::revoke_device,
+            wdic_gateway::tauri_api::get_revocations,
+            wdic_gateway::tauri_api::query_audit_log,
+            wdic_gateway::tauri_api::export_audit_log,
+            wdic_gateway::tauri_api::verify_audit_log,
+            wdic_gateway::tauri_api::export_identity_bundle,
+            wdic_gateway::tauri_api::import_identity_bundle,
         ])
         .setup(|_app| {
             // 初始化全局状态将在API调用时进行
@@ -25,42 +25,94 @@
 //! }
 //! ```
 
+pub mod access_control;
+pub mod audit;
+pub mod authz;
 pub mod cache;
+pub mod cert_monitor;
 pub mod compression;
+pub mod crypto;
+pub mod envelope;
+#[allow(clippy::module_inception)]
 pub mod gateway;
+pub mod heartbeat;
+pub mod identity_bundle;
+pub mod interfaces;
 pub mod mount;
+pub mod mount_rules;
+pub mod nat;
 pub mod network;
+pub mod pairing;
+pub mod peer_store;
 pub mod performance;
 pub mod protocol;
+pub mod quic;
+pub mod rate_limit;
 pub mod registry;
+pub mod relay;
+pub mod search_token;
 pub mod security;
+pub mod share;
 pub mod tauri_api;
 pub mod tauri_api_tests;
 pub mod tls;
+pub mod token_cipher;
+pub mod transport;
 pub mod udp_protocol;
 
+pub use access_control::{AccessDecision, AccessGroup, RuleEffect, RuleSubject};
+pub use audit::{
+    AuditCategory, AuditEntry, AuditEvent, AuditFilter, AuditLog, AuditOutcome, AuditVerification,
+};
+pub use authz::{AuthzDenial, Authorizer, RemoteOperation};
 pub use cache::{CacheEntry, CacheMetadata, GatewayCache};
+pub use cert_monitor::{CertificateEvent, CertificateMonitor};
+pub use crypto::AgreementKeyPair;
+pub use envelope::{MessageAuthenticator, MessageSigningPolicy, SignedEnvelope};
 pub use compression::{
     CompressionConfig, CompressionFlag, CompressionManager, CompressionStats,
     CompressionStatsSnapshot,
 };
-pub use gateway::{Gateway, GatewayConfig};
+pub use gateway::{Gateway, GatewayConfig, RevocationEvent, SessionEvent};
+pub use identity_bundle::{BundleFormat, IdentityBundle, IdentityBundleSummary};
+pub use heartbeat::{HeartbeatScheduler, PeerHealth, PeerLiveness, PeerStateChange};
+pub use interfaces::{InterfaceChange, InterfacePolicy, IpCidr, LocalInterface};
 pub use mount::{MountManager, SearchToken, FileAuthorization};
+pub use mount_rules::{MountFilter, MountRules};
+pub use nat::{HolePunchConfig, HolePunchStats, PathOutcome, RendezvousService};
 pub use network::NetworkManager;
+pub use pairing::{PairingManager, PairingSession, TrustedDevice};
+pub use peer_store::PeerStore;
 pub use performance::{
-    BenchmarkResult, PerformanceMonitor, PerformanceReport, PerformanceTestSuite,
+    BenchmarkResult, PeerLatencyMetrics, PerformanceMonitor, PerformanceReport,
+    PerformanceTestSuite,
 };
 pub use protocol::WdicProtocol;
-pub use registry::{Registry, RegistryEntry};
+pub use quic::{QuicEndpoint, QuicSecurity};
+pub use rate_limit::{
+    InboundRateLimiter, MessageClass, RateBudget, RateLimitConfig, RateVerdict, ThrottleStats,
+    TokenBucket,
+};
+pub use registry::{Registry, RegistryEntry, TrustState};
+pub use relay::{PeerRoute, RelayManager, RelayStats};
+pub use search_token::{RevokedSearchToken, SearchTokenSigner};
 pub use tauri_api::{
     GlobalGatewayState, GatewayStatus, NetworkStatus, MountPoint, FileTransferTask,
     SecurityConfig, AccessRule, SystemInfo, HealthStatus, LogEntry, CacheStats,
     BenchmarkResult as TauriBenchmarkResult, BenchmarkStatus,
     DirectoryEntry as TauriDirectoryEntry, DiscoveredNode, CertificateInfo, GeneratedCertificate,
-    ActiveSession, TransferStatus, NetworkInterface,
+    ActiveSession, TransferStatus, NetworkInterface, KeyAlgorithm, MountStatus,
 };
 pub use security::{PathValidator, SecureFileReader, SearchResultFilter};
-pub use tls::{MtlsConfig, TlsManager, TlsVersion, VerifyMode};
+pub use share::{Share, ShareAccess, ShareManager, ShareOptions, ShareSummary};
+pub use tls::{
+    CertificateIdentity, MtlsConfig, NegotiatedTls, PeerCertificate, QuicRole, Revocation,
+    TlsManager, TlsVersion, VerifyMode, QUIC_CIPHER_SUITES,
+};
+pub use token_cipher::{SealedToken, TokenCipher, TokenRecipient};
+pub use transport::{
+    DatagramTransport, MemoryNetwork, MemoryTransport, SwappableTransport, UdpTransport,
+};
 pub use udp_protocol::{
     DirectoryEntry, DirectoryIndex, UdpBroadcastEvent, UdpBroadcastManager, UdpToken,
 };
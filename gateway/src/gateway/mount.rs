@@ -1,26 +1,58 @@
 //! 目录挂载模块
 //!
 //! 处理目录挂载、文件授权、搜索令牌系统和文件路径安全处理。
-//! 此模块独立于注册表，专门负责文件系统访问控制和挂载点管理。
+//! 挂载点、目录索引、允许访问的根目录和文件搜索都由 [`MountManager`] 统一管理，
+//! 界面展示的挂载点与其他网关能够搜索和读取的内容始终一致。
+//! 每个挂载点的过滤规则（见 [`crate::gateway::mount_rules`]）同时作用于索引、搜索、
+//! 列出目录和读取文件。
 
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock as StdRwLock};
 use uuid::Uuid;
 
-use crate::gateway::tauri_api::{DirectoryEntry, MountPoint};
+use crate::gateway::mount_rules::{MountFilter, MountRules};
+use crate::gateway::search_token::{SearchTokenSigner, MAX_SEARCH_TOKEN_TTL};
+use crate::gateway::security::SecureFileReader;
+use crate::gateway::tauri_api::{DirectoryEntry, MountPoint, MountStatus};
+use crate::gateway::udp_protocol::DirectoryIndex;
+
+/// 远程读取的单个文件大小上限（字节）
+const MAX_READ_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 挂载点持久化文件格式版本
+const MOUNT_STORE_VERSION: u32 = 1;
+
+/// 挂载点持久化文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MountStoreFile {
+    /// 文件格式版本
+    version: u32,
+    /// 保存时间
+    saved_at: DateTime<Utc>,
+    /// 挂载点定义
+    mounts: Vec<MountPoint>,
+}
 
 /// 搜索令牌信息
+///
+/// 令牌内容由签发网关签名后交给其他网关，参见 [`SearchTokenSigner`]。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchToken {
     /// 令牌 ID
     pub token_id: String,
+    /// 签发网关 ID
+    #[serde(default)]
+    pub issuer: Uuid,
+    /// 被授权的网关 ID，为空时任何已配对的网关都可以出示
+    #[serde(default)]
+    pub grantee: Option<Uuid>,
     /// 关联的挂载点 ID
     pub mount_id: String,
     /// 允许的路径模式
@@ -38,6 +70,8 @@ pub struct SearchToken {
 impl SearchToken {
     /// 创建新的搜索令牌
     pub fn new(
+        issuer: Uuid,
+        grantee: Option<Uuid>,
         mount_id: String,
         allowed_patterns: Vec<String>,
         permissions: Vec<String>,
@@ -46,6 +80,8 @@ impl SearchToken {
         let now = Utc::now();
         Self {
             token_id: Uuid::new_v4().to_string(),
+            issuer,
+            grantee,
             mount_id,
             allowed_patterns,
             permissions,
@@ -176,8 +212,18 @@ impl FileAuthorization {
 pub struct MountManager {
     /// 挂载点存储 (mount_id -> MountPoint)
     mount_points: Arc<DashMap<String, MountPoint>>,
-    /// 搜索令牌存储 (token_id -> SearchToken)
+    /// 挂载点持久化文件路径，为空时只保存在内存中
+    store_path: Option<PathBuf>,
+    /// 目录索引 (mount_id -> DirectoryIndex)，只包含可用的挂载点
+    indices: Arc<DashMap<String, DirectoryIndex>>,
+    /// 目录索引文件保存目录，为空时索引只保存在内存中
+    index_dir: Option<PathBuf>,
+    /// 保存的索引可直接加载的最长时间（秒）
+    index_max_age: u64,
+    /// 本网关签发的搜索令牌 (token_id -> SearchToken)，用于列出和吊销，验证不依赖此表
     search_tokens: Arc<DashMap<String, SearchToken>>,
+    /// 搜索令牌签发与验证器
+    token_signer: StdRwLock<Arc<SearchTokenSigner>>,
     /// 文件授权存储 (auth_id -> FileAuthorization)
     file_authorizations: Arc<DashMap<String, FileAuthorization>>,
     /// 路径到授权 ID 的映射
@@ -195,22 +241,335 @@ impl MountManager {
     pub fn new() -> Self {
         Self {
             mount_points: Arc::new(DashMap::new()),
+            store_path: None,
+            indices: Arc::new(DashMap::new()),
+            index_dir: None,
+            index_max_age: 0,
             search_tokens: Arc::new(DashMap::new()),
+            token_signer: StdRwLock::new(Arc::new(SearchTokenSigner::ephemeral())),
             file_authorizations: Arc::new(DashMap::new()),
             path_to_auth: Arc::new(DashMap::new()),
         }
     }
 
+    /// 创建将挂载点和目录索引保存到磁盘的挂载管理器
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 挂载点持久化文件路径
+    /// * `index_dir` - 目录索引文件保存目录
+    /// * `index_max_age` - 保存的索引可直接加载的最长时间（秒），超过后重新生成
+    pub fn with_store(path: PathBuf, index_dir: PathBuf, index_max_age: u64) -> Self {
+        Self {
+            store_path: Some(path),
+            index_dir: Some(index_dir),
+            index_max_age,
+            ..Self::new()
+        }
+    }
+
+    /// 从文件加载挂载点
+    ///
+    /// 文件不存在时返回空的管理器。加载后重新校验每个挂载点并恢复其目录索引，
+    /// 本地路径已消失的挂载点标记为 [`MountStatus::Missing`] 而不是丢弃。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 挂载点持久化文件路径，之后的挂载和卸载都会写回该文件
+    /// * `index_dir` - 目录索引文件保存目录
+    /// * `index_max_age` - 保存的索引可直接加载的最长时间（秒），超过后重新生成
+    ///
+    /// # 返回值
+    ///
+    /// 加载后的挂载管理器
+    pub fn load(path: PathBuf, index_dir: PathBuf, index_max_age: u64) -> Result<Self> {
+        let manager = Self::with_store(path.clone(), index_dir, index_max_age);
+        if path.exists() {
+            let data = fs::read(&path).map_err(|e| anyhow!("读取挂载点文件失败: {e}"))?;
+            let file: MountStoreFile = serde_json::from_slice(&data)
+                .map_err(|e| anyhow!("反序列化挂载点文件失败: {e}"))?;
+            if file.version > MOUNT_STORE_VERSION {
+                return Err(anyhow!("不支持的挂载点文件版本: {}", file.version));
+            }
+
+            for mount_point in file.mounts {
+                manager.mount_points.insert(mount_point.id.clone(), mount_point);
+            }
+        } else {
+            debug!("挂载点文件不存在: {:?}", path);
+        }
+        let adopted = manager.adopt_legacy_indices();
+
+        let missing = manager
+            .revalidate_mounts()
+            .iter()
+            .filter(|mount| mount.status == MountStatus::Missing)
+            .count();
+        if adopted > 0 {
+            manager.save_or_warn();
+        }
+        info!(
+            "从 {:?} 加载了 {} 个挂载点（{missing} 个路径已不存在）",
+            path,
+            manager.mount_points.len()
+        );
+        Ok(manager)
+    }
+
+    /// 重新校验所有挂载点
+    ///
+    /// 路径仍存在的挂载点重新计算统计信息、恢复为可用并确保目录索引有效，
+    /// 已消失的标记为缺失并停止提供索引。
+    ///
+    /// # 返回值
+    ///
+    /// 校验后的挂载点列表
+    pub fn revalidate_mounts(&self) -> Vec<MountPoint> {
+        // 先复制挂载点快照，文件系统操作期间不持有分片锁
+        let snapshot: Vec<MountPoint> = self
+            .mount_points
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        let mut changed = false;
+        for mount_point in snapshot {
+            let status = if mount_point.local_path.is_dir() {
+                MountStatus::Available
+            } else {
+                MountStatus::Missing
+            };
+
+            let mut stats = None;
+            if status == MountStatus::Available {
+                match self.calculate_directory_stats(&mount_point.local_path) {
+                    Ok(result) => stats = Some(result),
+                    Err(e) => warn!("计算挂载点 {} 的统计信息失败: {e}", mount_point.id),
+                }
+                if let Err(e) = self.refresh_index(&mount_point) {
+                    warn!("挂载点 '{}' 的目录索引不可用: {e}", mount_point.mount_name);
+                }
+            } else {
+                if mount_point.status == MountStatus::Available {
+                    warn!(
+                        "挂载点 '{}' 的路径已不存在: {:?}",
+                        mount_point.mount_name, mount_point.local_path
+                    );
+                }
+                self.indices.remove(&mount_point.id);
+            }
+
+            // 写回结果；校验期间被移除或改换路径的挂载点不再更新
+            let Some(mut current) = self.mount_points.get_mut(&mount_point.id) else {
+                continue;
+            };
+            if current.local_path != mount_point.local_path {
+                continue;
+            }
+            if let Some((file_count, total_size)) = stats {
+                current.file_count = file_count;
+                current.total_size = total_size;
+            }
+            if current.status != status {
+                current.status = status;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.save_or_warn();
+        }
+        self.mount_points.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    /// 确保挂载点的目录索引有效
+    ///
+    /// 内存或磁盘中的索引仍然新鲜时直接使用，否则重新生成并写回磁盘。
+    fn refresh_index(&self, mount_point: &MountPoint) -> Result<()> {
+        if self.indices.get(&mount_point.id).is_some_and(|index| {
+            index.rules == mount_point.rules && index.is_fresh(self.index_max_age)
+        }) {
+            return Ok(());
+        }
+
+        let index_file = self.index_file(&mount_point.id);
+        let saved = index_file
+            .as_ref()
+            .filter(|index_file| index_file.exists())
+            .and_then(|index_file| DirectoryIndex::load_from_file(&index_file.to_string_lossy()).ok())
+            .filter(|index| {
+                Path::new(&index.root_path) == mount_point.local_path
+                    && index.rules == mount_point.rules
+                    && index.is_fresh(self.index_max_age)
+            });
+        let index = match saved {
+            Some(index) => {
+                debug!("挂载点 '{}' 的索引仍然有效，直接加载", mount_point.mount_name);
+                index
+            }
+            None => {
+                let index = DirectoryIndex::generate_with_rules(
+                    &mount_point.local_path.to_string_lossy(),
+                    &mount_point.rules,
+                )?;
+                if let Some(index_file) = &index_file {
+                    if let Some(parent) = index_file.parent() {
+                        fs::create_dir_all(parent)
+                            .map_err(|e| anyhow!("创建索引目录失败: {e}"))?;
+                    }
+                    index.save_to_file(&index_file.to_string_lossy())?;
+                }
+                index
+            }
+        };
+
+        self.indices.insert(mount_point.id.clone(), index);
+        Ok(())
+    }
+
+    /// 挂载点的索引文件路径
+    fn index_file(&self, mount_id: &str) -> Option<PathBuf> {
+        self.index_dir
+            .as_ref()
+            .map(|index_dir| index_dir.join(format!("{mount_id}.index")))
+    }
+
+    /// 接管旧版本按挂载名称保存的索引文件
+    ///
+    /// 旧版本中网关直接挂载的目录只保存了索引文件，没有挂载点定义。
+    /// 这些目录以只读挂载点的形式加入，索引文件改按挂载点 ID 命名。
+    ///
+    /// # 返回值
+    ///
+    /// 接管的挂载点数量
+    fn adopt_legacy_indices(&self) -> usize {
+        let Some(index_dir) = &self.index_dir else {
+            return 0;
+        };
+        let Ok(read_dir) = fs::read_dir(index_dir) else {
+            return 0;
+        };
+
+        let mut adopted = 0;
+        for entry in read_dir.flatten() {
+            let index_file = entry.path();
+            if index_file.extension().and_then(|extension| extension.to_str()) != Some("index") {
+                continue;
+            }
+            let Some(name) = index_file.file_stem().map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            if self.mount_points.contains_key(&name) || self.mount_by_name(&name).is_some() {
+                continue;
+            }
+            let Ok(index) = DirectoryIndex::load_from_file(&index_file.to_string_lossy()) else {
+                continue;
+            };
+
+            let mount_point = MountPoint {
+                id: Uuid::new_v4().to_string(),
+                local_path: fs::canonicalize(&index.root_path)
+                    .unwrap_or_else(|_| PathBuf::from(&index.root_path)),
+                mount_name: name,
+                read_only: true,
+                mount_time: index.generated_at,
+                file_count: 0,
+                total_size: 0,
+                status: MountStatus::Available,
+                rules: index.rules.clone(),
+            };
+            if let Some(adopted_file) = self.index_file(&mount_point.id) {
+                if let Err(e) = fs::rename(&index_file, &adopted_file) {
+                    warn!("重命名索引文件 {:?} 失败: {e}", index_file);
+                }
+            }
+            info!("接管旧挂载目录 '{}': {:?}", mount_point.mount_name, mount_point.local_path);
+            self.mount_points.insert(mount_point.id.clone(), mount_point);
+            adopted += 1;
+        }
+        adopted
+    }
+
+    /// 保存挂载点到文件
+    ///
+    /// 先写入临时文件再重命名，避免写入中断导致文件损坏。
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.store_path else {
+            return Ok(());
+        };
+
+        let mut mounts: Vec<MountPoint> = self
+            .mount_points
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        mounts.sort_by_key(|mount| mount.mount_time);
+        let file = MountStoreFile {
+            version: MOUNT_STORE_VERSION,
+            saved_at: Utc::now(),
+            mounts,
+        };
+        let serialized =
+            serde_json::to_vec_pretty(&file).map_err(|e| anyhow!("序列化挂载点失败: {e}"))?;
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).map_err(|e| anyhow!("创建挂载点文件目录失败: {e}"))?;
+            }
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serialized).map_err(|e| anyhow!("写入挂载点文件失败: {e}"))?;
+        fs::rename(&tmp_path, path).map_err(|e| anyhow!("替换挂载点文件失败: {e}"))?;
+        Ok(())
+    }
+
+    /// 保存挂载点，失败时只记录警告
+    fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("保存挂载点失败: {e}");
+        }
+    }
+
+    /// 设置搜索令牌签发器
+    ///
+    /// 此前签发的令牌由旧签发器签名，之后将无法通过验证。
+    pub fn set_token_signer(&self, signer: Arc<SearchTokenSigner>) {
+        *self.token_signer.write().unwrap_or_else(|e| e.into_inner()) = signer;
+        self.search_tokens.clear();
+    }
+
+    /// 获取搜索令牌签发器
+    pub fn token_signer(&self) -> Arc<SearchTokenSigner> {
+        Arc::clone(&self.token_signer.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
     /// 挂载目录
     ///
+    /// 挂载时生成目录索引，挂载点随即可被其他网关搜索和读取。
+    ///
     /// # 参数
     ///
     /// * `mount_point` - 挂载点信息
     ///
     /// # 返回值
     ///
-    /// 操作结果
+    /// 挂载点 ID
     pub async fn mount_directory(&self, mut mount_point: MountPoint) -> Result<String> {
+        // 验证挂载点名称
+        let name = &mount_point.mount_name;
+        if name.is_empty() || name.len() > 255 {
+            return Err(anyhow!("挂载点名称无效: 长度必须在 1-255 字符之间"));
+        }
+        if name.contains(['/', '\\', ':', '<', '>', '|', '?', '*']) {
+            return Err(anyhow!("挂载点名称包含非法字符: {name}"));
+        }
+        if self.mount_by_name(name).is_some() {
+            return Err(anyhow!("挂载点已存在: {name}"));
+        }
+        mount_point.rules.validate()?;
+
         // 验证路径存在且是目录
         if !mount_point.local_path.exists() {
             return Err(anyhow!("路径不存在: {:?}", mount_point.local_path));
@@ -231,8 +590,12 @@ impl MountManager {
         mount_point.file_count = file_count;
         mount_point.total_size = total_size;
 
+        mount_point.status = MountStatus::Available;
+        self.refresh_index(&mount_point)?;
+
         let mount_id = mount_point.id.clone();
         self.mount_points.insert(mount_id.clone(), mount_point.clone());
+        self.save_or_warn();
 
         info!("成功挂载目录: {} -> {:?}", mount_id, mount_point.local_path);
         Ok(mount_id)
@@ -250,23 +613,175 @@ impl MountManager {
     pub async fn unmount_directory(&self, mount_id: &str) -> Result<()> {
         let mount_point = self.mount_points.remove(mount_id)
             .ok_or_else(|| anyhow!("挂载点不存在: {mount_id}"))?;
+        self.save_or_warn();
+
+        // 删除目录索引，卸载后其他网关无法再搜索到其中的文件
+        self.indices.remove(mount_id);
+        if let Some(index_file) = self.index_file(mount_id) {
+            if let Err(e) = fs::remove_file(&index_file) {
+                debug!("删除索引文件 {:?} 失败: {e}", index_file);
+            }
+        }
 
-        // 清理相关的搜索令牌
-        let mut tokens_to_remove = Vec::new();
+        // 吊销相关的搜索令牌，已交给其他网关的令牌随之失效
+        let mut tokens_to_revoke = Vec::new();
         for token_entry in self.search_tokens.iter() {
             if token_entry.value().mount_id == mount_id {
-                tokens_to_remove.push(token_entry.key().clone());
+                tokens_to_revoke.push(token_entry.key().clone());
             }
         }
 
-        for token_id in tokens_to_remove {
-            self.search_tokens.remove(&token_id);
+        for token_id in tokens_to_revoke {
+            if let Err(e) = self.revoke_search_token(&token_id) {
+                warn!("吊销搜索令牌 {token_id} 失败: {e}");
+            }
         }
 
         info!("成功卸载目录: {} -> {:?}", mount_id, mount_point.1.local_path);
         Ok(())
     }
 
+    /// 按名称查找挂载点
+    pub fn mount_by_name(&self, name: &str) -> Option<MountPoint> {
+        self.mount_points
+            .iter()
+            .find(|entry| entry.value().mount_name == name)
+            .map(|entry| entry.value().clone())
+    }
+
+    /// 修改挂载点的过滤规则
+    ///
+    /// 目录索引按新规则重新生成，规则随挂载点一起保存。
+    ///
+    /// # 参数
+    ///
+    /// * `mount_id` - 挂载点 ID
+    /// * `rules` - 新的过滤规则
+    ///
+    /// # 返回值
+    ///
+    /// 更新后的挂载点
+    pub fn set_mount_rules(&self, mount_id: &str, rules: MountRules) -> Result<MountPoint> {
+        rules.validate()?;
+        let mount_point = {
+            let mut mount_point = self
+                .mount_points
+                .get_mut(mount_id)
+                .ok_or_else(|| anyhow!("挂载点不存在: {mount_id}"))?;
+            mount_point.rules = rules;
+            mount_point.clone()
+        };
+        self.save_or_warn();
+
+        if mount_point.status == MountStatus::Available {
+            self.refresh_index(&mount_point)?;
+        }
+        info!("已更新挂载点 '{}' 的过滤规则", mount_point.mount_name);
+        Ok(mount_point)
+    }
+
+    /// 获取包含指定路径的挂载点的过滤器
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 规范化后的路径
+    pub fn filter_for(&self, path: &Path) -> Option<MountFilter> {
+        self.mount_containing(path)
+            .map(|mount_point| MountFilter::new(mount_point.local_path, &mount_point.rules))
+    }
+
+    /// 路径是否位于可用挂载点内且未被该挂载点的过滤规则排除
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 规范化后的路径
+    pub fn is_path_visible(&self, path: &Path) -> bool {
+        self.filter_for(path).is_some_and(|filter| filter.allows_path(path))
+    }
+
+    /// 获取可用挂载点的根目录，即允许访问的全部根目录
+    pub fn allowed_roots(&self) -> Vec<PathBuf> {
+        self.mount_points
+            .iter()
+            .filter(|entry| entry.value().status == MountStatus::Available)
+            .map(|entry| entry.value().local_path.clone())
+            .collect()
+    }
+
+    /// 在所有可用挂载点的目录索引中搜索文件
+    ///
+    /// # 参数
+    ///
+    /// * `keywords` - 搜索关键词
+    ///
+    /// # 返回值
+    ///
+    /// 匹配的文件路径列表
+    pub fn search_files(&self, keywords: &[String]) -> Vec<String> {
+        let hits: Vec<(String, Vec<String>)> = self
+            .indices
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().search(keywords).into_vec()))
+            .filter(|(_, paths)| !paths.is_empty())
+            .collect();
+
+        // 每个挂载点只构建一次过滤器，索引生成后新增的忽略文件同样生效
+        let mut results = Vec::new();
+        for (mount_id, paths) in hits {
+            let Some(filter) = self
+                .mount_points
+                .get(&mount_id)
+                .filter(|mount| mount.status == MountStatus::Available)
+                .map(|mount| MountFilter::new(mount.local_path.clone(), &mount.rules))
+            else {
+                continue;
+            };
+            results.extend(
+                paths
+                    .into_iter()
+                    .filter(|path| filter.allows_path(Path::new(path))),
+            );
+        }
+        results
+    }
+
+    /// 读取挂载点内的文件
+    ///
+    /// 文件必须位于可用挂载点内、未被过滤规则排除且出现在目录索引中，
+    /// 索引扫描时跳过的文件无法读取。
+    ///
+    /// # 参数
+    ///
+    /// * `file_path` - 文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 文件内容
+    pub fn read_file(&self, file_path: &str) -> Result<Vec<u8>> {
+        if !self.is_path_visible(Path::new(file_path)) {
+            warn!("尝试访问被挂载规则排除的文件: {file_path}");
+            return Err(anyhow!("文件访问被拒绝: 文件不在挂载点内或被挂载规则排除"));
+        }
+
+        let secure_reader = SecureFileReader::new(self.allowed_roots(), MAX_READ_FILE_SIZE);
+        let data = secure_reader
+            .read_file(file_path)
+            .map_err(|e| anyhow!("安全文件读取失败: {e}"))?;
+
+        let file_found_in_index = self.indices.iter().any(|index| {
+            index
+                .entries
+                .iter()
+                .any(|entry| entry.path == file_path && !entry.is_dir)
+        });
+        if !file_found_in_index {
+            warn!("尝试访问未在索引中的文件: {file_path}");
+            return Err(anyhow!("文件访问被拒绝: 文件不在任何挂载的目录索引中"));
+        }
+
+        Ok(data)
+    }
+
     /// 获取挂载点列表
     pub async fn get_mount_points(&self) -> Result<Vec<MountPoint>> {
         let mut mount_points: Vec<MountPoint> = self
@@ -276,7 +791,7 @@ impl MountManager {
             .collect();
 
         // 按挂载时间排序
-        mount_points.sort_by(|a, b| b.mount_time.cmp(&a.mount_time));
+        mount_points.sort_by_key(|mount| std::cmp::Reverse(mount.mount_time));
 
         Ok(mount_points)
     }
@@ -298,6 +813,9 @@ impl MountManager {
     ) -> Result<Vec<DirectoryEntry>> {
         let mount_point = self.mount_points.get(mount_id)
             .ok_or_else(|| anyhow!("挂载点不存在: {mount_id}"))?;
+        if mount_point.status == MountStatus::Missing {
+            return Err(anyhow!("挂载点路径已不存在: {:?}", mount_point.local_path));
+        }
 
         let mut target_path = mount_point.local_path.clone();
         if !relative_path.is_empty() && relative_path != "/" {
@@ -315,6 +833,13 @@ impl MountManager {
             return Err(anyhow!("不是目录: {:?}", target_path));
         }
 
+        // 过滤规则：被排除的目录不可列出，被排除的条目不出现在结果中
+        let target_path = fs::canonicalize(&target_path).context("无法规范化路径")?;
+        let filter = MountFilter::new(mount_point.local_path.clone(), &mount_point.rules);
+        if !filter.allows_path(&target_path) {
+            return Err(anyhow!("目录被挂载规则排除: {:?}", target_path));
+        }
+
         let mut entries = Vec::new();
         let read_dir = fs::read_dir(&target_path)?;
 
@@ -323,6 +848,9 @@ impl MountManager {
             let metadata = entry.metadata()?;
             let file_name = entry.file_name().to_string_lossy().to_string();
             let full_path = entry.path();
+            if !filter.allows_entry(&full_path, &metadata) {
+                continue;
+            }
 
             let modified_time = metadata.modified()?
                 .duration_since(std::time::UNIX_EPOCH)?
@@ -376,48 +904,172 @@ impl MountManager {
     /// * `patterns` - 搜索模式
     /// * `permissions` - 权限列表
     /// * `ttl_seconds` - 生存时间（秒）
+    /// * `grantee` - 被授权的网关 ID，为空时任何已配对的网关都可以出示
     ///
     /// # 返回值
     ///
-    /// 搜索令牌 ID
+    /// 签名后的搜索令牌
     pub async fn create_search_token(
         &self,
         mount_id: String,
         patterns: Vec<String>,
         permissions: Vec<String>,
         ttl_seconds: u64,
+        grantee: Option<Uuid>,
     ) -> Result<String> {
         // 验证挂载点存在
         if !self.mount_points.contains_key(&mount_id) {
             return Err(anyhow!("挂载点不存在: {mount_id}"));
         }
 
-        let token = SearchToken::new(mount_id, patterns, permissions, ttl_seconds);
+        if ttl_seconds == 0 || ttl_seconds > MAX_SEARCH_TOKEN_TTL {
+            return Err(anyhow!(
+                "搜索令牌有效期必须在 1 到 {MAX_SEARCH_TOKEN_TTL} 秒之间"
+            ));
+        }
+
+        let signer = self.token_signer();
+        let token = SearchToken::new(
+            signer.issuer(),
+            grantee,
+            mount_id,
+            patterns,
+            permissions,
+            ttl_seconds,
+        );
+        let signed = signer.sign(&token)?;
         let token_id = token.token_id.clone();
-        
+
         self.search_tokens.insert(token_id.clone(), token);
-        
+
         info!("创建搜索令牌: {token_id}");
-        Ok(token_id)
+        Ok(signed)
     }
 
-    /// 验证搜索令牌
+    /// 验证签名搜索令牌并返回其内容
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 签名后的搜索令牌
+    ///
+    /// # 返回值
+    ///
+    /// 令牌签名无效、过期、已吊销或已停用时返回错误
+    pub fn verify_search_token(&self, token: &str) -> Result<SearchToken> {
+        let token = self.token_signer().verify(token)?;
+        if !token.is_active {
+            return Err(anyhow!("搜索令牌已停用: {}", token.token_id));
+        }
+        Ok(token)
+    }
+
+    /// 吊销搜索令牌
     ///
     /// # 参数
     ///
     /// * `token_id` - 令牌 ID
+    ///
+    /// # 返回值
+    ///
+    /// 令牌此前未被吊销时返回 true
+    pub fn revoke_search_token(&self, token_id: &str) -> Result<bool> {
+        let expires_at = self
+            .search_tokens
+            .remove(token_id)
+            .map(|(_, token)| token.expires_at);
+        self.token_signer().revoke(token_id, expires_at)
+    }
+
+    /// 获取本网关签发且尚未过期或吊销的搜索令牌
+    pub fn list_search_tokens(&self) -> Vec<SearchToken> {
+        let signer = self.token_signer();
+        let mut tokens: Vec<SearchToken> = self
+            .search_tokens
+            .iter()
+            .map(|entry| entry.value().clone())
+            .filter(|token| !token.is_expired() && !signer.is_revoked(&token.token_id))
+            .collect();
+        tokens.sort_by_key(|token| token.created_at);
+        tokens
+    }
+
+    /// 验证搜索令牌
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 签名后的搜索令牌
     /// * `path` - 要访问的路径
     ///
     /// # 返回值
     ///
     /// 是否授权
-    pub async fn validate_search_token(&self, token_id: &str, path: &str) -> Result<bool> {
-        let token = self.search_tokens.get(token_id)
-            .ok_or_else(|| anyhow!("搜索令牌不存在: {token_id}"))?;
+    pub async fn validate_search_token(&self, token: &str, path: &str) -> Result<bool> {
+        let token = self.verify_search_token(token)?;
 
         Ok(token.is_path_authorized(path))
     }
 
+    /// 检查出示的搜索令牌是否授予指定路径和权限
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 签名后的搜索令牌
+    /// * `presenter` - 出示令牌的网关 ID
+    /// * `path` - 要访问的路径，为空时只检查令牌本身
+    /// * `permission` - 需要的权限
+    ///
+    /// # 返回值
+    ///
+    /// 令牌无效、过期、已吊销、不属于出示者或未覆盖路径与权限时返回错误
+    pub fn check_search_token(
+        &self,
+        token: &str,
+        presenter: Uuid,
+        path: Option<&str>,
+        permission: &str,
+    ) -> Result<()> {
+        let token = self.verify_search_token(token)?;
+
+        if token.grantee.is_some_and(|grantee| grantee != presenter) {
+            return Err(anyhow!("搜索令牌未授予网关 {presenter}"));
+        }
+
+        if !token.permissions.iter().any(|granted| granted == permission || granted == "*") {
+            return Err(anyhow!("搜索令牌未授予 {permission} 权限"));
+        }
+
+        if let Some(path) = path {
+            let in_mount = self.mount_points.get(&token.mount_id).is_some_and(|mount| {
+                mount.status == MountStatus::Available
+                    && Path::new(path).starts_with(&mount.local_path)
+            });
+            if !in_mount || !token.is_path_authorized(path) {
+                return Err(anyhow!("搜索令牌未授权访问该路径: {path}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 查找包含指定路径的挂载点
+    ///
+    /// 挂载点嵌套时返回最内层的挂载点，路径已不存在的挂载点不参与查找。
+    pub fn mount_containing(&self, path: &Path) -> Option<MountPoint> {
+        self.mount_points
+            .iter()
+            .filter(|entry| entry.value().status == MountStatus::Available)
+            .filter(|entry| path.starts_with(&entry.value().local_path))
+            .max_by_key(|entry| entry.value().local_path.components().count())
+            .map(|entry| entry.value().clone())
+    }
+
+    /// 是否存在可写的挂载点
+    pub fn has_writable_mount(&self) -> bool {
+        self.mount_points.iter().any(|entry| {
+            !entry.value().read_only && entry.value().status == MountStatus::Available
+        })
+    }
+
     /// 文件授权函数
     ///
     /// # 参数
@@ -444,18 +1096,13 @@ impl MountManager {
         let canonical_path = fs::canonicalize(&file_path)
             .context("无法规范化文件路径")?;
 
-        // 检查文件是否在任何挂载点范围内
-        let mut is_in_mount = false;
-        for mount_entry in self.mount_points.iter() {
-            if canonical_path.starts_with(&mount_entry.value().local_path) {
-                is_in_mount = true;
-                break;
-            }
-        }
-
-        if !is_in_mount {
+        // 检查文件是否在任何挂载点范围内且未被过滤规则排除
+        if self.mount_containing(&canonical_path).is_none() {
             return Err(anyhow!("文件不在任何挂载点范围内: {:?}", canonical_path));
         }
+        if !self.is_path_visible(&canonical_path) {
+            return Err(anyhow!("文件被挂载规则排除: {:?}", canonical_path));
+        }
 
         let authorization = FileAuthorization::new(canonical_path.clone(), auth_type, permissions);
         let auth_id = authorization.auth_id.clone();
@@ -467,22 +1114,28 @@ impl MountManager {
         Ok(auth_id)
     }
 
+    /// 获取文件授权
+    ///
+    /// # 参数
+    ///
+    /// * `auth_id` - 授权 ID
+    pub fn get_file_authorization(&self, auth_id: &str) -> Option<FileAuthorization> {
+        self.file_authorizations
+            .get(auth_id)
+            .map(|entry| entry.value().clone())
+    }
+
     /// 通过搜索令牌获取元数据
     ///
     /// # 参数
     ///
-    /// * `token_id` - 搜索令牌 ID
+    /// * `token` - 签名后的搜索令牌
     ///
     /// # 返回值
     ///
     /// 文件元数据列表
-    pub async fn get_metadata_by_token(&self, token_id: &str) -> Result<Vec<HashMap<String, String>>> {
-        let token = self.search_tokens.get(token_id)
-            .ok_or_else(|| anyhow!("搜索令牌不存在: {token_id}"))?;
-
-        if token.is_expired() {
-            return Err(anyhow!("搜索令牌已过期: {token_id}"));
-        }
+    pub async fn get_metadata_by_token(&self, token: &str) -> Result<Vec<HashMap<String, String>>> {
+        let token = self.verify_search_token(token)?;
 
         let mount_point = self.mount_points.get(&token.mount_id)
             .ok_or_else(|| anyhow!("挂载点不存在: {}", token.mount_id))?;
@@ -533,6 +1186,7 @@ impl MountManager {
         if count > 0 {
             info!("清理了 {count} 个过期搜索令牌");
         }
+        self.token_signer().prune();
 
         Ok(count)
     }
@@ -564,42 +1218,6 @@ impl MountManager {
         Ok((file_count, total_size))
     }
 
-    /// 复杂 glob 模式匹配
-    fn complex_glob_match(&self, path: &str, parts: &[&str]) -> bool {
-        if parts.is_empty() {
-            return false;
-        }
-        
-        let mut current_pos = 0;
-        
-        // 检查每个部分是否按顺序匹配
-        for (i, part) in parts.iter().enumerate() {
-            if part.is_empty() {
-                continue;
-            }
-            
-            if i == 0 {
-                // 第一部分必须在开头匹配
-                if !path[current_pos..].starts_with(part) {
-                    return false;
-                }
-                current_pos += part.len();
-            } else if i == parts.len() - 1 {
-                // 最后一部分必须在结尾匹配
-                return path[current_pos..].ends_with(part);
-            } else {
-                // 中间部分需要在剩余字符串中找到
-                if let Some(pos) = path[current_pos..].find(part) {
-                    current_pos += pos + part.len();
-                } else {
-                    return false;
-                }
-            }
-        }
-        
-        true
-    }
-
     /// 文件路径安全处理方法
     fn validate_path_security(&self, mount_root: &Path, target_path: &Path) -> Result<()> {
         // 规范化路径
@@ -656,13 +1274,11 @@ impl MountManager {
                         .unwrap_or("");
                     
                     // 简单的模式匹配
-                    if pattern.ends_with("*") {
-                        let prefix = &pattern[..pattern.len() - 1];
+                    if let Some(prefix) = pattern.strip_suffix('*') {
                         if filename.starts_with(prefix) {
                             files.push(path);
                         }
-                    } else if pattern.starts_with("*") {
-                        let suffix = &pattern[1..];
+                    } else if let Some(suffix) = pattern.strip_prefix('*') {
                         if filename.ends_with(suffix) {
                             files.push(path);
                         }
@@ -697,6 +1313,8 @@ mod tests {
             mount_time: Utc::now(),
             file_count: 0,
             total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
         };
 
         let result = mount_manager.mount_directory(mount_point.clone()).await;
@@ -707,6 +1325,173 @@ mod tests {
         assert_eq!(mount_points[0].mount_name, "测试挂载点");
     }
 
+    #[tokio::test]
+    async fn test_mounts_persist_and_flag_missing_paths() {
+        let store_dir = TempDir::new().unwrap();
+        let store_path = store_dir.path().join("mounts.json");
+        let kept_dir = TempDir::new().unwrap();
+        let removed_dir = TempDir::new().unwrap();
+        fs::write(kept_dir.path().join("a.txt"), "content").unwrap();
+
+        let index_dir = store_dir.path().join("indices");
+        let mount_manager = MountManager::load(store_path.clone(), index_dir.clone(), 3600).unwrap();
+        for (id, dir, read_only) in [("kept", &kept_dir, true), ("removed", &removed_dir, false)] {
+            mount_manager.mount_directory(MountPoint {
+                id: id.to_string(),
+                local_path: dir.path().to_path_buf(),
+                mount_name: id.to_string(),
+                read_only,
+                mount_time: Utc::now(),
+                file_count: 0,
+                total_size: 0,
+                status: MountStatus::Available,
+                rules: MountRules::default(),
+            }).await.unwrap();
+        }
+        let removed_path = fs::canonicalize(removed_dir.path()).unwrap();
+        drop(removed_dir);
+
+        // 重启后恢复挂载定义，路径已消失的挂载点被标记而不是丢弃
+        let restored = MountManager::load(store_path, index_dir, 3600).unwrap();
+        let mounts = restored.get_mount_points().await.unwrap();
+        assert_eq!(mounts.len(), 2);
+        let kept = mounts.iter().find(|mount| mount.id == "kept").unwrap();
+        assert_eq!(kept.status, MountStatus::Available);
+        assert!(kept.read_only);
+        assert_eq!(kept.file_count, 1);
+        let removed = mounts.iter().find(|mount| mount.id == "removed").unwrap();
+        assert_eq!(removed.status, MountStatus::Missing);
+
+        assert!(restored.mount_containing(&removed_path.join("b.txt")).is_none());
+        assert!(!restored.has_writable_mount());
+        assert!(restored.list_directory("removed", "/").await.is_err());
+        assert!(restored.list_directory("kept", "/").await.is_ok());
+
+        // 可用挂载点的索引从磁盘恢复，仍可搜索
+        assert_eq!(restored.search_files(&["a.txt".to_string()]).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_and_read_follow_mounts() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "挂载内容").unwrap();
+        fs::write(temp_dir.path().join(".secret"), "隐藏内容").unwrap();
+        let mount_manager = MountManager::new();
+
+        let mount_id = mount_manager.mount_directory(MountPoint {
+            id: Uuid::new_v4().to_string(),
+            local_path: temp_dir.path().to_path_buf(),
+            mount_name: "docs".to_string(),
+            read_only: true,
+            mount_time: Utc::now(),
+            file_count: 0,
+            total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
+        }).await.unwrap();
+        assert_eq!(mount_manager.mount_by_name("docs").unwrap().id, mount_id);
+
+        // 同名挂载点被拒绝
+        let duplicate = MountPoint {
+            id: Uuid::new_v4().to_string(),
+            local_path: temp_dir.path().to_path_buf(),
+            mount_name: "docs".to_string(),
+            read_only: true,
+            mount_time: Utc::now(),
+            file_count: 0,
+            total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
+        };
+        assert!(mount_manager.mount_directory(duplicate).await.is_err());
+
+        let matches = mount_manager.search_files(&["notes".to_string()]);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(mount_manager.read_file(&matches[0]).unwrap(), "挂载内容".as_bytes());
+
+        // 索引跳过的隐藏文件和挂载点外的文件都无法读取
+        let secret = fs::canonicalize(temp_dir.path().join(".secret")).unwrap();
+        assert!(mount_manager.read_file(&secret.to_string_lossy()).is_err());
+
+        // 卸载后不再能搜索或读取
+        mount_manager.unmount_directory(&mount_id).await.unwrap();
+        assert!(mount_manager.search_files(&["notes".to_string()]).is_empty());
+        assert!(mount_manager.read_file(&matches[0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mount_rules_apply_to_index_list_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp_dir.path()).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join("node_modules/pkg/index.js"), "依赖").unwrap();
+        fs::write(root.join(".env"), "SECRET=1").unwrap();
+        fs::write(root.join("report.txt"), "报告").unwrap();
+        fs::write(root.join("draft.txt"), "草稿").unwrap();
+        fs::write(root.join(".wdicignore"), "draft.txt\n").unwrap();
+        let mount_manager = MountManager::new();
+
+        let rules = MountRules {
+            exclude: vec![".env".to_string(), "node_modules/".to_string()],
+            include_hidden: true,
+            ..MountRules::default()
+        };
+        let mount_id = mount_manager.mount_directory(MountPoint {
+            id: Uuid::new_v4().to_string(),
+            local_path: root.clone(),
+            mount_name: "project".to_string(),
+            read_only: true,
+            mount_time: Utc::now(),
+            file_count: 0,
+            total_size: 0,
+            status: MountStatus::Available,
+            rules,
+        }).await.unwrap();
+
+        // 被排除和被忽略的路径不出现在搜索和目录列表中，也无法读取
+        assert!(mount_manager.search_files(&["index.js".to_string()]).is_empty());
+        assert!(mount_manager.search_files(&["draft".to_string()]).is_empty());
+        assert_eq!(mount_manager.search_files(&["report".to_string()]).len(), 1);
+        let names: Vec<String> = mount_manager
+            .list_directory(&mount_id, "/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, vec![".wdicignore".to_string(), "report.txt".to_string()]);
+        assert!(mount_manager.list_directory(&mount_id, "node_modules").await.is_err());
+        assert!(mount_manager.read_file(&root.join(".env").to_string_lossy()).is_err());
+        assert!(mount_manager
+            .authorize_file(root.join(".env"), "read".to_string(), vec![])
+            .await
+            .is_err());
+
+        // 之后新增的忽略文件对搜索和读取立即生效
+        fs::write(root.join(".gitignore"), "report.txt\n").unwrap();
+        let report = root.join("report.txt").to_string_lossy().to_string();
+        assert!(mount_manager.search_files(&["report".to_string()]).is_empty());
+        assert!(mount_manager.read_file(&report).is_err());
+
+        // 修改规则后按新规则重建索引
+        let updated = mount_manager
+            .set_mount_rules(&mount_id, MountRules {
+                respect_ignore_files: false,
+                ..MountRules::default()
+            })
+            .unwrap();
+        assert!(!updated.rules.respect_ignore_files);
+        assert_eq!(mount_manager.search_files(&["index.js".to_string()]).len(), 1);
+        assert_eq!(mount_manager.read_file(&report).unwrap(), "报告".as_bytes());
+        assert!(mount_manager.read_file(&root.join(".env").to_string_lossy()).is_err());
+        assert!(mount_manager
+            .set_mount_rules(&mount_id, MountRules {
+                max_entries_per_dir: 0,
+                ..MountRules::default()
+            })
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_search_token() {
         let mount_manager = MountManager::new();
@@ -722,24 +1507,41 @@ mod tests {
             mount_time: Utc::now(),
             file_count: 0,
             total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
         };
 
         mount_manager.mount_directory(mount_point).await.unwrap();
 
-        // 创建搜索令牌
-        let token_id = mount_manager.create_search_token(
+        // 创建只授予指定网关的搜索令牌
+        let grantee = Uuid::new_v4();
+        let token = mount_manager.create_search_token(
             mount_id,
             vec!["*.txt".to_string()],
             vec!["read".to_string()],
             3600,
+            Some(grantee),
         ).await.unwrap();
 
         // 验证令牌
-        let is_authorized = mount_manager.validate_search_token(&token_id, "test.txt").await.unwrap();
+        let is_authorized = mount_manager.validate_search_token(&token, "test.txt").await.unwrap();
         assert!(is_authorized);
 
-        let is_not_authorized = mount_manager.validate_search_token(&token_id, "test.jpg").await.unwrap();
+        let is_not_authorized = mount_manager.validate_search_token(&token, "test.jpg").await.unwrap();
         assert!(!is_not_authorized);
+
+        // 只有被授权的网关可以出示令牌
+        let file = temp_dir.path().canonicalize().unwrap().join("test.txt");
+        let file = file.to_string_lossy();
+        assert!(mount_manager.check_search_token(&token, grantee, Some(&file), "read").is_ok());
+        assert!(mount_manager.check_search_token(&token, Uuid::new_v4(), Some(&file), "read").is_err());
+        assert!(mount_manager.check_search_token(&token, grantee, Some(&file), "write").is_err());
+
+        // 吊销后令牌失效
+        let token_id = mount_manager.list_search_tokens()[0].token_id.clone();
+        assert!(mount_manager.revoke_search_token(&token_id).unwrap());
+        assert!(mount_manager.check_search_token(&token, grantee, Some(&file), "read").is_err());
+        assert!(mount_manager.list_search_tokens().is_empty());
     }
 
     #[tokio::test]
@@ -756,6 +1558,8 @@ mod tests {
             mount_time: Utc::now(),
             file_count: 0,
             total_size: 0,
+            status: MountStatus::Available,
+            rules: MountRules::default(),
         };
 
         mount_manager.mount_directory(mount_point).await.unwrap();
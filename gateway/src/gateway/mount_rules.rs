@@ -0,0 +1,473 @@
+//! 挂载点过滤规则
+//!
+//! 每个挂载点可以配置包含和排除的 glob 模式、文件大小和扩展名限制，
+//! 并遵循目录树中的 `.gitignore` 和 `.wdicignore` 文件。同一套规则用于生成目录索引、
+//! 搜索、列出目录和读取文件，被规则排除的路径在这些操作中都不可见。
+//!
+//! 模式语法与 `.gitignore` 一致：不含 `/` 的模式匹配任意层级的文件名，
+//! 含 `/` 的模式相对于挂载点根目录（或忽略文件所在目录）匹配，
+//! 以 `/` 结尾的模式只匹配目录，以 `!` 开头的模式重新包含之前被排除的路径，
+//! 多个模式匹配时以最后一个为准。目录被排除后其中的所有内容也被排除。
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::gateway::access_control::glob_match;
+
+/// 目录树中被遵循的忽略文件
+pub const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".wdicignore"];
+/// 每个目录默认最多索引的条目数
+pub const DEFAULT_MAX_ENTRIES_PER_DIR: usize = 10000;
+/// 不包含隐藏文件时仍然保留的隐藏文件
+const ALLOWED_HIDDEN_FILES: [&str; 3] = [".gitignore", ".env.example", ".dockerignore"];
+/// 始终跳过的系统目录和文件
+const SYSTEM_ENTRIES: [&str; 3] = ["System Volume Information", "$RECYCLE.BIN", "Thumbs.db"];
+/// 单个模式的最大长度
+const MAX_PATTERN_LENGTH: usize = 1024;
+
+/// 挂载点的过滤规则
+///
+/// 默认规则与旧版本的索引行为一致：跳过隐藏目录和大部分隐藏文件，
+/// 跳过系统目录，每个目录最多 10000 个条目，同时遵循忽略文件。
+/// `.env`、`node_modules/` 等敏感或庞大的路径可以通过 `exclude` 排除。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MountRules {
+    /// 包含的文件模式，非空时只有匹配其中之一的文件可见，目录不受影响
+    pub include: Vec<String>,
+    /// 排除的文件和目录模式
+    pub exclude: Vec<String>,
+    /// 是否遵循目录树中的 `.gitignore` 和 `.wdicignore` 文件
+    pub respect_ignore_files: bool,
+    /// 是否包含隐藏文件和隐藏目录
+    pub include_hidden: bool,
+    /// 文件大小上限（字节），为空时不限制
+    pub max_file_size: Option<u64>,
+    /// 允许的扩展名，非空时其他扩展名的文件不可见
+    pub allowed_extensions: Vec<String>,
+    /// 禁止的扩展名
+    pub denied_extensions: Vec<String>,
+    /// 每个目录最多索引的条目数
+    pub max_entries_per_dir: usize,
+}
+
+impl Default for MountRules {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_ignore_files: true,
+            include_hidden: false,
+            max_file_size: None,
+            allowed_extensions: Vec::new(),
+            denied_extensions: Vec::new(),
+            max_entries_per_dir: DEFAULT_MAX_ENTRIES_PER_DIR,
+        }
+    }
+}
+
+impl MountRules {
+    /// 验证规则
+    pub fn validate(&self) -> Result<()> {
+        for pattern in self.include.iter().chain(&self.exclude) {
+            if pattern.len() > MAX_PATTERN_LENGTH {
+                return Err(anyhow!("模式过长: 最多 {MAX_PATTERN_LENGTH} 字节"));
+            }
+            if RulePattern::parse(pattern).is_none() {
+                return Err(anyhow!("模式无效: '{pattern}'"));
+            }
+        }
+        for extension in self
+            .allowed_extensions
+            .iter()
+            .chain(&self.denied_extensions)
+        {
+            if normalize_extension(extension).is_empty() {
+                return Err(anyhow!("扩展名无效: '{extension}'"));
+            }
+        }
+        if self.max_entries_per_dir == 0 {
+            return Err(anyhow!("每个目录的最大条目数必须大于 0"));
+        }
+        Ok(())
+    }
+}
+
+/// 解析后的模式
+#[derive(Debug, Clone)]
+struct RulePattern {
+    /// glob 模式，已去掉前缀 `!`、首尾的 `/`
+    glob: String,
+    /// 是否为重新包含的模式
+    negated: bool,
+    /// 是否只匹配目录
+    directory_only: bool,
+    /// 是否相对于基准目录匹配整个路径，否则只匹配文件名
+    anchored: bool,
+}
+
+impl RulePattern {
+    /// 解析一行模式，空行和注释返回 `None`
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let line = line.replace('\\', "/");
+        let directory_only = line.ends_with('/');
+        let trimmed = line.trim_end_matches('/');
+        let anchored = trimmed.contains('/');
+        let glob = trimmed.trim_start_matches('/');
+        if glob.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            glob: glob.to_string(),
+            negated,
+            directory_only,
+            anchored,
+        })
+    }
+
+    /// 检查相对于基准目录的路径是否匹配
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            glob_match(&self.glob, relative)
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            glob_match(&self.glob, name)
+        }
+    }
+}
+
+/// 按顺序匹配模式，返回最后一个匹配的模式是否为排除
+fn last_match(patterns: &[RulePattern], relative: &str, is_dir: bool) -> Option<bool> {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern.matches(relative, is_dir))
+        .map(|pattern| !pattern.negated)
+}
+
+/// 规范化扩展名：去掉前导 `.` 并转为小写
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// 绑定到挂载点根目录的过滤器
+///
+/// 忽略文件按目录读取并缓存，过滤器只在单次操作（一次索引扫描、一次目录列出）内使用，
+/// 因此能看到忽略文件的最新内容。
+#[derive(Debug)]
+pub struct MountFilter {
+    /// 挂载点根目录
+    root: PathBuf,
+    /// 过滤规则
+    rules: MountRules,
+    /// 解析后的包含模式
+    include: Vec<RulePattern>,
+    /// 解析后的排除模式
+    exclude: Vec<RulePattern>,
+    /// 允许的扩展名
+    allowed_extensions: Vec<String>,
+    /// 禁止的扩展名
+    denied_extensions: Vec<String>,
+    /// 目录 -> 该目录中忽略文件的模式
+    ignore_patterns: DashMap<PathBuf, Arc<Vec<RulePattern>>>,
+}
+
+impl MountFilter {
+    /// 创建过滤器
+    ///
+    /// # 参数
+    ///
+    /// * `root` - 挂载点根目录（规范化后的路径）
+    /// * `rules` - 过滤规则
+    pub fn new(root: impl Into<PathBuf>, rules: &MountRules) -> Self {
+        let parse_all = |patterns: &[String]| -> Vec<RulePattern> {
+            patterns
+                .iter()
+                .filter_map(|pattern| RulePattern::parse(pattern))
+                .collect()
+        };
+        let normalize_all = |extensions: &[String]| -> Vec<String> {
+            extensions
+                .iter()
+                .map(|extension| normalize_extension(extension))
+                .collect()
+        };
+
+        Self {
+            root: root.into(),
+            include: parse_all(&rules.include),
+            exclude: parse_all(&rules.exclude),
+            allowed_extensions: normalize_all(&rules.allowed_extensions),
+            denied_extensions: normalize_all(&rules.denied_extensions),
+            rules: rules.clone(),
+            ignore_patterns: DashMap::new(),
+        }
+    }
+
+    /// 过滤规则
+    pub fn rules(&self) -> &MountRules {
+        &self.rules
+    }
+
+    /// 检查目录条目是否可见
+    ///
+    /// 只检查条目本身，调用方需要保证其所在目录可见（例如逐层扫描时）。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 条目路径
+    /// * `metadata` - 条目元数据
+    pub fn allows_entry(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        let Some(relative) = self.relative_path(path) else {
+            return false;
+        };
+        self.allows_relative(&relative, metadata.is_dir(), metadata.len())
+    }
+
+    /// 检查路径是否可见
+    ///
+    /// 从挂载点根目录开始逐层检查路径上的每一级，任意一级被排除时整个路径不可见。
+    /// 路径不存在或不在挂载点内时返回 `false`。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 规范化后的路径
+    pub fn allows_path(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative_path(path) else {
+            return false;
+        };
+        if relative.is_empty() {
+            return true;
+        }
+
+        let mut current = self.root.clone();
+        let mut prefix = String::new();
+        for component in relative.split('/') {
+            current.push(component);
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(component);
+
+            let Ok(metadata) = fs::metadata(&current) else {
+                return false;
+            };
+            if !self.allows_relative(&prefix, metadata.is_dir(), metadata.len()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 相对于挂载点根目录、以 `/` 分隔的路径
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        Some(parts.join("/"))
+    }
+
+    /// 检查单个条目，`relative` 为相对于根目录的路径
+    fn allows_relative(&self, relative: &str, is_dir: bool, size: u64) -> bool {
+        if relative.is_empty() {
+            return true;
+        }
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+
+        if !self.rules.include_hidden
+            && name.starts_with('.')
+            && (is_dir || !ALLOWED_HIDDEN_FILES.contains(&name))
+        {
+            return false;
+        }
+        if SYSTEM_ENTRIES.contains(&name) {
+            return false;
+        }
+        if last_match(&self.exclude, relative, is_dir) == Some(true) {
+            return false;
+        }
+        if self.rules.respect_ignore_files && self.ignored_by_files(relative, is_dir) {
+            debug!("忽略文件排除了路径: {relative}");
+            return false;
+        }
+        if is_dir {
+            return true;
+        }
+
+        if !self.include.is_empty()
+            && !self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(relative, false))
+        {
+            return false;
+        }
+
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if !self.allowed_extensions.is_empty() && !self.allowed_extensions.contains(&extension) {
+            return false;
+        }
+        if self.denied_extensions.contains(&extension) {
+            return false;
+        }
+
+        self.rules
+            .max_file_size
+            .is_none_or(|max_size| size <= max_size)
+    }
+
+    /// 检查条目是否被路径上各级目录中的忽略文件排除
+    ///
+    /// 各级忽略文件中的模式相对于其所在目录匹配，深层目录的模式优先。
+    fn ignored_by_files(&self, relative: &str, is_dir: bool) -> bool {
+        let parts: Vec<&str> = relative.split('/').collect();
+        let mut directory = self.root.clone();
+        let mut ignored = None;
+
+        for depth in 0..parts.len() {
+            if depth > 0 {
+                directory.push(parts[depth - 1]);
+            }
+            let patterns = self.patterns_in(&directory);
+            let sub_path = parts[depth..].join("/");
+            if let Some(excluded) = last_match(&patterns, &sub_path, is_dir) {
+                ignored = Some(excluded);
+            }
+        }
+
+        ignored.unwrap_or(false)
+    }
+
+    /// 读取目录中忽略文件的模式
+    fn patterns_in(&self, directory: &Path) -> Arc<Vec<RulePattern>> {
+        if let Some(patterns) = self.ignore_patterns.get(directory) {
+            return patterns.clone();
+        }
+
+        let patterns: Vec<RulePattern> = IGNORE_FILE_NAMES
+            .iter()
+            .filter_map(|file_name| fs::read_to_string(directory.join(file_name)).ok())
+            .flat_map(|content| {
+                content
+                    .lines()
+                    .filter_map(RulePattern::parse)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let patterns = Arc::new(patterns);
+        self.ignore_patterns
+            .insert(directory.to_path_buf(), patterns.clone());
+        patterns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn allows(filter: &MountFilter, root: &Path, relative: &str) -> bool {
+        filter.allows_path(&root.join(relative))
+    }
+
+    #[test]
+    fn test_rules_and_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp_dir.path()).unwrap();
+        for dir in ["src", "node_modules/pkg", "build", "docs/drafts"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "src/main.rs",
+            "src/secret.key",
+            "node_modules/pkg/index.js",
+            "build/out.bin",
+            "docs/readme.md",
+            "docs/drafts/todo.md",
+            "docs/drafts/keep.md",
+            ".env",
+            ".env.example",
+        ] {
+            fs::write(root.join(file), "content").unwrap();
+        }
+        fs::write(root.join("big.log"), vec![0u8; 2048]).unwrap();
+        fs::write(root.join(".gitignore"), "# 构建产物\nbuild/\n*.log\n").unwrap();
+        fs::write(root.join("docs/.wdicignore"), "drafts/*\n!drafts/keep.md\n").unwrap();
+
+        // 默认规则：遵循忽略文件，跳过隐藏文件但保留白名单
+        let filter = MountFilter::new(&root, &MountRules::default());
+        assert!(allows(&filter, &root, "src/main.rs"));
+        assert!(allows(&filter, &root, "node_modules/pkg/index.js"));
+        assert!(!allows(&filter, &root, "build/out.bin"));
+        assert!(!allows(&filter, &root, "big.log"));
+        assert!(!allows(&filter, &root, "docs/drafts/todo.md"));
+        assert!(allows(&filter, &root, "docs/drafts/keep.md"));
+        assert!(!allows(&filter, &root, ".env"));
+        assert!(allows(&filter, &root, ".env.example"));
+        assert!(!allows(&filter, &root, "missing.txt"));
+
+        // 按策略排除敏感文件和依赖目录，并限制扩展名和大小
+        let rules = MountRules {
+            exclude: vec![".env*".to_string(), "node_modules/".to_string()],
+            include_hidden: true,
+            respect_ignore_files: false,
+            denied_extensions: vec![".KEY".to_string()],
+            max_file_size: Some(1024),
+            ..MountRules::default()
+        };
+        rules.validate().unwrap();
+        let filter = MountFilter::new(&root, &rules);
+        assert!(!allows(&filter, &root, ".env"));
+        assert!(!allows(&filter, &root, "node_modules/pkg/index.js"));
+        assert!(!allows(&filter, &root, "src/secret.key"));
+        assert!(allows(&filter, &root, "build/out.bin"));
+        assert!(allows(&filter, &root, ".gitignore"));
+        assert!(!allows(&filter, &root, "big.log"));
+
+        // 包含模式只限制文件，目录仍可遍历
+        let rules = MountRules {
+            include: vec!["*.md".to_string()],
+            ..MountRules::default()
+        };
+        let filter = MountFilter::new(&root, &rules);
+        assert!(allows(&filter, &root, "docs"));
+        assert!(allows(&filter, &root, "docs/readme.md"));
+        assert!(!allows(&filter, &root, "src/main.rs"));
+
+        assert!(MountRules {
+            max_entries_per_dir: 0,
+            ..MountRules::default()
+        }
+        .validate()
+        .is_err());
+        assert!(MountRules {
+            exclude: vec!["!".to_string()],
+            ..MountRules::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,482 @@
+//! NAT 穿透模块
+//!
+//! 通过双方都可达的会合网关交换各自观察到的公网端点，再由双方同时向对端发送探测，
+//! 在各自的 NAT 上打开映射（UDP 打洞），为只能经中继通信的网关建立直连路径。
+
+use dashmap::DashMap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::gateway::registry::RegistryEntry;
+
+/// UDP 打洞配置
+#[derive(Debug, Clone)]
+pub struct HolePunchConfig {
+    /// 探测发送间隔
+    pub probe_interval: Duration,
+    /// 最多发送的探测轮数
+    pub probe_attempts: u32,
+    /// 等待会合网关下发打洞指令的超时时间
+    pub instruction_timeout: Duration,
+    /// 打洞失败后对同一网关再次尝试前的冷却时间
+    pub retry_cooldown: Duration,
+}
+
+impl Default for HolePunchConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval: Duration::from_millis(100),
+            probe_attempts: 20,
+            instruction_timeout: Duration::from_secs(5),
+            retry_cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+impl HolePunchConfig {
+    /// 探测阶段的最长持续时间
+    pub fn probing_duration(&self) -> Duration {
+        self.probe_interval * self.probe_attempts
+    }
+}
+
+/// 打洞会话状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunchState {
+    /// 已发出请求，等待会合网关下发指令
+    AwaitingInstruction,
+    /// 正在向对端候选端点发送探测
+    Probing,
+    /// 直连路径已建立
+    Established(SocketAddr),
+    /// 打洞失败
+    Failed,
+}
+
+impl PunchState {
+    /// 会话是否已结束
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Established(_) | Self::Failed)
+    }
+}
+
+/// 建立到对端网关路径的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum PathOutcome {
+    /// 已存在直连路径
+    Direct(SocketAddr),
+    /// 经 UDP 打洞建立了直连路径
+    HolePunched(SocketAddr),
+    /// 打洞未成功，继续经中继通信
+    Relayed {
+        /// 中继网关地址
+        relay_addr: SocketAddr,
+    },
+}
+
+/// UDP 打洞统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HolePunchStats {
+    /// 会合网关观察到的本网关公网端点
+    pub public_endpoint: Option<SocketAddr>,
+    /// 进行中的打洞会话数
+    pub active_sessions: usize,
+    /// 发起或参与的打洞次数
+    pub attempts: u64,
+    /// 成功次数
+    pub succeeded: u64,
+    /// 失败次数
+    pub failed: u64,
+}
+
+/// 单个打洞会话
+#[derive(Debug)]
+struct PunchSession {
+    /// 对端网关 ID
+    peer_id: Uuid,
+    /// 会话状态
+    state: watch::Sender<PunchState>,
+}
+
+/// 打洞会话跟踪器
+///
+/// 记录进行中的打洞会话、失败后的冷却时间和统计数据，由网络管理器驱动。
+#[derive(Debug)]
+pub struct HolePunchTracker {
+    /// 打洞配置
+    config: HolePunchConfig,
+    /// 打洞会话（会话 ID -> 会话）
+    sessions: DashMap<Uuid, PunchSession>,
+    /// 冷却截止时间（对端网关 ID -> 截止时间）
+    cooldowns: DashMap<Uuid, Instant>,
+    /// 会合网关观察到的公网端点
+    public_endpoint: RwLock<Option<SocketAddr>>,
+    /// 打洞次数
+    attempts: AtomicU64,
+    /// 成功次数
+    succeeded: AtomicU64,
+    /// 失败次数
+    failed: AtomicU64,
+}
+
+impl HolePunchTracker {
+    /// 创建新的打洞会话跟踪器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 打洞配置
+    ///
+    /// # 返回值
+    ///
+    /// 跟踪器实例
+    pub fn new(config: HolePunchConfig) -> Self {
+        Self {
+            config,
+            sessions: DashMap::new(),
+            cooldowns: DashMap::new(),
+            public_endpoint: RwLock::new(None),
+            attempts: AtomicU64::new(0),
+            succeeded: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// 获取打洞配置
+    pub fn config(&self) -> &HolePunchConfig {
+        &self.config
+    }
+
+    /// 为指定网关发起新的打洞会话
+    ///
+    /// 与该网关的打洞处于冷却期或已有进行中的会话时不会发起。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对端网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 新会话 ID
+    pub fn begin_request(&self, peer_id: Uuid) -> Option<Uuid> {
+        if self
+            .cooldowns
+            .get(&peer_id)
+            .is_some_and(|until| Instant::now() < *until)
+        {
+            return None;
+        }
+
+        let in_progress = self
+            .sessions
+            .iter()
+            .any(|session| session.peer_id == peer_id && !session.state.borrow().is_finished());
+        if in_progress {
+            return None;
+        }
+
+        let session_id = Uuid::new_v4();
+        let (state, _) = watch::channel(PunchState::AwaitingInstruction);
+        self.sessions
+            .insert(session_id, PunchSession { peer_id, state });
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        Some(session_id)
+    }
+
+    /// 接受会合网关下发的打洞指令，进入探测阶段
+    ///
+    /// 本网关发起的会话必须处于等待指令状态且对端一致；
+    /// 对端发起的会话在此时创建。
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    /// * `peer_id` - 对端网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 是否应开始探测
+    pub fn accept_instruction(&self, session_id: Uuid, peer_id: Uuid) -> bool {
+        if let Some(session) = self.sessions.get(&session_id) {
+            if session.peer_id != peer_id
+                || *session.state.borrow() != PunchState::AwaitingInstruction
+            {
+                return false;
+            }
+            session.state.send_replace(PunchState::Probing);
+            return true;
+        }
+
+        let (state, _) = watch::channel(PunchState::Probing);
+        self.sessions
+            .insert(session_id, PunchSession { peer_id, state });
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// 获取会话状态
+    pub fn state(&self, session_id: &Uuid) -> Option<PunchState> {
+        self.sessions
+            .get(session_id)
+            .map(|session| *session.state.borrow())
+    }
+
+    /// 订阅会话状态变化
+    pub fn subscribe(&self, session_id: &Uuid) -> Option<watch::Receiver<PunchState>> {
+        self.sessions
+            .get(session_id)
+            .map(|session| session.state.subscribe())
+    }
+
+    /// 检查会话是否仍接受来自指定网关的探测
+    ///
+    /// 直连建立后会话在宽限期内保留，以便回复对端迟到的探测。
+    pub fn accepts_probe(&self, session_id: &Uuid, peer_id: &Uuid) -> bool {
+        self.sessions.get(session_id).is_some_and(|session| {
+            session.peer_id == *peer_id && *session.state.borrow() != PunchState::Failed
+        })
+    }
+
+    /// 记录直连路径建立
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    /// * `peer_id` - 对端网关 ID
+    /// * `address` - 对端直连地址
+    ///
+    /// # 返回值
+    ///
+    /// 是否为该会话首次建立直连
+    pub fn establish(&self, session_id: &Uuid, peer_id: &Uuid, address: SocketAddr) -> bool {
+        let Some(session) = self.sessions.get(session_id) else {
+            return false;
+        };
+        if session.peer_id != *peer_id || session.state.borrow().is_finished() {
+            return false;
+        }
+
+        session.state.send_replace(PunchState::Established(address));
+        self.cooldowns.remove(peer_id);
+        self.succeeded.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// 将未结束的会话标记为失败，并对该网关进入冷却期
+    ///
+    /// # 返回值
+    ///
+    /// 会话是否由本次调用标记为失败
+    pub fn fail(&self, session_id: &Uuid) -> bool {
+        let Some(session) = self.sessions.get(session_id) else {
+            return false;
+        };
+        if session.state.borrow().is_finished() {
+            return false;
+        }
+
+        session.state.send_replace(PunchState::Failed);
+        self.cooldowns
+            .insert(session.peer_id, Instant::now() + self.config.retry_cooldown);
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// 将仍在等待指令的会话标记为失败
+    ///
+    /// # 返回值
+    ///
+    /// 会话是否由本次调用标记为失败
+    pub fn fail_if_awaiting(&self, session_id: &Uuid) -> bool {
+        if self.state(session_id) != Some(PunchState::AwaitingInstruction) {
+            return false;
+        }
+        self.fail(session_id)
+    }
+
+    /// 移除会话
+    pub fn remove(&self, session_id: &Uuid) {
+        self.sessions.remove(session_id);
+    }
+
+    /// 获取会话的对端网关 ID
+    pub fn peer_of(&self, session_id: &Uuid) -> Option<Uuid> {
+        self.sessions.get(session_id).map(|session| session.peer_id)
+    }
+
+    /// 记录会合网关观察到的公网端点
+    pub fn set_public_endpoint(&self, endpoint: SocketAddr) {
+        if let Ok(mut guard) = self.public_endpoint.write() {
+            *guard = Some(endpoint);
+        }
+    }
+
+    /// 获取会合网关观察到的公网端点
+    pub fn public_endpoint(&self) -> Option<SocketAddr> {
+        self.public_endpoint.read().ok().and_then(|guard| *guard)
+    }
+
+    /// 获取打洞统计
+    pub fn stats(&self) -> HolePunchStats {
+        HolePunchStats {
+            public_endpoint: self.public_endpoint(),
+            active_sessions: self
+                .sessions
+                .iter()
+                .filter(|session| !session.state.borrow().is_finished())
+                .count(),
+            attempts: self.attempts.load(Ordering::Relaxed),
+            succeeded: self.succeeded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 会合网关记录的端点信息
+#[derive(Debug, Clone)]
+struct EndpointRecord {
+    /// 网关上报的本地端点
+    local_endpoints: Vec<SocketAddr>,
+    /// 上报时间
+    updated_at: Instant,
+}
+
+/// 会合服务
+///
+/// 记录各网关上报的本地端点，结合注册表中观察到的公网端点，
+/// 为打洞双方提供对端候选端点。
+#[derive(Debug)]
+pub struct RendezvousService {
+    /// 端点记录（网关 ID -> 记录）
+    records: DashMap<Uuid, EndpointRecord>,
+    /// 记录有效期
+    ttl: Duration,
+}
+
+impl RendezvousService {
+    /// 创建新的会合服务
+    ///
+    /// # 参数
+    ///
+    /// * `ttl` - 端点记录有效期
+    ///
+    /// # 返回值
+    ///
+    /// 会合服务实例
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            records: DashMap::new(),
+            ttl,
+        }
+    }
+
+    /// 记录网关上报的本地端点，同时清理过期记录
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    /// * `local_endpoints` - 本地端点列表
+    pub fn record(&self, gateway_id: Uuid, local_endpoints: Vec<SocketAddr>) {
+        let ttl = self.ttl;
+        self.records
+            .retain(|_, record| record.updated_at.elapsed() <= ttl);
+        self.records.insert(
+            gateway_id,
+            EndpointRecord {
+                local_endpoints,
+                updated_at: Instant::now(),
+            },
+        );
+    }
+
+    /// 获取网关的候选端点
+    ///
+    /// 观察到的公网端点排在最前，其后为未过期的上报本地端点，
+    /// 未指定地址和端口为 0 的端点会被过滤。
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 网关在会合网关注册表中的条目
+    ///
+    /// # 返回值
+    ///
+    /// 去重后的候选端点列表
+    pub fn candidates(&self, entry: &RegistryEntry) -> Vec<SocketAddr> {
+        let mut candidates = vec![entry.address];
+        if let Some(record) = self.records.get(&entry.id) {
+            if record.updated_at.elapsed() <= self.ttl {
+                candidates.extend(record.local_endpoints.iter().copied());
+            }
+        }
+
+        let mut unique = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            if candidate.ip().is_unspecified() || candidate.port() == 0 {
+                continue;
+            }
+            if !unique.contains(&candidate) {
+                unique.push(candidate);
+            }
+        }
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hole_punch_tracker_lifecycle() {
+        let tracker = HolePunchTracker::new(HolePunchConfig::default());
+        let peer_id = Uuid::new_v4();
+        let address: SocketAddr = "203.0.113.2:40001".parse().unwrap();
+
+        // 同一网关同时只能有一个进行中的会话
+        let session_id = tracker.begin_request(peer_id).unwrap();
+        assert!(tracker.begin_request(peer_id).is_none());
+        assert!(!tracker.accept_instruction(session_id, Uuid::new_v4()));
+        assert!(tracker.accept_instruction(session_id, peer_id));
+        assert_eq!(tracker.state(&session_id), Some(PunchState::Probing));
+
+        assert!(tracker.establish(&session_id, &peer_id, address));
+        assert!(!tracker.establish(&session_id, &peer_id, address));
+        assert!(tracker.accepts_probe(&session_id, &peer_id));
+        tracker.remove(&session_id);
+
+        // 失败后进入冷却期
+        let session_id = tracker.begin_request(peer_id).unwrap();
+        assert!(tracker.fail_if_awaiting(&session_id));
+        assert!(!tracker.accepts_probe(&session_id, &peer_id));
+        assert!(tracker.begin_request(peer_id).is_none());
+
+        let stats = tracker.stats();
+        assert_eq!((stats.attempts, stats.succeeded, stats.failed), (2, 1, 1));
+        assert_eq!(stats.active_sessions, 0);
+    }
+
+    #[test]
+    fn test_rendezvous_candidates() {
+        let rendezvous = RendezvousService::new(Duration::from_secs(60));
+        let entry = RegistryEntry::new("网关".to_string(), "203.0.113.1:40000".parse().unwrap());
+
+        assert_eq!(rendezvous.candidates(&entry), vec![entry.address]);
+
+        rendezvous.record(
+            entry.id,
+            vec![
+                "10.0.1.2:55555".parse().unwrap(),
+                "0.0.0.0:55555".parse().unwrap(),
+                entry.address,
+            ],
+        );
+        assert_eq!(
+            rendezvous.candidates(&entry),
+            vec![entry.address, "10.0.1.2:55555".parse().unwrap()]
+        );
+    }
+}
@@ -3,17 +3,26 @@
 //! 处理 QUIC 连接、UDP 广播和网络通信，支持 IPv4/IPv6 双栈网络。
 
 use anyhow::Result;
+use dashmap::DashMap;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
-use std::sync::Arc;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, RwLock as StdRwLock};
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::time::{interval, Duration};
 use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 
+use crate::gateway::envelope::MessageAuthenticator;
+use crate::gateway::interfaces::{InterfaceChange, InterfacePolicy, LocalInterface};
+use crate::gateway::nat::{HolePunchConfig, HolePunchStats, HolePunchTracker, PunchState};
 use crate::gateway::protocol::WdicMessage;
 use crate::gateway::protocol::WdicProtocol;
+use crate::gateway::rate_limit::{InboundRateLimiter, RateVerdict};
+use crate::gateway::quic::{AuthenticationResult, QuicEndpoint, QuicSecurity, MAX_DATAGRAM_SIZE};
+use crate::gateway::tls::{NegotiatedTls, PeerCertificate, QuicRole};
+use crate::gateway::transport::{DatagramTransport, SwappableTransport, UdpTransport};
+use uuid::Uuid;
 
 /// 网络事件类型
 #[derive(Debug, Clone)]
@@ -24,6 +33,8 @@ pub enum NetworkEvent {
         message: WdicMessage,
         /// 发送者地址
         sender: SocketAddr,
+        /// 经签名信封确认的发送者，未签名消息为 `None`
+        signer: Option<Uuid>,
     },
     /// 新的连接建立
     ConnectionEstablished {
@@ -52,6 +63,38 @@ pub enum NetworkEvent {
         /// 错误信息
         error: String,
     },
+    /// UDP 打洞成功，已建立到对端的直连路径
+    HolePunchSucceeded {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 对端网关 ID
+        peer_id: Uuid,
+        /// 对端直连地址
+        address: SocketAddr,
+    },
+    /// UDP 打洞失败
+    HolePunchFailed {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 对端网关 ID
+        peer_id: Uuid,
+    },
+    /// QUIC 握手完成，协商参数符合 TLS 策略且对端通过认证
+    PeerAuthenticated {
+        /// 远程地址
+        remote_addr: SocketAddr,
+        /// 对端证书身份，对端未出示证书且不要求证书时为 `None`
+        certificate: Option<PeerCertificate>,
+        /// 握手协商出的 TLS 参数
+        negotiated: NegotiatedTls,
+    },
+    /// QUIC 握手完成，但协商参数不符合 TLS 策略或对端证书未通过认证，连接已关闭
+    PeerAuthenticationFailed {
+        /// 远程地址
+        remote_addr: SocketAddr,
+        /// 失败原因
+        error: String,
+    },
 }
 
 /// 连接状态
@@ -63,6 +106,16 @@ pub struct ConnectionState {
     pub last_active: chrono::DateTime<chrono::Utc>,
     /// 连接建立时间
     pub established_at: chrono::DateTime<chrono::Utc>,
+    /// 收到的字节数
+    pub bytes_received: u64,
+    /// 发送的字节数
+    pub bytes_sent: u64,
+    /// 对端证书声明的网关 ID
+    pub peer_id: Option<Uuid>,
+    /// 对端证书指纹
+    pub fingerprint: Option<String>,
+    /// QUIC 握手协商出的 TLS 参数
+    pub tls: Option<NegotiatedTls>,
 }
 
 impl ConnectionState {
@@ -73,6 +126,11 @@ impl ConnectionState {
             remote_addr,
             last_active: now,
             established_at: now,
+            bytes_received: 0,
+            bytes_sent: 0,
+            peer_id: None,
+            fingerprint: None,
+            tls: None,
         }
     }
 
@@ -81,6 +139,31 @@ impl ConnectionState {
         self.last_active = chrono::Utc::now();
     }
 
+    /// 记录收到的数据
+    pub fn record_received(&mut self, len: usize) {
+        self.bytes_received += len as u64;
+        self.update_activity();
+    }
+
+    /// 记录发送的数据
+    pub fn record_sent(&mut self, len: usize) {
+        self.bytes_sent += len as u64;
+    }
+
+    /// 记录 QUIC 握手认证出的对端身份和协商参数
+    ///
+    /// # 参数
+    ///
+    /// * `certificate` - 对端证书身份
+    /// * `negotiated` - 握手协商出的 TLS 参数
+    pub fn authenticate(&mut self, certificate: Option<&PeerCertificate>, negotiated: NegotiatedTls) {
+        if let Some(certificate) = certificate {
+            self.peer_id = certificate.gateway_id;
+            self.fingerprint = Some(certificate.fingerprint.clone());
+        }
+        self.tls = Some(negotiated);
+    }
+
     /// 检查连接是否超时
     pub fn is_expired(&self, timeout_seconds: i64) -> bool {
         let now = chrono::Utc::now();
@@ -94,7 +177,7 @@ impl ConnectionState {
 }
 
 /// 发现的节点信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DiscoveredNodeInfo {
     /// 节点 ID
     pub node_id: String,
@@ -230,10 +313,12 @@ impl DiscoveredNodeInfo {
 /// 负责处理网络通信，包括 UDP 广播和消息收发。
 #[derive(Debug)]
 pub struct NetworkManager {
-    /// 本地地址
-    local_addr: SocketAddr,
-    /// UDP 套接字
-    udp_socket: Arc<UdpSocket>,
+    /// 本地地址（接口变化重新绑定后会更新）
+    local_addr: StdRwLock<SocketAddr>,
+    /// 配置的绑定地址，重新绑定时据此选择接口地址
+    bind_template: SocketAddr,
+    /// 数据报传输层（默认为 UDP 套接字）
+    transport: Arc<SwappableTransport>,
     /// 协议处理器
     protocol: WdicProtocol,
     /// 活跃连接
@@ -243,7 +328,13 @@ pub struct NetworkManager {
     /// 事件接收通道
     event_receiver: Arc<Mutex<Option<mpsc::UnboundedReceiver<NetworkEvent>>>>,
     /// 广播地址列表
-    broadcast_addresses: Vec<SocketAddr>,
+    broadcast_addresses: Arc<RwLock<Vec<SocketAddr>>>,
+    /// 网络接口策略
+    interface_policy: InterfacePolicy,
+    /// 最近一次扫描到的符合策略的接口
+    interfaces: Arc<RwLock<Vec<LocalInterface>>>,
+    /// 接口变化时是否重新绑定监听地址
+    auto_rebind: bool,
     /// 已发现的节点
     pub discovered_nodes: Arc<RwLock<HashMap<String, DiscoveredNodeInfo>>>,
     /// P2P 发现状态
@@ -254,6 +345,16 @@ pub struct NetworkManager {
     pub transfer_tasks: Arc<RwLock<HashMap<String, FileTransferTaskInfo>>>,
     /// 节点 ID 到连接地址的映射
     pub node_connections: Arc<RwLock<HashMap<String, SocketAddr>>>,
+    /// UDP 打洞会话跟踪器
+    hole_punch: Arc<HolePunchTracker>,
+    /// QUIC 端点（入站握手与对端证书身份）
+    quic: Arc<QuicEndpoint>,
+    /// 消息签名与验证器
+    authenticator: Arc<StdRwLock<Arc<MessageAuthenticator>>>,
+    /// 会话被强制断开的地址及冷却期截止时间，冷却期内丢弃其数据
+    cooldowns: Arc<DashMap<SocketAddr, std::time::Instant>>,
+    /// 入站限速器
+    rate_limiter: Arc<StdRwLock<Arc<InboundRateLimiter>>>,
 }
 
 impl NetworkManager {
@@ -267,34 +368,289 @@ impl NetworkManager {
     ///
     /// 网络管理器实例
     pub fn new(local_addr: SocketAddr) -> Result<Self> {
-        let udp_socket = UdpSocket::bind(local_addr)?;
-        udp_socket.set_broadcast(true)?;
-        udp_socket.set_nonblocking(true)?;
+        let transport = UdpTransport::bind(local_addr)?;
+        Self::with_transport(local_addr, Arc::new(transport))
+    }
 
+    /// 使用指定的数据报传输层创建网络管理器
+    ///
+    /// # 参数
+    ///
+    /// * `local_addr` - 本地监听地址
+    /// * `transport` - 数据报传输层
+    ///
+    /// # 返回值
+    ///
+    /// 网络管理器实例
+    pub fn with_transport(
+        local_addr: SocketAddr,
+        transport: Arc<dyn DatagramTransport>,
+    ) -> Result<Self> {
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
 
         // 生成常见的广播地址
         let broadcast_addresses = Self::generate_broadcast_addresses(local_addr);
+        let interfaces = InterfacePolicy::default().scan().unwrap_or_default();
 
         Ok(Self {
-            local_addr,
-            udp_socket: Arc::new(udp_socket),
+            local_addr: StdRwLock::new(local_addr),
+            bind_template: local_addr,
+            transport: Arc::new(SwappableTransport::new(transport)),
             protocol: WdicProtocol::new(),
             connections: Arc::new(Mutex::new(HashMap::new())),
             event_sender,
             event_receiver: Arc::new(Mutex::new(Some(event_receiver))),
-            broadcast_addresses,
+            broadcast_addresses: Arc::new(RwLock::new(broadcast_addresses)),
+            interface_policy: InterfacePolicy::default(),
+            interfaces: Arc::new(RwLock::new(interfaces)),
+            auto_rebind: false,
             discovered_nodes: Arc::new(RwLock::new(HashMap::new())),
             p2p_discovery_enabled: Arc::new(Mutex::new(false)),
             discovery_task_handle: Arc::new(Mutex::new(None)),
             transfer_tasks: Arc::new(RwLock::new(HashMap::new())),
             node_connections: Arc::new(RwLock::new(HashMap::new())),
+            hole_punch: Arc::new(HolePunchTracker::new(HolePunchConfig::default())),
+            quic: Arc::new(QuicEndpoint::new()),
+            authenticator: Arc::new(StdRwLock::new(Arc::new(MessageAuthenticator::disabled()))),
+            cooldowns: Arc::new(DashMap::new()),
+            rate_limiter: Arc::new(StdRwLock::new(Arc::new(InboundRateLimiter::disabled()))),
         })
     }
 
+    /// 设置 UDP 打洞配置
+    ///
+    /// 需在网络管理器共享之前调用，已有的打洞会话和统计会被重置。
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 打洞配置
+    pub fn set_hole_punch_config(&mut self, config: HolePunchConfig) {
+        self.hole_punch = Arc::new(HolePunchTracker::new(config));
+    }
+
+    /// 设置网络接口策略
+    ///
+    /// 需在网络管理器共享之前调用，会按新策略重新扫描接口并生成广播地址。
+    ///
+    /// # 参数
+    ///
+    /// * `policy` - 接口策略
+    pub fn set_interface_policy(&mut self, policy: InterfacePolicy) {
+        let broadcast_addresses =
+            Self::generate_broadcast_addresses_with_policy(self.local_addr(), &policy);
+        self.broadcast_addresses = Arc::new(RwLock::new(broadcast_addresses));
+        self.interfaces = Arc::new(RwLock::new(policy.scan().unwrap_or_default()));
+        self.interface_policy = policy;
+    }
+
+    /// 启用接口变化时的自动重新绑定
+    ///
+    /// # 参数
+    ///
+    /// * `bind_template` - 配置的绑定地址，为未指定地址时按接口策略选择具体地址
+    pub fn enable_auto_rebind(&mut self, bind_template: SocketAddr) {
+        self.bind_template = bind_template;
+        self.auto_rebind = true;
+    }
+
+    /// 获取网络接口策略
+    pub fn interface_policy(&self) -> &InterfacePolicy {
+        &self.interface_policy
+    }
+
     /// 获取本地地址
     pub fn local_addr(&self) -> SocketAddr {
-        self.local_addr
+        *self.local_addr.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 获取数据报传输层
+    pub fn transport(&self) -> Arc<dyn DatagramTransport> {
+        self.transport.clone()
+    }
+
+    /// 设置 QUIC 传输安全配置
+    ///
+    /// 设置后才会接受入站 QUIC 握手和发起 QUIC 连接，握手双方均出示 `MtlsConfig` 中的证书。
+    ///
+    /// # 参数
+    ///
+    /// * `security` - 传输安全配置
+    pub fn set_quic_security(&self, security: QuicSecurity) {
+        self.quic.set_security(security);
+    }
+
+    /// 设置消息签名与验证器
+    ///
+    /// 设置后发出的 WDIC 消息都会签名，收到的消息按签名策略验证。
+    ///
+    /// # 参数
+    ///
+    /// * `authenticator` - 签名与验证器
+    pub fn set_message_authenticator(&self, authenticator: Arc<MessageAuthenticator>) {
+        *self.authenticator.write().unwrap_or_else(|e| e.into_inner()) = authenticator;
+    }
+
+    /// 设置入站限速器
+    ///
+    /// # 参数
+    ///
+    /// * `rate_limiter` - 按来源和消息类别限速的限速器
+    pub fn set_rate_limiter(&self, rate_limiter: Arc<InboundRateLimiter>) {
+        *self.rate_limiter.write().unwrap_or_else(|e| e.into_inner()) = rate_limiter;
+    }
+
+    /// 获取当前的入站限速器
+    pub fn rate_limiter(&self) -> Arc<InboundRateLimiter> {
+        Arc::clone(&self.rate_limiter.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 获取消息签名与验证器
+    pub fn message_authenticator(&self) -> Arc<MessageAuthenticator> {
+        Self::current_authenticator(&self.authenticator)
+    }
+
+    /// 读取当前的消息签名与验证器
+    fn current_authenticator(
+        authenticator: &StdRwLock<Arc<MessageAuthenticator>>,
+    ) -> Arc<MessageAuthenticator> {
+        Arc::clone(&authenticator.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 序列化并签名消息
+    fn encode_message(&self, message: &WdicMessage) -> Result<Vec<u8>> {
+        self.message_authenticator().seal(&message.to_bytes()?)
+    }
+
+    /// 获取对端地址经 QUIC 握手认证的证书身份
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 对端地址
+    pub fn peer_certificate(&self, remote_addr: &SocketAddr) -> Option<PeerCertificate> {
+        self.quic.peer_certificate(remote_addr)
+    }
+
+    /// 根据已认证的证书指纹查找对端网关 ID
+    ///
+    /// # 参数
+    ///
+    /// * `fingerprint` - 证书 SHA-256 指纹
+    pub fn gateway_for_fingerprint(&self, fingerprint: &str) -> Option<Uuid> {
+        self.quic.gateway_for_fingerprint(fingerprint)
+    }
+
+    /// 获取当前的广播地址列表
+    pub async fn broadcast_addresses(&self) -> Vec<SocketAddr> {
+        self.broadcast_addresses.read().await.clone()
+    }
+
+    /// 获取最近一次扫描到的符合策略的接口
+    pub async fn permitted_interfaces(&self) -> Vec<LocalInterface> {
+        self.interfaces.read().await.clone()
+    }
+
+    /// 按接口策略选择绑定地址
+    ///
+    /// 配置了白名单且绑定地址为未指定地址时，改为绑定第一个符合策略的接口地址，
+    /// 避免在被排除的接口上监听；没有可用接口时只监听回环地址。
+    ///
+    /// # 参数
+    ///
+    /// * `configured` - 配置的绑定地址
+    /// * `policy` - 接口策略
+    ///
+    /// # 返回值
+    ///
+    /// 实际使用的绑定地址
+    pub fn select_bind_address(configured: SocketAddr, policy: &InterfacePolicy) -> SocketAddr {
+        if !policy.is_restricted() || !configured.ip().is_unspecified() {
+            return configured;
+        }
+
+        let interfaces = policy.scan().unwrap_or_else(|e| {
+            warn!("无法获取网络接口列表: {e}");
+            Vec::new()
+        });
+        // 链路本地 IPv6 地址需要作用域 ID 才能绑定，这里跳过
+        let candidate = interfaces.iter().find(|interface| match interface.ip {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(v6) => configured.is_ipv6() && v6.segments()[0] & 0xffc0 != 0xfe80,
+        });
+
+        match candidate {
+            Some(interface) => SocketAddr::new(interface.ip, configured.port()),
+            None => {
+                warn!("没有符合接口策略的网络接口，暂时只监听回环地址");
+                let loopback = if configured.is_ipv6() {
+                    IpAddr::V6(Ipv6Addr::LOCALHOST)
+                } else {
+                    IpAddr::V4(Ipv4Addr::LOCALHOST)
+                };
+                SocketAddr::new(loopback, configured.port())
+            }
+        }
+    }
+
+    /// 重新扫描网络接口
+    ///
+    /// 接口启用、停用或地址变化时重新生成广播地址；启用自动重新绑定时，
+    /// 若应使用的监听地址发生变化则在原端口上重新绑定。
+    ///
+    /// # 返回值
+    ///
+    /// 接口有变化时返回变化内容
+    pub async fn refresh_interfaces(&self) -> Result<Option<InterfaceChange>> {
+        let current = self
+            .interface_policy
+            .scan()
+            .map_err(|e| anyhow::anyhow!("扫描网络接口失败: {e}"))?;
+
+        let change = {
+            let mut known = self.interfaces.write().await;
+            let change = InterfaceChange::between(&known, &current);
+            *known = current;
+            change
+        };
+        let Some(mut change) = change else {
+            return Ok(None);
+        };
+
+        info!(
+            "网络接口变化: 新增 {:?}，移除 {:?}",
+            change.added, change.removed
+        );
+
+        if self.auto_rebind {
+            change.rebound = self.rebind()?;
+        }
+
+        let broadcast_addresses =
+            Self::generate_broadcast_addresses_with_policy(self.local_addr(), &self.interface_policy);
+        *self.broadcast_addresses.write().await = broadcast_addresses;
+
+        Ok(Some(change))
+    }
+
+    /// 按接口策略重新绑定监听地址
+    ///
+    /// # 返回值
+    ///
+    /// 重新绑定后的地址，无需重新绑定时返回 None
+    fn rebind(&self) -> Result<Option<SocketAddr>> {
+        let current = self.local_addr();
+        let template = SocketAddr::new(self.bind_template.ip(), current.port());
+        let desired = Self::select_bind_address(template, &self.interface_policy);
+        if desired == current {
+            return Ok(None);
+        }
+
+        let transport = UdpTransport::bind(desired)?;
+        let bound = transport.local_addr()?;
+        self.transport.replace(Arc::new(transport));
+        *self.local_addr.write().unwrap_or_else(|e| e.into_inner()) = bound;
+
+        info!("监听地址从 {current} 切换到 {bound}");
+        Ok(Some(bound))
     }
 
     /// 获取事件接收器
@@ -321,18 +677,43 @@ impl NetworkManager {
     ///
     /// 广播和多播地址列表
     fn generate_broadcast_addresses(local_addr: SocketAddr) -> Vec<SocketAddr> {
+        Self::generate_broadcast_addresses_with_policy(local_addr, &InterfacePolicy::default())
+    }
+
+    /// 按接口策略生成广播地址列表
+    ///
+    /// 只为符合策略的接口生成地址；策略排除了公网接口时不使用有限广播，
+    /// 策略有任何限制时也不使用会发往所有接口的后备地址。
+    ///
+    /// # 参数
+    ///
+    /// * `local_addr` - 本地绑定地址
+    /// * `policy` - 接口策略
+    ///
+    /// # 返回值
+    ///
+    /// 广播和多播地址列表
+    pub fn generate_broadcast_addresses_with_policy(
+        local_addr: SocketAddr,
+        policy: &InterfacePolicy,
+    ) -> Vec<SocketAddr> {
         let mut addresses = Vec::new();
         let port = local_addr.port();
+        let permissive = policy.is_permissive();
 
         debug!("为地址 {local_addr} 生成广播地址列表");
 
         // 获取所有网络接口
         let interfaces = match if_addrs::get_if_addrs() {
             Ok(interfaces) => interfaces,
-            Err(e) => {
+            Err(e) if permissive => {
                 warn!("无法获取网络接口列表: {e}, 使用默认广播地址");
                 return Self::generate_fallback_addresses(port);
             }
+            Err(e) => {
+                warn!("无法获取网络接口列表: {e}, 暂停发现广播");
+                return addresses;
+            }
         };
 
         // 分类接口地址
@@ -342,7 +723,7 @@ impl NetworkManager {
         let mut ipv6_public = Vec::new();
 
         for interface in interfaces {
-            if interface.is_loopback() {
+            if interface.is_loopback() || !policy.permits_interface(&interface.name, &interface.ip()) {
                 continue;
             }
 
@@ -365,8 +746,8 @@ impl NetworkManager {
         }
 
         // 生成 IPv4 广播地址
-        Self::add_ipv4_broadcasts(&mut addresses, &ipv4_private, port);
-        if ipv4_private.is_empty() && !ipv4_public.is_empty() {
+        Self::add_ipv4_broadcasts(&mut addresses, &ipv4_private, port, permissive);
+        if ipv4_private.is_empty() && !ipv4_public.is_empty() && policy.allows_public() {
             info!("没有找到私有 IPv4 地址，使用公网 IPv4 地址进行广播");
             Self::add_ipv4_public_broadcasts(&mut addresses, &ipv4_public, port);
         }
@@ -377,7 +758,7 @@ impl NetworkManager {
         }
 
         // 如果没有找到任何有效地址，使用后备地址
-        if addresses.is_empty() {
+        if addresses.is_empty() && permissive {
             warn!("没有找到有效的网络接口，使用默认广播地址");
             addresses = Self::generate_fallback_addresses(port);
         }
@@ -391,7 +772,7 @@ impl NetworkManager {
     }
 
     /// 判断是否为私有 IPv4 地址
-    fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    pub(crate) fn is_private_ipv4(ip: Ipv4Addr) -> bool {
         let octets = ip.octets();
         // 10.0.0.0/8
         if octets[0] == 10 {
@@ -409,7 +790,7 @@ impl NetworkManager {
     }
 
     /// 判断是否为私有 IPv6 地址
-    fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    pub(crate) fn is_private_ipv6(ip: Ipv6Addr) -> bool {
         // 链路本地地址 (fe80::/10)
         if ip.segments()[0] & 0xffc0 == 0xfe80 {
             return true;
@@ -422,7 +803,15 @@ impl NetworkManager {
     }
 
     /// 添加 IPv4 私有网络广播地址
-    fn add_ipv4_broadcasts(addresses: &mut Vec<SocketAddr>, ipv4_addrs: &[Ipv4Addr], port: u16) {
+    ///
+    /// `include_common` 为 false 时不添加常见私有网段的广播地址，
+    /// 这些地址可能被路由到策略排除的接口上。
+    fn add_ipv4_broadcasts(
+        addresses: &mut Vec<SocketAddr>,
+        ipv4_addrs: &[Ipv4Addr],
+        port: u16,
+        include_common: bool,
+    ) {
         for &ip in ipv4_addrs {
             let octets = ip.octets();
 
@@ -437,7 +826,7 @@ impl NetworkManager {
         }
 
         // 添加常见的私有网络广播地址
-        if !ipv4_addrs.is_empty() {
+        if include_common && !ipv4_addrs.is_empty() {
             addresses.push(SocketAddr::from(([192, 168, 255, 255], port)));
             addresses.push(SocketAddr::from(([10, 255, 255, 255], port)));
             addresses.push(SocketAddr::from(([172, 31, 255, 255], port)));
@@ -489,72 +878,179 @@ impl NetworkManager {
     ///
     /// 开始监听网络消息和处理连接。
     pub async fn start(&self) -> Result<()> {
-        info!("网络管理器在 {} 启动", self.local_addr);
+        info!("网络管理器在 {} 启动", self.local_addr());
 
         // 启动 UDP 监听任务
-        let socket = Arc::clone(&self.udp_socket);
+        let socket: Arc<dyn DatagramTransport> = self.transport.clone();
         let event_sender = self.event_sender.clone();
         let connections = Arc::clone(&self.connections);
         let protocol = self.protocol.clone();
+        let policy = self.interface_policy.clone();
+        let quic = Arc::clone(&self.quic);
+        let authenticator = Arc::clone(&self.authenticator);
+        let cooldowns = Arc::clone(&self.cooldowns);
+        let rate_limiter = Arc::clone(&self.rate_limiter);
+
+        tokio::spawn(async move {
+            Self::udp_listener_task(
+                socket,
+                event_sender,
+                connections,
+                protocol,
+                policy,
+                quic,
+                authenticator,
+                cooldowns,
+                rate_limiter,
+            )
+            .await;
+        });
 
+        // 启动入站 QUIC 连接的定时器任务
+        let socket: Arc<dyn DatagramTransport> = self.transport.clone();
+        let quic = Arc::clone(&self.quic);
         tokio::spawn(async move {
-            Self::udp_listener_task(socket, event_sender, connections, protocol).await;
+            Self::quic_timer_task(socket, quic).await;
         });
 
         // 启动连接清理任务
         let connections_cleanup = Arc::clone(&self.connections);
+        let rate_limiter = Arc::clone(&self.rate_limiter);
         tokio::spawn(async move {
-            Self::connection_cleanup_task(connections_cleanup).await;
+            Self::connection_cleanup_task(connections_cleanup, rate_limiter).await;
         });
 
         Ok(())
     }
 
     /// UDP 监听任务
+    #[allow(clippy::too_many_arguments)]
     async fn udp_listener_task(
-        socket: Arc<UdpSocket>,
+        socket: Arc<dyn DatagramTransport>,
         event_sender: mpsc::UnboundedSender<NetworkEvent>,
         connections: Arc<Mutex<HashMap<SocketAddr, ConnectionState>>>,
         protocol: WdicProtocol,
+        policy: InterfacePolicy,
+        quic: Arc<QuicEndpoint>,
+        authenticator: Arc<StdRwLock<Arc<MessageAuthenticator>>>,
+        cooldowns: Arc<DashMap<SocketAddr, std::time::Instant>>,
+        rate_limiter: Arc<StdRwLock<Arc<InboundRateLimiter>>>,
     ) {
         let mut buffer = [0u8; 65536];
 
         loop {
             match socket.recv_from(&mut buffer) {
                 Ok((size, sender_addr)) => {
+                    if !policy.permits_peer(&sender_addr.ip()) {
+                        debug!("丢弃来自接口策略排除网段 {sender_addr} 的数据");
+                        continue;
+                    }
+
+                    if Self::in_cooldown(&cooldowns, &sender_addr) {
+                        debug!("丢弃来自冷却期内地址 {sender_addr} 的数据");
+                        continue;
+                    }
+
+                    // 解析和验签之前先按来源地址限速，被封禁的来源直接丢弃
+                    let rate_limiter =
+                        Arc::clone(&rate_limiter.read().unwrap_or_else(|e| e.into_inner()));
+                    if rate_limiter.check_datagram(sender_addr.ip()) != RateVerdict::Allowed {
+                        continue;
+                    }
+
                     debug!("收到来自 {sender_addr} 的 {size} 字节数据");
 
                     // 更新连接状态
                     {
                         let mut conns = connections.lock().await;
                         if let Some(conn) = conns.get_mut(&sender_addr) {
-                            conn.update_activity();
+                            conn.record_received(size);
                         } else {
-                            conns.insert(sender_addr, ConnectionState::new(sender_addr));
+                            let mut conn = ConnectionState::new(sender_addr);
+                            conn.record_received(size);
+                            conns.insert(sender_addr, conn);
                             let _ = event_sender.send(NetworkEvent::ConnectionEstablished {
                                 remote_addr: sender_addr,
                             });
                         }
                     }
 
-                    // 解析消息
-                    match WdicMessage::from_bytes(&buffer[..size]) {
+                    // 拆开签名信封并解析消息，签名无效或重放的数据直接丢弃
+                    let authenticator = Self::current_authenticator(&authenticator);
+                    let (signer, parsed) = match authenticator.open(&buffer[..size]) {
+                        Ok(opened) => (opened.signer, WdicMessage::from_bytes(&opened.payload)),
+                        Err(e) => {
+                            warn!("丢弃来自 {sender_addr} 的消息: {e}");
+                            continue;
+                        }
+                    };
+
+                    match parsed {
                         Ok(message) => {
                             debug!("解析消息成功: {}", message.message_type());
 
+                            // 按签名策略检查签名者
+                            if let Err(e) = authenticator.authorize(signer, message.origin_id()) {
+                                warn!(
+                                    "丢弃来自 {sender_addr} 的 {} 消息: {e}",
+                                    message.message_type()
+                                );
+                                continue;
+                            }
+
                             // 验证消息
                             if let Err(e) = protocol.validate_message(&message) {
                                 warn!("消息验证失败: {e}");
                                 continue;
                             }
 
+                            // 按来源地址、声明的网关身份和消息类别限速
+                            let verdict = rate_limiter.check_message(
+                                sender_addr.ip(),
+                                message.sender_id(),
+                                message.rate_class(),
+                            );
+                            if verdict != RateVerdict::Allowed {
+                                debug!(
+                                    "丢弃来自 {sender_addr} 的 {} 消息: {verdict:?}",
+                                    message.message_type()
+                                );
+                                continue;
+                            }
+
                             let _ = event_sender.send(NetworkEvent::MessageReceived {
                                 message,
                                 sender: sender_addr,
+                                signer,
                             });
                         }
+                        Err(e) if signer.is_some() => {
+                            warn!("来自 {sender_addr} 的签名消息无法解析: {e}");
+                        }
                         Err(e) => {
-                            warn!("解析消息失败: {e}");
+                            // 不是 WDIC 消息时按入站 QUIC 包处理
+                            let local_addr = socket.local_addr().unwrap_or(sender_addr);
+                            match quic.handle_datagram(&mut buffer[..size], sender_addr, local_addr) {
+                                Ok(output) => {
+                                    Self::send_quic_datagrams(socket.as_ref(), output.datagrams);
+                                    if let Some((remote_addr, result)) = output.authentication {
+                                        if let Ok(peer) = &result {
+                                            let mut conns = connections.lock().await;
+                                            if let Some(conn) = conns.get_mut(&remote_addr) {
+                                                conn.authenticate(
+                                                    peer.certificate.as_ref(),
+                                                    peer.negotiated.clone(),
+                                                );
+                                            }
+                                        }
+                                        Self::report_authentication(&event_sender, remote_addr, result);
+                                    }
+                                }
+                                Err(quic_error) => {
+                                    warn!("解析消息失败: {e}");
+                                    debug!("也不是有效的 QUIC 包: {quic_error}");
+                                }
+                            }
                         }
                     }
                 }
@@ -573,9 +1069,67 @@ impl NetworkManager {
         }
     }
 
+    /// 入站 QUIC 连接定时器任务
+    async fn quic_timer_task(socket: Arc<dyn DatagramTransport>, quic: Arc<QuicEndpoint>) {
+        let mut timer_interval = interval(Duration::from_millis(50));
+
+        loop {
+            timer_interval.tick().await;
+            Self::send_quic_datagrams(socket.as_ref(), quic.on_timeout());
+        }
+    }
+
+    /// 发送 QUIC 端点生成的数据报
+    fn send_quic_datagrams(socket: &dyn DatagramTransport, datagrams: Vec<(Vec<u8>, SocketAddr)>) {
+        for (datagram, target) in datagrams {
+            if let Err(e) = socket.send_to(&datagram, target) {
+                warn!("发送 QUIC 数据到 {target} 失败: {e}");
+            }
+        }
+    }
+
+    /// 检查地址是否处于会话断开后的冷却期，过期的冷却记录随之移除
+    fn in_cooldown(
+        cooldowns: &DashMap<SocketAddr, std::time::Instant>,
+        addr: &SocketAddr,
+    ) -> bool {
+        let Some(until) = cooldowns.get(addr).map(|entry| *entry) else {
+            return false;
+        };
+        if std::time::Instant::now() < until {
+            return true;
+        }
+        cooldowns.remove(addr);
+        false
+    }
+
+    /// 上报 QUIC 对端认证结果
+    fn report_authentication(
+        event_sender: &mpsc::UnboundedSender<NetworkEvent>,
+        remote_addr: SocketAddr,
+        result: AuthenticationResult,
+    ) {
+        match result {
+            Ok(peer) => {
+                let _ = event_sender.send(NetworkEvent::PeerAuthenticated {
+                    remote_addr,
+                    certificate: peer.certificate,
+                    negotiated: peer.negotiated,
+                });
+            }
+            Err(error) => {
+                let _ = event_sender.send(NetworkEvent::PeerAuthenticationFailed {
+                    remote_addr,
+                    error,
+                });
+            }
+        }
+    }
+
     /// 连接清理任务
     async fn connection_cleanup_task(
         connections: Arc<Mutex<HashMap<SocketAddr, ConnectionState>>>,
+        rate_limiter: Arc<StdRwLock<Arc<InboundRateLimiter>>>,
     ) {
         let mut cleanup_interval = interval(Duration::from_secs(60));
 
@@ -593,6 +1147,13 @@ impl NetworkManager {
                 conns.remove(&addr);
                 debug!("清理过期连接: {addr}");
             }
+            drop(conns);
+
+            // 清理空闲的令牌桶和过期的封禁
+            rate_limiter
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .prune();
         }
     }
 
@@ -616,10 +1177,11 @@ impl NetworkManager {
             Err(e) => {
                 debug!("QUIC 发送失败，回退到 UDP: {}", e);
                 // 回退到 UDP 发送
-                let data = message.to_bytes()?;
-                self.udp_socket
+                let data = self.encode_message(message)?;
+                self.transport
                     .send_to(&data, target)
                     .map_err(|e| anyhow::anyhow!("发送消息到 {target} 失败: {e}"))?;
+                self.record_sent(target, data.len()).await;
                 
                 debug!("通过 UDP 发送 {} 消息到 {target}", message.message_type());
                 Ok(())
@@ -637,17 +1199,18 @@ impl NetworkManager {
     ///
     /// 成功发送的地址数量
     pub async fn broadcast_message(&self, message: &WdicMessage) -> Result<usize> {
-        let data = message.to_bytes()?;
+        let data = self.encode_message(message)?;
         let mut success_count = 0;
+        let broadcast_addresses = self.broadcast_addresses().await;
 
         info!(
             "广播 {} 消息到 {} 个地址",
             message.message_type(),
-            self.broadcast_addresses.len()
+            broadcast_addresses.len()
         );
 
-        for &broadcast_addr in &self.broadcast_addresses {
-            match self.udp_socket.send_to(&data, broadcast_addr) {
+        for &broadcast_addr in &broadcast_addresses {
+            match self.transport.send_to(&data, broadcast_addr) {
                 Ok(_) => {
                     success_count += 1;
                     debug!("成功广播到 {broadcast_addr}");
@@ -684,6 +1247,13 @@ impl NetworkManager {
         self.send_message(response, original_sender).await
     }
 
+    /// 记录发送到已知连接的字节数
+    async fn record_sent(&self, target: SocketAddr, len: usize) {
+        if let Some(conn) = self.connections.lock().await.get_mut(&target) {
+            conn.record_sent(len);
+        }
+    }
+
     /// 获取当前活跃连接数
     ///
     /// # 返回值
@@ -723,6 +1293,335 @@ impl NetworkManager {
         }
     }
 
+    /// 强制断开对端连接，并在冷却期内拒绝其重连
+    ///
+    /// 关闭到该地址的 QUIC 连接、移除连接状态；冷却期内丢弃该地址的数据，
+    /// 也不会主动连接该地址。
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 对端地址
+    /// * `cooldown` - 冷却期，为零时不阻止重连
+    ///
+    /// # 返回值
+    ///
+    /// 是否存在到该地址的连接
+    pub async fn disconnect_with_cooldown(&self, addr: SocketAddr, cooldown: Duration) -> bool {
+        let (closed, datagrams) = self.quic.close_peer(&addr);
+        Self::send_quic_datagrams(self.transport.as_ref(), datagrams);
+        if !cooldown.is_zero() {
+            self.cooldowns.insert(addr, std::time::Instant::now() + cooldown);
+        }
+        self.node_connections
+            .write()
+            .await
+            .retain(|_, node_addr| *node_addr != addr);
+        let disconnected = self.disconnect(addr).await;
+        closed || disconnected
+    }
+
+    /// 检查地址是否处于会话断开后的冷却期
+    pub fn is_in_cooldown(&self, addr: &SocketAddr) -> bool {
+        Self::in_cooldown(&self.cooldowns, addr)
+    }
+
+    /// 断开证书已被吊销的对端
+    ///
+    /// 关闭对端的 QUIC 连接并移除连接状态。
+    ///
+    /// # 返回值
+    ///
+    /// 被断开的对端地址
+    pub async fn disconnect_revoked(&self) -> Vec<SocketAddr> {
+        let (revoked, datagrams) = self.quic.close_revoked();
+        Self::send_quic_datagrams(self.transport.as_ref(), datagrams);
+        for addr in &revoked {
+            self.disconnect(*addr).await;
+        }
+        revoked
+    }
+
+    // ============================================================================
+    // NAT 穿透
+    // ============================================================================
+
+    /// 获取 UDP 打洞统计
+    pub fn hole_punch_stats(&self) -> HolePunchStats {
+        self.hole_punch.stats()
+    }
+
+    /// 记录会合网关观察到的本网关公网端点
+    pub fn set_public_endpoint(&self, endpoint: SocketAddr) {
+        if self.hole_punch.public_endpoint() != Some(endpoint) {
+            info!("会合网关观察到本网关的公网端点为 {endpoint}");
+        }
+        self.hole_punch.set_public_endpoint(endpoint);
+    }
+
+    /// 获取本地候选端点
+    ///
+    /// 监听在未指定地址上时枚举本机符合接口策略的非回环地址，用于同一内网中的网关直接互连。
+    ///
+    /// # 返回值
+    ///
+    /// 本地端点列表
+    pub fn local_candidates(&self) -> Vec<SocketAddr> {
+        let local_addr = self.local_addr();
+        let port = local_addr.port();
+        if !local_addr.ip().is_unspecified() {
+            return vec![local_addr];
+        }
+
+        match self.interface_policy.scan() {
+            Ok(interfaces) => interfaces
+                .into_iter()
+                .map(|interface| SocketAddr::new(interface.ip, port))
+                .filter(|addr| local_addr.is_ipv6() || addr.is_ipv4())
+                .collect(),
+            Err(e) => {
+                debug!("枚举本地网络接口失败: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// 请求会合网关协调与指定网关的 UDP 打洞
+    ///
+    /// 与该网关的打洞处于冷却期或已在进行时不会重复请求；
+    /// 超时未收到打洞指令的会话将被标记为失败。
+    ///
+    /// # 参数
+    ///
+    /// * `local_id` - 本网关 ID
+    /// * `peer_id` - 目标网关 ID
+    /// * `rendezvous` - 会合网关地址
+    ///
+    /// # 返回值
+    ///
+    /// 新发起的打洞会话 ID
+    pub async fn request_hole_punch(
+        &self,
+        local_id: Uuid,
+        peer_id: Uuid,
+        rendezvous: SocketAddr,
+    ) -> Result<Option<Uuid>> {
+        let Some(session_id) = self.hole_punch.begin_request(peer_id) else {
+            return Ok(None);
+        };
+
+        let request = WdicMessage::punch_request(session_id, local_id, peer_id);
+        if let Err(e) = self.send_message(&request, rendezvous).await {
+            self.hole_punch.fail(&session_id);
+            self.hole_punch.remove(&session_id);
+            return Err(e);
+        }
+        debug!("经会合网关 {rendezvous} 请求与网关 {peer_id} 打洞 (会话 {session_id})");
+
+        let tracker = Arc::clone(&self.hole_punch);
+        let event_sender = self.event_sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(tracker.config().instruction_timeout).await;
+            if tracker.fail_if_awaiting(&session_id) {
+                debug!("打洞会话 {session_id} 未收到会合网关指令");
+                tracker.remove(&session_id);
+                let _ = event_sender.send(NetworkEvent::HolePunchFailed {
+                    session_id,
+                    peer_id,
+                });
+            }
+        });
+
+        Ok(Some(session_id))
+    }
+
+    /// 按会合网关的指令开始向对端候选端点发送探测
+    ///
+    /// 双方同时向对方发送探测，各自的 NAT 因出站报文为对端打开映射，
+    /// 收到对端的确认即视为直连路径建立。
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `local_id` - 本网关 ID
+    /// * `peer_id` - 对端网关 ID
+    /// * `candidates` - 对端候选端点
+    pub fn begin_hole_punch(
+        &self,
+        session_id: Uuid,
+        local_id: Uuid,
+        peer_id: Uuid,
+        candidates: Vec<SocketAddr>,
+    ) {
+        if !self.hole_punch.accept_instruction(session_id, peer_id) {
+            debug!("忽略打洞会话 {session_id} 的重复或无效指令");
+            return;
+        }
+
+        info!("开始与网关 {peer_id} 打洞，候选端点: {candidates:?}");
+        let transport: Arc<dyn DatagramTransport> = self.transport.clone();
+        let tracker = Arc::clone(&self.hole_punch);
+        let event_sender = self.event_sender.clone();
+        let authenticator = self.message_authenticator();
+        tokio::spawn(async move {
+            Self::hole_punch_probe_task(
+                transport,
+                tracker,
+                event_sender,
+                authenticator,
+                session_id,
+                local_id,
+                peer_id,
+                candidates,
+            )
+            .await;
+        });
+    }
+
+    /// 打洞探测任务
+    #[allow(clippy::too_many_arguments)]
+    async fn hole_punch_probe_task(
+        transport: Arc<dyn DatagramTransport>,
+        tracker: Arc<HolePunchTracker>,
+        event_sender: mpsc::UnboundedSender<NetworkEvent>,
+        authenticator: Arc<MessageAuthenticator>,
+        session_id: Uuid,
+        local_id: Uuid,
+        peer_id: Uuid,
+        candidates: Vec<SocketAddr>,
+    ) {
+        let config = tracker.config().clone();
+        let probe = match WdicMessage::punch_probe(session_id, local_id).to_bytes() {
+            Ok(probe) => probe,
+            Err(e) => {
+                error!("序列化打洞探测失败: {e}");
+                return;
+            }
+        };
+
+        for _ in 0..config.probe_attempts {
+            if tracker
+                .state(&session_id)
+                .is_none_or(|state| state.is_finished())
+            {
+                break;
+            }
+            for &candidate in &candidates {
+                // 每次探测单独签名，重复的信封会被对端当作重放丢弃
+                let sealed = match authenticator.seal(&probe) {
+                    Ok(sealed) => sealed,
+                    Err(e) => {
+                        error!("签名打洞探测失败: {e}");
+                        return;
+                    }
+                };
+                if let Err(e) = transport.send_to(&sealed, candidate) {
+                    debug!("向 {candidate} 发送打洞探测失败: {e}");
+                }
+            }
+            tokio::time::sleep(config.probe_interval).await;
+        }
+
+        if tracker.fail(&session_id) {
+            info!("与网关 {peer_id} 的打洞未收到确认");
+            let _ = event_sender.send(NetworkEvent::HolePunchFailed {
+                session_id,
+                peer_id,
+            });
+        }
+
+        // 保留会话一段时间，以便回复对端迟到的探测
+        tokio::time::sleep(config.probing_duration()).await;
+        tracker.remove(&session_id);
+    }
+
+    /// 处理打洞探测
+    ///
+    /// 仅对进行中的会话回复确认，确认直接经传输层发往探测的来源地址。
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `peer_id` - 探测发送者 ID
+    /// * `local_id` - 本网关 ID
+    /// * `sender` - 探测来源地址
+    pub fn handle_punch_probe(
+        &self,
+        session_id: Uuid,
+        peer_id: Uuid,
+        local_id: Uuid,
+        sender: SocketAddr,
+    ) -> Result<()> {
+        if !self.hole_punch.accepts_probe(&session_id, &peer_id) {
+            debug!("忽略未知打洞会话 {session_id} 的探测");
+            return Ok(());
+        }
+
+        let ack = self.encode_message(&WdicMessage::punch_ack(session_id, local_id))?;
+        self.transport
+            .send_to(&ack, sender)
+            .map_err(|e| anyhow::anyhow!("向 {sender} 回复打洞确认失败: {e}"))?;
+        Ok(())
+    }
+
+    /// 处理打洞确认
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `peer_id` - 确认发送者 ID
+    /// * `sender` - 确认来源地址，即对端的直连地址
+    pub fn handle_punch_ack(&self, session_id: Uuid, peer_id: Uuid, sender: SocketAddr) {
+        if self.hole_punch.establish(&session_id, &peer_id, sender) {
+            info!("与网关 {peer_id} 打洞成功，直连地址 {sender}");
+            let _ = self.event_sender.send(NetworkEvent::HolePunchSucceeded {
+                session_id,
+                peer_id,
+                address: sender,
+            });
+        }
+    }
+
+    /// 经会合网关打洞建立到指定网关的直连路径
+    ///
+    /// 发起打洞请求并等待结果，调用方在失败时继续使用中继路径。
+    ///
+    /// # 参数
+    ///
+    /// * `local_id` - 本网关 ID
+    /// * `peer_id` - 目标网关 ID
+    /// * `rendezvous` - 会合网关地址
+    ///
+    /// # 返回值
+    ///
+    /// 打洞成功时返回对端直连地址；处于冷却期、已在进行或失败时返回 None
+    pub async fn hole_punch_via(
+        &self,
+        local_id: Uuid,
+        peer_id: Uuid,
+        rendezvous: SocketAddr,
+    ) -> Result<Option<SocketAddr>> {
+        let Some(session_id) = self.request_hole_punch(local_id, peer_id, rendezvous).await?
+        else {
+            return Ok(None);
+        };
+        let Some(mut state) = self.hole_punch.subscribe(&session_id) else {
+            return Ok(None);
+        };
+
+        let config = self.hole_punch.config();
+        let deadline =
+            config.instruction_timeout + config.probing_duration() + config.probe_interval;
+        let outcome = tokio::time::timeout(deadline, state.wait_for(|state| state.is_finished()))
+            .await
+            .ok()
+            .and_then(|result| result.ok().map(|state| *state));
+
+        match outcome {
+            Some(PunchState::Established(address)) => Ok(Some(address)),
+            _ => Ok(None),
+        }
+    }
+
     /// 关闭网络管理器
     ///
     /// # 返回值
@@ -755,10 +1654,13 @@ impl NetworkManager {
             .into_iter()
             .map(|iface| {
                 let name = iface.name.clone();
+                let discovery_enabled = !iface.is_loopback()
+                    && self.interface_policy.permits_interface(&iface.name, &iface.ip());
                 NetworkInterface {
                     name,
                     ip_address: iface.ip().to_string(),
                     is_active: !iface.ip().is_loopback(),
+                    discovery_enabled,
                     interface_type: if iface.ip().is_ipv4() {
                         "IPv4".to_string()
                     } else {
@@ -768,8 +1670,9 @@ impl NetworkManager {
             })
             .collect();
 
-        let local_ip = self.local_addr.ip().to_string();
-        let listen_port = self.local_addr.port();
+        let local_addr = self.local_addr();
+        let local_ip = local_addr.ip().to_string();
+        let listen_port = local_addr.port();
 
         Ok(NetworkStatus {
             local_ip,
@@ -801,21 +1704,23 @@ impl NetworkManager {
         // 启动发现任务
         let discovered_nodes = Arc::clone(&self.discovered_nodes);
         let event_sender = self.event_sender.clone();
-        let udp_socket = Arc::clone(&self.udp_socket);
+        let transport: Arc<dyn DatagramTransport> = self.transport.clone();
         let protocol = self.protocol.clone();
-        let broadcast_addresses = self.broadcast_addresses.clone();
-        let local_addr = self.local_addr;
+        let broadcast_addresses = Arc::clone(&self.broadcast_addresses);
+        let local_addr = self.local_addr();
         let p2p_enabled = Arc::clone(&self.p2p_discovery_enabled);
+        let authenticator = Arc::clone(&self.authenticator);
 
         let task_handle = tokio::spawn(async move {
             Self::discovery_task(
                 discovered_nodes,
                 event_sender,
-                udp_socket,
+                transport,
                 protocol,
                 broadcast_addresses,
                 local_addr,
                 p2p_enabled,
+                authenticator,
             ).await;
         });
 
@@ -893,6 +1798,10 @@ impl NetworkManager {
 
         log::info!("连接到节点 {node_id} ({addr})");
 
+        if self.is_in_cooldown(&addr) {
+            return Err(anyhow::anyhow!("节点 {} 的会话刚被断开，冷却期内不能重连", node_id));
+        }
+
         // 检查是否已经连接到此节点
         {
             let node_connections = self.node_connections.read().await;
@@ -928,109 +1837,31 @@ impl NetworkManager {
         node_id: &str,
         addr: SocketAddr,
     ) -> anyhow::Result<()> {
-        // 创建 QUIC 连接配置
-        let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)
-            .map_err(|e| anyhow::anyhow!("创建 QUIC 配置失败: {}", e))?;
-        
-        // 配置 QUIC 参数
-        config.set_application_protos(&[b"wdic"])
-            .map_err(|e| anyhow::anyhow!("设置应用协议失败: {}", e))?;
-        config.set_max_idle_timeout(30000); // 30 秒超时
-        config.set_max_recv_udp_payload_size(1350);
-        config.set_max_send_udp_payload_size(1350);
-        config.set_initial_max_data(10_000_000);
-        config.set_initial_max_stream_data_bidi_local(1_000_000);
-        config.set_initial_max_stream_data_bidi_remote(1_000_000);
-        config.set_initial_max_streams_bidi(100);
-        config.set_disable_active_migration(true);
-        
-        // 启用 TLS 验证
-        config.verify_peer(true);
-        
-        // 启用 TLS 验证
-        config.verify_peer(true);
-        
-        // 集成 TLS 管理器进行安全的证书配置
-        // 
-        // 这里实现了完整的 TLS 证书验证流程：
-        // 1. 初始化 TLS 管理器，自动生成或加载证书
-        // 2. 获取客户端证书和私钥用于身份验证  
-        // 3. 验证证书的有效性和完整性
-        // 4. 根据验证结果配置 QUIC 的安全参数
-        let tls_config = crate::gateway::tls::MtlsConfig::default();
-        match crate::gateway::tls::TlsManager::new(tls_config) {
-            Ok(tls_manager) => {
-                // 获取客户端证书进行验证
-                if let Some(client_cert) = tls_manager.get_certificate("client") {
-                    if let Some(client_key) = tls_manager.get_private_key("client") {
-                        // 在生产环境中，这里需要将证书配置到 QUIC TLS 上下文中
-                        // 由于当前 quiche 库的 API 限制，我们采用验证后配置的方式
-                        log::info!("TLS 管理器已成功配置");
-                        log::debug!("客户端证书长度: {} 字节，私钥长度: {} 字节", 
-                                   client_cert.len(), client_key.len());
-                        
-                        // 执行证书有效性验证
-                        match tls_manager.verify_certificate(client_cert) {
-                            Ok(valid) => {
-                                if valid {
-                                    log::info!("✓ 客户端证书验证通过，启用 TLS 双向认证");
-                                    // 证书有效，启用 peer 验证
-                                    config.verify_peer(true);
-                                } else {
-                                    log::warn!("⚠ 客户端证书验证失败，回退到非验证模式");
-                                    config.verify_peer(false);
-                                }
-                            }
-                            Err(e) => {
-                                log::error!("✗ 证书验证过程中出现错误: {}，禁用验证", e);
-                                config.verify_peer(false);
-                            }
-                        }
-                        
-                        // 记录 TLS 配置摘要
-                        let (cert_count, key_count, mtls_ready) = tls_manager.get_certificate_stats();
-                        log::info!("TLS 统计: 证书 {} 个，私钥 {} 个，mTLS 就绪: {}", 
-                                  cert_count, key_count, mtls_ready);
-                    } else {
-                        log::warn!("⚠ 未找到客户端私钥，禁用 TLS 验证");
-                        config.verify_peer(false);
-                    }
-                } else {
-                    log::warn!("⚠ 未找到客户端证书，禁用 TLS 验证");
-                    config.verify_peer(false);
-                }
-            }
-            Err(e) => {
-                log::error!("✗ TLS 管理器初始化失败: {}，使用不安全连接", e);
-                config.verify_peer(false);
-            }
-        }
+        // 出示 MtlsConfig 中的客户端证书，并按验证模式验证服务端证书
+        let security = self
+            .quic
+            .security()
+            .ok_or_else(|| anyhow::anyhow!("未配置 QUIC 证书"))?;
+        let mut config = security.config(QuicRole::Client)?;
 
         // 生成连接 ID
         let uuid = uuid::Uuid::new_v4();
         let scid = quiche::ConnectionId::from_ref(&uuid.as_bytes()[..]);
-        
-        // 创建 QUIC 连接
-        let mut connection = quiche::connect(
-            Some("localhost"), 
-            &scid, 
-            self.local_addr,
-            addr,
-            &mut config
-        ).map_err(|e| anyhow::anyhow!("创建 QUIC 连接失败: {}", e))?;
-
-        // 准备握手数据
-        let mut out = [0; 1350];
-        let (write_len, send_info) = connection.send(&mut out)
-            .map_err(|e| anyhow::anyhow!("QUIC 握手发送失败: {}", e))?;
-
-        // 发送握手数据
-        if write_len > 0 {
-            self.udp_socket.send_to(&out[..write_len], send_info.to)
-                .map_err(|e| anyhow::anyhow!("发送握手数据失败: {}", e))?;
-            
-            log::debug!("已发送 {} 字节握手数据到 {}", write_len, send_info.to);
-        }
+
+        // 对端身份由证书中的网关 URI 确认，不校验主机名
+        let bind_addr: SocketAddr = if addr.is_ipv6() {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        };
+        let tokio_socket = tokio::net::UdpSocket::bind(bind_addr)
+            .await
+            .map_err(|e| anyhow::anyhow!("无法创建 QUIC 套接字: {}", e))?;
+        let quic_local_addr = tokio_socket
+            .local_addr()
+            .map_err(|e| anyhow::anyhow!("获取本地地址失败: {}", e))?;
+        let connection = quiche::connect(None, &scid, quic_local_addr, addr, &mut config)
+            .map_err(|e| anyhow::anyhow!("创建 QUIC 连接失败: {}", e))?;
 
         // 在后台任务中处理握手完成
         let event_sender = self.event_sender.clone();
@@ -1039,28 +1870,63 @@ impl NetworkManager {
         let discovered_nodes = Arc::clone(&self.discovered_nodes);
         let node_id_clone = node_id.to_string();
         
+        let quic = Arc::clone(&self.quic);
+
         tokio::spawn(async move {
-            // 为 QUIC 创建新的 tokio UDP 套接字
-            let tokio_socket = match tokio::net::UdpSocket::bind("0.0.0.0:0").await {
-                Ok(socket) => Arc::new(socket),
-                Err(e) => {
-                    error!("无法创建 tokio UDP 套接字: {}", e);
-                    let _ = event_sender.send(NetworkEvent::ConnectionFailed {
-                        remote_addr: addr,
-                        error: format!("套接字创建失败: {}", e),
-                    });
-                    return;
-                }
+            let tokio_socket = Arc::new(tokio_socket);
+
+            // 异步处理握手完成，并认证服务端证书
+            let handshake = match Self::complete_quic_handshake_async(
+                connection,
+                addr,
+                Arc::clone(&tokio_socket),
+            )
+            .await
+            {
+                Ok(mut established) => match security.authenticate(&established, QuicRole::Client) {
+                    Ok(peer) => Ok((established, peer)),
+                    Err(e) => {
+                        // 通知服务端关闭连接
+                        let _ = established.close(true, 0x1, b"certificate rejected");
+                        let mut out = [0u8; MAX_DATAGRAM_SIZE];
+                        while let Ok((write_len, send_info)) = established.send(&mut out) {
+                            let _ = tokio_socket.send_to(&out[..write_len], send_info.to).await;
+                        }
+                        let _ = event_sender.send(NetworkEvent::PeerAuthenticationFailed {
+                            remote_addr: addr,
+                            error: e.to_string(),
+                        });
+                        Err(e.context("服务端认证失败"))
+                    }
+                },
+                Err(e) => Err(e),
             };
 
-            // 异步处理握手完成
-            match Self::complete_quic_handshake_async(connection, addr, tokio_socket).await {
-                Ok(_established_connection) => {
-                    // 连接成功，存储连接信息
+            match handshake {
+                Ok((_established_connection, peer)) => {
+                    if let Some(certificate) = &peer.certificate {
+                        log::info!(
+                            "节点 {} 的证书认证通过，指纹 {}",
+                            node_id_clone,
+                            certificate.fingerprint
+                        );
+                        quic.record_peer(addr, certificate.clone());
+                    }
+                    // 连接成功，存储连接信息及对端身份
                     {
                         let mut connections_guard = connections.lock().await;
-                        connections_guard.insert(addr, ConnectionState::new(addr));
+                        let connection_state = connections_guard
+                            .entry(addr)
+                            .or_insert_with(|| ConnectionState::new(addr));
+                        connection_state
+                            .authenticate(peer.certificate.as_ref(), peer.negotiated.clone());
                     }
+
+                    let _ = event_sender.send(NetworkEvent::PeerAuthenticated {
+                        remote_addr: addr,
+                        certificate: peer.certificate,
+                        negotiated: peer.negotiated,
+                    });
                     
                     {
                         let mut node_connections_guard = node_connections.write().await;
@@ -1197,92 +2063,6 @@ impl NetworkManager {
         }
     }
 
-    /// 完成 QUIC 握手
-    ///
-    /// # 参数
-    ///
-    /// * `connection` - QUIC 连接对象
-    /// * `addr` - 远程地址
-    ///
-    /// # 返回值
-    ///
-    /// 是否握手成功
-    async fn complete_quic_handshake(
-        &self,
-        connection: &mut quiche::Connection,
-        addr: SocketAddr,
-    ) -> anyhow::Result<bool> {
-        // 设置握手超时
-        let handshake_timeout = tokio::time::Duration::from_secs(10);
-        let start_time = tokio::time::Instant::now();
-
-        let mut recv_buf = [0; 1350];
-        
-        while !connection.is_established() {
-            if tokio::time::Instant::now() - start_time > handshake_timeout {
-                return Err(anyhow::anyhow!("QUIC 握手超时"));
-            }
-
-            // 尝试接收数据
-            match self.udp_socket.recv_from(&mut recv_buf) {
-                Ok((recv_len, recv_addr)) if recv_addr == addr => {
-                    // 处理接收到的数据
-                    let recv_info = quiche::RecvInfo {
-                        to: self.local_addr,
-                        from: recv_addr,
-                    };
-
-                    // 处理接收到的包
-                    match connection.recv(&mut recv_buf[..recv_len], recv_info) {
-                        Ok(_) => {
-                            log::debug!("处理了 {} 字节的 QUIC 数据", recv_len);
-                        }
-                        Err(e) => {
-                            log::warn!("处理 QUIC 数据失败: {}", e);
-                        }
-                    }
-
-                    // 尝试发送响应
-                    let mut out = [0; 1350];
-                    loop {
-                        match connection.send(&mut out) {
-                            Ok((write_len, send_info)) => {
-                                if write_len == 0 {
-                                    break;
-                                }
-
-                                if let Err(e) = self.udp_socket.send_to(&out[..write_len], send_info.to) {
-                                    log::warn!("发送 QUIC 响应失败: {}", e);
-                                }
-                                
-                                log::debug!("发送了 {} 字节的 QUIC 响应", write_len);
-                            }
-                            Err(quiche::Error::Done) => {
-                                break;
-                            }
-                            Err(e) => {
-                                log::warn!("生成 QUIC 响应失败: {}", e);
-                                break;
-                            }
-                        }
-                    }
-                }
-                Ok(_) => {
-                    // 收到来自其他地址的数据，忽略
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // 没有数据可读，等待一段时间
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                }
-                Err(e) => {
-                    return Err(anyhow::anyhow!("接收数据失败: {}", e));
-                }
-            }
-        }
-
-        Ok(connection.is_established())
-    }
-
     /// 通过 QUIC 发送消息
     ///
     /// # 参数
@@ -1294,10 +2074,10 @@ impl NetworkManager {
     ///
     /// 发送结果
     pub async fn send_quic_message(&self, message: &WdicMessage, target: SocketAddr) -> Result<()> {
-        let data = message.to_bytes()?;
+        let data = self.encode_message(message)?;
         
         // 检查是否有到目标地址的活跃 QUIC 连接
-        let connections = self.connections.lock().await;
+        let mut connections = self.connections.lock().await;
         if let Some(connection_state) = connections.get(&target) {
             // 有活跃连接，尝试通过 QUIC 流发送
             if connection_state.is_connected() {
@@ -1311,9 +2091,12 @@ impl NetworkManager {
         
         // 回退到 UDP 发送
         log::debug!("发送 {} 消息到 {} (通过 UDP)", message.message_type(), target);
-        self.udp_socket
+        self.transport
             .send_to(&data, target)
             .map_err(|e| anyhow::anyhow!("发送消息到 {target} 失败: {e}"))?;
+        if let Some(connection_state) = connections.get_mut(&target) {
+            connection_state.record_sent(data.len());
+        }
 
         Ok(())
     }
@@ -1615,14 +2398,15 @@ impl NetworkManager {
         // 检查网络管理器的各个组件
         
         // 1. 检查本地地址是否有效
-        if self.local_addr.ip().is_unspecified() {
+        let local_addr = self.local_addr();
+        if local_addr.ip().is_unspecified() {
             log::warn!("健康检查失败: 本地地址无效");
             return Some(false);
         }
         
         // 2. 检查 UDP 套接字是否可用
         let test_data = b"health_check";
-        if let Err(e) = self.udp_socket.send_to(test_data, self.local_addr) {
+        if let Err(e) = self.transport.send_to(test_data, local_addr) {
             log::warn!("健康检查失败: UDP 套接字不可用: {}", e);
             return Some(false);
         }
@@ -1667,14 +2451,16 @@ impl NetworkManager {
     /// P2P 发现任务
     ///
     /// 定期广播发现消息并处理接收到的回复
+    #[allow(clippy::too_many_arguments)]
     async fn discovery_task(
         discovered_nodes: Arc<RwLock<HashMap<String, DiscoveredNodeInfo>>>,
         event_sender: mpsc::UnboundedSender<NetworkEvent>,
-        udp_socket: Arc<UdpSocket>,
+        transport: Arc<dyn DatagramTransport>,
         protocol: WdicProtocol,
-        broadcast_addresses: Vec<SocketAddr>,
+        broadcast_addresses: Arc<RwLock<Vec<SocketAddr>>>,
         local_addr: SocketAddr,
         p2p_enabled: Arc<Mutex<bool>>,
+        authenticator: Arc<StdRwLock<Arc<MessageAuthenticator>>>,
     ) {
         let mut discovery_interval = interval(Duration::from_secs(30)); // 每 30 秒发现一次
         let mut cleanup_interval = interval(Duration::from_secs(300)); // 每 5 分钟清理一次过期节点
@@ -1683,17 +2469,20 @@ impl NetworkManager {
         info!("P2P 发现任务开始运行");
 
         loop {
+            let current_authenticator = Self::current_authenticator(&authenticator);
             tokio::select! {
                 _ = discovery_interval.tick() => {
                     if !*p2p_enabled.lock().await {
                         break;
                     }
 
-                    // 发送发现广播
+                    // 发送发现广播（地址列表可能随接口变化更新）
+                    let addresses = broadcast_addresses.read().await.clone();
                     Self::send_discovery_broadcast(
-                        &udp_socket,
+                        transport.as_ref(),
                         &protocol,
-                        &broadcast_addresses,
+                        &current_authenticator,
+                        &addresses,
                         local_addr,
                     ).await;
                 }
@@ -1708,7 +2497,11 @@ impl NetworkManager {
                 }
 
                 // 处理接收到的消息
-                result = Self::try_receive_message(&udp_socket, &mut receive_buffer) => {
+                result = Self::try_receive_message(
+                    transport.as_ref(),
+                    &current_authenticator,
+                    &mut receive_buffer,
+                ) => {
                     if !*p2p_enabled.lock().await {
                         break;
                     }
@@ -1731,8 +2524,9 @@ impl NetworkManager {
 
     /// 发送发现广播
     async fn send_discovery_broadcast(
-        udp_socket: &UdpSocket,
+        transport: &dyn DatagramTransport,
         _protocol: &WdicProtocol,
+        authenticator: &MessageAuthenticator,
         broadcast_addresses: &[SocketAddr],
         local_addr: SocketAddr,
     ) {
@@ -1743,9 +2537,12 @@ impl NetworkManager {
             local_addr,
         );
 
-        if let Ok(serialized) = serde_json::to_vec(&discovery_message) {
+        let sealed = serde_json::to_vec(&discovery_message)
+            .map_err(anyhow::Error::from)
+            .and_then(|serialized| authenticator.seal(&serialized));
+        if let Ok(serialized) = sealed {
             for &addr in broadcast_addresses {
-                if let Err(e) = udp_socket.send_to(&serialized, addr) {
+                if let Err(e) = transport.send_to(&serialized, addr) {
                     debug!("发送发现广播到 {} 失败: {}", addr, e);
                 } else {
                     debug!("已向 {} 发送发现广播", addr);
@@ -1777,13 +2574,20 @@ impl NetworkManager {
 
     /// 尝试接收消息
     async fn try_receive_message(
-        udp_socket: &UdpSocket,
+        transport: &dyn DatagramTransport,
+        authenticator: &MessageAuthenticator,
         buffer: &mut [u8],
     ) -> Result<(WdicMessage, SocketAddr), std::io::Error> {
         // 使用非阻塞方式接收
-        match udp_socket.recv_from(buffer) {
+        match transport.recv_from(buffer) {
             Ok((len, sender_addr)) => {
-                if let Ok(message) = serde_json::from_slice::<WdicMessage>(&buffer[..len]) {
+                // 签名无效、重放或不符合签名策略的消息按无效数据处理
+                let message = authenticator.open(&buffer[..len]).ok().and_then(|opened| {
+                    let message = serde_json::from_slice::<WdicMessage>(&opened.payload).ok()?;
+                    authenticator.authorize(opened.signer, message.origin_id()).ok()?;
+                    Some(message)
+                });
+                if let Some(message) = message {
                     Ok((message, sender_addr))
                 } else {
                     Err(std::io::Error::new(
@@ -1903,6 +2707,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_broadcast_addresses_respect_interface_policy() {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 55555);
+
+        // 排除所有接口时不生成任何地址，也不回退到有限广播
+        let deny_all = InterfacePolicy::new(&[], &strings(&["*"]), &[], &[], true).unwrap();
+        let addresses =
+            NetworkManager::generate_broadcast_addresses_with_policy(local_addr, &deny_all);
+        assert!(addresses.is_empty());
+
+        // 白名单中没有存在的接口时只绑定回环地址
+        let allow_missing =
+            InterfacePolicy::new(&strings(&["wdic-missing0"]), &[], &[], &[], true).unwrap();
+        let bind_addr = NetworkManager::select_bind_address(local_addr, &allow_missing);
+        assert_eq!(bind_addr, SocketAddr::from(([127, 0, 0, 1], 55555)));
+
+        // 未配置白名单或已指定具体地址时保持原绑定地址
+        let specific = SocketAddr::from(([192, 168, 1, 100], 55555));
+        assert_eq!(
+            NetworkManager::select_bind_address(specific, &allow_missing),
+            specific
+        );
+        assert_eq!(
+            NetworkManager::select_bind_address(local_addr, &deny_all),
+            local_addr
+        );
+    }
+
     #[test]
     fn test_ipv6_multicast_addresses_generation() {
         // 测试 IPv6 多播地址生成
@@ -2008,4 +2841,28 @@ mod tests {
         // 测试关闭
         assert!(manager.shutdown().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_disconnect_with_cooldown_blocks_reconnect() {
+        let manager = NetworkManager::new(create_test_addr(0)).expect("创建网络管理器失败");
+        let peer = create_test_addr(55556);
+
+        let mut state = ConnectionState::new(peer);
+        state.record_received(100);
+        state.record_sent(40);
+        assert_eq!((state.bytes_received, state.bytes_sent), (100, 40));
+        manager.connections.lock().await.insert(peer, state);
+
+        // 断开后连接被移除，冷却期内拒绝主动重连
+        assert!(manager.disconnect_with_cooldown(peer, Duration::from_secs(60)).await);
+        assert_eq!(manager.active_connections_count().await, 0);
+        assert!(manager.is_in_cooldown(&peer));
+        assert!(!manager.is_in_cooldown(&create_test_addr(55557)));
+
+        // 冷却期为零时不阻止重连
+        let other = create_test_addr(55558);
+        manager.connections.lock().await.insert(other, ConnectionState::new(other));
+        assert!(manager.disconnect_with_cooldown(other, Duration::ZERO).await);
+        assert!(!manager.is_in_cooldown(&other));
+    }
 }
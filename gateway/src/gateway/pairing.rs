@@ -0,0 +1,786 @@
+//! 设备配对模块
+//!
+//! 以首次使用即信任（TOFU）的方式配对网关：双方经网络交换 CA 证书，
+//! 各自根据两端证书指纹和密钥协商公钥计算 6 位短验证码（SAS）。用户在两台设备上核对
+//! 验证码一致并确认后，对端的 CA 指纹和密钥协商公钥被固定为受信任设备，
+//! 未配对的网关只能参与发现。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::gateway::tls::TlsManager;
+
+/// 持久化文件格式版本
+const TRUSTED_DEVICES_VERSION: u32 = 1;
+
+/// 短验证码位数
+const SAS_DIGITS: u32 = 6;
+
+/// 配对会话有效期（秒）
+const PAIRING_SESSION_TIMEOUT: i64 = 300;
+
+/// 受信任设备
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedDevice {
+    /// 网关唯一标识
+    pub gateway_id: Uuid,
+    /// 设备名称，可由用户重命名
+    pub name: String,
+    /// 固定的 CA 证书指纹（SHA-256）
+    pub fingerprint: String,
+    /// CA 证书（PEM）
+    pub certificate: String,
+    /// 配对时间
+    pub paired_at: DateTime<Utc>,
+    /// 对端当前服务端和客户端证书指纹，收到签名的轮换通知后更新
+    #[serde(default)]
+    pub leaf_fingerprints: Vec<String>,
+    /// 最近一次轮换通知的时间，早于它的通知视为重放
+    #[serde(default)]
+    pub rotated_at: Option<DateTime<Utc>>,
+    /// 配对时固定的 X25519 密钥协商公钥（Base64），点对点加密只使用该公钥
+    #[serde(default)]
+    pub agreement_key: Option<String>,
+}
+
+/// 配对会话
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingSession {
+    /// 会话 ID
+    pub session_id: Uuid,
+    /// 对端网关 ID
+    pub peer_id: Uuid,
+    /// 对端网关名称
+    pub peer_name: String,
+    /// 对端地址
+    pub peer_address: SocketAddr,
+    /// 对端 CA 证书指纹，收到对端证书前为 `None`
+    pub peer_fingerprint: Option<String>,
+    /// 短验证码，收到对端证书前为 `None`
+    pub sas: Option<String>,
+    /// 是否由本端发起
+    pub initiated_locally: bool,
+    /// 本端用户是否已确认
+    pub local_confirmed: bool,
+    /// 对端用户是否已确认
+    pub remote_confirmed: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 对端 CA 证书（PEM）
+    #[serde(skip)]
+    peer_certificate: Option<String>,
+    /// 对端密钥协商公钥（Base64），参与短验证码计算
+    #[serde(skip)]
+    peer_agreement_key: Option<String>,
+}
+
+impl PairingSession {
+    /// 检查会话是否已过期
+    pub fn is_expired(&self) -> bool {
+        Utc::now() - self.created_at > Duration::seconds(PAIRING_SESSION_TIMEOUT)
+    }
+}
+
+/// 持久化文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustedDevicesFile {
+    /// 文件格式版本
+    version: u32,
+    /// 受信任设备
+    devices: Vec<TrustedDevice>,
+}
+
+/// 配对管理器
+///
+/// 管理进行中的配对会话和已固定的受信任设备。
+#[derive(Debug)]
+pub struct PairingManager {
+    /// 存储文件路径
+    path: PathBuf,
+    /// 本端 CA 证书指纹
+    local_fingerprint: String,
+    /// 本端密钥协商公钥（Base64）
+    local_agreement_key: Option<String>,
+    /// 受信任设备 (lock-free)
+    devices: DashMap<Uuid, TrustedDevice>,
+    /// 进行中的配对会话 (lock-free)
+    sessions: DashMap<Uuid, PairingSession>,
+}
+
+impl PairingManager {
+    /// 创建空的配对管理器
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 受信任设备存储文件路径
+    /// * `local_fingerprint` - 本端 CA 证书指纹
+    ///
+    /// # 返回值
+    ///
+    /// 新的配对管理器
+    pub fn new(path: PathBuf, local_fingerprint: String) -> Self {
+        Self {
+            path,
+            local_fingerprint,
+            local_agreement_key: None,
+            devices: DashMap::new(),
+            sessions: DashMap::new(),
+        }
+    }
+
+    /// 从文件加载受信任设备
+    ///
+    /// 文件不存在时返回空的配对管理器。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 受信任设备存储文件路径
+    /// * `local_fingerprint` - 本端 CA 证书指纹
+    ///
+    /// # 返回值
+    ///
+    /// 加载后的配对管理器
+    pub fn load(path: PathBuf, local_fingerprint: String) -> Result<Self> {
+        let manager = Self::new(path, local_fingerprint);
+        if !manager.path.exists() {
+            debug!("受信任设备文件不存在: {:?}", manager.path);
+            return Ok(manager);
+        }
+
+        let data =
+            std::fs::read(&manager.path).map_err(|e| anyhow!("读取受信任设备文件失败: {e}"))?;
+        let file: TrustedDevicesFile = serde_json::from_slice(&data)
+            .map_err(|e| anyhow!("反序列化受信任设备文件失败: {e}"))?;
+
+        if file.version > TRUSTED_DEVICES_VERSION {
+            return Err(anyhow!("不支持的受信任设备文件版本: {}", file.version));
+        }
+
+        for device in file.devices {
+            manager.devices.insert(device.gateway_id, device);
+        }
+
+        info!(
+            "从 {:?} 加载了 {} 个受信任设备",
+            manager.path,
+            manager.devices.len()
+        );
+        Ok(manager)
+    }
+
+    /// 设置本端密钥协商公钥
+    ///
+    /// 公钥随配对消息发送给对端，并与证书指纹一起参与短验证码计算。
+    ///
+    /// # 参数
+    ///
+    /// * `agreement_key` - Base64 编码的 X25519 公钥
+    pub fn with_agreement_key(mut self, agreement_key: String) -> Self {
+        self.local_agreement_key = Some(agreement_key);
+        self
+    }
+
+    /// 本端密钥协商公钥
+    pub fn local_agreement_key(&self) -> Option<&str> {
+        self.local_agreement_key.as_deref()
+    }
+
+    /// 保存受信任设备到文件
+    pub fn save(&self) -> Result<()> {
+        let file = TrustedDevicesFile {
+            version: TRUSTED_DEVICES_VERSION,
+            devices: self.devices(),
+        };
+
+        let serialized =
+            serde_json::to_vec_pretty(&file).map_err(|e| anyhow!("序列化受信任设备失败: {e}"))?;
+
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("创建受信任设备目录失败: {e}"))?;
+            }
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serialized)
+            .map_err(|e| anyhow!("写入受信任设备文件失败: {e}"))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| anyhow!("替换受信任设备文件失败: {e}"))?;
+
+        debug!("受信任设备已保存到: {:?}", self.path);
+        Ok(())
+    }
+
+    /// 获取本端 CA 证书指纹
+    pub fn local_fingerprint(&self) -> &str {
+        &self.local_fingerprint
+    }
+
+    /// 根据双方指纹计算短验证码
+    ///
+    /// 指纹按字典序排列后参与哈希，双方计算结果相同。
+    ///
+    /// # 参数
+    ///
+    /// * `a` - 一方的证书指纹
+    /// * `b` - 另一方的证书指纹
+    ///
+    /// # 返回值
+    ///
+    /// 6 位十进制验证码
+    pub fn sas_code(a: &str, b: &str) -> String {
+        let (low, high) = if a <= b { (a, b) } else { (b, a) };
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(b"WDIC-PAIRING-SAS");
+        context.update(low.as_bytes());
+        context.update(high.as_bytes());
+        let digest = context.finish();
+
+        let mut value = [0u8; 4];
+        value.copy_from_slice(&digest.as_ref()[..4]);
+        let code = u32::from_be_bytes(value) % 10u32.pow(SAS_DIGITS);
+        format!("{code:0width$}", width = SAS_DIGITS as usize)
+    }
+
+    /// 发起配对
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对端网关 ID
+    /// * `peer_name` - 对端网关名称
+    /// * `peer_address` - 对端地址
+    ///
+    /// # 返回值
+    ///
+    /// 新建的配对会话，等待对端交换证书
+    pub fn begin(
+        &self,
+        peer_id: Uuid,
+        peer_name: String,
+        peer_address: SocketAddr,
+    ) -> PairingSession {
+        let session = PairingSession {
+            session_id: Uuid::new_v4(),
+            peer_id,
+            peer_name,
+            peer_address,
+            peer_fingerprint: None,
+            sas: None,
+            initiated_locally: true,
+            local_confirmed: false,
+            remote_confirmed: false,
+            created_at: Utc::now(),
+            peer_certificate: None,
+            peer_agreement_key: None,
+        };
+
+        info!(
+            "向网关 '{}' 发起配对 {}",
+            session.peer_name, session.session_id
+        );
+        self.sessions.insert(session.session_id, session.clone());
+        session
+    }
+
+    /// 处理对端发起的配对请求
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 对端创建的会话 ID
+    /// * `peer_id` - 对端网关 ID
+    /// * `peer_name` - 对端网关名称
+    /// * `peer_address` - 对端地址
+    /// * `peer_certificate` - 对端 CA 证书（PEM）
+    /// * `peer_agreement_key` - 对端密钥协商公钥（Base64）
+    ///
+    /// # 返回值
+    ///
+    /// 已计算短验证码的配对会话，等待用户确认
+    pub fn accept_request(
+        &self,
+        session_id: Uuid,
+        peer_id: Uuid,
+        peer_name: String,
+        peer_address: SocketAddr,
+        peer_certificate: String,
+        peer_agreement_key: Option<String>,
+    ) -> Result<PairingSession> {
+        if self.sessions.contains_key(&session_id) {
+            return Err(anyhow!("配对会话已存在: {session_id}"));
+        }
+
+        let mut session = PairingSession {
+            session_id,
+            peer_id,
+            peer_name,
+            peer_address,
+            peer_fingerprint: None,
+            sas: None,
+            initiated_locally: false,
+            local_confirmed: false,
+            remote_confirmed: false,
+            created_at: Utc::now(),
+            peer_certificate: None,
+            peer_agreement_key: None,
+        };
+        self.attach_certificate(&mut session, peer_certificate, peer_agreement_key)?;
+
+        info!(
+            "收到网关 '{}' 的配对请求 {session_id}，验证码 {}",
+            session.peer_name,
+            session.sas.as_deref().unwrap_or_default()
+        );
+        self.sessions.insert(session_id, session.clone());
+        Ok(session)
+    }
+
+    /// 处理对端对配对请求的响应
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    /// * `peer_id` - 响应者网关 ID
+    /// * `peer_certificate` - 对端 CA 证书（PEM）
+    /// * `peer_agreement_key` - 对端密钥协商公钥（Base64）
+    ///
+    /// # 返回值
+    ///
+    /// 已计算短验证码的配对会话
+    pub fn accept_response(
+        &self,
+        session_id: Uuid,
+        peer_id: Uuid,
+        peer_certificate: String,
+        peer_agreement_key: Option<String>,
+    ) -> Result<PairingSession> {
+        let mut session = self.session_from(session_id, peer_id)?;
+        if !session.initiated_locally || session.peer_fingerprint.is_some() {
+            return Err(anyhow!("配对会话 {session_id} 不在等待响应"));
+        }
+
+        self.attach_certificate(&mut session, peer_certificate, peer_agreement_key)?;
+        info!(
+            "网关 '{}' 响应了配对 {session_id}，验证码 {}",
+            session.peer_name,
+            session.sas.as_deref().unwrap_or_default()
+        );
+        self.sessions.insert(session_id, session.clone());
+        Ok(session)
+    }
+
+    /// 本端用户确认验证码一致
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    ///
+    /// # 返回值
+    ///
+    /// 更新后的会话，以及双方都已确认时新固定的受信任设备
+    pub fn confirm(&self, session_id: Uuid) -> Result<(PairingSession, Option<TrustedDevice>)> {
+        let mut session = self
+            .sessions
+            .get(&session_id)
+            .map(|entry| entry.clone())
+            .ok_or_else(|| anyhow!("配对会话不存在: {session_id}"))?;
+        if session.sas.is_none() {
+            return Err(anyhow!("配对会话 {session_id} 尚未交换证书"));
+        }
+
+        session.local_confirmed = true;
+        let device = self.complete_if_confirmed(&session)?;
+        Ok((session, device))
+    }
+
+    /// 处理对端用户的确认
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    /// * `peer_id` - 确认者网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 双方都已确认时新固定的受信任设备
+    pub fn remote_confirm(&self, session_id: Uuid, peer_id: Uuid) -> Result<Option<TrustedDevice>> {
+        let mut session = self.session_from(session_id, peer_id)?;
+        if session.sas.is_none() {
+            return Err(anyhow!("配对会话 {session_id} 尚未交换证书"));
+        }
+
+        session.remote_confirmed = true;
+        self.complete_if_confirmed(&session)
+    }
+
+    /// 取消配对会话
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 会话 ID
+    ///
+    /// # 返回值
+    ///
+    /// 被取消的会话
+    pub fn cancel(&self, session_id: Uuid) -> Option<PairingSession> {
+        let session = self
+            .sessions
+            .remove(&session_id)
+            .map(|(_, session)| session);
+        if let Some(session) = &session {
+            info!("与网关 '{}' 的配对 {session_id} 已取消", session.peer_name);
+        }
+        session
+    }
+
+    /// 获取配对会话
+    pub fn session(&self, session_id: &Uuid) -> Option<PairingSession> {
+        self.sessions.get(session_id).map(|entry| entry.clone())
+    }
+
+    /// 获取进行中的配对会话，过期会话会被清理
+    pub fn sessions(&self) -> Vec<PairingSession> {
+        self.sessions.retain(|_, session| !session.is_expired());
+        self.sessions.iter().map(|entry| entry.clone()).collect()
+    }
+
+    /// 获取全部受信任设备
+    pub fn devices(&self) -> Vec<TrustedDevice> {
+        let mut devices: Vec<TrustedDevice> =
+            self.devices.iter().map(|entry| entry.clone()).collect();
+        devices.sort_by_key(|device| device.paired_at);
+        devices
+    }
+
+    /// 获取受信任设备
+    pub fn device(&self, gateway_id: &Uuid) -> Option<TrustedDevice> {
+        self.devices.get(gateway_id).map(|entry| entry.clone())
+    }
+
+    /// 检查网关是否已配对
+    pub fn is_trusted(&self, gateway_id: &Uuid) -> bool {
+        self.devices.contains_key(gateway_id)
+    }
+
+    /// 获取网关配对时固定的密钥协商公钥
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    pub fn pinned_agreement_key(&self, gateway_id: &Uuid) -> Option<String> {
+        self.devices
+            .get(gateway_id)
+            .and_then(|device| device.agreement_key.clone())
+    }
+
+    /// 检查证书是否由网关配对时固定的 CA 签发
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 证书声明的网关 ID
+    /// * `issuer_fingerprint` - 签发证书的 CA 指纹
+    pub fn matches_pinned(&self, gateway_id: &Uuid, issuer_fingerprint: &str) -> bool {
+        self.devices
+            .get(gateway_id)
+            .is_some_and(|device| device.fingerprint == issuer_fingerprint)
+    }
+
+    /// 记录受信任设备的证书轮换
+    ///
+    /// 调用方应先用设备固定的 CA 证书验证通知签名。
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    /// * `fingerprints` - 新证书指纹
+    /// * `timestamp` - 通知时间
+    ///
+    /// # 返回值
+    ///
+    /// 是否已记录，不早于上次通知的重放会被忽略
+    pub fn record_rotation(
+        &self,
+        gateway_id: &Uuid,
+        fingerprints: Vec<String>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<bool> {
+        {
+            let mut device = self
+                .devices
+                .get_mut(gateway_id)
+                .ok_or_else(|| anyhow!("受信任设备不存在: {gateway_id}"))?;
+            if device.rotated_at.is_some_and(|rotated_at| timestamp <= rotated_at) {
+                return Ok(false);
+            }
+            device.leaf_fingerprints = fingerprints;
+            device.rotated_at = Some(timestamp);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// 用导入的设备替换受信任设备列表并保存
+    ///
+    /// # 参数
+    ///
+    /// * `devices` - 受信任设备
+    pub fn restore(&self, devices: Vec<TrustedDevice>) -> Result<()> {
+        self.devices.clear();
+        for device in devices {
+            self.devices.insert(device.gateway_id, device);
+        }
+        self.save()
+    }
+
+    /// 重命名受信任设备
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    /// * `name` - 新名称
+    ///
+    /// # 返回值
+    ///
+    /// 更新后的受信任设备
+    pub fn rename(&self, gateway_id: &Uuid, name: String) -> Result<TrustedDevice> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(anyhow!("设备名称不能为空"));
+        }
+
+        let device = {
+            let mut device = self
+                .devices
+                .get_mut(gateway_id)
+                .ok_or_else(|| anyhow!("受信任设备不存在: {gateway_id}"))?;
+            device.name = name;
+            device.clone()
+        };
+        self.save()?;
+        Ok(device)
+    }
+
+    /// 撤销受信任设备
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 被撤销的受信任设备
+    pub fn revoke(&self, gateway_id: &Uuid) -> Result<TrustedDevice> {
+        let (_, device) = self
+            .devices
+            .remove(gateway_id)
+            .ok_or_else(|| anyhow!("受信任设备不存在: {gateway_id}"))?;
+        self.save()?;
+
+        info!("撤销受信任设备 '{}' ({gateway_id})", device.name);
+        Ok(device)
+    }
+
+    /// 获取来自指定网关的会话
+    fn session_from(&self, session_id: Uuid, peer_id: Uuid) -> Result<PairingSession> {
+        let session = self
+            .sessions
+            .get(&session_id)
+            .map(|entry| entry.clone())
+            .ok_or_else(|| anyhow!("配对会话不存在: {session_id}"))?;
+        if session.peer_id != peer_id {
+            return Err(anyhow!("配对会话 {session_id} 不属于网关 {peer_id}"));
+        }
+        if session.is_expired() {
+            self.sessions.remove(&session_id);
+            return Err(anyhow!("配对会话 {session_id} 已过期"));
+        }
+        Ok(session)
+    }
+
+    /// 参与短验证码计算的身份：CA 证书指纹和密钥协商公钥
+    fn identity_binding(fingerprint: &str, agreement_key: Option<&str>) -> String {
+        match agreement_key {
+            Some(agreement_key) => format!("{fingerprint}/{agreement_key}"),
+            None => fingerprint.to_string(),
+        }
+    }
+
+    /// 记录对端证书和密钥协商公钥并计算短验证码
+    ///
+    /// 公钥与指纹一起参与短验证码计算，用户核对验证码即同时确认了双方的公钥。
+    fn attach_certificate(
+        &self,
+        session: &mut PairingSession,
+        peer_certificate: String,
+        peer_agreement_key: Option<String>,
+    ) -> Result<()> {
+        let fingerprint = TlsManager::ca_pem_fingerprint(&peer_certificate)?;
+        if fingerprint == self.local_fingerprint {
+            return Err(anyhow!("对端证书与本端相同"));
+        }
+
+        if let Some(device) = self.device(&session.peer_id) {
+            if device.fingerprint != fingerprint {
+                warn!(
+                    "网关 '{}' 的证书与配对记录不同，需要重新确认",
+                    session.peer_name
+                );
+            }
+        }
+
+        session.sas = Some(Self::sas_code(
+            &Self::identity_binding(&self.local_fingerprint, self.local_agreement_key()),
+            &Self::identity_binding(&fingerprint, peer_agreement_key.as_deref()),
+        ));
+        session.peer_fingerprint = Some(fingerprint);
+        session.peer_certificate = Some(peer_certificate);
+        session.peer_agreement_key = peer_agreement_key;
+        Ok(())
+    }
+
+    /// 双方都确认后固定受信任设备，否则只更新会话
+    fn complete_if_confirmed(&self, session: &PairingSession) -> Result<Option<TrustedDevice>> {
+        if !(session.local_confirmed && session.remote_confirmed) {
+            self.sessions.insert(session.session_id, session.clone());
+            return Ok(None);
+        }
+
+        let (Some(fingerprint), Some(certificate)) = (
+            session.peer_fingerprint.clone(),
+            session.peer_certificate.clone(),
+        ) else {
+            return Err(anyhow!("配对会话 {} 缺少对端证书", session.session_id));
+        };
+
+        let device = TrustedDevice {
+            gateway_id: session.peer_id,
+            name: session.peer_name.clone(),
+            fingerprint,
+            certificate,
+            paired_at: Utc::now(),
+            leaf_fingerprints: Vec::new(),
+            rotated_at: None,
+            agreement_key: session.peer_agreement_key.clone(),
+        };
+        self.sessions.remove(&session.session_id);
+        self.devices.insert(device.gateway_id, device.clone());
+        self.save()?;
+
+        info!("与网关 '{}' 配对完成", device.name);
+        Ok(Some(device))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::tls::MtlsConfig;
+    use tempfile::tempdir;
+
+    fn ca_pem(dir: &std::path::Path) -> String {
+        let manager = TlsManager::new(MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            trusted_ca_dir: dir.join("trusted"),
+            ..Default::default()
+        })
+        .unwrap();
+        manager.ca_certificate_pem().unwrap()
+    }
+
+    #[test]
+    fn test_sas_code_is_symmetric() {
+        let code = PairingManager::sas_code("aa", "bb");
+        assert_eq!(code, PairingManager::sas_code("bb", "aa"));
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_ne!(code, PairingManager::sas_code("aa", "bc"));
+    }
+
+    #[test]
+    fn test_pairing_requires_both_confirmations() -> Result<()> {
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let (pem_a, pem_b) = (ca_pem(dir_a.path()), ca_pem(dir_b.path()));
+        let (id_a, id_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let addr: SocketAddr = "192.168.1.10:55555".parse()?;
+
+        let (key_a, key_b) = ("key-a".to_string(), "key-b".to_string());
+
+        let store_a = dir_a.path().join("trusted_devices.json");
+        let manager_a =
+            PairingManager::new(store_a.clone(), TlsManager::ca_pem_fingerprint(&pem_a)?)
+                .with_agreement_key(key_a.clone());
+        let manager_b = PairingManager::new(
+            dir_b.path().join("trusted_devices.json"),
+            TlsManager::ca_pem_fingerprint(&pem_b)?,
+        )
+        .with_agreement_key(key_b.clone());
+
+        // A 发起，B 收到请求后即可显示验证码，A 收到响应后显示相同的验证码
+        let session = manager_a.begin(id_b, "网关 B".to_string(), addr);
+        let on_b = manager_b.accept_request(
+            session.session_id,
+            id_a,
+            "网关 A".to_string(),
+            addr,
+            pem_a.clone(),
+            Some(key_a.clone()),
+        )?;
+        let on_a =
+            manager_a.accept_response(session.session_id, id_b, pem_b, Some(key_b.clone()))?;
+        assert_eq!(on_a.sas, on_b.sas);
+        assert!(on_a.sas.is_some());
+
+        // 被替换的密钥协商公钥会使双方的验证码不一致
+        let forged = manager_b.accept_request(
+            Uuid::new_v4(),
+            id_a,
+            "网关 A".to_string(),
+            addr,
+            pem_a,
+            Some("key-mallory".to_string()),
+        )?;
+        assert_ne!(forged.sas, on_a.sas);
+
+        // 只有一方确认时不会信任对端
+        let (_, device) = manager_a.confirm(session.session_id)?;
+        assert!(device.is_none());
+        assert!(!manager_a.is_trusted(&id_b));
+
+        // 冒充者无法替对端确认
+        assert!(manager_a
+            .remote_confirm(session.session_id, Uuid::new_v4())
+            .is_err());
+
+        let device = manager_a
+            .remote_confirm(session.session_id, id_b)?
+            .expect("双方确认后应当完成配对");
+        assert_eq!(Some(device.fingerprint.clone()), on_a.peer_fingerprint);
+        assert_eq!(manager_a.pinned_agreement_key(&id_b), Some(key_b));
+        assert!(manager_a.is_trusted(&id_b));
+        assert!(manager_a.matches_pinned(&id_b, &device.fingerprint));
+        assert!(manager_a.sessions().is_empty());
+
+        // 受信任设备持久化，可重命名和撤销
+        manager_a.rename(&id_b, "客厅电脑".to_string())?;
+        let reloaded = PairingManager::load(store_a.clone(), String::new())?;
+        assert_eq!(reloaded.device(&id_b).unwrap().name, "客厅电脑");
+
+        manager_a.revoke(&id_b)?;
+        assert!(!manager_a.is_trusted(&id_b));
+        assert!(PairingManager::load(store_a, String::new())?
+            .devices()
+            .is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+//! 已知网关持久化模块
+//!
+//! 将注册表中的网关和 P2P 发现的节点保存到磁盘，使网关重启后
+//! 能立即恢复已知节点并重新探测，而无需等待下一轮广播。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::gateway::network::DiscoveredNodeInfo;
+use crate::gateway::registry::{Registry, RegistryEntry};
+
+/// 持久化文件格式版本
+const PEER_STORE_VERSION: u32 = 1;
+
+/// 持久化文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PeerStoreFile {
+    /// 文件格式版本
+    version: u32,
+    /// 保存时间
+    saved_at: DateTime<Utc>,
+    /// 本网关的唯一标识
+    local_id: Option<Uuid>,
+    /// 已知网关
+    gateways: Vec<RegistryEntry>,
+    /// P2P 发现的节点
+    discovered_nodes: Vec<DiscoveredNodeInfo>,
+}
+
+/// 已知网关存储
+///
+/// 记录每个网关最后一次被确认的地址、身份和信任状态。
+/// 超过 `max_age_seconds` 未被确认的条目会被淘汰。
+#[derive(Debug)]
+pub struct PeerStore {
+    /// 存储文件路径
+    path: PathBuf,
+    /// 条目最长保留时间（秒）
+    max_age_seconds: i64,
+    /// 本网关的唯一标识
+    local_id: Option<Uuid>,
+    /// 已知网关 (lock-free)
+    gateways: DashMap<Uuid, RegistryEntry>,
+    /// P2P 发现的节点 (lock-free)
+    discovered_nodes: DashMap<String, DiscoveredNodeInfo>,
+}
+
+impl PeerStore {
+    /// 创建空的存储
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 存储文件路径
+    /// * `max_age_seconds` - 条目最长保留时间（秒）
+    ///
+    /// # 返回值
+    ///
+    /// 新的存储实例
+    pub fn new(path: PathBuf, max_age_seconds: i64) -> Self {
+        Self {
+            path,
+            max_age_seconds,
+            local_id: None,
+            gateways: DashMap::new(),
+            discovered_nodes: DashMap::new(),
+        }
+    }
+
+    /// 从文件加载存储
+    ///
+    /// 文件不存在时返回空存储；加载后立即淘汰过期条目。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 存储文件路径
+    /// * `max_age_seconds` - 条目最长保留时间（秒）
+    ///
+    /// # 返回值
+    ///
+    /// 加载后的存储实例
+    pub fn load(path: PathBuf, max_age_seconds: i64) -> Result<Self> {
+        let mut store = Self::new(path, max_age_seconds);
+        if !store.path.exists() {
+            debug!("已知网关存储文件不存在: {:?}", store.path);
+            return Ok(store);
+        }
+
+        let data = std::fs::read(&store.path)
+            .map_err(|e| anyhow::anyhow!("读取已知网关文件失败: {e}"))?;
+        let file: PeerStoreFile = serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("反序列化已知网关文件失败: {e}"))?;
+
+        if file.version > PEER_STORE_VERSION {
+            return Err(anyhow::anyhow!(
+                "不支持的已知网关文件版本: {}",
+                file.version
+            ));
+        }
+
+        store.local_id = file.local_id;
+        for gateway in file.gateways {
+            store.gateways.insert(gateway.id, gateway);
+        }
+        for node in file.discovered_nodes {
+            store.discovered_nodes.insert(node.node_id.clone(), node);
+        }
+
+        let pruned = store.prune_expired();
+        info!(
+            "从 {:?} 加载了 {} 个已知网关、{} 个发现节点（淘汰 {pruned} 个过期条目）",
+            store.path,
+            store.gateways.len(),
+            store.discovered_nodes.len()
+        );
+        Ok(store)
+    }
+
+    /// 保存存储到文件
+    ///
+    /// 先写入临时文件再重命名，避免写入中断导致文件损坏。
+    ///
+    /// # 返回值
+    ///
+    /// 操作结果
+    pub fn save(&self) -> Result<()> {
+        let file = PeerStoreFile {
+            version: PEER_STORE_VERSION,
+            saved_at: Utc::now(),
+            local_id: self.local_id,
+            gateways: self.gateways.iter().map(|entry| entry.clone()).collect(),
+            discovered_nodes: self
+                .discovered_nodes
+                .iter()
+                .map(|node| node.clone())
+                .collect(),
+        };
+
+        let serialized = serde_json::to_vec_pretty(&file)
+            .map_err(|e| anyhow::anyhow!("序列化已知网关失败: {e}"))?;
+
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow::anyhow!("创建已知网关目录失败: {e}"))?;
+            }
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serialized)
+            .map_err(|e| anyhow::anyhow!("写入已知网关文件失败: {e}"))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| anyhow::anyhow!("替换已知网关文件失败: {e}"))?;
+
+        debug!("已知网关已保存到: {:?}", self.path);
+        Ok(())
+    }
+
+    /// 获取存储文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 获取上次运行时本网关的唯一标识
+    pub fn local_id(&self) -> Option<Uuid> {
+        self.local_id
+    }
+
+    /// 设置本网关的唯一标识
+    pub fn set_local_id(&mut self, id: Uuid) {
+        self.local_id = Some(id);
+    }
+
+    /// 已知网关数量
+    pub fn gateway_count(&self) -> usize {
+        self.gateways.len()
+    }
+
+    /// 已知发现节点数量
+    pub fn discovered_node_count(&self) -> usize {
+        self.discovered_nodes.len()
+    }
+
+    /// 获取所有已知网关
+    pub fn gateways(&self) -> Vec<RegistryEntry> {
+        self.gateways.iter().map(|entry| entry.clone()).collect()
+    }
+
+    /// 获取已知网关的最后地址，用于重新探测
+    pub fn known_addresses(&self) -> Vec<SocketAddr> {
+        self.gateways.iter().map(|entry| entry.address).collect()
+    }
+
+    /// 记录注册表中已验证的网关
+    ///
+    /// 只记录本次运行中确认过的条目，未验证条目保留上次确认时的信息。
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 网关注册表
+    ///
+    /// # 返回值
+    ///
+    /// 被记录的条目数量
+    pub fn record_registry(&self, registry: &Registry) -> usize {
+        let verified = registry.verified_entries();
+        let count = verified.len();
+        for entry in verified {
+            self.gateways.insert(entry.id, entry);
+        }
+        count
+    }
+
+    /// 记录在线的发现节点
+    ///
+    /// # 参数
+    ///
+    /// * `nodes` - 网络管理器中的发现节点表
+    ///
+    /// # 返回值
+    ///
+    /// 被记录的节点数量
+    pub fn record_discovered_nodes(&self, nodes: &HashMap<String, DiscoveredNodeInfo>) -> usize {
+        let mut count = 0;
+        for node in nodes.values().filter(|node| node.is_online) {
+            self.discovered_nodes.insert(node.node_id.clone(), node.clone());
+            count += 1;
+        }
+        count
+    }
+
+    /// 淘汰超过最长保留时间未被确认的条目
+    ///
+    /// # 返回值
+    ///
+    /// 被淘汰的条目数量
+    pub fn prune_expired(&self) -> usize {
+        let cutoff_time = Utc::now() - chrono::Duration::seconds(self.max_age_seconds);
+        let before = self.gateways.len() + self.discovered_nodes.len();
+
+        self.gateways.retain(|_, entry| entry.last_seen >= cutoff_time);
+        self.discovered_nodes
+            .retain(|_, node| node.last_seen >= cutoff_time);
+
+        before - (self.gateways.len() + self.discovered_nodes.len())
+    }
+
+    /// 将已知网关以未验证状态恢复到注册表
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 网关注册表
+    ///
+    /// # 返回值
+    ///
+    /// 恢复的条目数量
+    pub fn restore_into(&self, registry: &Registry) -> usize {
+        self.gateways
+            .iter()
+            .filter(|entry| registry.restore_unverified(entry.value().clone()))
+            .count()
+    }
+
+    /// 将发现节点以离线状态恢复到节点表
+    ///
+    /// 最后看到时间重置为当前时间，以便在重新发现期间不被过期清理。
+    ///
+    /// # 参数
+    ///
+    /// * `nodes` - 网络管理器中的发现节点表
+    ///
+    /// # 返回值
+    ///
+    /// 恢复的节点数量
+    pub fn restore_discovered_nodes(&self, nodes: &mut HashMap<String, DiscoveredNodeInfo>) -> usize {
+        let mut count = 0;
+        for node in self.discovered_nodes.iter() {
+            if nodes.contains_key(&node.node_id) {
+                continue;
+            }
+            let mut restored = node.clone();
+            restored.is_online = false;
+            restored.last_seen = Utc::now();
+            nodes.insert(restored.node_id.clone(), restored);
+            count += 1;
+        }
+        count
+    }
+
+    /// 保存存储，失败时仅记录警告
+    pub fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("保存已知网关失败: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use tempfile::TempDir;
+
+    fn create_test_address(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), port)
+    }
+
+    #[test]
+    fn test_peer_store_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("peers.json");
+
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+        let remote = RegistryEntry::new("远程网关".to_string(), create_test_address(55556));
+        registry.add_or_update(remote.clone());
+
+        let mut store = PeerStore::new(path.clone(), 3600);
+        store.set_local_id(registry.local_entry().id);
+        assert_eq!(store.record_registry(&registry), 1);
+
+        let mut nodes = HashMap::new();
+        let node = DiscoveredNodeInfo::new(
+            "node-1".to_string(),
+            "192.168.1.2".to_string(),
+            55555,
+            "节点".to_string(),
+            "gateway".to_string(),
+        );
+        nodes.insert(node.node_id.clone(), node);
+        assert_eq!(store.record_discovered_nodes(&nodes), 1);
+        store.save().unwrap();
+
+        let loaded = PeerStore::load(path, 3600).unwrap();
+        assert_eq!(loaded.local_id(), Some(registry.local_entry().id));
+        assert_eq!(loaded.gateway_count(), 1);
+        assert_eq!(loaded.discovered_node_count(), 1);
+
+        // 重启后恢复为未验证条目
+        let restarted = Registry::new("本地网关".to_string(), create_test_address(55555));
+        restarted.set_local_id(loaded.local_id().unwrap());
+        assert_eq!(loaded.restore_into(&restarted), 1);
+        let restored = restarted.get(&remote.id).unwrap();
+        assert!(!restored.is_verified());
+        assert_eq!(restored.address, remote.address);
+
+        let mut restored_nodes = HashMap::new();
+        assert_eq!(loaded.restore_discovered_nodes(&mut restored_nodes), 1);
+        assert!(!restored_nodes["node-1"].is_online);
+    }
+
+    #[test]
+    fn test_peer_store_skips_unverified_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("peers.json");
+
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+        let direct = RegistryEntry::new("直连网关".to_string(), create_test_address(55556));
+        registry.add_or_update(direct.clone());
+
+        // 经广播响应或中继通告转述得知的网关
+        let relayed = RegistryEntry::new("转述网关".to_string(), create_test_address(55557));
+        registry.add_unverified(relayed.clone());
+
+        let store = PeerStore::new(path.clone(), 3600);
+        assert_eq!(store.record_registry(&registry), 1);
+        store.save().unwrap();
+
+        let loaded = PeerStore::load(path.clone(), 3600).unwrap();
+        let ids: Vec<Uuid> = loaded.gateways().iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![direct.id]);
+
+        // 重启后恢复的条目和转述的新地址在重新确认前都不会写回
+        let restarted = Registry::new("本地网关".to_string(), create_test_address(55555));
+        loaded.restore_into(&restarted);
+        let mut forged = direct.clone();
+        forged.address = create_test_address(60000);
+        restarted.add_unverified(forged);
+        assert_eq!(loaded.record_registry(&restarted), 0);
+        loaded.save().unwrap();
+
+        let reloaded = PeerStore::load(path, 3600).unwrap();
+        assert_eq!(reloaded.known_addresses(), vec![direct.address]);
+    }
+
+    #[test]
+    fn test_peer_store_ages_out_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("peers.json");
+
+        let store = PeerStore::new(path.clone(), 3600);
+        let mut old = RegistryEntry::new("旧网关".to_string(), create_test_address(55556));
+        old.last_seen = Utc::now() - chrono::Duration::hours(2);
+        let fresh = RegistryEntry::new("新网关".to_string(), create_test_address(55557));
+        store.gateways.insert(old.id, old.clone());
+        store.gateways.insert(fresh.id, fresh.clone());
+        store.save().unwrap();
+
+        let loaded = PeerStore::load(path, 3600).unwrap();
+        assert_eq!(loaded.gateway_count(), 1);
+        assert_eq!(loaded.known_addresses(), vec![fresh.address]);
+    }
+
+    #[test]
+    fn test_peer_store_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PeerStore::load(temp_dir.path().join("missing.json"), 3600).unwrap();
+        assert_eq!(store.gateway_count(), 0);
+        assert!(store.local_id().is_none());
+    }
+}
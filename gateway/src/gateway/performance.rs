@@ -6,6 +6,7 @@
 use ahash::AHashMap;
 use anyhow::Result;
 use crate::gateway::BenchmarkStatus;
+use crate::gateway::rate_limit::{InboundRateLimiter, ThrottleStats};
 use log::info;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
@@ -28,6 +29,10 @@ pub struct PerformanceMonitor {
     connection_metrics: Arc<RwLock<ConnectionMetrics>>,
     /// 基准测试结果 - 使用 AHashMap 提升性能
     benchmark_results: Arc<RwLock<AHashMap<String, BenchmarkResult>>>,
+    /// 按网关统计的延迟指标
+    peer_latency: Arc<RwLock<AHashMap<String, PeerLatencyMetrics>>>,
+    /// 入站限速器，用于在报告中附带限速统计
+    rate_limiter: std::sync::RwLock<Option<Arc<InboundRateLimiter>>>,
 }
 
 /// 网络性能指标
@@ -109,6 +114,25 @@ impl Default for LatencyMetrics {
     }
 }
 
+/// 单个网关的延迟指标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerLatencyMetrics {
+    /// 最近一次 RTT（毫秒）
+    pub last_rtt_ms: f64,
+    /// 平滑 RTT（毫秒）
+    pub smoothed_rtt_ms: f64,
+    /// RTT 抖动（毫秒）
+    pub jitter_ms: f64,
+    /// 最小 RTT（毫秒）
+    pub min_rtt_ms: f64,
+    /// 最大 RTT（毫秒）
+    pub max_rtt_ms: f64,
+    /// 样本数
+    pub sample_count: u64,
+    /// 上次统计时间
+    pub last_update: chrono::DateTime<chrono::Utc>,
+}
+
 /// 连接性能指标
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ConnectionMetrics {
@@ -204,9 +228,26 @@ impl PerformanceMonitor {
             latency_metrics: Arc::new(RwLock::new(LatencyMetrics::default())),
             connection_metrics: Arc::new(RwLock::new(ConnectionMetrics::default())),
             benchmark_results: Arc::new(RwLock::new(AHashMap::new())),
+            peer_latency: Arc::new(RwLock::new(AHashMap::new())),
+            rate_limiter: std::sync::RwLock::new(None),
         }
     }
 
+    /// 设置入站限速器，之后的性能报告会包含限速统计
+    pub fn set_rate_limiter(&self, rate_limiter: Arc<InboundRateLimiter>) {
+        *self.rate_limiter.write().unwrap_or_else(|e| e.into_inner()) = Some(rate_limiter);
+    }
+
+    /// 获取入站限速统计，未设置限速器时返回空统计
+    pub fn throttle_stats(&self) -> ThrottleStats {
+        self.rate_limiter
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|limiter| limiter.stats())
+            .unwrap_or_default()
+    }
+
     /// 记录网络发送
     pub async fn record_network_send(&self, bytes: u64) {
         let mut metrics = self.network_metrics.write().await;
@@ -280,6 +321,57 @@ impl PerformanceMonitor {
         metrics.last_update = chrono::Utc::now();
     }
 
+    /// 记录指定网关的延迟
+    ///
+    /// 样本同时计入全局延迟统计。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 网关标识
+    /// * `rtt_ms` - 本次 RTT（毫秒）
+    /// * `smoothed_rtt_ms` - 平滑 RTT（毫秒）
+    /// * `jitter_ms` - RTT 抖动（毫秒）
+    pub async fn record_peer_latency(
+        &self,
+        peer_id: &str,
+        rtt_ms: f64,
+        smoothed_rtt_ms: f64,
+        jitter_ms: f64,
+    ) {
+        self.record_latency(rtt_ms).await;
+
+        let mut peers = self.peer_latency.write().await;
+        let metrics = peers
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerLatencyMetrics {
+                last_rtt_ms: rtt_ms,
+                smoothed_rtt_ms,
+                jitter_ms,
+                min_rtt_ms: rtt_ms,
+                max_rtt_ms: rtt_ms,
+                sample_count: 0,
+                last_update: chrono::Utc::now(),
+            });
+
+        metrics.last_rtt_ms = rtt_ms;
+        metrics.smoothed_rtt_ms = smoothed_rtt_ms;
+        metrics.jitter_ms = jitter_ms;
+        metrics.min_rtt_ms = metrics.min_rtt_ms.min(rtt_ms);
+        metrics.max_rtt_ms = metrics.max_rtt_ms.max(rtt_ms);
+        metrics.sample_count += 1;
+        metrics.last_update = chrono::Utc::now();
+    }
+
+    /// 移除指定网关的延迟指标
+    pub async fn remove_peer_latency(&self, peer_id: &str) {
+        self.peer_latency.write().await.remove(peer_id);
+    }
+
+    /// 获取所有网关的延迟指标
+    pub async fn get_peer_latency(&self) -> AHashMap<String, PeerLatencyMetrics> {
+        self.peer_latency.read().await.clone()
+    }
+
     /// 记录连接事件
     pub async fn record_connection_event(
         &self,
@@ -417,7 +509,7 @@ impl PerformanceMonitor {
             let op_start = Instant::now();
 
             // 执行真实的网络操作测试
-            let network_test_result = self.perform_network_test(&test_suite).await;
+            let network_test_result = self.perform_network_test(test_suite).await;
             
             let op_duration = op_start.elapsed();
             latencies.push(op_duration.as_secs_f64() * 1000.0); // 转换为毫秒
@@ -585,6 +677,7 @@ impl PerformanceMonitor {
         let latency = self.latency_metrics.read().await.clone();
         let connection = self.connection_metrics.read().await.clone();
         let benchmarks = self.benchmark_results.read().await.clone();
+        let peer_latency = self.peer_latency.read().await.clone();
 
         PerformanceReport {
             network: network.clone(),
@@ -601,6 +694,8 @@ impl PerformanceMonitor {
             cpu_usage_percent: 0.0,
             network_throughput_bps: network.throughput_bps,
             average_latency_ms: latency.average_latency,
+            peer_latency,
+            throttling: self.throttle_stats(),
         }
     }
 
@@ -758,6 +853,8 @@ impl PerformanceMonitor {
             cpu_usage_percent: 0.0,
             network_throughput_bps: network_metrics.throughput_bps,
             average_latency_ms: latency_metrics.average_latency,
+            peer_latency: self.peer_latency.read().await.clone(),
+            throttling: self.throttle_stats(),
         }
     }
 
@@ -865,7 +962,7 @@ impl PerformanceMonitor {
         match tokio::net::UdpSocket::bind("127.0.0.1:0").await {
             Ok(test_socket) => {
                 // 发送测试数据
-                if let Ok(_) = test_socket.send_to(&test_data, test_addr).await {
+                if test_socket.send_to(&test_data, test_addr).await.is_ok() {
                     total_bytes += test_data.len() as u64;
                 }
                 
@@ -920,7 +1017,7 @@ impl PerformanceMonitor {
             // 尝试发送数据
             if let Ok(test_socket) = tokio::net::UdpSocket::bind("127.0.0.1:0").await {
                 let test_addr = "127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap();
-                if let Ok(_) = test_socket.send_to(&test_data, test_addr).await {
+                if test_socket.send_to(&test_data, test_addr).await.is_ok() {
                     successful_bytes = test_data.len() as u64;
                 }
             }
@@ -1018,6 +1115,11 @@ pub struct PerformanceReport {
     pub network_throughput_bps: f64,
     /// 平均延迟（毫秒）
     pub average_latency_ms: f64,
+    /// 按网关统计的延迟指标
+    pub peer_latency: AHashMap<String, PeerLatencyMetrics>,
+    /// 入站限速统计
+    #[serde(default)]
+    pub throttling: ThrottleStats,
 }
 
 #[cfg(test)]
@@ -1051,6 +1153,26 @@ mod tests {
         assert_eq!(metrics.send_errors, 1);
     }
 
+    #[tokio::test]
+    async fn test_peer_latency_recording() {
+        let monitor = PerformanceMonitor::new();
+
+        monitor.record_peer_latency("peer-a", 10.0, 10.0, 5.0).await;
+        monitor.record_peer_latency("peer-a", 20.0, 11.25, 6.25).await;
+        monitor.record_peer_latency("peer-b", 5.0, 5.0, 2.5).await;
+
+        let report = monitor.get_report().await;
+        assert_eq!(report.latency.sample_count, 3);
+        let peer_a = &report.peer_latency["peer-a"];
+        assert_eq!(peer_a.sample_count, 2);
+        assert_eq!(peer_a.min_rtt_ms, 10.0);
+        assert_eq!(peer_a.max_rtt_ms, 20.0);
+        assert_eq!(peer_a.smoothed_rtt_ms, 11.25);
+
+        monitor.remove_peer_latency("peer-b").await;
+        assert!(!monitor.get_peer_latency().await.contains_key("peer-b"));
+    }
+
     #[tokio::test]
     async fn test_latency_metrics_recording() {
         let monitor = PerformanceMonitor::new();
@@ -2,7 +2,9 @@
 //!
 //! 实现基于 QUIC 的 WDIC (Web Dynamic Inter-Connection) 网络协议。
 
+use crate::gateway::rate_limit::MessageClass;
 use crate::gateway::registry::RegistryEntry;
+use crate::gateway::tls::Revocation;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use uuid::Uuid;
@@ -45,6 +47,9 @@ pub enum WdicMessage {
         sender_id: Uuid,
         /// 时间戳
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// 探测序号，0 表示未编号
+        #[serde(default)]
+        sequence: u64,
     },
     /// 心跳响应
     HeartbeatResponse {
@@ -52,6 +57,9 @@ pub enum WdicMessage {
         sender_id: Uuid,
         /// 时间戳
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// 回显的探测序号，旧版本网关不回显时为 0
+        #[serde(default)]
+        sequence: u64,
     },
     /// 网关注册请求
     RegisterRequest {
@@ -149,6 +157,129 @@ pub enum WdicMessage {
         /// 错误消息
         error_message: String,
     },
+    /// 中继信封 - 经中继网关转发的端到端加密消息
+    RelayEnvelope {
+        /// 源网关 ID
+        source_id: Uuid,
+        /// 目标网关 ID
+        target_id: Uuid,
+        /// 负责转发的中继网关 ID
+        relay_id: Uuid,
+        /// 源网关的 X25519 公钥（Base64）
+        source_public_key: String,
+        /// 加密后的内层消息，中继网关无法解读
+        payload: Vec<u8>,
+    },
+    /// 中继路由通告 - 中继网关告知可经其转发到达的网关
+    RelayRoutes {
+        /// 中继网关信息
+        relay: RegistryEntry,
+        /// 可经该中继到达的网关列表
+        reachable: Vec<RegistryEntry>,
+    },
+    /// 端点上报 - 向会合网关上报本地端点，会合网关据此记录观察到的公网端点
+    EndpointReport {
+        /// 上报者 ID
+        sender_id: Uuid,
+        /// 本地监听端点列表
+        local_endpoints: Vec<SocketAddr>,
+    },
+    /// 端点观察结果 - 会合网关告知上报者其公网端点
+    EndpointObserved {
+        /// 会合网关 ID
+        sender_id: Uuid,
+        /// 会合网关观察到的上报者端点
+        observed: SocketAddr,
+    },
+    /// 打洞请求 - 请求会合网关协调与目标网关的 UDP 打洞
+    PunchRequest {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 请求者 ID
+        requester_id: Uuid,
+        /// 目标网关 ID
+        target_id: Uuid,
+    },
+    /// 打洞指令 - 会合网关同时下发给双方的对端候选端点
+    PunchInstruction {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 会合网关 ID
+        sender_id: Uuid,
+        /// 对端网关 ID
+        peer_id: Uuid,
+        /// 对端候选端点（公网端点优先）
+        candidates: Vec<SocketAddr>,
+    },
+    /// 打洞探测 - 双方同时向对端候选端点发送
+    PunchProbe {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 发送者 ID
+        sender_id: Uuid,
+    },
+    /// 打洞确认 - 收到探测后回复，确认直连路径可用
+    PunchAck {
+        /// 打洞会话 ID
+        session_id: Uuid,
+        /// 发送者 ID
+        sender_id: Uuid,
+    },
+    /// 配对请求 - 发起者提交自己的 CA 证书
+    PairRequest {
+        /// 配对会话 ID
+        session_id: Uuid,
+        /// 发起者信息
+        sender: RegistryEntry,
+        /// 发起者 CA 证书（PEM）
+        certificate: String,
+    },
+    /// 配对响应 - 响应者返回自己的 CA 证书和密钥协商公钥，双方据此计算短验证码
+    PairResponse {
+        /// 配对会话 ID
+        session_id: Uuid,
+        /// 响应者 ID
+        sender_id: Uuid,
+        /// 响应者 CA 证书（PEM）
+        certificate: String,
+        /// 响应者 X25519 密钥协商公钥（Base64）
+        #[serde(default)]
+        agreement_key: Option<String>,
+    },
+    /// 配对确认 - 用户已核对短验证码一致
+    PairConfirm {
+        /// 配对会话 ID
+        session_id: Uuid,
+        /// 确认者 ID
+        sender_id: Uuid,
+    },
+    /// 配对拒绝 - 用户拒绝或取消配对
+    PairReject {
+        /// 配对会话 ID
+        session_id: Uuid,
+        /// 拒绝者 ID
+        sender_id: Uuid,
+    },
+    /// 证书轮换通知 - 由发送者 CA 私钥签名，告知已配对网关新的证书指纹
+    CertificateRotated {
+        /// 发送者 ID
+        sender_id: Uuid,
+        /// 新的服务端和客户端证书指纹
+        fingerprints: Vec<String>,
+        /// 通知时间
+        timestamp: chrono::DateTime<chrono::Utc>,
+        /// CA 私钥签名（Base64）
+        signature: String,
+    },
+    /// 证书吊销通知 - 由转发者 CA 私钥签名，在受信任网关之间传播吊销记录
+    RevocationNotice {
+        /// 发送者 ID
+        sender_id: Uuid,
+        /// 吊销记录
+        revocation: Revocation,
+        /// CA 私钥签名（Base64）
+        signature: String,
+    },
 }
 
 impl WdicMessage {
@@ -184,14 +315,16 @@ impl WdicMessage {
     /// # 参数
     ///
     /// * `sender_id` - 发送者 ID
+    /// * `sequence` - 探测序号，0 表示未编号
     ///
     /// # 返回值
     ///
     /// 心跳消息实例
-    pub fn heartbeat(sender_id: Uuid) -> Self {
+    pub fn heartbeat(sender_id: Uuid, sequence: u64) -> Self {
         Self::Heartbeat {
             sender_id,
             timestamp: chrono::Utc::now(),
+            sequence,
         }
     }
 
@@ -200,14 +333,16 @@ impl WdicMessage {
     /// # 参数
     ///
     /// * `sender_id` - 响应者 ID
+    /// * `sequence` - 回显的探测序号
     ///
     /// # 返回值
     ///
     /// 心跳响应消息实例
-    pub fn heartbeat_response(sender_id: Uuid) -> Self {
+    pub fn heartbeat_response(sender_id: Uuid, sequence: u64) -> Self {
         Self::HeartbeatResponse {
             sender_id,
             timestamp: chrono::Utc::now(),
+            sequence,
         }
     }
 
@@ -405,6 +540,286 @@ impl WdicMessage {
         }
     }
 
+    /// 创建中继信封消息
+    ///
+    /// # 参数
+    ///
+    /// * `source_id` - 源网关 ID
+    /// * `target_id` - 目标网关 ID
+    /// * `relay_id` - 中继网关 ID
+    /// * `source_public_key` - 源网关公钥（Base64）
+    /// * `payload` - 已加密的内层消息
+    ///
+    /// # 返回值
+    ///
+    /// 中继信封消息实例
+    pub fn relay_envelope(
+        source_id: Uuid,
+        target_id: Uuid,
+        relay_id: Uuid,
+        source_public_key: String,
+        payload: Vec<u8>,
+    ) -> Self {
+        Self::RelayEnvelope {
+            source_id,
+            target_id,
+            relay_id,
+            source_public_key,
+            payload,
+        }
+    }
+
+    /// 创建中继路由通告消息
+    ///
+    /// # 参数
+    ///
+    /// * `relay` - 中继网关信息
+    /// * `reachable` - 可经该中继到达的网关列表
+    ///
+    /// # 返回值
+    ///
+    /// 中继路由通告消息实例
+    pub fn relay_routes(relay: RegistryEntry, reachable: Vec<RegistryEntry>) -> Self {
+        Self::RelayRoutes { relay, reachable }
+    }
+
+    /// 创建端点上报消息
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 上报者 ID
+    /// * `local_endpoints` - 本地监听端点列表
+    ///
+    /// # 返回值
+    ///
+    /// 端点上报消息实例
+    pub fn endpoint_report(sender_id: Uuid, local_endpoints: Vec<SocketAddr>) -> Self {
+        Self::EndpointReport {
+            sender_id,
+            local_endpoints,
+        }
+    }
+
+    /// 创建端点观察结果消息
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 会合网关 ID
+    /// * `observed` - 观察到的上报者端点
+    ///
+    /// # 返回值
+    ///
+    /// 端点观察结果消息实例
+    pub fn endpoint_observed(sender_id: Uuid, observed: SocketAddr) -> Self {
+        Self::EndpointObserved {
+            sender_id,
+            observed,
+        }
+    }
+
+    /// 创建打洞请求消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `requester_id` - 请求者 ID
+    /// * `target_id` - 目标网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 打洞请求消息实例
+    pub fn punch_request(session_id: Uuid, requester_id: Uuid, target_id: Uuid) -> Self {
+        Self::PunchRequest {
+            session_id,
+            requester_id,
+            target_id,
+        }
+    }
+
+    /// 创建打洞指令消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `sender_id` - 会合网关 ID
+    /// * `peer_id` - 对端网关 ID
+    /// * `candidates` - 对端候选端点
+    ///
+    /// # 返回值
+    ///
+    /// 打洞指令消息实例
+    pub fn punch_instruction(
+        session_id: Uuid,
+        sender_id: Uuid,
+        peer_id: Uuid,
+        candidates: Vec<SocketAddr>,
+    ) -> Self {
+        Self::PunchInstruction {
+            session_id,
+            sender_id,
+            peer_id,
+            candidates,
+        }
+    }
+
+    /// 创建打洞探测消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `sender_id` - 发送者 ID
+    ///
+    /// # 返回值
+    ///
+    /// 打洞探测消息实例
+    pub fn punch_probe(session_id: Uuid, sender_id: Uuid) -> Self {
+        Self::PunchProbe {
+            session_id,
+            sender_id,
+        }
+    }
+
+    /// 创建打洞确认消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 打洞会话 ID
+    /// * `sender_id` - 发送者 ID
+    ///
+    /// # 返回值
+    ///
+    /// 打洞确认消息实例
+    pub fn punch_ack(session_id: Uuid, sender_id: Uuid) -> Self {
+        Self::PunchAck {
+            session_id,
+            sender_id,
+        }
+    }
+
+    /// 创建配对请求消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 配对会话 ID
+    /// * `sender` - 发起者信息
+    /// * `certificate` - 发起者 CA 证书（PEM）
+    ///
+    /// # 返回值
+    ///
+    /// 配对请求消息实例
+    pub fn pair_request(session_id: Uuid, sender: RegistryEntry, certificate: String) -> Self {
+        Self::PairRequest {
+            session_id,
+            sender,
+            certificate,
+        }
+    }
+
+    /// 创建配对响应消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 配对会话 ID
+    /// * `sender_id` - 响应者 ID
+    /// * `certificate` - 响应者 CA 证书（PEM）
+    /// * `agreement_key` - 响应者密钥协商公钥（Base64）
+    ///
+    /// # 返回值
+    ///
+    /// 配对响应消息实例
+    pub fn pair_response(
+        session_id: Uuid,
+        sender_id: Uuid,
+        certificate: String,
+        agreement_key: Option<String>,
+    ) -> Self {
+        Self::PairResponse {
+            session_id,
+            sender_id,
+            certificate,
+            agreement_key,
+        }
+    }
+
+    /// 创建配对确认消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 配对会话 ID
+    /// * `sender_id` - 确认者 ID
+    ///
+    /// # 返回值
+    ///
+    /// 配对确认消息实例
+    pub fn pair_confirm(session_id: Uuid, sender_id: Uuid) -> Self {
+        Self::PairConfirm {
+            session_id,
+            sender_id,
+        }
+    }
+
+    /// 创建配对拒绝消息
+    ///
+    /// # 参数
+    ///
+    /// * `session_id` - 配对会话 ID
+    /// * `sender_id` - 拒绝者 ID
+    ///
+    /// # 返回值
+    ///
+    /// 配对拒绝消息实例
+    pub fn pair_reject(session_id: Uuid, sender_id: Uuid) -> Self {
+        Self::PairReject {
+            session_id,
+            sender_id,
+        }
+    }
+
+    /// 创建证书轮换通知
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送者 ID
+    /// * `fingerprints` - 新证书指纹
+    /// * `timestamp` - 通知时间
+    /// * `signature` - CA 私钥对通知内容的签名（Base64）
+    ///
+    /// # 返回值
+    ///
+    /// 证书轮换通知
+    pub fn certificate_rotated(
+        sender_id: Uuid,
+        fingerprints: Vec<String>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        signature: String,
+    ) -> Self {
+        Self::CertificateRotated {
+            sender_id,
+            fingerprints,
+            timestamp,
+            signature,
+        }
+    }
+
+    /// 创建证书吊销通知
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送者 ID
+    /// * `revocation` - 吊销记录
+    /// * `signature` - CA 私钥对通知内容的签名（Base64）
+    ///
+    /// # 返回值
+    ///
+    /// 证书吊销通知
+    pub fn revocation_notice(sender_id: Uuid, revocation: Revocation, signature: String) -> Self {
+        Self::RevocationNotice {
+            sender_id,
+            revocation,
+            signature,
+        }
+    }
+
     /// 序列化消息为字节
     ///
     /// # 返回值
@@ -450,6 +865,56 @@ impl WdicMessage {
             Self::FileTransferTokenResponse { .. } => "FileTransferTokenResponse",
             Self::FileTransferData { .. } => "FileTransferData",
             Self::FileTransferError { .. } => "FileTransferError",
+            Self::RelayEnvelope { .. } => "RelayEnvelope",
+            Self::RelayRoutes { .. } => "RelayRoutes",
+            Self::EndpointReport { .. } => "EndpointReport",
+            Self::EndpointObserved { .. } => "EndpointObserved",
+            Self::PunchRequest { .. } => "PunchRequest",
+            Self::PunchInstruction { .. } => "PunchInstruction",
+            Self::PunchProbe { .. } => "PunchProbe",
+            Self::PunchAck { .. } => "PunchAck",
+            Self::PairRequest { .. } => "PairRequest",
+            Self::PairResponse { .. } => "PairResponse",
+            Self::PairConfirm { .. } => "PairConfirm",
+            Self::PairReject { .. } => "PairReject",
+            Self::CertificateRotated { .. } => "CertificateRotated",
+            Self::RevocationNotice { .. } => "RevocationNotice",
+        }
+    }
+
+    /// 获取消息的入站限速类别
+    pub fn rate_class(&self) -> MessageClass {
+        match self {
+            Self::Broadcast { .. }
+            | Self::BroadcastResponse { .. }
+            | Self::Heartbeat { .. }
+            | Self::HeartbeatResponse { .. }
+            | Self::RegisterRequest { .. }
+            | Self::RegisterResponse { .. }
+            | Self::UnregisterRequest { .. }
+            | Self::UnregisterResponse { .. }
+            | Self::QueryGateways { .. }
+            | Self::QueryResponse { .. }
+            | Self::Discovery { .. }
+            | Self::RelayRoutes { .. }
+            | Self::EndpointReport { .. }
+            | Self::EndpointObserved { .. }
+            | Self::PunchRequest { .. }
+            | Self::PunchInstruction { .. }
+            | Self::PunchProbe { .. }
+            | Self::PunchAck { .. } => MessageClass::Discovery,
+            Self::FileTransferTokenRequest { .. } => MessageClass::FileRequest,
+            Self::PairRequest { .. }
+            | Self::PairResponse { .. }
+            | Self::PairConfirm { .. }
+            | Self::PairReject { .. } => MessageClass::Pairing,
+            Self::Error { .. }
+            | Self::FileTransferTokenResponse { .. }
+            | Self::FileTransferData { .. }
+            | Self::FileTransferError { .. }
+            | Self::RelayEnvelope { .. }
+            | Self::CertificateRotated { .. }
+            | Self::RevocationNotice { .. } => MessageClass::Control,
         }
     }
 
@@ -468,9 +933,38 @@ impl WdicMessage {
             Self::QueryGateways { requester_id } => Some(*requester_id),
             Self::QueryResponse { sender_id, .. } => Some(*sender_id),
             Self::UnregisterRequest { gateway_id } => Some(*gateway_id),
+            Self::RelayEnvelope { source_id, .. } => Some(*source_id),
+            Self::RelayRoutes { relay, .. } => Some(relay.id),
+            Self::EndpointReport { sender_id, .. } => Some(*sender_id),
+            Self::EndpointObserved { sender_id, .. } => Some(*sender_id),
+            Self::PunchRequest { requester_id, .. } => Some(*requester_id),
+            Self::PunchInstruction { sender_id, .. } => Some(*sender_id),
+            Self::PunchProbe { sender_id, .. } => Some(*sender_id),
+            Self::PunchAck { sender_id, .. } => Some(*sender_id),
+            Self::PairRequest { sender, .. } => Some(sender.id),
+            Self::PairResponse { sender_id, .. } => Some(*sender_id),
+            Self::PairConfirm { sender_id, .. } => Some(*sender_id),
+            Self::PairReject { sender_id, .. } => Some(*sender_id),
+            Self::CertificateRotated { sender_id, .. } => Some(*sender_id),
+            Self::RevocationNotice { sender_id, .. } => Some(*sender_id),
             _ => None,
         }
     }
+
+    /// 获取必须为消息签名的发送者 ID
+    ///
+    /// 中继信封由中继网关转发，端到端的来源由信封内的会话密钥认证，
+    /// 因此不要求与签名者一致。
+    ///
+    /// # 返回值
+    ///
+    /// 必须与签名者一致的发送者 ID，不要求一致时返回 None
+    pub fn origin_id(&self) -> Option<Uuid> {
+        match self {
+            Self::RelayEnvelope { .. } => None,
+            _ => self.sender_id(),
+        }
+    }
 }
 
 /// WDIC 协议处理器
@@ -540,6 +1034,73 @@ impl WdicProtocol {
                     return Err(anyhow::anyhow!("注册请求网关端口无效"));
                 }
             }
+            WdicMessage::RelayEnvelope {
+                source_id,
+                target_id,
+                payload,
+                ..
+            } => {
+                if source_id == target_id {
+                    return Err(anyhow::anyhow!("中继信封的源网关与目标网关相同"));
+                }
+                if payload.is_empty() {
+                    return Err(anyhow::anyhow!("中继信封内容不能为空"));
+                }
+            }
+            WdicMessage::PunchRequest {
+                requester_id,
+                target_id,
+                ..
+            } if requester_id == target_id => {
+                return Err(anyhow::anyhow!("打洞请求的请求者与目标网关相同"));
+            }
+            WdicMessage::PunchInstruction { candidates, .. } => {
+                if candidates.is_empty() {
+                    return Err(anyhow::anyhow!("打洞指令缺少候选端点"));
+                }
+                if candidates.iter().any(|addr| addr.port() == 0) {
+                    return Err(anyhow::anyhow!("打洞指令候选端点端口无效"));
+                }
+            }
+            WdicMessage::PairRequest {
+                sender,
+                certificate,
+                ..
+            } => {
+                if sender.name.is_empty() {
+                    return Err(anyhow::anyhow!("配对请求发起者名称不能为空"));
+                }
+                if certificate.is_empty() {
+                    return Err(anyhow::anyhow!("配对请求缺少证书"));
+                }
+            }
+            WdicMessage::PairResponse { certificate, .. } if certificate.is_empty() => {
+                return Err(anyhow::anyhow!("配对响应缺少证书"));
+            }
+            WdicMessage::CertificateRotated {
+                fingerprints,
+                signature,
+                ..
+            } => {
+                if fingerprints.is_empty() {
+                    return Err(anyhow::anyhow!("证书轮换通知缺少证书指纹"));
+                }
+                if signature.is_empty() {
+                    return Err(anyhow::anyhow!("证书轮换通知缺少签名"));
+                }
+            }
+            WdicMessage::RevocationNotice {
+                revocation,
+                signature,
+                ..
+            } => {
+                if revocation.gateway_id.is_none() && revocation.fingerprints.is_empty() {
+                    return Err(anyhow::anyhow!("证书吊销通知没有指定网关或证书指纹"));
+                }
+                if signature.is_empty() {
+                    return Err(anyhow::anyhow!("证书吊销通知缺少签名"));
+                }
+            }
             WdicMessage::Error { code, message } => {
                 if *code == 0 {
                     return Err(anyhow::anyhow!("错误代码不能为0"));
@@ -648,15 +1209,17 @@ mod tests {
     #[test]
     fn test_wdic_message_heartbeat() {
         let id = uuid::Uuid::new_v4();
-        let message = WdicMessage::heartbeat(id);
+        let message = WdicMessage::heartbeat(id, 7);
 
         match message {
             WdicMessage::Heartbeat {
                 sender_id,
                 timestamp,
+                sequence,
             } => {
                 assert_eq!(sender_id, id);
                 assert!(timestamp <= chrono::Utc::now());
+                assert_eq!(sequence, 7);
             }
             _ => panic!("消息类型不正确"),
         }
@@ -730,4 +1293,50 @@ mod tests {
         // 当前实现返回 None，实际处理在网关层
         assert!(result.unwrap().is_none());
     }
+
+    #[test]
+    fn test_wdic_message_hole_punch_validation() {
+        let protocol = WdicProtocol::new();
+        let session_id = uuid::Uuid::new_v4();
+        let (a, b) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let candidate = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), 40000);
+
+        let instruction = WdicMessage::punch_instruction(session_id, b, a, vec![candidate]);
+        assert_eq!(instruction.message_type(), "PunchInstruction");
+        assert_eq!(instruction.sender_id(), Some(b));
+        assert!(protocol.validate_message(&instruction).is_ok());
+
+        // 缺少候选端点或请求自身的打洞均无效
+        let empty = WdicMessage::punch_instruction(session_id, b, a, Vec::new());
+        assert!(protocol.validate_message(&empty).is_err());
+        let to_self = WdicMessage::punch_request(session_id, a, a);
+        assert!(protocol.validate_message(&to_self).is_err());
+
+        let probe = WdicMessage::punch_probe(session_id, a);
+        let bytes = probe.to_bytes().unwrap();
+        assert_eq!(WdicMessage::from_bytes(&bytes).unwrap(), probe);
+    }
+
+    #[test]
+    fn test_wdic_message_pairing_validation() {
+        let protocol = WdicProtocol::new();
+        let session_id = uuid::Uuid::new_v4();
+        let sender = create_test_entry("配对网关", 55555);
+        let sender_id = sender.id;
+
+        let request = WdicMessage::pair_request(session_id, sender.clone(), "PEM".to_string());
+        assert_eq!(request.message_type(), "PairRequest");
+        assert_eq!(request.sender_id(), Some(sender_id));
+        assert!(protocol.validate_message(&request).is_ok());
+
+        // 缺少证书的配对请求与响应均无效
+        let empty = WdicMessage::pair_request(session_id, sender, String::new());
+        assert!(protocol.validate_message(&empty).is_err());
+        let response = WdicMessage::pair_response(session_id, sender_id, String::new(), None);
+        assert!(protocol.validate_message(&response).is_err());
+
+        let confirm = WdicMessage::pair_confirm(session_id, sender_id);
+        let bytes = confirm.to_bytes().unwrap();
+        assert_eq!(WdicMessage::from_bytes(&bytes).unwrap(), confirm);
+    }
 }
@@ -0,0 +1,746 @@
+//! QUIC 传输安全模块
+//!
+//! 根据 mTLS 配置生成 QUIC 连接配置，在网关的数据报传输上接受入站 QUIC 握手，
+//! 并在握手完成后检查协商参数是否符合 TLS 策略、认证对端证书，
+//! 记录证书指纹与网关身份的对应关系。
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use ring::hmac;
+use ring::rand::SystemRandom;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::gateway::tls::{
+    NegotiatedTls, PeerCertificate, QuicRole, TlsManager, TlsVersion, VerifyMode,
+    QUIC_CIPHER_SUITES,
+};
+
+/// WDIC 应用层协议标识（ALPN）
+pub const WDIC_ALPN: &[u8] = b"wdic";
+
+/// QUIC 数据报最大载荷
+pub const MAX_DATAGRAM_SIZE: usize = 1350;
+
+/// 证书认证失败时关闭连接使用的应用错误码
+const AUTH_FAILED_ERROR_CODE: u64 = 0x1;
+
+/// 会话被强制断开时关闭连接使用的应用错误码
+const SESSION_CLOSED_ERROR_CODE: u64 = 0x2;
+
+/// QUIC 传输安全配置
+#[derive(Debug, Clone)]
+pub struct QuicSecurity {
+    /// 提供证书与验证的 TLS 管理器
+    tls_manager: Arc<TlsManager>,
+    /// 是否启用 mTLS
+    enable_mtls: bool,
+}
+
+impl QuicSecurity {
+    /// 创建 QUIC 传输安全配置
+    ///
+    /// # 参数
+    ///
+    /// * `tls_manager` - TLS 管理器
+    /// * `enable_mtls` - 是否启用 mTLS，关闭时仍出示证书但不验证对端
+    ///
+    /// # 返回值
+    ///
+    /// QUIC 传输安全配置
+    pub fn new(tls_manager: Arc<TlsManager>, enable_mtls: bool) -> Self {
+        Self {
+            tls_manager,
+            enable_mtls,
+        }
+    }
+
+    /// 获取 TLS 管理器
+    pub fn tls_manager(&self) -> &Arc<TlsManager> {
+        &self.tls_manager
+    }
+
+    /// 是否验证对端出示的证书
+    pub fn verifies_peer(&self) -> bool {
+        self.enable_mtls && self.tls_manager.config().verify_mode != VerifyMode::None
+    }
+
+    /// 接受连接时是否要求客户端出示证书
+    pub fn requires_client_certificate(&self) -> bool {
+        self.enable_mtls && self.tls_manager.is_mutual_auth_enabled()
+    }
+
+    /// 指定角色下是否必须取得对端证书
+    fn requires_peer_certificate(&self, role: QuicRole) -> bool {
+        match role {
+            QuicRole::Client => self.verifies_peer(),
+            QuicRole::Server => self.requires_client_certificate(),
+        }
+    }
+
+    /// 创建 QUIC 连接配置
+    ///
+    /// # 参数
+    ///
+    /// * `role` - 连接中的角色
+    ///
+    /// # 返回值
+    ///
+    /// 已加载证书和验证设置的 QUIC 连接配置
+    pub fn config(&self, role: QuicRole) -> Result<quiche::Config> {
+        let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)
+            .map_err(|e| anyhow!("创建 QUIC 配置失败: {e}"))?;
+
+        config
+            .set_application_protos(&[WDIC_ALPN])
+            .map_err(|e| anyhow!("设置应用协议失败: {e}"))?;
+        config.set_max_idle_timeout(30000); // 30 秒超时
+        config.set_max_recv_udp_payload_size(MAX_DATAGRAM_SIZE);
+        config.set_max_send_udp_payload_size(MAX_DATAGRAM_SIZE);
+        config.set_initial_max_data(10_000_000);
+        config.set_initial_max_stream_data_bidi_local(1_000_000);
+        config.set_initial_max_stream_data_bidi_remote(1_000_000);
+        config.set_initial_max_streams_bidi(100);
+        config.set_disable_active_migration(true);
+
+        // 客户端验证服务端证书；服务端仅在双向认证时要求客户端证书
+        let verify_peer = match role {
+            QuicRole::Client => self.verifies_peer(),
+            QuicRole::Server => self.requires_client_certificate(),
+        };
+        self.tls_manager
+            .configure_quic(&mut config, role, verify_peer)?;
+
+        Ok(config)
+    }
+
+    /// 读取已完成握手的连接协商出的 TLS 参数
+    ///
+    /// QUIC 固定使用 TLS 1.3；quiche 不公开最终选择的密码套件，
+    /// 因此只记录可能使用的套件集合。这些参数仅用于展示，
+    /// TLS 策略在生成连接配置时由 [`TlsManager::check_quic_policy`] 落实。
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 已建立的 QUIC 连接
+    pub fn negotiated(connection: &quiche::Connection) -> NegotiatedTls {
+        let application_protocol = connection.application_proto();
+        NegotiatedTls {
+            transport: "QUIC".to_string(),
+            tls_version: TlsVersion::Tls13,
+            cipher_suite: None,
+            permitted_cipher_suites: QUIC_CIPHER_SUITES.map(String::from).to_vec(),
+            application_protocol: (!application_protocol.is_empty())
+                .then(|| String::from_utf8_lossy(application_protocol).into_owned()),
+            resumed: connection.is_resumed(),
+        }
+    }
+
+    /// 认证已完成握手的对端
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 已建立的 QUIC 连接
+    /// * `role` - 本端在连接中的角色
+    ///
+    /// # 返回值
+    ///
+    /// 对端证书身份与协商出的 TLS 参数；要求证书但对端未出示、
+    /// 证书未通过验证或已被吊销时返回错误
+    pub fn authenticate(
+        &self,
+        connection: &quiche::Connection,
+        role: QuicRole,
+    ) -> Result<AuthenticatedPeer> {
+        let negotiated = Self::negotiated(connection);
+        if connection.application_proto() != WDIC_ALPN {
+            return Err(anyhow!("对端未协商 WDIC 应用层协议"));
+        }
+
+        let Some(der_data) = connection.peer_cert() else {
+            if self.requires_peer_certificate(role) {
+                return Err(anyhow!("对端未出示证书"));
+            }
+            return Ok(AuthenticatedPeer {
+                certificate: None,
+                negotiated,
+            });
+        };
+
+        let certificate = self.tls_manager.peer_certificate(der_data)?;
+        // 无论是否验证对端，被吊销的证书都会被拒绝
+        if self.tls_manager.is_revoked(&certificate) {
+            return Err(anyhow!("对端证书 {} 已被吊销", certificate.fingerprint));
+        }
+        if self.verifies_peer() && !certificate.verified {
+            return Err(anyhow!("对端证书 {} 未通过验证", certificate.fingerprint));
+        }
+
+        Ok(AuthenticatedPeer {
+            certificate: Some(certificate),
+            negotiated,
+        })
+    }
+}
+
+/// 完成握手并通过认证的对端
+#[derive(Debug, Clone)]
+pub struct AuthenticatedPeer {
+    /// 对端证书身份，对端未出示证书且不要求证书时为 `None`
+    pub certificate: Option<PeerCertificate>,
+    /// 握手协商出的 TLS 参数
+    pub negotiated: NegotiatedTls,
+}
+
+/// 对端认证结果
+pub type AuthenticationResult = std::result::Result<AuthenticatedPeer, String>;
+
+/// 入站数据报的处理结果
+#[derive(Debug, Default)]
+pub struct QuicOutput {
+    /// 需要发回的数据报及目标地址
+    pub datagrams: Vec<(Vec<u8>, SocketAddr)>,
+    /// 握手刚完成的连接的认证结果
+    pub authentication: Option<(SocketAddr, AuthenticationResult)>,
+}
+
+/// 入站 QUIC 连接
+struct InboundConnection {
+    /// QUIC 连接
+    connection: quiche::Connection,
+    /// 对端地址
+    remote_addr: SocketAddr,
+    /// 是否已完成对端认证
+    authenticated: bool,
+}
+
+/// QUIC 端点
+///
+/// 持有传输安全配置、入站连接以及已认证对端的证书身份。
+pub struct QuicEndpoint {
+    /// 传输安全配置，未配置时不接受入站 QUIC 连接
+    security: RwLock<Option<QuicSecurity>>,
+    /// 入站连接，以本端连接 ID 为键
+    connections: Mutex<HashMap<Vec<u8>, InboundConnection>>,
+    /// 派生连接 ID 的密钥
+    conn_id_key: hmac::Key,
+    /// 已认证对端的证书身份
+    peers: DashMap<SocketAddr, PeerCertificate>,
+}
+
+impl std::fmt::Debug for QuicEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuicEndpoint")
+            .field("security", &self.security())
+            .field("peers", &self.peers.len())
+            .finish()
+    }
+}
+
+impl Default for QuicEndpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuicEndpoint {
+    /// 创建 QUIC 端点
+    pub fn new() -> Self {
+        let conn_id_key = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .expect("生成连接 ID 密钥失败");
+
+        Self {
+            security: RwLock::new(None),
+            connections: Mutex::new(HashMap::new()),
+            conn_id_key,
+            peers: DashMap::new(),
+        }
+    }
+
+    /// 设置传输安全配置
+    ///
+    /// # 参数
+    ///
+    /// * `security` - 传输安全配置
+    pub fn set_security(&self, security: QuicSecurity) {
+        *self.security.write().unwrap_or_else(|e| e.into_inner()) = Some(security);
+    }
+
+    /// 获取传输安全配置
+    pub fn security(&self) -> Option<QuicSecurity> {
+        self.security
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 记录已认证对端的证书身份
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 对端地址
+    /// * `certificate` - 对端证书身份
+    pub fn record_peer(&self, remote_addr: SocketAddr, certificate: PeerCertificate) {
+        self.peers.insert(remote_addr, certificate);
+    }
+
+    /// 获取对端地址的证书身份
+    pub fn peer_certificate(&self, remote_addr: &SocketAddr) -> Option<PeerCertificate> {
+        self.peers.get(remote_addr).map(|entry| entry.clone())
+    }
+
+    /// 根据证书指纹查找对端网关 ID
+    pub fn gateway_for_fingerprint(&self, fingerprint: &str) -> Option<Uuid> {
+        self.peers
+            .iter()
+            .find(|entry| entry.fingerprint == fingerprint)
+            .and_then(|entry| entry.gateway_id)
+    }
+
+    /// 当前入站连接数量
+    pub fn inbound_count(&self) -> usize {
+        self.connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// 由客户端选择的连接 ID 派生本端连接 ID
+    ///
+    /// 客户端重传的 Initial 包仍使用原连接 ID，派生保证其落到同一连接上。
+    fn derive_conn_id(&self, dcid: &[u8]) -> Vec<u8> {
+        let tag = hmac::sign(&self.conn_id_key, dcid);
+        tag.as_ref()[..quiche::MAX_CONN_ID_LEN].to_vec()
+    }
+
+    /// 处理入站 QUIC 数据报
+    ///
+    /// # 参数
+    ///
+    /// * `buf` - 收到的数据报
+    /// * `from` - 发送者地址
+    /// * `local` - 本地地址
+    ///
+    /// # 返回值
+    ///
+    /// 需要发回的数据报以及握手完成时的认证结果；数据报不是 QUIC 包时返回错误
+    pub fn handle_datagram(
+        &self,
+        buf: &mut [u8],
+        from: SocketAddr,
+        local: SocketAddr,
+    ) -> Result<QuicOutput> {
+        let Some(security) = self.security() else {
+            return Err(anyhow!("未配置 QUIC 证书"));
+        };
+
+        let header = quiche::Header::from_slice(buf, quiche::MAX_CONN_ID_LEN)
+            .map_err(|e| anyhow!("解析 QUIC 包头失败: {e}"))?;
+        let mut output = QuicOutput::default();
+        let mut connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+
+        let conn_id = if connections.contains_key(header.dcid.as_ref()) {
+            header.dcid.to_vec()
+        } else {
+            let derived = self.derive_conn_id(&header.dcid);
+            if !connections.contains_key(&derived) {
+                if header.ty != quiche::Type::Initial {
+                    return Err(anyhow!("收到未知连接的非 Initial QUIC 包"));
+                }
+
+                if !quiche::version_is_supported(header.version) {
+                    debug!("向 {from} 发送 QUIC 版本协商");
+                    let mut out = [0u8; MAX_DATAGRAM_SIZE];
+                    let len = quiche::negotiate_version(&header.scid, &header.dcid, &mut out)
+                        .map_err(|e| anyhow!("生成版本协商包失败: {e}"))?;
+                    output.datagrams.push((out[..len].to_vec(), from));
+                    return Ok(output);
+                }
+
+                let mut config = security.config(QuicRole::Server)?;
+                let scid = quiche::ConnectionId::from_ref(&derived);
+                let connection = quiche::accept(&scid, None, local, from, &mut config)
+                    .map_err(|e| anyhow!("接受 QUIC 连接失败: {e}"))?;
+                debug!("接受来自 {from} 的 QUIC 连接");
+                connections.insert(
+                    derived.clone(),
+                    InboundConnection {
+                        connection,
+                        remote_addr: from,
+                        authenticated: false,
+                    },
+                );
+            }
+            derived
+        };
+
+        let Some(inbound) = connections.get_mut(&conn_id) else {
+            return Ok(output);
+        };
+
+        let recv_info = quiche::RecvInfo { from, to: local };
+        if let Err(e) = inbound.connection.recv(buf, recv_info) {
+            debug!("处理来自 {from} 的 QUIC 数据失败: {e}");
+        }
+
+        if inbound.connection.is_established() && !inbound.authenticated {
+            inbound.authenticated = true;
+            let result = security.authenticate(&inbound.connection, QuicRole::Server);
+            match &result {
+                Ok(AuthenticatedPeer {
+                    certificate: Some(certificate),
+                    ..
+                }) => {
+                    info!(
+                        "QUIC 对端 {from} 认证通过，证书指纹 {}",
+                        certificate.fingerprint
+                    );
+                    self.record_peer(from, certificate.clone());
+                }
+                Ok(AuthenticatedPeer {
+                    certificate: None, ..
+                }) => debug!("QUIC 对端 {from} 未出示证书"),
+                Err(e) => {
+                    warn!("QUIC 对端 {from} 认证失败: {e}");
+                    let _ = inbound.connection.close(
+                        true,
+                        AUTH_FAILED_ERROR_CODE,
+                        b"certificate rejected",
+                    );
+                }
+            }
+            output.authentication = Some((from, result.map_err(|e| e.to_string())));
+        }
+
+        Self::flush(&mut inbound.connection, &mut output.datagrams);
+        connections.retain(|_, inbound| !inbound.connection.is_closed());
+
+        Ok(output)
+    }
+
+    /// 关闭证书已被吊销的对端的连接
+    ///
+    /// 对端的证书身份随之移除。
+    ///
+    /// # 返回值
+    ///
+    /// 被吊销的对端地址，以及关闭连接需要发送的数据报
+    pub fn close_revoked(&self) -> (Vec<SocketAddr>, Vec<(Vec<u8>, SocketAddr)>) {
+        let Some(security) = self.security() else {
+            return (Vec::new(), Vec::new());
+        };
+        let tls_manager = security.tls_manager();
+
+        let revoked: Vec<SocketAddr> = self
+            .peers
+            .iter()
+            .filter(|entry| tls_manager.is_revoked(entry.value()))
+            .map(|entry| *entry.key())
+            .collect();
+        let mut datagrams = Vec::new();
+        if revoked.is_empty() {
+            return (revoked, datagrams);
+        }
+
+        for remote_addr in &revoked {
+            info!("对端 {remote_addr} 的证书已被吊销，关闭 QUIC 连接");
+            self.close_connections(
+                remote_addr,
+                AUTH_FAILED_ERROR_CODE,
+                b"certificate revoked",
+                &mut datagrams,
+            );
+            self.peers.remove(remote_addr);
+        }
+
+        (revoked, datagrams)
+    }
+
+    /// 关闭与指定对端的入站连接
+    ///
+    /// 用于强制断开会话，对端的证书身份随之移除。
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 对端地址
+    ///
+    /// # 返回值
+    ///
+    /// 是否存在到该对端的连接，以及关闭连接需要发送的数据报
+    pub fn close_peer(&self, remote_addr: &SocketAddr) -> (bool, Vec<(Vec<u8>, SocketAddr)>) {
+        let mut datagrams = Vec::new();
+        let closed = self.close_connections(
+            remote_addr,
+            SESSION_CLOSED_ERROR_CODE,
+            b"session closed",
+            &mut datagrams,
+        );
+        let known = self.peers.remove(remote_addr).is_some();
+        (closed > 0 || known, datagrams)
+    }
+
+    /// 关闭并移除到指定对端的全部入站连接
+    ///
+    /// # 返回值
+    ///
+    /// 被关闭的连接数量
+    fn close_connections(
+        &self,
+        remote_addr: &SocketAddr,
+        error_code: u64,
+        reason: &[u8],
+        datagrams: &mut Vec<(Vec<u8>, SocketAddr)>,
+    ) -> usize {
+        let mut connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+        let before = connections.len();
+        connections.retain(|_, inbound| {
+            if inbound.remote_addr != *remote_addr {
+                return true;
+            }
+            let _ = inbound.connection.close(true, error_code, reason);
+            Self::flush(&mut inbound.connection, datagrams);
+            false
+        });
+        before - connections.len()
+    }
+
+    /// 处理入站连接的定时器
+    ///
+    /// # 返回值
+    ///
+    /// 需要发送的数据报及目标地址
+    pub fn on_timeout(&self) -> Vec<(Vec<u8>, SocketAddr)> {
+        let mut datagrams = Vec::new();
+        let mut connections = self.connections.lock().unwrap_or_else(|e| e.into_inner());
+
+        for inbound in connections.values_mut() {
+            if inbound.connection.timeout() == Some(std::time::Duration::ZERO) {
+                inbound.connection.on_timeout();
+            }
+            Self::flush(&mut inbound.connection, &mut datagrams);
+        }
+
+        connections.retain(|_, inbound| {
+            if inbound.connection.is_closed() {
+                debug!("清理已关闭的入站 QUIC 连接");
+            }
+            !inbound.connection.is_closed()
+        });
+
+        datagrams
+    }
+
+    /// 取出连接待发送的全部数据报
+    fn flush(connection: &mut quiche::Connection, datagrams: &mut Vec<(Vec<u8>, SocketAddr)>) {
+        let mut out = [0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            match connection.send(&mut out) {
+                Ok((len, send_info)) => datagrams.push((out[..len].to_vec(), send_info.to)),
+                Err(quiche::Error::Done) => break,
+                Err(e) => {
+                    warn!("生成 QUIC 数据失败: {e}");
+                    let _ = connection.close(false, 0x1, b"send failed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::tls::{CertificateIdentity, MtlsConfig, Revocation};
+    use tempfile::tempdir;
+
+    fn config_in(dir: &std::path::Path, trusted_ca_dir: std::path::PathBuf) -> MtlsConfig {
+        MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            trusted_ca_dir,
+            ..Default::default()
+        }
+    }
+
+    /// 在内存中驱动客户端与端点完成握手，返回端点的认证结果
+    fn handshake(
+        client_security: &QuicSecurity,
+        endpoint: &QuicEndpoint,
+    ) -> Result<(quiche::Connection, Option<AuthenticationResult>)> {
+        let client_addr: SocketAddr = "127.0.0.1:40001".parse()?;
+        let server_addr: SocketAddr = "127.0.0.1:40002".parse()?;
+        let scid = quiche::ConnectionId::from_ref(&[7u8; 16]);
+        let mut config = client_security.config(QuicRole::Client)?;
+        let mut client = quiche::connect(None, &scid, client_addr, server_addr, &mut config)?;
+
+        let mut authentication = None;
+        let mut out = [0u8; MAX_DATAGRAM_SIZE];
+        for _ in 0..20 {
+            while let Ok((len, _)) = client.send(&mut out) {
+                let output = endpoint.handle_datagram(&mut out[..len], client_addr, server_addr)?;
+                authentication = authentication.or(output.authentication.map(|(_, r)| r));
+                for (mut datagram, _) in output.datagrams {
+                    let recv_info = quiche::RecvInfo {
+                        from: server_addr,
+                        to: client_addr,
+                    };
+                    let _ = client.recv(&mut datagram, recv_info);
+                }
+            }
+            if authentication.is_some() {
+                break;
+            }
+        }
+
+        Ok((client, authentication))
+    }
+
+    #[test]
+    fn test_mutual_authentication_records_gateway_identity() -> Result<()> {
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let (id_a, id_b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // 两个网关各自的 CA 互相加入受信任目录
+        let trusted_a = dir_a.path().join("trusted");
+        let trusted_b = dir_b.path().join("trusted");
+        let manager_a = TlsManager::with_identity(
+            config_in(dir_a.path(), trusted_a.clone()),
+            CertificateIdentity::for_gateway("网关 A", id_a, &[]),
+        )?;
+        let manager_b = TlsManager::with_identity(
+            config_in(dir_b.path(), trusted_b.clone()),
+            CertificateIdentity::for_gateway("网关 B", id_b, &[]),
+        )?;
+        drop((manager_a, manager_b));
+        std::fs::create_dir_all(&trusted_a)?;
+        std::fs::create_dir_all(&trusted_b)?;
+        std::fs::copy(dir_b.path().join("ca.crt"), trusted_a.join("b.crt"))?;
+        std::fs::copy(dir_a.path().join("ca.crt"), trusted_b.join("a.crt"))?;
+
+        let security_a = QuicSecurity::new(
+            Arc::new(TlsManager::new(config_in(dir_a.path(), trusted_a))?),
+            true,
+        );
+        let security_b = QuicSecurity::new(
+            Arc::new(TlsManager::new(config_in(dir_b.path(), trusted_b))?),
+            true,
+        );
+        assert!(security_a.verifies_peer());
+        assert!(security_a.requires_client_certificate());
+
+        let endpoint = QuicEndpoint::new();
+        endpoint.set_security(security_b);
+        let (client, authentication) = handshake(&security_a, &endpoint)?;
+
+        // 服务端记录客户端证书指纹对应的网关身份
+        let peer = authentication.expect("握手未完成").expect("认证失败");
+        assert_eq!(peer.negotiated.tls_version, TlsVersion::Tls13);
+        assert_eq!(
+            peer.negotiated.application_protocol.as_deref(),
+            Some("wdic")
+        );
+        let certificate = peer.certificate.expect("未取得客户端证书");
+        assert_eq!(certificate.gateway_id, Some(id_a));
+        assert_eq!(
+            endpoint.gateway_for_fingerprint(&certificate.fingerprint),
+            Some(id_a)
+        );
+
+        // 客户端同样取得并验证服务端证书
+        let server = security_a.authenticate(&client, QuicRole::Client)?;
+        assert_eq!(server.certificate.and_then(|c| c.gateway_id), Some(id_b));
+
+        // 吊销客户端后，其连接被关闭并移除证书身份
+        let client_addr: SocketAddr = "127.0.0.1:40001".parse()?;
+        let security_b = endpoint.security().expect("未配置 QUIC 证书");
+        security_b.tls_manager().revoke(Revocation {
+            gateway_id: Some(id_a),
+            fingerprints: Vec::new(),
+            reason: "测试吊销".to_string(),
+            revoked_at: chrono::Utc::now(),
+            revoked_by: id_b,
+        })?;
+        let (revoked, datagrams) = endpoint.close_revoked();
+        assert_eq!(revoked, vec![client_addr]);
+        assert!(!datagrams.is_empty());
+        assert!(endpoint.peer_certificate(&client_addr).is_none());
+        assert_eq!(endpoint.inbound_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unenforceable_policy_is_refused() -> Result<()> {
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let weak_policy = |dir: &std::path::Path| MtlsConfig {
+            cipher_suites: vec!["TLS_AES_256_GCM_SHA384".to_string()],
+            ..config_in(dir, dir.join("trusted"))
+        };
+        let weak = QuicSecurity::new(Arc::new(TlsManager::new(weak_policy(dir_a.path()))?), true);
+        let strict = QuicSecurity::new(
+            Arc::new(TlsManager::new(config_in(dir_b.path(), dir_b.path().join("trusted")))?),
+            true,
+        );
+
+        // QUIC 无法只使用部分 TLS 1.3 套件，客户端不会以削弱后的策略发起握手
+        let endpoint = QuicEndpoint::new();
+        endpoint.set_security(strict.clone());
+        let error = weak.config(QuicRole::Client).err().map(|e| e.to_string());
+        assert!(error.is_some_and(|e| e.contains("TLS_CHACHA20_POLY1305_SHA256")));
+        assert!(handshake(&weak, &endpoint).is_err());
+
+        // 服务端同样拒绝接受连接，不会留下连接状态
+        let endpoint = QuicEndpoint::new();
+        endpoint.set_security(weak);
+        assert!(handshake(&strict, &endpoint).is_err());
+        assert_eq!(endpoint.inbound_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_untrusted_client_is_rejected() -> Result<()> {
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let trusted_a = dir_a.path().join("trusted");
+        let trusted_b = dir_b.path().join("trusted");
+
+        // 客户端信任服务端 CA，服务端不信任客户端 CA
+        let manager_b = TlsManager::new(config_in(dir_b.path(), trusted_b.clone()))?;
+        drop(manager_b);
+        std::fs::create_dir_all(&trusted_a)?;
+        std::fs::copy(dir_b.path().join("ca.crt"), trusted_a.join("b.crt"))?;
+
+        let security_a = QuicSecurity::new(
+            Arc::new(TlsManager::new(config_in(dir_a.path(), trusted_a))?),
+            true,
+        );
+        let security_b = QuicSecurity::new(
+            Arc::new(TlsManager::new(config_in(dir_b.path(), trusted_b))?),
+            true,
+        );
+
+        let endpoint = QuicEndpoint::new();
+        endpoint.set_security(security_b);
+        let (_, authentication) = handshake(&security_a, &endpoint)?;
+
+        // 握手在 TLS 层被拒绝或认证失败，都不会记录客户端身份
+        assert!(!matches!(
+            authentication,
+            Some(Ok(AuthenticatedPeer {
+                certificate: Some(_),
+                ..
+            }))
+        ));
+        assert!(endpoint
+            .peer_certificate(&"127.0.0.1:40001".parse()?)
+            .is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+//! 限速模块
+//!
+//! 提供令牌桶算法实现，用于限制中继转发带宽等资源消耗；
+//! 以及按来源地址和网关身份、按消息类别的入站限速，反复超限的来源会被临时封禁。
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 令牌桶
+///
+/// 以固定速率补充令牌，桶容量决定允许的突发量。
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    /// 桶容量
+    capacity: f64,
+    /// 每秒补充的令牌数
+    refill_per_second: f64,
+    /// 当前令牌数
+    tokens: f64,
+    /// 上次补充时间
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// 创建新的令牌桶，初始为满
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 桶容量
+    /// * `refill_per_second` - 每秒补充的令牌数
+    ///
+    /// # 返回值
+    ///
+    /// 令牌桶实例
+    pub fn new(capacity: u64, refill_per_second: u64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_second: refill_per_second as f64,
+            tokens: capacity as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 按经过的时间补充令牌
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// 获取当前可用令牌数
+    pub fn available(&mut self) -> u64 {
+        self.refill();
+        self.tokens as u64
+    }
+
+    /// 检查是否有足够的令牌（不消耗）
+    ///
+    /// # 参数
+    ///
+    /// * `amount` - 需要的令牌数
+    pub fn can_consume(&mut self, amount: u64) -> bool {
+        self.refill();
+        self.tokens >= amount as f64
+    }
+
+    /// 尝试消耗令牌
+    ///
+    /// # 参数
+    ///
+    /// * `amount` - 需要消耗的令牌数
+    ///
+    /// # 返回值
+    ///
+    /// 令牌充足并已扣除时返回 true
+    pub fn try_consume(&mut self, amount: u64) -> bool {
+        self.refill();
+        if self.tokens >= amount as f64 {
+            self.tokens -= amount as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 入站消息类别，每个类别有独立的限速预算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageClass {
+    /// 任意数据报（解析之前按来源地址计数）
+    Datagram,
+    /// 发现、注册、心跳与 NAT 穿透消息
+    Discovery,
+    /// 目录搜索与列目录请求
+    Search,
+    /// 文件读取与传输请求
+    FileRequest,
+    /// 配对与密钥交换消息
+    Pairing,
+    /// 其他控制与响应消息
+    Control,
+}
+
+impl MessageClass {
+    /// 获取类别名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Datagram => "datagram",
+            Self::Discovery => "discovery",
+            Self::Search => "search",
+            Self::FileRequest => "file_request",
+            Self::Pairing => "pairing",
+            Self::Control => "control",
+        }
+    }
+}
+
+/// 限速预算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateBudget {
+    /// 允许的突发数量（令牌桶容量）
+    pub burst: u64,
+    /// 每秒补充的数量
+    pub per_second: u64,
+}
+
+impl RateBudget {
+    /// 创建限速预算
+    pub const fn new(burst: u64, per_second: u64) -> Self {
+        Self { burst, per_second }
+    }
+}
+
+/// 入站限速配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// 是否启用入站限速
+    pub enabled: bool,
+    /// 每个来源地址的数据报预算
+    pub datagram: RateBudget,
+    /// 发现、注册、心跳消息预算
+    pub discovery: RateBudget,
+    /// 目录搜索与列目录请求预算
+    pub search: RateBudget,
+    /// 文件读取与传输请求预算
+    pub file_request: RateBudget,
+    /// 配对与密钥交换消息预算
+    pub pairing: RateBudget,
+    /// 其他控制与响应消息预算
+    pub control: RateBudget,
+    /// 统计窗口内被限速多少次后封禁来源
+    pub ban_threshold: u32,
+    /// 统计限速次数的窗口（秒）
+    pub violation_window: u64,
+    /// 封禁时长（秒）
+    pub ban_duration: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            datagram: RateBudget::new(400, 200),
+            discovery: RateBudget::new(60, 20),
+            search: RateBudget::new(10, 2),
+            file_request: RateBudget::new(5, 1),
+            pairing: RateBudget::new(5, 1),
+            control: RateBudget::new(100, 50),
+            ban_threshold: 50,
+            violation_window: 10,
+            ban_duration: 300,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// 获取消息类别的预算
+    pub fn budget(&self, class: MessageClass) -> RateBudget {
+        match class {
+            MessageClass::Datagram => self.datagram,
+            MessageClass::Discovery => self.discovery,
+            MessageClass::Search => self.search,
+            MessageClass::FileRequest => self.file_request,
+            MessageClass::Pairing => self.pairing,
+            MessageClass::Control => self.control,
+        }
+    }
+
+    /// 验证配置
+    ///
+    /// # 返回值
+    ///
+    /// 启用限速时任一预算或封禁参数为 0 返回错误
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let classes = [
+            MessageClass::Datagram,
+            MessageClass::Discovery,
+            MessageClass::Search,
+            MessageClass::FileRequest,
+            MessageClass::Pairing,
+            MessageClass::Control,
+        ];
+        for class in classes {
+            let budget = self.budget(class);
+            if budget.burst == 0 || budget.per_second == 0 {
+                return Err(anyhow::anyhow!("{} 限速预算不能为 0", class.as_str()));
+            }
+        }
+        if self.ban_threshold == 0 || self.violation_window == 0 || self.ban_duration == 0 {
+            return Err(anyhow::anyhow!("封禁阈值、统计窗口和封禁时长不能为 0"));
+        }
+        Ok(())
+    }
+}
+
+/// 限速对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RateSubject {
+    /// 来源 IP 地址
+    Address(IpAddr),
+    /// 消息声明的网关身份
+    Identity(Uuid),
+}
+
+impl std::fmt::Display for RateSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address(ip) => write!(f, "{ip}"),
+            Self::Identity(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// 限速检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateVerdict {
+    /// 允许处理
+    Allowed,
+    /// 超出预算，丢弃
+    Throttled,
+    /// 来源已被封禁，丢弃
+    Banned,
+}
+
+/// 被封禁的来源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerBan {
+    /// 来源 IP 地址或网关 ID
+    pub subject: String,
+    /// 封禁截止时间
+    pub until: DateTime<Utc>,
+}
+
+/// 入站限速统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThrottleStats {
+    /// 是否启用入站限速
+    pub enabled: bool,
+    /// 允许处理的数量
+    pub allowed: u64,
+    /// 超出预算被丢弃的数量
+    pub throttled: u64,
+    /// 来源被封禁而丢弃的数量
+    pub dropped_banned: u64,
+    /// 累计封禁次数
+    pub bans_issued: u64,
+    /// 按消息类别统计的被限速数量
+    pub throttled_by_class: HashMap<String, u64>,
+    /// 当前生效的封禁
+    pub active_bans: Vec<PeerBan>,
+}
+
+/// 入站限速器
+///
+/// 每个来源地址和网关身份在每个消息类别上各有一个令牌桶；
+/// 统计窗口内被限速次数达到阈值的来源会被封禁一段时间。
+#[derive(Debug)]
+pub struct InboundRateLimiter {
+    /// 限速配置
+    config: RwLock<RateLimitConfig>,
+    /// 令牌桶，以限速对象和消息类别为键
+    buckets: DashMap<(RateSubject, MessageClass), TokenBucket>,
+    /// 统计窗口内的限速次数及窗口开始时间
+    violations: DashMap<RateSubject, (u32, Instant)>,
+    /// 封禁截止时间
+    bans: DashMap<RateSubject, (Instant, DateTime<Utc>)>,
+    /// 允许处理的数量
+    allowed: AtomicU64,
+    /// 被限速的数量
+    throttled: AtomicU64,
+    /// 因封禁丢弃的数量
+    dropped_banned: AtomicU64,
+    /// 累计封禁次数
+    bans_issued: AtomicU64,
+    /// 按消息类别统计的被限速数量
+    throttled_by_class: DashMap<MessageClass, u64>,
+}
+
+impl Default for InboundRateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl InboundRateLimiter {
+    /// 创建入站限速器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 限速配置
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            buckets: DashMap::new(),
+            violations: DashMap::new(),
+            bans: DashMap::new(),
+            allowed: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            dropped_banned: AtomicU64::new(0),
+            bans_issued: AtomicU64::new(0),
+            throttled_by_class: DashMap::new(),
+        }
+    }
+
+    /// 创建不限速的限速器
+    pub fn disabled() -> Self {
+        Self::new(RateLimitConfig {
+            enabled: false,
+            ..Default::default()
+        })
+    }
+
+    /// 获取当前配置
+    pub fn config(&self) -> RateLimitConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 更新配置，已有的令牌桶按新预算重建
+    pub fn set_config(&self, config: RateLimitConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+        self.buckets.clear();
+    }
+
+    /// 检查解析前的数据报
+    ///
+    /// # 参数
+    ///
+    /// * `ip` - 来源 IP 地址
+    pub fn check_datagram(&self, ip: IpAddr) -> RateVerdict {
+        self.check(&[RateSubject::Address(ip)], MessageClass::Datagram)
+    }
+
+    /// 检查解析后的消息
+    ///
+    /// 来源地址和消息声明的网关身份各自消耗预算，任一超限即丢弃。
+    ///
+    /// # 参数
+    ///
+    /// * `ip` - 来源 IP 地址
+    /// * `identity` - 消息声明的网关 ID
+    /// * `class` - 消息类别
+    pub fn check_message(
+        &self,
+        ip: IpAddr,
+        identity: Option<Uuid>,
+        class: MessageClass,
+    ) -> RateVerdict {
+        match identity {
+            Some(id) => self.check(
+                &[RateSubject::Address(ip), RateSubject::Identity(id)],
+                class,
+            ),
+            None => self.check(&[RateSubject::Address(ip)], class),
+        }
+    }
+
+    /// 对一组限速对象检查并消耗预算
+    fn check(&self, subjects: &[RateSubject], class: MessageClass) -> RateVerdict {
+        let config = self.config();
+        if !config.enabled {
+            return RateVerdict::Allowed;
+        }
+
+        if subjects.iter().any(|subject| self.is_banned(subject)) {
+            self.dropped_banned.fetch_add(1, Ordering::Relaxed);
+            return RateVerdict::Banned;
+        }
+
+        let budget = config.budget(class);
+        let mut verdict = RateVerdict::Allowed;
+        for subject in subjects {
+            let consumed = self
+                .buckets
+                .entry((*subject, class))
+                .or_insert_with(|| TokenBucket::new(budget.burst, budget.per_second))
+                .try_consume(1);
+            if !consumed {
+                verdict = RateVerdict::Throttled;
+                self.record_violation(*subject, &config);
+            }
+        }
+
+        if verdict == RateVerdict::Allowed {
+            self.allowed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            *self.throttled_by_class.entry(class).or_insert(0) += 1;
+            debug!("{} 消息超出限速预算: {}", class.as_str(), subjects[0]);
+        }
+        verdict
+    }
+
+    /// 检查对象是否处于封禁期，过期的封禁随之移除
+    fn is_banned(&self, subject: &RateSubject) -> bool {
+        let Some(expires) = self.bans.get(subject).map(|entry| entry.0) else {
+            return false;
+        };
+        if Instant::now() < expires {
+            return true;
+        }
+        self.bans.remove(subject);
+        false
+    }
+
+    /// 记录一次限速，统计窗口内达到阈值时封禁
+    fn record_violation(&self, subject: RateSubject, config: &RateLimitConfig) {
+        let now = Instant::now();
+        let window = Duration::from_secs(config.violation_window);
+        let count = {
+            let mut entry = self.violations.entry(subject).or_insert((0, now));
+            if now.duration_since(entry.1) > window {
+                *entry = (0, now);
+            }
+            entry.0 += 1;
+            entry.0
+        };
+
+        if count >= config.ban_threshold {
+            let duration = Duration::from_secs(config.ban_duration);
+            let until = Utc::now() + chrono::Duration::seconds(config.ban_duration as i64);
+            self.bans.insert(subject, (now + duration, until));
+            self.violations.remove(&subject);
+            self.bans_issued.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "来源 {subject} 在 {} 秒内被限速 {count} 次，封禁 {} 秒",
+                config.violation_window, config.ban_duration
+            );
+        }
+    }
+
+    /// 清理过期的封禁、限速记录和已补满的令牌桶
+    ///
+    /// 补满的令牌桶与新建的等价，移除后不影响限速结果。
+    pub fn prune(&self) {
+        let now = Instant::now();
+        let config = self.config();
+        let window = Duration::from_secs(config.violation_window);
+        self.bans.retain(|_, (expires, _)| now < *expires);
+        self.violations
+            .retain(|_, (_, started)| now.duration_since(*started) <= window);
+        self.buckets
+            .retain(|(_, class), bucket| bucket.available() < config.budget(*class).burst);
+    }
+
+    /// 获取限速统计
+    pub fn stats(&self) -> ThrottleStats {
+        let now = Instant::now();
+        let mut active_bans: Vec<PeerBan> = self
+            .bans
+            .iter()
+            .filter(|entry| now < entry.value().0)
+            .map(|entry| PeerBan {
+                subject: entry.key().to_string(),
+                until: entry.value().1,
+            })
+            .collect();
+        active_bans.sort_by_key(|ban| ban.until);
+
+        ThrottleStats {
+            enabled: self.config().enabled,
+            allowed: self.allowed.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            dropped_banned: self.dropped_banned.load(Ordering::Relaxed),
+            bans_issued: self.bans_issued.load(Ordering::Relaxed),
+            throttled_by_class: self
+                .throttled_by_class
+                .iter()
+                .map(|entry| (entry.key().as_str().to_string(), *entry.value()))
+                .collect(),
+            active_bans,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_consume_and_refill() {
+        let mut bucket = TokenBucket::new(100, 1000);
+        assert!(bucket.try_consume(60));
+        assert!(!bucket.try_consume(60));
+        assert!(bucket.can_consume(40));
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(bucket.try_consume(60));
+
+        // 补充不会超过容量
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert_eq!(bucket.available(), 100);
+    }
+
+    #[test]
+    fn test_flooding_peer_is_throttled_then_banned() {
+        let limiter = InboundRateLimiter::new(RateLimitConfig {
+            search: RateBudget::new(5, 1),
+            ban_threshold: 10,
+            ..Default::default()
+        });
+        let flooder: IpAddr = "10.0.0.66".parse().unwrap();
+        let neighbour: IpAddr = "10.0.0.7".parse().unwrap();
+        let flooder_id = Uuid::new_v4();
+
+        // 突发预算内的搜索被放行，之后被限速
+        let verdicts: Vec<RateVerdict> = (0..15)
+            .map(|_| limiter.check_message(flooder, Some(flooder_id), MessageClass::Search))
+            .collect();
+        assert!(verdicts[..5].iter().all(|v| *v == RateVerdict::Allowed));
+        assert_eq!(verdicts[5], RateVerdict::Throttled);
+
+        // 达到阈值后地址和身份都被封禁，换地址也无法继续
+        assert_eq!(
+            limiter.check_message(flooder, None, MessageClass::Discovery),
+            RateVerdict::Banned
+        );
+        assert_eq!(limiter.check_datagram(flooder), RateVerdict::Banned);
+        assert_eq!(
+            limiter.check_message(neighbour, Some(flooder_id), MessageClass::Search),
+            RateVerdict::Banned
+        );
+
+        // 其他来源不受影响
+        assert_eq!(
+            limiter.check_message(neighbour, None, MessageClass::Search),
+            RateVerdict::Allowed
+        );
+
+        let stats = limiter.stats();
+        assert_eq!(stats.bans_issued, 2);
+        assert_eq!(stats.active_bans.len(), 2);
+        assert_eq!(stats.throttled_by_class.get("search"), Some(&10));
+        assert!(stats.dropped_banned >= 3);
+    }
+
+    #[test]
+    fn test_disabled_limiter_allows_everything() {
+        let limiter = InboundRateLimiter::disabled();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!((0..1000).all(|_| limiter.check_datagram(ip) == RateVerdict::Allowed));
+        assert_eq!(limiter.stats(), ThrottleStats::default());
+        assert!(RateLimitConfig {
+            search: RateBudget::new(0, 1),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
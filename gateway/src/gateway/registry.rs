@@ -8,11 +8,20 @@ use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
-use std::path::PathBuf;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::gateway::{MountPoint, TauriDirectoryEntry};
+/// 网关信任状态
+///
+/// 从磁盘恢复的条目在重新探测确认前均为未验证状态。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrustState {
+    /// 未验证（从持久化存储恢复，尚未在本次运行中确认）
+    #[default]
+    Unverified,
+    /// 已验证（本次运行中直接收到过该网关的消息）
+    Verified,
+}
 
 /// 注册表条目
 ///
@@ -27,6 +36,12 @@ pub struct RegistryEntry {
     pub address: SocketAddr,
     /// 最后更新时间
     pub last_seen: DateTime<Utc>,
+    /// 信任状态
+    #[serde(default)]
+    pub trust_state: TrustState,
+    /// X25519 密钥协商公钥（Base64），用于端到端加密
+    #[serde(default)]
+    pub public_key: Option<String>,
 }
 
 impl RegistryEntry {
@@ -46,9 +61,16 @@ impl RegistryEntry {
             name,
             address,
             last_seen: Utc::now(),
+            trust_state: TrustState::Unverified,
+            public_key: None,
         }
     }
 
+    /// 检查条目是否已在本次运行中验证
+    pub fn is_verified(&self) -> bool {
+        self.trust_state == TrustState::Verified
+    }
+
     /// 更新最后访问时间
     pub fn update_last_seen(&mut self) {
         self.last_seen = Utc::now();
@@ -65,8 +87,6 @@ pub struct Registry {
     entries: Arc<DashMap<Uuid, RegistryEntry>>,
     /// 本网关的信息
     local_entry: Arc<AtomicRefCell<RegistryEntry>>,
-    /// 存储挂载点信息 (lock-free)
-    mount_points: Arc<DashMap<String, MountPoint>>,
 }
 
 impl Clone for Registry {
@@ -74,7 +94,6 @@ impl Clone for Registry {
         Self {
             entries: Arc::clone(&self.entries),
             local_entry: Arc::clone(&self.local_entry),
-            mount_points: Arc::clone(&self.mount_points),
         }
     }
 }
@@ -97,7 +116,6 @@ impl Registry {
                 local_name,
                 local_address,
             ))),
-            mount_points: Arc::new(DashMap::new()),
         }
     }
 
@@ -106,6 +124,37 @@ impl Registry {
         self.local_entry.borrow().clone()
     }
 
+    /// 设置本网关的唯一标识
+    ///
+    /// 用于从持久化存储恢复上次运行时的网关身份，使其他网关在重启后仍能识别本网关。
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 网关唯一标识
+    pub fn set_local_id(&self, id: Uuid) {
+        self.local_entry.borrow_mut().id = id;
+    }
+
+    /// 设置本网关的密钥协商公钥
+    ///
+    /// # 参数
+    ///
+    /// * `public_key` - Base64 编码的 X25519 公钥
+    pub fn set_local_public_key(&self, public_key: String) {
+        self.local_entry.borrow_mut().public_key = Some(public_key);
+    }
+
+    /// 设置本网关的地址
+    ///
+    /// 网络接口变化导致监听地址重新绑定后调用。
+    ///
+    /// # 参数
+    ///
+    /// * `address` - 新的监听地址
+    pub fn set_local_address(&self, address: SocketAddr) {
+        self.local_entry.borrow_mut().address = address;
+    }
+
     /// 添加或更新网关条目
     ///
     /// # 参数
@@ -123,11 +172,94 @@ impl Registry {
         }
 
         entry.update_last_seen();
+        entry.trust_state = TrustState::Verified;
+
+        // 同一地址上的未验证条目已被新的身份取代
+        let stale_ids: Vec<Uuid> = self
+            .entries
+            .iter()
+            .filter(|existing| {
+                existing.id != entry.id
+                    && existing.address == entry.address
+                    && !existing.is_verified()
+            })
+            .map(|existing| existing.id)
+            .collect();
+        for id in &stale_ids {
+            self.entries.remove(id);
+        }
+
         let is_new = !self.entries.contains_key(&entry.id);
         self.entries.insert(entry.id, entry);
         is_new
     }
 
+    /// 恢复未验证的网关条目
+    ///
+    /// 用于启动时从持久化存储加载已知网关。条目以未验证状态加入，
+    /// 最后更新时间重置为当前时间，以便在重新探测期间不被过期清理。
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 要恢复的条目
+    ///
+    /// # 返回值
+    ///
+    /// 如果条目被加入返回 true；若为本网关或已存在则返回 false
+    pub fn restore_unverified(&self, entry: RegistryEntry) -> bool {
+        self.add_unverified(entry)
+    }
+
+    /// 添加间接得知的网关条目
+    ///
+    /// 用于经中继路由通告等无法确认来源的途径得知的网关。条目以未验证状态加入，
+    /// 已存在的条目（包括其地址和公钥）不会被覆盖。
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 要添加的条目
+    ///
+    /// # 返回值
+    ///
+    /// 如果条目被加入返回 true；若为本网关或已存在则返回 false
+    pub fn add_unverified(&self, mut entry: RegistryEntry) -> bool {
+        let local_id = self.local_entry.borrow().id;
+        if entry.id == local_id || self.entries.contains_key(&entry.id) {
+            return false;
+        }
+
+        entry.update_last_seen();
+        entry.trust_state = TrustState::Unverified;
+        self.entries.insert(entry.id, entry);
+        true
+    }
+
+    /// 获取所有已验证的条目
+    ///
+    /// # 返回值
+    ///
+    /// 本次运行中已确认的条目向量
+    pub fn verified_entries(&self) -> Vec<RegistryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_verified())
+            .map(|entry| entry.clone())
+            .collect()
+    }
+
+    /// 获取所有未验证的条目
+    ///
+    /// # 返回值
+    ///
+    /// 尚待重新探测的条目向量
+    pub fn unverified_entries(&self) -> Vec<RegistryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.is_verified())
+            .map(|entry| entry.clone())
+            .collect()
+    }
+
     /// 根据 ID 获取网关条目
     ///
     /// # 参数
@@ -243,168 +375,6 @@ impl Registry {
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
-
-    // ============================================================================
-    // 挂载点管理方法（为 Tauri API 添加）
-    // ============================================================================
-
-    /// 注册挂载点
-    ///
-    /// # 参数
-    ///
-    /// * `mount_id` - 挂载点 ID
-    /// * `local_path` - 本地路径
-    /// * `mount_name` - 挂载名称
-    /// * `read_only` - 是否只读
-    ///
-    /// # 返回值
-    ///
-    /// 取消注册挂载点
-    ///
-    /// # 参数
-    ///
-    /// * `mount_id` - 挂载点 ID
-    ///
-    /// # 返回值
-    ///
-    /// 操作结果
-    pub async fn unregister_mount_point(&self, mount_id: &str) -> anyhow::Result<()> {
-        if let Some(_) = self.mount_points.remove(mount_id) {
-            log::info!("成功取消注册挂载点: {mount_id}");
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("挂载点不存在: {mount_id}"))
-        }
-    }
-
-    /// 注册挂载点
-    ///
-    /// # 参数
-    ///
-    /// * `mount_point` - 挂载点信息
-    ///
-    /// # 返回值
-    ///
-    /// 操作结果
-    pub async fn register_mount_point(&self, mount_point: MountPoint) -> anyhow::Result<()> {
-        let mount_id = mount_point.id.clone();
-        self.mount_points.insert(mount_id.clone(), mount_point);
-        log::info!("成功注册挂载点: {mount_id}");
-        Ok(())
-    }
-
-    /// 获取挂载点列表
-    ///
-    /// # 返回值
-    ///
-    /// 挂载点列表
-    pub async fn get_mount_points(&self) -> anyhow::Result<Vec<MountPoint>> {
-        let mount_points: Vec<MountPoint> = self
-            .mount_points
-            .iter()
-            .map(|entry| entry.value().clone())
-            .collect();
-
-        Ok(mount_points)
-    }
-
-    /// 列出目录内容
-    ///
-    /// # 参数
-    ///
-    /// * `mount_id` - 挂载点 ID
-    /// * `path` - 目录路径
-    ///
-    /// # 返回值
-    ///
-    /// 目录条目列表
-    pub async fn list_directory(
-        &self,
-        mount_id: &str,
-        path: &str,
-    ) -> anyhow::Result<Vec<TauriDirectoryEntry>> {
-        use std::fs;
-        use chrono::{DateTime, Utc};
-
-        // 根据 mount_id 找到实际的挂载点
-        let mount_point = self.mount_points.get(mount_id)
-            .ok_or_else(|| anyhow::anyhow!("挂载点不存在: {mount_id}"))?;
-
-        // 构建完整路径：挂载点路径 + 相对路径
-        let mut target_path = mount_point.local_path.clone();
-        if !path.is_empty() && path != "/" {
-            target_path.push(path.trim_start_matches('/'));
-        }
-
-        // 安全检查：确保路径在挂载点范围内
-        let canonical_mount = std::fs::canonicalize(&mount_point.local_path)?;
-        let canonical_target = std::fs::canonicalize(&target_path)
-            .or_else(|_| Ok::<PathBuf, std::io::Error>(target_path.clone()))?;
-        
-        if !canonical_target.starts_with(&canonical_mount) {
-            return Err(anyhow::anyhow!("访问被拒绝：路径超出挂载点范围"));
-        }
-
-        if !target_path.exists() {
-            return Err(anyhow::anyhow!("路径不存在: {:?}", target_path));
-        }
-
-        if !target_path.is_dir() {
-            return Err(anyhow::anyhow!("不是目录: {:?}", target_path));
-        }
-
-        let mut entries = Vec::new();
-        let read_dir = fs::read_dir(&target_path)?;
-
-        for entry in read_dir {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            let full_path = entry.path();
-
-            let modified_time = metadata.modified()?
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs();
-            let modified_time = DateTime::from_timestamp(modified_time as i64, 0)
-                .unwrap_or_else(Utc::now);
-
-            let created_time = metadata.created().ok()
-                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
-                .and_then(|duration| DateTime::from_timestamp(duration.as_secs() as i64, 0));
-
-            entries.push(TauriDirectoryEntry {
-                name: file_name.clone(),
-                path: full_path.to_string_lossy().to_string(),
-                is_directory: metadata.is_dir(),
-                size: if metadata.is_file() { metadata.len() } else { 0 },
-                modified_time,
-                created_time,
-                file_type: if metadata.is_dir() {
-                    "directory".to_string()
-                } else if metadata.is_file() {
-                    // 简单的文件类型推断
-                    if let Some(extension) = full_path.extension() {
-                        extension.to_string_lossy().to_string()
-                    } else {
-                        "file".to_string()
-                    }
-                } else {
-                    "unknown".to_string()
-                },
-            });
-        }
-
-        // 按名称排序，目录在前
-        entries.sort_by(|a, b| {
-            match (a.is_directory, b.is_directory) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.cmp(&b.name),
-            }
-        });
-
-        Ok(entries)
-    }
 }
 
 #[cfg(test)]
@@ -595,4 +565,59 @@ mod tests {
         assert!(ids.contains(&entry1.id));
         assert!(ids.contains(&entry2.id));
     }
+
+    #[test]
+    fn test_registry_restore_unverified() {
+        let local_address = create_test_address(55555);
+        let registry = Registry::new("本地网关".to_string(), local_address);
+
+        let remote_address = create_test_address(55556);
+        let mut stale = RegistryEntry::new("旧身份".to_string(), remote_address);
+        stale.last_seen = Utc::now() - chrono::Duration::days(2);
+
+        assert!(registry.restore_unverified(stale.clone()));
+        assert!(!registry.restore_unverified(stale.clone()));
+        assert_eq!(registry.unverified_entries().len(), 1);
+        assert!(registry.verified_entries().is_empty());
+
+        // 恢复的条目不应被立即清理
+        assert_eq!(registry.cleanup_expired(300), 0);
+
+        // 同一条目再次被直接看到后转为已验证
+        registry.add_or_update(stale.clone());
+        assert!(registry.get(&stale.id).unwrap().is_verified());
+
+        // 同一地址出现新身份时，旧的未验证条目被取代
+        let mut other = RegistryEntry::new("另一网关".to_string(), create_test_address(55557));
+        other.trust_state = TrustState::Unverified;
+        registry.restore_unverified(other.clone());
+        let replacement = RegistryEntry::new("新身份".to_string(), other.address);
+        registry.add_or_update(replacement.clone());
+        assert!(registry.get(&other.id).is_none());
+        assert!(registry.get(&replacement.id).is_some());
+    }
+
+    #[test]
+    fn test_registry_add_unverified_keeps_existing_entry() {
+        let registry = Registry::new("本地网关".to_string(), create_test_address(55555));
+
+        let mut known = RegistryEntry::new("已知网关".to_string(), create_test_address(55556));
+        known.public_key = Some("真实公钥".to_string());
+        registry.add_or_update(known.clone());
+
+        // 中继通告的同 ID 条目不得覆盖已知地址和公钥
+        let mut forged = known.clone();
+        forged.address = create_test_address(55599);
+        forged.public_key = Some("伪造公钥".to_string());
+        assert!(!registry.add_unverified(forged));
+        let stored = registry.get(&known.id).unwrap();
+        assert_eq!(stored.address, known.address);
+        assert_eq!(stored.public_key.as_deref(), Some("真实公钥"));
+        assert!(stored.is_verified());
+
+        // 未知网关以未验证状态加入
+        let relayed = RegistryEntry::new("中继网关".to_string(), create_test_address(55557));
+        assert!(registry.add_unverified(relayed.clone()));
+        assert!(!registry.get(&relayed.id).unwrap().is_verified());
+    }
 }
@@ -0,0 +1,710 @@
+//! 中继模块
+//!
+//! 让两个无法直接通信的网关经由第三个网关交换消息。中继是可选角色，
+//! 需要在配置中显式启用。转发路径由注册表中的可达性决定，转发带宽受令牌桶限制；
+//! 内层消息由两端通过 X25519 协商的密钥加密，中继网关只能看到信封头部。
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::gateway::crypto::{decode_public_key, AgreementKeyPair};
+use crate::gateway::heartbeat::{HeartbeatScheduler, PeerLiveness};
+use crate::gateway::pairing::PairingManager;
+use crate::gateway::protocol::WdicMessage;
+use crate::gateway::rate_limit::TokenBucket;
+use crate::gateway::registry::{Registry, RegistryEntry};
+
+/// 中继端到端加密的密钥用途标签
+const RELAY_KEY_CONTEXT: &[u8] = b"wdic-relay-v1";
+
+/// 错误代码：源网关未知，拒绝为其转发
+pub const RELAY_ERROR_UNKNOWN_SOURCE: u32 = 401;
+/// 错误代码：本网关未启用中继
+pub const RELAY_ERROR_DISABLED: u32 = 403;
+/// 错误代码：目标网关不可达
+pub const RELAY_ERROR_UNREACHABLE: u32 = 404;
+
+/// 中继配置
+#[derive(Debug, Clone, Copy)]
+pub struct RelayConfig {
+    /// 是否为其他网关提供中继转发
+    pub enabled: bool,
+    /// 中继总带宽上限（字节/秒）
+    pub max_bandwidth: u64,
+    /// 单个源-目标网关对的带宽上限（字节/秒）
+    pub max_session_bandwidth: u64,
+    /// 直连确认的有效期，超过该时间未直接收到对端消息则视为不可直连
+    pub direct_timeout: Duration,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bandwidth: 1024 * 1024,
+            max_session_bandwidth: 256 * 1024,
+            direct_timeout: Duration::from_secs(180),
+        }
+    }
+}
+
+/// 到达某个网关的路径
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerRoute {
+    /// 直接发送到网关地址
+    Direct(SocketAddr),
+    /// 经由中继网关转发
+    Relay {
+        /// 中继网关 ID
+        relay_id: Uuid,
+        /// 中继网关地址
+        relay_addr: SocketAddr,
+    },
+}
+
+/// 中继对一个待转发信封的处理决定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayDecision {
+    /// 转发到目标地址
+    Forward(SocketAddr),
+    /// 拒绝转发，并告知源网关原因
+    Reject {
+        /// 错误代码
+        code: u32,
+        /// 错误描述
+        reason: String,
+    },
+    /// 超出带宽上限，静默丢弃
+    RateLimited,
+}
+
+/// 中继转发统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelayStats {
+    /// 已转发的消息数量
+    pub forwarded_messages: u64,
+    /// 已转发的字节数
+    pub forwarded_bytes: u64,
+    /// 因带宽上限丢弃的消息数量
+    pub rate_limited: u64,
+    /// 因目标不可达或源未知而拒绝的消息数量
+    pub rejected: u64,
+}
+
+/// 经中继学到的路由
+#[derive(Debug, Clone, Copy)]
+struct RelayRoute {
+    /// 中继网关 ID
+    relay_id: Uuid,
+}
+
+/// 中继管理器
+///
+/// 同时承担两种职责：作为发送端，根据直连确认与中继通告为每个网关选择路径；
+/// 作为中继网关（启用时），校验并限速转发其他网关的信封。
+#[derive(Debug)]
+pub struct RelayManager {
+    /// 中继配置
+    config: RelayConfig,
+    /// 最近一次直接收到各网关消息的时间
+    direct_peers: DashMap<Uuid, Instant>,
+    /// 目标网关 ID -> 可用的中继路由
+    routes: DashMap<Uuid, RelayRoute>,
+    /// 中继总带宽令牌桶
+    total_bucket: Mutex<TokenBucket>,
+    /// 每个源-目标网关对的带宽令牌桶
+    session_buckets: DashMap<(Uuid, Uuid), TokenBucket>,
+    /// 已转发消息数
+    forwarded_messages: AtomicU64,
+    /// 已转发字节数
+    forwarded_bytes: AtomicU64,
+    /// 限速丢弃数
+    rate_limited: AtomicU64,
+    /// 拒绝转发数
+    rejected: AtomicU64,
+}
+
+impl RelayManager {
+    /// 创建新的中继管理器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 中继配置
+    ///
+    /// # 返回值
+    ///
+    /// 中继管理器实例
+    pub fn new(config: RelayConfig) -> Self {
+        Self {
+            total_bucket: Mutex::new(TokenBucket::new(config.max_bandwidth, config.max_bandwidth)),
+            config,
+            direct_peers: DashMap::new(),
+            routes: DashMap::new(),
+            session_buckets: DashMap::new(),
+            forwarded_messages: AtomicU64::new(0),
+            forwarded_bytes: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// 本网关是否为其他网关提供中继
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 记录直接收到某网关的消息
+    pub fn mark_direct(&self, peer_id: Uuid) {
+        self.direct_peers.insert(peer_id, Instant::now());
+    }
+
+    /// 检查最近是否直接收到过某网关的消息
+    pub fn is_direct(&self, peer_id: &Uuid) -> bool {
+        self.direct_peers
+            .get(peer_id)
+            .is_some_and(|seen| seen.elapsed() < self.config.direct_timeout)
+    }
+
+    /// 记录中继通告的可达网关
+    ///
+    /// 同一中继之前通告过但本次未包含的路由会被移除。
+    ///
+    /// # 参数
+    ///
+    /// * `relay_id` - 中继网关 ID
+    /// * `reachable` - 可经该中继到达的网关 ID
+    pub fn learn_routes(&self, relay_id: Uuid, reachable: &[Uuid]) {
+        self.routes
+            .retain(|target, route| route.relay_id != relay_id || reachable.contains(target));
+        for target in reachable {
+            self.routes.insert(*target, RelayRoute { relay_id });
+        }
+    }
+
+    /// 遗忘与某网关相关的直连确认、路由和限速状态
+    ///
+    /// 网关被判定死亡时调用。
+    pub fn forget_peer(&self, peer_id: &Uuid) {
+        self.direct_peers.remove(peer_id);
+        self.routes
+            .retain(|target, route| target != peer_id && route.relay_id != *peer_id);
+        self.session_buckets
+            .retain(|(source, target), _| source != peer_id && target != peer_id);
+    }
+
+    /// 为目标网关选择路径
+    ///
+    /// 最近直接收到过对端消息时直连；否则若有直连可达的中继通告过该网关则经中继转发；
+    /// 都没有时仍尝试直连。
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 本地注册表
+    /// * `target` - 目标网关条目
+    ///
+    /// # 返回值
+    ///
+    /// 选定的路径
+    pub fn route(&self, registry: &Registry, target: &RegistryEntry) -> PeerRoute {
+        if self.is_direct(&target.id) {
+            return PeerRoute::Direct(target.address);
+        }
+
+        let relay = self
+            .routes
+            .get(&target.id)
+            .map(|route| route.relay_id)
+            .filter(|relay_id| self.is_direct(relay_id))
+            .and_then(|relay_id| registry.get(&relay_id));
+
+        match relay {
+            Some(relay) => PeerRoute::Relay {
+                relay_id: relay.id,
+                relay_addr: relay.address,
+            },
+            None => PeerRoute::Direct(target.address),
+        }
+    }
+
+    /// 本网关作为中继时可以直接到达的网关
+    ///
+    /// 要求网关已验证、最近直接收到过其消息且心跳未标记为可疑或死亡。
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 本地注册表
+    /// * `heartbeat` - 心跳调度器
+    ///
+    /// # 返回值
+    ///
+    /// 可达网关条目列表
+    pub fn reachable_peers(
+        &self,
+        registry: &Registry,
+        heartbeat: &HeartbeatScheduler,
+    ) -> Vec<RegistryEntry> {
+        registry
+            .verified_entries()
+            .into_iter()
+            .filter(|entry| self.is_reachable(entry, heartbeat))
+            .collect()
+    }
+
+    /// 检查网关是否可以由本网关直接到达
+    fn is_reachable(&self, entry: &RegistryEntry, heartbeat: &HeartbeatScheduler) -> bool {
+        entry.is_verified()
+            && self.is_direct(&entry.id)
+            && heartbeat
+                .get(&entry.id)
+                .is_none_or(|health| health.state == PeerLiveness::Alive)
+    }
+
+    /// 决定如何处理一个请求本网关转发的信封
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 本地注册表
+    /// * `heartbeat` - 心跳调度器
+    /// * `source_id` - 源网关 ID
+    /// * `target_id` - 目标网关 ID
+    /// * `sender` - 信封的实际发送地址
+    /// * `size` - 信封大小（字节）
+    ///
+    /// # 返回值
+    ///
+    /// 处理决定
+    pub fn decide(
+        &self,
+        registry: &Registry,
+        heartbeat: &HeartbeatScheduler,
+        source_id: Uuid,
+        target_id: Uuid,
+        sender: SocketAddr,
+        size: usize,
+    ) -> RelayDecision {
+        if !self.config.enabled {
+            return self.reject(RELAY_ERROR_DISABLED, "本网关未启用中继".to_string());
+        }
+
+        // 只为已验证且地址一致的网关转发，防止被陌生节点当作开放代理
+        let source_known = registry
+            .get(&source_id)
+            .is_some_and(|entry| entry.is_verified() && entry.address == sender);
+        if !source_known {
+            return self.reject(
+                RELAY_ERROR_UNKNOWN_SOURCE,
+                format!("未知的源网关 {source_id}"),
+            );
+        }
+
+        let target = match registry.get(&target_id) {
+            Some(entry) if self.is_reachable(&entry, heartbeat) => entry,
+            _ => {
+                return self.reject(
+                    RELAY_ERROR_UNREACHABLE,
+                    format!("目标网关 {target_id} 不可达"),
+                );
+            }
+        };
+
+        let size = size as u64;
+        let session_allowed = {
+            let mut bucket = self
+                .session_buckets
+                .entry((source_id, target_id))
+                .or_insert_with(|| {
+                    TokenBucket::new(
+                        self.config.max_session_bandwidth,
+                        self.config.max_session_bandwidth,
+                    )
+                });
+            bucket.can_consume(size)
+        };
+        let total_allowed = session_allowed
+            && self
+                .total_bucket
+                .lock()
+                .map(|mut bucket| bucket.try_consume(size))
+                .unwrap_or(false);
+        if !total_allowed {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+            return RelayDecision::RateLimited;
+        }
+        if let Some(mut bucket) = self.session_buckets.get_mut(&(source_id, target_id)) {
+            bucket.try_consume(size);
+        }
+
+        self.forwarded_messages.fetch_add(1, Ordering::Relaxed);
+        self.forwarded_bytes.fetch_add(size, Ordering::Relaxed);
+        RelayDecision::Forward(target.address)
+    }
+
+    /// 记录一次拒绝并返回拒绝决定
+    fn reject(&self, code: u32, reason: String) -> RelayDecision {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        RelayDecision::Reject { code, reason }
+    }
+
+    /// 获取中继转发统计
+    pub fn stats(&self) -> RelayStats {
+        RelayStats {
+            forwarded_messages: self.forwarded_messages.load(Ordering::Relaxed),
+            forwarded_bytes: self.forwarded_bytes.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 中继端到端加密使用的可信公钥
+///
+/// 只信任配对时固定的公钥，以及网关在自己签名的消息中声明的公钥。
+/// 注册表条目中的公钥可能经广播响应或中继通告转述，不作为信任依据。
+#[derive(Debug)]
+pub struct TrustedKeys {
+    /// 配对管理器，提供配对时固定的公钥
+    pairing: Arc<PairingManager>,
+    /// 网关在自己签名的消息中声明的公钥，以网关 ID 为键
+    announced: DashMap<Uuid, String>,
+}
+
+impl TrustedKeys {
+    /// 创建可信公钥表
+    ///
+    /// # 参数
+    ///
+    /// * `pairing` - 配对管理器
+    ///
+    /// # 返回值
+    ///
+    /// 可信公钥表实例
+    pub fn new(pairing: Arc<PairingManager>) -> Self {
+        Self {
+            pairing,
+            announced: DashMap::new(),
+        }
+    }
+
+    /// 记录网关在自己签名的消息中声明的公钥
+    ///
+    /// # 参数
+    ///
+    /// * `signer` - 消息的签名者
+    /// * `entry` - 消息中声明的网关条目
+    ///
+    /// # 返回值
+    ///
+    /// 条目由其本人签名且带有公钥时返回 true
+    pub fn record_announced(&self, signer: Option<Uuid>, entry: &RegistryEntry) -> bool {
+        if signer != Some(entry.id) {
+            return false;
+        }
+        let Some(public_key) = &entry.public_key else {
+            return false;
+        };
+        self.announced.insert(entry.id, public_key.clone());
+        true
+    }
+
+    /// 获取网关的可信公钥
+    ///
+    /// 优先使用配对时固定的公钥，其次使用网关本人声明的公钥。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 网关 ID
+    ///
+    /// # 返回值
+    ///
+    /// 可信的公钥，若不存在返回 None
+    pub fn get(&self, peer_id: &Uuid) -> Option<String> {
+        self.pairing
+            .pinned_agreement_key(peer_id)
+            .or_else(|| self.announced.get(peer_id).map(|key| key.clone()))
+    }
+
+    /// 忘记网关本人声明的公钥，配对时固定的公钥不受影响
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 网关 ID
+    pub fn forget(&self, peer_id: &Uuid) {
+        self.announced.remove(peer_id);
+    }
+}
+
+/// 信封的附加认证数据，绑定源和目标网关，防止信封被改投
+fn envelope_aad(source_id: &Uuid, target_id: &Uuid) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(32 + RELAY_KEY_CONTEXT.len());
+    aad.extend_from_slice(RELAY_KEY_CONTEXT);
+    aad.extend_from_slice(source_id.as_bytes());
+    aad.extend_from_slice(target_id.as_bytes());
+    aad
+}
+
+/// 将消息加密封装为中继信封
+///
+/// # 参数
+///
+/// * `keypair` - 本网关密钥对
+/// * `source_id` - 本网关 ID
+/// * `target_id` - 目标网关 ID
+/// * `target_public_key` - 目标网关公钥（Base64）
+/// * `relay_id` - 中继网关 ID
+/// * `message` - 内层消息
+///
+/// # 返回值
+///
+/// 中继信封消息
+pub fn seal_envelope(
+    keypair: &AgreementKeyPair,
+    source_id: Uuid,
+    target_id: Uuid,
+    target_public_key: &str,
+    relay_id: Uuid,
+    message: &WdicMessage,
+) -> Result<WdicMessage> {
+    let session_key =
+        keypair.derive_session_key(&decode_public_key(target_public_key)?, RELAY_KEY_CONTEXT)?;
+    let payload = session_key.seal(&message.to_bytes()?, &envelope_aad(&source_id, &target_id))?;
+
+    Ok(WdicMessage::relay_envelope(
+        source_id,
+        target_id,
+        relay_id,
+        keypair.public_key_base64(),
+        payload,
+    ))
+}
+
+/// 解密中继信封中的内层消息
+///
+/// # 参数
+///
+/// * `keypair` - 本网关密钥对
+/// * `source_id` - 源网关 ID
+/// * `target_id` - 目标网关 ID（应为本网关）
+/// * `source_public_key` - 源网关公钥（Base64）
+/// * `payload` - 信封密文
+///
+/// # 返回值
+///
+/// 内层消息，密文被篡改、改投或密钥不匹配时返回错误
+pub fn open_envelope(
+    keypair: &AgreementKeyPair,
+    source_id: Uuid,
+    target_id: Uuid,
+    source_public_key: &str,
+    payload: &[u8],
+) -> Result<WdicMessage> {
+    let session_key =
+        keypair.derive_session_key(&decode_public_key(source_public_key)?, RELAY_KEY_CONTEXT)?;
+    let plaintext = session_key.open(payload, &envelope_aad(&source_id, &target_id))?;
+    let message = WdicMessage::from_bytes(&plaintext)?;
+
+    // 内层消息声明的发送者必须与信封源一致
+    if let Some(inner_sender) = message.sender_id() {
+        if inner_sender != source_id {
+            return Err(anyhow!("中继信封内层消息的发送者与源网关不一致"));
+        }
+    }
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_envelope_is_opaque_to_relay() {
+        let source = AgreementKeyPair::generate().unwrap();
+        let target = AgreementKeyPair::generate().unwrap();
+        let relay = AgreementKeyPair::generate().unwrap();
+        let (source_id, target_id, relay_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let inner = WdicMessage::file_transfer_data(
+            "transfer-1".to_string(),
+            0,
+            b"secret data".to_vec(),
+            true,
+        );
+        let envelope = seal_envelope(
+            &source,
+            source_id,
+            target_id,
+            &target.public_key_base64(),
+            relay_id,
+            &inner,
+        )
+        .unwrap();
+
+        let WdicMessage::RelayEnvelope {
+            source_public_key,
+            payload,
+            ..
+        } = envelope
+        else {
+            panic!("应当生成中继信封");
+        };
+        assert!(!payload.windows(11).any(|w| w == b"secret data"));
+
+        // 中继网关无法解密，改投给其他目标也无法通过认证
+        assert!(open_envelope(&relay, source_id, relay_id, &source_public_key, &payload).is_err());
+        assert!(open_envelope(&target, source_id, relay_id, &source_public_key, &payload).is_err());
+
+        let opened =
+            open_envelope(&target, source_id, target_id, &source_public_key, &payload).unwrap();
+        assert_eq!(opened, inner);
+    }
+
+    #[test]
+    fn test_relay_decision_and_bandwidth_cap() {
+        let registry = Registry::new("中继".to_string(), addr(42000));
+        let heartbeat = HeartbeatScheduler::new(Default::default());
+        let source = RegistryEntry::new("源".to_string(), addr(42001));
+        let target = RegistryEntry::new("目标".to_string(), addr(42002));
+        registry.add_or_update(source.clone());
+        registry.add_or_update(target.clone());
+
+        let disabled = RelayManager::new(RelayConfig::default());
+        assert!(matches!(
+            disabled.decide(
+                &registry,
+                &heartbeat,
+                source.id,
+                target.id,
+                source.address,
+                10
+            ),
+            RelayDecision::Reject {
+                code: RELAY_ERROR_DISABLED,
+                ..
+            }
+        ));
+
+        let relay = RelayManager::new(RelayConfig {
+            enabled: true,
+            max_bandwidth: 10_000,
+            max_session_bandwidth: 1_000,
+            ..Default::default()
+        });
+
+        // 目标尚未直接确认，视为不可达
+        relay.mark_direct(source.id);
+        assert!(matches!(
+            relay.decide(
+                &registry,
+                &heartbeat,
+                source.id,
+                target.id,
+                source.address,
+                10
+            ),
+            RelayDecision::Reject {
+                code: RELAY_ERROR_UNREACHABLE,
+                ..
+            }
+        ));
+
+        // 源地址与注册表不一致时拒绝
+        relay.mark_direct(target.id);
+        assert!(matches!(
+            relay.decide(&registry, &heartbeat, source.id, target.id, addr(42099), 10),
+            RelayDecision::Reject {
+                code: RELAY_ERROR_UNKNOWN_SOURCE,
+                ..
+            }
+        ));
+
+        assert_eq!(
+            relay.decide(
+                &registry,
+                &heartbeat,
+                source.id,
+                target.id,
+                source.address,
+                600
+            ),
+            RelayDecision::Forward(target.address)
+        );
+        assert_eq!(
+            relay.decide(
+                &registry,
+                &heartbeat,
+                source.id,
+                target.id,
+                source.address,
+                600
+            ),
+            RelayDecision::RateLimited
+        );
+        // 其他网关对不受该会话带宽影响
+        assert_eq!(
+            relay.decide(
+                &registry,
+                &heartbeat,
+                target.id,
+                source.id,
+                target.address,
+                600
+            ),
+            RelayDecision::Forward(source.address)
+        );
+
+        let stats = relay.stats();
+        assert_eq!(stats.forwarded_messages, 2);
+        assert_eq!(stats.forwarded_bytes, 1200);
+        assert_eq!(stats.rate_limited, 1);
+        assert_eq!(stats.rejected, 2);
+    }
+
+    #[test]
+    fn test_route_selection() {
+        let registry = Registry::new("本地".to_string(), addr(43000));
+        let relay_entry = RegistryEntry::new("中继".to_string(), addr(43001));
+        let target = RegistryEntry::new("目标".to_string(), addr(43002));
+        registry.add_or_update(relay_entry.clone());
+        registry.add_or_update(target.clone());
+
+        let manager = RelayManager::new(RelayConfig::default());
+        assert_eq!(
+            manager.route(&registry, &target),
+            PeerRoute::Direct(target.address)
+        );
+
+        manager.mark_direct(relay_entry.id);
+        manager.learn_routes(relay_entry.id, &[target.id]);
+        assert_eq!(
+            manager.route(&registry, &target),
+            PeerRoute::Relay {
+                relay_id: relay_entry.id,
+                relay_addr: relay_entry.address,
+            }
+        );
+
+        // 直接收到目标消息后改为直连
+        manager.mark_direct(target.id);
+        assert_eq!(
+            manager.route(&registry, &target),
+            PeerRoute::Direct(target.address)
+        );
+
+        manager.forget_peer(&relay_entry.id);
+        manager.forget_peer(&target.id);
+        assert_eq!(
+            manager.route(&registry, &target),
+            PeerRoute::Direct(target.address)
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! 签名搜索令牌模块
+//!
+//! 搜索令牌是自包含的：挂载点、路径模式、权限、有效期和被授权的网关都编码在令牌内，
+//! 并由签发网关使用从 CA 私钥派生的 HMAC 密钥签名。签发网关无需保存令牌即可离线验证，
+//! 需要在过期前撤销时将令牌 ID 加入吊销列表。
+//!
+//! 令牌格式：`wst1.<Base64URL 编码的令牌内容>.<Base64URL 编码的 HMAC-SHA256>`。
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine as _};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::gateway::mount::SearchToken;
+
+/// 令牌格式前缀，格式变化时递增
+pub const SEARCH_TOKEN_PREFIX: &str = "wst1";
+/// 从 CA 私钥派生签名密钥时使用的用途标签
+pub const SEARCH_TOKEN_KEY_LABEL: &[u8] = b"wdic-search-token-v1";
+/// 搜索令牌的最长有效期（秒）
+pub const MAX_SEARCH_TOKEN_TTL: u64 = 30 * 24 * 3600;
+/// 吊销列表文件格式版本
+const REVOCATIONS_VERSION: u32 = 1;
+
+/// 已吊销的搜索令牌
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedSearchToken {
+    /// 令牌 ID
+    pub token_id: String,
+    /// 令牌原本的过期时间，过期后吊销记录即可删除
+    pub expires_at: DateTime<Utc>,
+    /// 吊销时间
+    pub revoked_at: DateTime<Utc>,
+}
+
+/// 吊销列表文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct RevocationsFile {
+    /// 文件格式版本
+    version: u32,
+    /// 已吊销的令牌
+    revoked: Vec<RevokedSearchToken>,
+}
+
+/// 搜索令牌签发与验证器
+pub struct SearchTokenSigner {
+    /// 签发网关 ID，只接受本网关签发的令牌
+    issuer: Uuid,
+    /// HMAC 签名密钥
+    key: hmac::Key,
+    /// 吊销列表 (token_id -> 吊销记录)
+    revoked: DashMap<String, RevokedSearchToken>,
+    /// 吊销列表文件路径，为空时只保存在内存中
+    path: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for SearchTokenSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchTokenSigner")
+            .field("issuer", &self.issuer)
+            .field("revoked", &self.revoked.len())
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SearchTokenSigner {
+    /// 创建吊销列表为空的签发器
+    ///
+    /// # 参数
+    ///
+    /// * `issuer` - 签发网关 ID
+    /// * `key` - 签名密钥
+    /// * `path` - 吊销列表文件路径，为空时只保存在内存中
+    pub fn new(issuer: Uuid, key: &[u8], path: Option<PathBuf>) -> Self {
+        Self {
+            issuer,
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+            revoked: DashMap::new(),
+            path,
+        }
+    }
+
+    /// 使用随机密钥创建签发器，签发的令牌在进程退出后失效
+    pub fn ephemeral() -> Self {
+        Self::new(Uuid::nil(), &rand::random::<[u8; 32]>(), None)
+    }
+
+    /// 创建签发器并从文件加载吊销列表
+    ///
+    /// 文件不存在时从空列表开始，已过期的吊销记录在加载时丢弃。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 吊销列表文件路径
+    /// * `issuer` - 签发网关 ID
+    /// * `key` - 签名密钥
+    ///
+    /// # 返回值
+    ///
+    /// 加载后的签发器
+    pub fn load(path: PathBuf, issuer: Uuid, key: &[u8]) -> Result<Self> {
+        let signer = Self::new(issuer, key, Some(path.clone()));
+        if path.exists() {
+            let data = std::fs::read(&path).map_err(|e| anyhow!("读取令牌吊销列表失败: {e}"))?;
+            let file: RevocationsFile = serde_json::from_slice(&data)
+                .map_err(|e| anyhow!("反序列化令牌吊销列表失败: {e}"))?;
+            if file.version > REVOCATIONS_VERSION {
+                return Err(anyhow!("不支持的令牌吊销列表版本: {}", file.version));
+            }
+
+            let now = Utc::now();
+            for revoked in file.revoked.into_iter().filter(|r| r.expires_at > now) {
+                signer.revoked.insert(revoked.token_id.clone(), revoked);
+            }
+            info!(
+                "从 {:?} 加载了 {} 条搜索令牌吊销记录",
+                path,
+                signer.revoked.len()
+            );
+        } else {
+            debug!("令牌吊销列表文件不存在: {path:?}");
+        }
+
+        Ok(signer)
+    }
+
+    /// 获取签发网关 ID
+    pub fn issuer(&self) -> Uuid {
+        self.issuer
+    }
+
+    /// 签发令牌
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 令牌内容，签发者会被设置为本网关
+    ///
+    /// # 返回值
+    ///
+    /// 可交给其他网关出示的令牌字符串
+    pub fn sign(&self, token: &SearchToken) -> Result<String> {
+        let mut token = token.clone();
+        token.issuer = self.issuer;
+
+        let payload = serde_json::to_vec(&token).map_err(|e| anyhow!("序列化搜索令牌失败: {e}"))?;
+        let signed_part = format!("{SEARCH_TOKEN_PREFIX}.{}", BASE64_URL.encode(payload));
+        let tag = hmac::sign(&self.key, signed_part.as_bytes());
+        Ok(format!("{signed_part}.{}", BASE64_URL.encode(tag.as_ref())))
+    }
+
+    /// 验证令牌
+    ///
+    /// 依次检查格式、签名、签发者、有效期和吊销状态。
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 令牌字符串
+    ///
+    /// # 返回值
+    ///
+    /// 验证通过时返回令牌内容
+    pub fn verify(&self, token: &str) -> Result<SearchToken> {
+        let (signed_part, tag) = token
+            .rsplit_once('.')
+            .ok_or_else(|| anyhow!("搜索令牌格式无效"))?;
+        let payload = signed_part
+            .strip_prefix(SEARCH_TOKEN_PREFIX)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .ok_or_else(|| anyhow!("不支持的搜索令牌格式"))?;
+
+        let tag = BASE64_URL
+            .decode(tag)
+            .map_err(|_| anyhow!("搜索令牌签名编码无效"))?;
+        hmac::verify(&self.key, signed_part.as_bytes(), &tag)
+            .map_err(|_| anyhow!("搜索令牌签名无效"))?;
+
+        let payload = BASE64_URL
+            .decode(payload)
+            .map_err(|_| anyhow!("搜索令牌内容编码无效"))?;
+        let token: SearchToken =
+            serde_json::from_slice(&payload).map_err(|e| anyhow!("解析搜索令牌失败: {e}"))?;
+
+        if token.issuer != self.issuer {
+            return Err(anyhow!("搜索令牌不是由本网关签发的"));
+        }
+        if token.is_expired() {
+            return Err(anyhow!("搜索令牌已过期: {}", token.token_id));
+        }
+        if self.is_revoked(&token.token_id) {
+            return Err(anyhow!("搜索令牌已被吊销: {}", token.token_id));
+        }
+
+        Ok(token)
+    }
+
+    /// 吊销令牌
+    ///
+    /// # 参数
+    ///
+    /// * `token_id` - 令牌 ID
+    /// * `expires_at` - 令牌的过期时间，未知时按最长有效期保留吊销记录
+    ///
+    /// # 返回值
+    ///
+    /// 令牌此前未被吊销时返回 true
+    pub fn revoke(&self, token_id: &str, expires_at: Option<DateTime<Utc>>) -> Result<bool> {
+        if self.is_revoked(token_id) {
+            return Ok(false);
+        }
+
+        let now = Utc::now();
+        let expires_at = expires_at
+            .unwrap_or_else(|| now + chrono::Duration::seconds(MAX_SEARCH_TOKEN_TTL as i64));
+        self.revoked.insert(
+            token_id.to_string(),
+            RevokedSearchToken {
+                token_id: token_id.to_string(),
+                expires_at,
+                revoked_at: now,
+            },
+        );
+        self.save()?;
+
+        info!("吊销搜索令牌: {token_id}");
+        Ok(true)
+    }
+
+    /// 检查令牌是否已被吊销
+    pub fn is_revoked(&self, token_id: &str) -> bool {
+        self.revoked.contains_key(token_id)
+    }
+
+    /// 获取吊销列表，按吊销时间排序
+    pub fn revocations(&self) -> Vec<RevokedSearchToken> {
+        let mut revoked: Vec<RevokedSearchToken> = self
+            .revoked
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        revoked.sort_by_key(|r| r.revoked_at);
+        revoked
+    }
+
+    /// 删除已过期令牌的吊销记录
+    ///
+    /// # 返回值
+    ///
+    /// 删除的记录数
+    pub fn prune(&self) -> usize {
+        let now = Utc::now();
+        let before = self.revoked.len();
+        self.revoked.retain(|_, revoked| revoked.expires_at > now);
+        let removed = before - self.revoked.len();
+
+        if removed > 0 {
+            if let Err(e) = self.save() {
+                warn!("保存令牌吊销列表失败: {e}");
+            }
+        }
+        removed
+    }
+
+    /// 保存吊销列表到文件
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let file = RevocationsFile {
+            version: REVOCATIONS_VERSION,
+            revoked: self.revocations(),
+        };
+        let serialized =
+            serde_json::to_vec_pretty(&file).map_err(|e| anyhow!("序列化令牌吊销列表失败: {e}"))?;
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("创建令牌吊销列表目录失败: {e}"))?;
+            }
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serialized).map_err(|e| anyhow!("写入令牌吊销列表失败: {e}"))?;
+        std::fs::rename(&tmp_path, path).map_err(|e| anyhow!("替换令牌吊销列表失败: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(grantee: Option<Uuid>) -> SearchToken {
+        SearchToken::new(
+            Uuid::nil(),
+            grantee,
+            "mount".to_string(),
+            vec!["*.txt".to_string()],
+            vec!["read".to_string()],
+            60,
+        )
+    }
+
+    #[test]
+    fn test_signed_token_round_trip_and_tampering() {
+        let issuer = Uuid::new_v4();
+        let grantee = Uuid::new_v4();
+        let signer = SearchTokenSigner::new(issuer, b"0123456789abcdef0123456789abcdef", None);
+
+        let signed = signer.sign(&token(Some(grantee))).unwrap();
+        assert!(signed.starts_with("wst1."));
+        let verified = signer.verify(&signed).unwrap();
+        assert_eq!(verified.issuer, issuer);
+        assert_eq!(verified.grantee, Some(grantee));
+        assert_eq!(verified.allowed_patterns, vec!["*.txt".to_string()]);
+
+        // 篡改内容后签名失效
+        let (_, payload_and_tag) = signed.split_once('.').unwrap();
+        let (_, tag) = payload_and_tag.split_once('.').unwrap();
+        let mut forged = token(Some(grantee));
+        forged.issuer = issuer;
+        forged.permissions = vec!["*".to_string()];
+        let forged_payload = BASE64_URL.encode(serde_json::to_vec(&forged).unwrap());
+        assert!(signer
+            .verify(&format!("wst1.{forged_payload}.{tag}"))
+            .is_err());
+
+        // 其他网关的密钥无法验证
+        let other = SearchTokenSigner::new(issuer, b"another key for another gateway!", None);
+        assert!(other.verify(&signed).is_err());
+
+        // 已过期的令牌被拒绝
+        let mut expired = token(None);
+        expired.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        assert!(signer.verify(&signer.sign(&expired).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_revocation_list_persists() {
+        let path =
+            std::env::temp_dir().join(format!("wdic_token_revocations_{}.json", Uuid::new_v4()));
+        let issuer = Uuid::new_v4();
+        let key = b"0123456789abcdef0123456789abcdef";
+
+        let signer = SearchTokenSigner::load(path.clone(), issuer, key).unwrap();
+        let signed = signer.sign(&token(None)).unwrap();
+        let token_id = signer.verify(&signed).unwrap().token_id;
+
+        assert!(signer.revoke(&token_id, None).unwrap());
+        assert!(!signer.revoke(&token_id, None).unwrap());
+        assert!(signer.verify(&signed).is_err());
+
+        // 重启后吊销仍然有效
+        let reloaded = SearchTokenSigner::load(path.clone(), issuer, key).unwrap();
+        assert!(reloaded.is_revoked(&token_id));
+        assert!(reloaded.verify(&signed).is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
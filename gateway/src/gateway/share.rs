@@ -0,0 +1,606 @@
+//! 文件分享模块
+//!
+//! 将文件授权转换为分享：为被授权的文件或文件夹生成短链接，其他网关凭链接
+//! （以及可选的密码）读取分享内容。分享始终是只读的，带有有效期和可选的下载次数上限，
+//! 可以随时吊销。
+//!
+//! 链接格式：`wdic-share://<网关 ID>/<分享码>`。
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine as _};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::info;
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::gateway::mount::FileAuthorization;
+
+/// 分享链接前缀
+pub const SHARE_LINK_SCHEME: &str = "wdic-share://";
+/// 分享的默认有效期（秒）
+pub const DEFAULT_SHARE_TTL: u64 = 24 * 3600;
+/// 分享码的随机字节数
+const SHARE_CODE_BYTES: usize = 12;
+/// 分享密码的 PBKDF2 迭代次数
+const PASSWORD_ITERATIONS: u32 = 100_000;
+/// 密码分享允许的密码错误次数，用尽后分享被锁定
+pub const MAX_PASSWORD_FAILURES: u32 = 5;
+
+/// 创建分享的选项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareOptions {
+    /// 有效期（秒）
+    pub ttl_seconds: u64,
+    /// 最大下载次数，为空时不限次数
+    pub max_downloads: Option<u32>,
+    /// 访问密码，为空时只凭链接即可访问
+    pub password: Option<String>,
+}
+
+impl Default for ShareOptions {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: DEFAULT_SHARE_TTL,
+            max_downloads: None,
+            password: None,
+        }
+    }
+}
+
+/// 加盐哈希后的分享密码
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SharePassword {
+    /// 盐（Base64URL）
+    salt: String,
+    /// PBKDF2-HMAC-SHA256 哈希（Base64URL）
+    hash: String,
+}
+
+impl SharePassword {
+    /// 对密码加盐哈希
+    fn new(password: &str) -> Self {
+        let salt: [u8; 16] = rand::random();
+        let mut hash = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            Self::iterations(),
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        Self {
+            salt: BASE64_URL.encode(salt),
+            hash: BASE64_URL.encode(hash),
+        }
+    }
+
+    /// 以常量时间比较密码
+    fn verify(&self, password: &str) -> bool {
+        let (Ok(salt), Ok(hash)) = (BASE64_URL.decode(&self.salt), BASE64_URL.decode(&self.hash))
+        else {
+            return false;
+        };
+        pbkdf2::verify(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            Self::iterations(),
+            &salt,
+            password.as_bytes(),
+            &hash,
+        )
+        .is_ok()
+    }
+
+    fn iterations() -> NonZeroU32 {
+        NonZeroU32::new(PASSWORD_ITERATIONS).unwrap_or(NonZeroU32::MIN)
+    }
+}
+
+/// 文件或文件夹分享
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    /// 分享 ID
+    pub share_id: String,
+    /// 分享码，出现在链接中
+    pub code: String,
+    /// 来源文件授权 ID
+    pub auth_id: String,
+    /// 被分享的文件或文件夹
+    pub path: PathBuf,
+    /// 是否为文件夹
+    pub is_directory: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 过期时间
+    pub expires_at: DateTime<Utc>,
+    /// 最大下载次数，为空时不限次数
+    pub max_downloads: Option<u32>,
+    /// 已下载次数
+    pub download_count: u32,
+    /// 最近一次访问时间
+    pub last_accessed: Option<DateTime<Utc>>,
+    /// 访问密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password: Option<SharePassword>,
+    /// 密码错误次数
+    #[serde(default)]
+    password_failures: u32,
+}
+
+impl Share {
+    /// 检查分享是否过期
+    pub fn is_expired(&self) -> bool {
+        Utc::now() > self.expires_at
+    }
+
+    /// 剩余下载次数，为空时不限次数
+    pub fn remaining_downloads(&self) -> Option<u32> {
+        self.max_downloads
+            .map(|max| max.saturating_sub(self.download_count))
+    }
+
+    /// 是否仍可访问：未过期且下载次数未用完
+    pub fn is_available(&self) -> bool {
+        !self.is_expired() && self.remaining_downloads() != Some(0)
+    }
+
+    /// 是否设置了访问密码
+    pub fn has_password(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// 是否因密码错误次数过多而被锁定
+    pub fn is_locked(&self) -> bool {
+        self.password_failures >= MAX_PASSWORD_FAILURES
+    }
+}
+
+/// 分享概要，用于列出分享及其使用情况
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareSummary {
+    /// 分享 ID
+    pub share_id: String,
+    /// 分享链接
+    pub link: String,
+    /// 被分享的文件或文件夹
+    pub path: PathBuf,
+    /// 是否为文件夹
+    pub is_directory: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 过期时间
+    pub expires_at: DateTime<Utc>,
+    /// 最大下载次数，为空时不限次数
+    pub max_downloads: Option<u32>,
+    /// 已下载次数
+    pub download_count: u32,
+    /// 剩余下载次数，为空时不限次数
+    pub remaining_downloads: Option<u32>,
+    /// 是否设置了访问密码
+    pub has_password: bool,
+    /// 最近一次访问时间
+    pub last_accessed: Option<DateTime<Utc>>,
+}
+
+/// 兑现分享后得到的访问目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareAccess {
+    /// 读取文件，已计入下载次数
+    File {
+        /// 分享 ID
+        share_id: String,
+        /// 文件路径
+        path: PathBuf,
+    },
+    /// 列出文件夹，不计入下载次数
+    Directory {
+        /// 分享 ID
+        share_id: String,
+        /// 分享的根文件夹
+        root: PathBuf,
+        /// 要列出的文件夹
+        path: PathBuf,
+    },
+}
+
+/// 分享管理器
+#[derive(Debug)]
+pub struct ShareManager {
+    /// 本网关 ID，用于生成链接
+    local_id: Uuid,
+    /// 分享存储 (分享码 -> Share)
+    shares: DashMap<String, Share>,
+}
+
+impl ShareManager {
+    /// 创建分享管理器
+    ///
+    /// # 参数
+    ///
+    /// * `local_id` - 本网关 ID
+    pub fn new(local_id: Uuid) -> Self {
+        Self {
+            local_id,
+            shares: DashMap::new(),
+        }
+    }
+
+    /// 由文件授权创建分享
+    ///
+    /// # 参数
+    ///
+    /// * `authorization` - 文件授权，必须处于激活状态并包含读取权限
+    /// * `options` - 有效期、下载次数上限和密码
+    ///
+    /// # 返回值
+    ///
+    /// 新建的分享
+    pub fn create_share(
+        &self,
+        authorization: &FileAuthorization,
+        options: ShareOptions,
+    ) -> Result<Share> {
+        if !authorization.is_active {
+            return Err(anyhow!("文件授权已停用: {}", authorization.auth_id));
+        }
+        if !authorization
+            .permissions
+            .iter()
+            .any(|granted| granted == "read" || granted == "*")
+        {
+            return Err(anyhow!("文件授权未包含读取权限: {}", authorization.auth_id));
+        }
+        if options.ttl_seconds == 0 {
+            return Err(anyhow!("分享有效期不能为 0"));
+        }
+        if options.max_downloads == Some(0) {
+            return Err(anyhow!("最大下载次数不能为 0"));
+        }
+
+        let metadata = std::fs::metadata(&authorization.file_path)
+            .map_err(|e| anyhow!("无法访问被分享的路径: {e}"))?;
+
+        let now = Utc::now();
+        let share = Share {
+            share_id: Uuid::new_v4().to_string(),
+            code: BASE64_URL.encode(rand::random::<[u8; SHARE_CODE_BYTES]>()),
+            auth_id: authorization.auth_id.clone(),
+            path: authorization.file_path.clone(),
+            is_directory: metadata.is_dir(),
+            created_at: now,
+            expires_at: now + chrono::Duration::seconds(options.ttl_seconds as i64),
+            max_downloads: options.max_downloads,
+            download_count: 0,
+            last_accessed: None,
+            password: options
+                .password
+                .filter(|password| !password.is_empty())
+                .map(|password| SharePassword::new(&password)),
+            password_failures: 0,
+        };
+        // 顺带清理已过期或用尽下载次数的分享
+        self.prune();
+        self.shares.insert(share.code.clone(), share.clone());
+
+        info!("创建分享 {}: {:?}", share.share_id, share.path);
+        Ok(share)
+    }
+
+    /// 生成分享链接
+    pub fn link(&self, share: &Share) -> String {
+        format!("{SHARE_LINK_SCHEME}{}/{}", self.local_id, share.code)
+    }
+
+    /// 解析分享链接
+    ///
+    /// # 参数
+    ///
+    /// * `link` - 分享链接
+    ///
+    /// # 返回值
+    ///
+    /// 分享所在的网关 ID 和分享码
+    pub fn parse_link(link: &str) -> Result<(Uuid, String)> {
+        let (gateway_id, code) = link
+            .trim()
+            .strip_prefix(SHARE_LINK_SCHEME)
+            .and_then(|rest| rest.split_once('/'))
+            .ok_or_else(|| anyhow!("无效的分享链接: {link}"))?;
+        let gateway_id =
+            Uuid::parse_str(gateway_id).map_err(|e| anyhow!("分享链接中的网关 ID 无效: {e}"))?;
+        if code.is_empty() || code.contains('/') {
+            return Err(anyhow!("无效的分享码: {code}"));
+        }
+        Ok((gateway_id, code.to_string()))
+    }
+
+    /// 兑现分享
+    ///
+    /// 读取文件时计入下载次数，列出文件夹不计入。密码错误累计达到
+    /// [`MAX_PASSWORD_FAILURES`] 次后分享被锁定，之后即使密码正确也拒绝访问。
+    ///
+    /// # 参数
+    ///
+    /// * `code` - 分享码
+    /// * `password` - 访问密码
+    /// * `relative_path` - 文件夹分享中的相对路径，为空时指分享本身
+    ///
+    /// # 返回值
+    ///
+    /// 分享不存在、已过期、次数用完、已锁定、密码错误或路径超出分享范围时返回错误
+    pub fn redeem(
+        &self,
+        code: &str,
+        password: Option<&str>,
+        relative_path: Option<&str>,
+    ) -> Result<ShareAccess> {
+        // 密码校验和文件系统访问较慢，在副本上进行，避免长时间持有分享表的锁
+        let share = self
+            .shares
+            .get(code)
+            .map(|share| share.clone())
+            .ok_or_else(|| anyhow!("分享不存在或已被吊销"))?;
+
+        Self::check_available(&share)?;
+        if let Some(expected) = &share.password {
+            if !password.is_some_and(|password| expected.verify(password)) {
+                if let Some(mut share) = self.shares.get_mut(code) {
+                    share.password_failures += 1;
+                }
+                return Err(anyhow!("分享密码错误"));
+            }
+        }
+        let target = Self::resolve(&share, relative_path.unwrap_or_default())?;
+        let is_dir = target.is_dir();
+
+        // 重新获取分享并再次检查，期间可能已被吊销、锁定或用尽下载次数
+        let mut share = self
+            .shares
+            .get_mut(code)
+            .ok_or_else(|| anyhow!("分享不存在或已被吊销"))?;
+        Self::check_available(&share)?;
+        share.last_accessed = Some(Utc::now());
+
+        if is_dir {
+            return Ok(ShareAccess::Directory {
+                share_id: share.share_id.clone(),
+                root: share.path.clone(),
+                path: target,
+            });
+        }
+
+        share.download_count += 1;
+        info!(
+            "分享 {} 被下载 ({}/{})",
+            share.share_id,
+            share.download_count,
+            share
+                .max_downloads
+                .map_or_else(|| "不限".to_string(), |max| max.to_string())
+        );
+        Ok(ShareAccess::File {
+            share_id: share.share_id.clone(),
+            path: target,
+        })
+    }
+
+    /// 检查分享是否仍可兑现
+    fn check_available(share: &Share) -> Result<()> {
+        if share.is_expired() {
+            return Err(anyhow!("分享已过期"));
+        }
+        if share.remaining_downloads() == Some(0) {
+            return Err(anyhow!("分享的下载次数已用完"));
+        }
+        if share.is_locked() {
+            return Err(anyhow!("分享因密码错误次数过多已被锁定"));
+        }
+        Ok(())
+    }
+
+    /// 将分享中的相对路径解析为本地路径，并确保不超出分享范围
+    fn resolve(share: &Share, relative_path: &str) -> Result<PathBuf> {
+        let relative_path = relative_path.trim_start_matches('/');
+        if relative_path.is_empty() {
+            return Ok(share.path.clone());
+        }
+        if !share.is_directory {
+            return Err(anyhow!("文件分享不包含子路径"));
+        }
+
+        let target = std::fs::canonicalize(share.path.join(relative_path))
+            .map_err(|_| anyhow!("分享中不存在该路径: {relative_path}"))?;
+        if !target.starts_with(&share.path) {
+            return Err(anyhow!("路径超出分享范围: {relative_path}"));
+        }
+        Ok(target)
+    }
+
+    /// 吊销分享
+    ///
+    /// # 参数
+    ///
+    /// * `share_id` - 分享 ID
+    ///
+    /// # 返回值
+    ///
+    /// 被吊销的分享
+    pub fn revoke(&self, share_id: &str) -> Result<Share> {
+        let code = self
+            .shares
+            .iter()
+            .find(|entry| entry.value().share_id == share_id)
+            .map(|entry| entry.key().clone())
+            .ok_or_else(|| anyhow!("分享不存在: {share_id}"))?;
+        let (_, share) = self
+            .shares
+            .remove(&code)
+            .ok_or_else(|| anyhow!("分享不存在: {share_id}"))?;
+
+        info!("吊销分享 {share_id}: {:?}", share.path);
+        Ok(share)
+    }
+
+    /// 获取仍可访问的分享及其使用情况，按创建时间排序
+    pub fn list_active(&self) -> Vec<ShareSummary> {
+        let mut shares: Vec<ShareSummary> = self
+            .shares
+            .iter()
+            .filter(|entry| entry.value().is_available())
+            .map(|entry| self.summary(entry.value()))
+            .collect();
+        shares.sort_by_key(|share| share.created_at);
+        shares
+    }
+
+    /// 生成分享概要
+    pub fn summary(&self, share: &Share) -> ShareSummary {
+        ShareSummary {
+            share_id: share.share_id.clone(),
+            link: self.link(share),
+            path: share.path.clone(),
+            is_directory: share.is_directory,
+            created_at: share.created_at,
+            expires_at: share.expires_at,
+            max_downloads: share.max_downloads,
+            download_count: share.download_count,
+            remaining_downloads: share.remaining_downloads(),
+            has_password: share.has_password(),
+            last_accessed: share.last_accessed,
+        }
+    }
+
+    /// 删除已过期或下载次数用完的分享
+    ///
+    /// # 返回值
+    ///
+    /// 删除的分享数量
+    pub fn prune(&self) -> usize {
+        let before = self.shares.len();
+        self.shares.retain(|_, share| share.is_available());
+        before - self.shares.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn authorization(path: &Path) -> FileAuthorization {
+        FileAuthorization::new(
+            std::fs::canonicalize(path).unwrap(),
+            "share".to_string(),
+            vec!["read".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_file_share_download_limit_and_password() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("report.txt");
+        std::fs::write(&file, "内容").unwrap();
+
+        let manager = ShareManager::new(Uuid::new_v4());
+        let share = manager
+            .create_share(
+                &authorization(&file),
+                ShareOptions {
+                    max_downloads: Some(2),
+                    password: Some("secret".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let (gateway_id, code) = ShareManager::parse_link(&manager.link(&share)).unwrap();
+        assert_eq!(gateway_id, manager.local_id);
+        assert_eq!(code, share.code);
+
+        // 密码错误或缺失时拒绝，且不计入下载次数
+        assert!(manager.redeem(&code, None, None).is_err());
+        assert!(manager.redeem(&code, Some("wrong"), None).is_err());
+        assert!(manager
+            .redeem(&code, Some("secret"), Some("other.txt"))
+            .is_err());
+
+        for _ in 0..2 {
+            let access = manager.redeem(&code, Some("secret"), None).unwrap();
+            assert!(matches!(access, ShareAccess::File { .. }));
+        }
+        assert!(manager.redeem(&code, Some("secret"), None).is_err());
+        assert!(manager.list_active().is_empty());
+        assert_eq!(manager.prune(), 1);
+    }
+
+    #[test]
+    fn test_password_share_locks_after_repeated_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("report.txt");
+        std::fs::write(&file, "内容").unwrap();
+
+        let manager = ShareManager::new(Uuid::new_v4());
+        let share = manager
+            .create_share(
+                &authorization(&file),
+                ShareOptions {
+                    password: Some("secret".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        for _ in 0..MAX_PASSWORD_FAILURES {
+            assert!(manager.redeem(&share.code, Some("guess"), None).is_err());
+        }
+
+        // 锁定后正确的密码也被拒绝，且不计入下载次数
+        let error = manager
+            .redeem(&share.code, Some("secret"), None)
+            .unwrap_err();
+        assert!(error.to_string().contains("锁定"));
+        let locked = manager.shares.get(&share.code).unwrap().clone();
+        assert!(locked.is_locked());
+        assert_eq!(locked.download_count, 0);
+    }
+
+    #[test]
+    fn test_folder_share_scope_and_revoke() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path().join("shared");
+        std::fs::create_dir_all(folder.join("sub")).unwrap();
+        std::fs::write(folder.join("sub/a.txt"), "a").unwrap();
+        std::fs::write(temp_dir.path().join("secret.txt"), "s").unwrap();
+
+        let manager = ShareManager::new(Uuid::new_v4());
+        let share = manager
+            .create_share(&authorization(&folder), ShareOptions::default())
+            .unwrap();
+        assert!(share.is_directory);
+
+        // 列出文件夹不计入下载次数，读取文件计入
+        let access = manager.redeem(&share.code, None, Some("sub")).unwrap();
+        assert!(matches!(access, ShareAccess::Directory { .. }));
+        let access = manager
+            .redeem(&share.code, None, Some("/sub/a.txt"))
+            .unwrap();
+        assert!(matches!(access, ShareAccess::File { .. }));
+        assert!(manager
+            .redeem(&share.code, None, Some("../secret.txt"))
+            .is_err());
+
+        let summary = &manager.list_active()[0];
+        assert_eq!(summary.download_count, 1);
+        assert_eq!(summary.remaining_downloads, None);
+        assert!(!summary.has_password);
+
+        manager.revoke(&share.share_id).unwrap();
+        assert!(manager.redeem(&share.code, None, None).is_err());
+        assert!(manager.list_active().is_empty());
+    }
+}
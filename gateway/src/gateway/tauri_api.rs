@@ -12,13 +12,25 @@
 //! 所有接口都遵循 Tauri 的最佳实践，提供异步支持和错误处理。
 
 use crate::gateway::{
+    access_control::{AccessDecision, AccessGroup, RuleEffect},
+    audit::{AuditEntry, AuditFilter, AuditLog, AuditVerification},
     cache::GatewayCache,
+    cert_monitor::CertificateEvent,
     compression::CompressionStatsSnapshot,
-    gateway::{Gateway, GatewayConfig},
+    gateway::{Gateway, GatewayConfig, RevocationEvent, SessionEvent},
+    heartbeat::{PeerHealth, PeerStateChange},
+    identity_bundle::{BundleFormat, IdentityBundle, IdentityBundleSummary},
+    mount::SearchToken,
+    mount_rules::MountRules,
+    nat::HolePunchStats,
     network::NetworkManager,
+    pairing::{PairingSession, TrustedDevice},
     performance::{PerformanceMonitor, PerformanceReport},
     registry::Registry,
+    relay::RelayStats,
     security::SecurityManager,
+    share::{ShareOptions, ShareSummary},
+    tls::{NegotiatedTls, Revocation},
 };
 use tokio::sync::RwLock;
 
@@ -35,7 +47,7 @@ use tauri::{command, AppHandle, Emitter};
 use uuid::Uuid;
 
 /// 事件发射器 - 用于向前端发送事件
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EventEmitter {
     app_handle: AppHandle,
 }
@@ -73,6 +85,34 @@ impl EventEmitter {
             .map_err(|e| format!("发送异常事件失败: {e}"))
     }
 
+    /// 发送证书有效期事件
+    pub fn emit_certificate_event(&self, event: &CertificateEvent) -> Result<(), String> {
+        self.app_handle
+            .emit("certificate-event", event)
+            .map_err(|e| format!("发送证书事件失败: {e}"))
+    }
+
+    /// 发送设备吊销事件
+    pub fn emit_device_revoked(&self, event: &RevocationEvent) -> Result<(), String> {
+        self.app_handle
+            .emit("device-revoked", event)
+            .map_err(|e| format!("发送设备吊销事件失败: {e}"))
+    }
+
+    /// 发送 QUIC 握手被拒绝事件
+    pub fn emit_tls_handshake_rejected(&self, event: &SessionEvent) -> Result<(), String> {
+        self.app_handle
+            .emit("tls-handshake-rejected", event)
+            .map_err(|e| format!("发送握手拒绝事件失败: {e}"))
+    }
+
+    /// 发送网关存活状态变更事件
+    pub fn emit_peer_state_changed(&self, change: &PeerStateChange) -> Result<(), String> {
+        self.app_handle
+            .emit("peer-state-changed", change)
+            .map_err(|e| format!("发送网关状态变更事件失败: {e}"))
+    }
+
     /// 发送缓存统计更新事件
     pub fn emit_cache_stats_updated(&self, stats: serde_json::Value) -> Result<(), String> {
         self.app_handle
@@ -116,7 +156,7 @@ pub async fn initialize_event_emitter(app_handle: AppHandle) -> Result<(), Strin
 #[derive(Debug)]
 pub struct GlobalGatewayState {
     /// 网关实例
-    pub gateway: Arc<RwLock<Option<Gateway>>>,
+    pub gateway: Arc<RwLock<Option<Arc<Gateway>>>>,
     /// 性能监控器
     pub performance_monitor: Arc<PerformanceMonitor>,
     /// 缓存管理器
@@ -131,6 +171,8 @@ pub struct GlobalGatewayState {
     pub event_emitter: Option<EventEmitter>,
     /// 数据传输请求存储
     pub transfer_requests: Arc<RwLock<HashMap<String, DataTransferRequest>>>,
+    /// 审计日志（网关未运行时记录规则变更等安全事件）
+    pub audit_log: Arc<AuditLog>,
 }
 
 impl GlobalGatewayState {
@@ -139,6 +181,15 @@ impl GlobalGatewayState {
         let performance_monitor = Arc::new(PerformanceMonitor::new());
         let cache = Arc::new(GatewayCache::new("./cache", 3600, 1024 * 1024 * 1024)?); // 1GB 缓存
         let security_manager = Arc::new(SecurityManager::new().await?);
+
+        // 与网关共用同一审计日志文件，测试时写入临时文件
+        let audit_log_path = if cfg!(test) {
+            std::env::temp_dir().join(format!("wdic_audit_{}.jsonl", Uuid::new_v4()))
+        } else {
+            GatewayConfig::default().audit_log_path
+        };
+        let audit_log = AuditLog::shared(audit_log_path)?;
+        security_manager.set_audit_log(Arc::clone(&audit_log));
         
         // 使用默认地址创建网络管理器
         let default_addr = "0.0.0.0:0".parse().unwrap();
@@ -156,6 +207,7 @@ impl GlobalGatewayState {
             registry,
             event_emitter: None,
             transfer_requests: Arc::new(RwLock::new(HashMap::new())),
+            audit_log,
         })
     }
 }
@@ -205,6 +257,8 @@ pub struct NetworkInterface {
     pub is_active: bool,
     /// 接口类型
     pub interface_type: String,
+    /// 是否按接口策略参与发现广播和监听
+    pub discovery_enabled: bool,
 }
 
 /// 目录挂载信息
@@ -224,6 +278,22 @@ pub struct MountPoint {
     pub file_count: u64,
     /// 总大小（字节）
     pub total_size: u64,
+    /// 挂载状态，启动时重新校验
+    #[serde(default)]
+    pub status: MountStatus,
+    /// 过滤规则，决定哪些文件可以被索引、搜索、列出和读取
+    #[serde(default)]
+    pub rules: MountRules,
+}
+
+/// 挂载状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MountStatus {
+    /// 可用
+    #[default]
+    Available,
+    /// 本地路径已不存在或不再是目录，挂载点保留但不可访问
+    Missing,
 }
 
 /// 文件传输任务
@@ -290,14 +360,20 @@ pub struct AccessRule {
     pub id: String,
     /// 规则名称
     pub name: String,
-    /// 客户端 IP 或主机名
+    /// 规则主体：`*`、IP 地址、CIDR 网段、`device:<网关 ID>` 或 `group:<分组名>`
     pub client: String,
-    /// 允许的路径
+    /// 规则适用的路径，不含通配符时匹配该路径及其子路径，否则按 glob 模式匹配
     pub allowed_paths: Vec<String>,
-    /// 权限（read, write, admin）
+    /// 规则适用的操作（read, write, admin），`*` 表示任意操作
     pub permissions: Vec<String>,
     /// 是否启用
     pub enabled: bool,
+    /// 规则效果
+    #[serde(default)]
+    pub effect: RuleEffect,
+    /// 优先级，数值越小越先求值
+    #[serde(default = "crate::gateway::access_control::default_rule_priority")]
+    pub priority: i32,
 }
 
 // ============================================================================
@@ -321,6 +397,14 @@ pub async fn start_gateway(config: GatewayConfig) -> Result<(), String> {
         .await
         .map_err(|e| format!("网关创建失败: {e}"))?;
 
+    // 远程操作按前端管理的访问规则授权
+    gateway
+        .authorizer()
+        .set_security_manager(Arc::clone(&_state.security_manager));
+    _state
+        .security_manager
+        .set_audit_log(Arc::clone(gateway.audit_log()));
+
     // 启动网络管理器和UDP广播管理器（非阻塞）
     gateway.network_manager().start().await
         .map_err(|e| format!("网络管理器启动失败: {e}"))?;
@@ -331,60 +415,127 @@ pub async fn start_gateway(config: GatewayConfig) -> Result<(), String> {
     // 设置运行状态为true
     *gateway.running().lock().await = true;
 
-    // 克隆必要的Arc引用用于后台任务
-    let registry_clone = Arc::clone(gateway.registry());
-    let network_clone = Arc::clone(gateway.network_manager());
-    let udp_clone = Arc::clone(gateway.udp_broadcast_manager());
-    let config_clone = gateway.config().clone();
-    let running_clone = Arc::clone(gateway.running());
-    let cache_clone = Arc::clone(gateway.cache());
+    let gateway = Arc::new(gateway);
 
     // 启动初始广播
     if let Err(e) = gateway.initial_broadcast().await {
         warn!("初始广播失败: {e}");
     }
 
-    // 在后台启动网关主循环
+    // 重新探测上次运行时已知的网关
+    gateway.probe_known_peers().await;
+
+    // 启动定期任务（广播、心跳、注册表和缓存清理）
+    gateway.spawn_background_tasks();
+
+    // 设备被吊销时立即断开其活跃会话，并通知前端
+    let mut revocation_receiver = gateway.subscribe_revocations();
+    let security_manager = Arc::clone(&_state.security_manager);
+    let revocation_emitter = _state.event_emitter.clone();
+    let running_revocations = Arc::clone(gateway.running());
     tokio::spawn(async move {
-        // 启动定期任务
-        let broadcast_handle = tokio::spawn(Gateway::broadcast_task(
-            Arc::clone(&registry_clone),
-            Arc::clone(&network_clone),
-            Arc::clone(&udp_clone),
-            Arc::clone(&cache_clone),
-            config_clone.clone(),
-            Arc::clone(&running_clone),
-        ));
-
-        // 启动事件循环
-        if let Some(mut event_receiver) = network_clone.take_event_receiver().await {
-            if let Some(mut udp_event_receiver) = udp_clone.take_event_receiver().await {
-                // 使用现有的网关实例处理事件，而不是创建临时实例
-                // 因为事件处理主要依赖网络管理器和UDP广播管理器
-                info!("网关事件循环启动成功");
-                
-                // 简单的事件处理循环
-                loop {
-                    tokio::select! {
-                        Some(network_event) = event_receiver.recv() => {
-                            info!("收到网络事件: {network_event:?}");
-                            // 这里可以添加具体的事件处理逻辑
+        while *running_revocations.lock().await {
+            match revocation_receiver.recv().await {
+                Ok(event) => {
+                    let disconnected =
+                        security_manager.revoke_client(&event.revocation, &event.addresses);
+                    if disconnected > 0 {
+                        info!("设备已被吊销，断开了 {disconnected} 个会话");
+                    }
+                    if let Some(emitter) = &revocation_emitter {
+                        if let Err(e) = emitter.emit_device_revoked(&event) {
+                            warn!("{e}");
+                        }
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("吊销事件积压，丢弃 {skipped} 条");
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // 握手被拒绝时通知前端（握手完成的连接由网关自动记录为活跃会话）
+    if let Some(session_emitter) = _state.event_emitter.clone() {
+        let mut session_receiver = gateway.subscribe_sessions();
+        let running_sessions = Arc::clone(gateway.running());
+        tokio::spawn(async move {
+            while *running_sessions.lock().await {
+                match session_receiver.recv().await {
+                    Ok(event @ SessionEvent::Rejected { .. }) => {
+                        if let Err(e) = session_emitter.emit_tls_handshake_rejected(&event) {
+                            warn!("{e}");
                         }
-                        Some(udp_event) = udp_event_receiver.recv() => {
-                            info!("收到UDP广播事件: {udp_event:?}");
-                            // 这里可以添加具体的事件处理逻辑
+                    }
+                    Ok(SessionEvent::Established { .. }) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("会话事件积压，丢弃 {skipped} 条");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // 将网关存活状态变更和证书事件转发到前端
+    if let Some(emitter) = _state.event_emitter.clone() {
+        let certificate_emitter = emitter.clone();
+        let mut state_receiver = gateway.heartbeat_scheduler().subscribe();
+        let running_events = Arc::clone(gateway.running());
+        tokio::spawn(async move {
+            while *running_events.lock().await {
+                match state_receiver.recv().await {
+                    Ok(change) => {
+                        if let Err(e) = emitter.emit_peer_state_changed(&change) {
+                            warn!("{e}");
                         }
-                        else => {
-                            // 两个接收器都关闭了，退出循环
-                            break;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("网关状态事件积压，丢弃 {skipped} 条");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        // 将证书到期警告和轮换结果转发到前端
+        let mut certificate_receiver = gateway.cert_monitor().subscribe();
+        let running_certificates = Arc::clone(gateway.running());
+        tokio::spawn(async move {
+            while *running_certificates.lock().await {
+                match certificate_receiver.recv().await {
+                    Ok(event) => {
+                        if let Err(e) = certificate_emitter.emit_certificate_event(&event) {
+                            warn!("{e}");
                         }
                     }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("证书事件积压，丢弃 {skipped} 条");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
                 }
             }
-        }
+        });
+    }
 
-        // 等待广播任务完成
-        broadcast_handle.await.ok();
+    // 在后台启动网关主事件循环
+    let gateway_loop = Arc::clone(&gateway);
+    tokio::spawn(async move {
+        let network_receiver = gateway_loop.network_manager().take_event_receiver().await;
+        let udp_receiver = gateway_loop.udp_broadcast_manager().take_event_receiver().await;
+
+        if let (Some(mut event_receiver), Some(mut udp_event_receiver)) = (network_receiver, udp_receiver) {
+            info!("网关事件循环启动成功");
+            if let Err(e) = gateway_loop
+                .event_loop(&mut event_receiver, &mut udp_event_receiver)
+                .await
+            {
+                warn!("网关事件循环异常退出: {e}");
+            }
+        } else {
+            warn!("无法获取网关事件接收器，事件循环未启动");
+        }
     });
 
     *gateway_lock = Some(gateway);
@@ -401,9 +552,14 @@ pub async fn stop_gateway() -> Result<String, String> {
         // 获取网关的写入锁
         let mut _gateway = state.gateway.write().await;
         
-        // 停止服务
-        *_gateway = None;
-        
+        // 停止服务（同时保存已知网关）
+        if let Some(gateway) = _gateway.take() {
+            if let Err(e) = gateway.stop().await {
+                warn!("停止网关时出错: {e}");
+            }
+        }
+        state.security_manager.set_audit_log(Arc::clone(&state.audit_log));
+
         Ok("网关服务已停止".to_string())
     } else {
         Err("网关服务未初始化".to_string())
@@ -521,11 +677,14 @@ pub async fn reset_to_default_config() -> Result<GatewayConfig, String> {
 // ============================================================================
 
 /// 挂载目录
+///
+/// 未指定过滤规则时使用默认规则。
 #[command]
 pub async fn mount_directory(
     local_path: PathBuf,
     mount_name: String,
     read_only: bool,
+    rules: Option<MountRules>,
 ) -> Result<String, String> {
     
     // 验证路径存在
@@ -556,6 +715,8 @@ pub async fn mount_directory(
         mount_time: Utc::now(),
         file_count: 0, // 初始值，挂载时会计算实际值
         total_size: 0, // 初始值，挂载时会计算实际值
+        status: MountStatus::Available,
+        rules: rules.unwrap_or_default(),
     };
     
     // 通过网关的挂载管理器进行挂载
@@ -609,6 +770,47 @@ pub async fn get_mount_points() -> Result<Vec<MountPoint>, String> {
     }
 }
 
+/// 重新校验挂载点
+///
+/// 路径重新出现的挂载点恢复为可用，已消失的标记为缺失。
+#[command]
+pub async fn revalidate_mounts() -> Result<Vec<MountPoint>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        let mut mount_points = gateway.mount_manager().revalidate_mounts();
+        mount_points.sort_by_key(|mount| std::cmp::Reverse(mount.mount_time));
+        Ok(mount_points)
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
+/// 修改挂载点的过滤规则
+///
+/// 目录索引按新规则重新生成。
+#[command]
+pub async fn set_mount_rules(mount_id: String, rules: MountRules) -> Result<MountPoint, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway
+            .mount_manager()
+            .set_mount_rules(&mount_id, rules)
+            .map_err(|e| format!("修改挂载规则失败: {e}"))
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
 /// 列出目录内容
 #[command]
 pub async fn list_directory(mount_id: String, path: String) -> Result<Vec<DirectoryEntry>, String> {
@@ -630,7 +832,7 @@ pub async fn list_directory(mount_id: String, path: String) -> Result<Vec<Direct
 }
 
 /// 目录条目
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DirectoryEntry {
     /// 名称
     pub name: String,
@@ -648,29 +850,36 @@ pub struct DirectoryEntry {
     pub file_type: String,
 }
 
-/// 创建搜索令牌
+/// 创建签名搜索令牌
+///
+/// 返回的令牌可交给其他网关，对端在搜索、列目录和读取请求中出示以证明访问权限。
+/// 指定 `grantee_id` 时只有该网关可以出示令牌。
 #[command]
 pub async fn create_search_token(
     mount_id: String,
     patterns: Vec<String>,
     permissions: Vec<String>,
     ttl_seconds: u64,
+    grantee_id: Option<String>,
 ) -> Result<String, String> {
     ensure_global_state().await?;
     
     let global_state = GLOBAL_STATE.lock().await;
     let state = global_state.as_ref().unwrap();
+
+    let grantee = grantee_id.as_deref().map(parse_uuid).transpose()?;
     
     let gateway_lock = state.gateway.read().await;
     if let Some(gateway) = gateway_lock.as_ref() {
-        let token_id = gateway.mount_manager().create_search_token(
+        let token = gateway.mount_manager().create_search_token(
             mount_id,
             patterns,
             permissions,
             ttl_seconds,
+            grantee,
         ).await
         .map_err(|e| format!("创建搜索令牌失败: {e}"))?;
-        Ok(token_id)
+        Ok(token)
     } else {
         Err("网关未初始化".to_string())
     }
@@ -678,7 +887,7 @@ pub async fn create_search_token(
 
 /// 验证搜索令牌
 #[command]
-pub async fn validate_search_token(token_id: String, path: String) -> Result<bool, String> {
+pub async fn validate_search_token(token: String, path: String) -> Result<bool, String> {
     ensure_global_state().await?;
     
     let global_state = GLOBAL_STATE.lock().await;
@@ -686,7 +895,7 @@ pub async fn validate_search_token(token_id: String, path: String) -> Result<boo
     
     let gateway_lock = state.gateway.read().await;
     if let Some(gateway) = gateway_lock.as_ref() {
-        let is_authorized = gateway.mount_manager().validate_search_token(&token_id, &path)
+        let is_authorized = gateway.mount_manager().validate_search_token(&token, &path)
             .await
             .map_err(|e| format!("验证搜索令牌失败: {e}"))?;
         Ok(is_authorized)
@@ -695,6 +904,41 @@ pub async fn validate_search_token(token_id: String, path: String) -> Result<boo
     }
 }
 
+/// 获取本网关签发且仍然有效的搜索令牌
+#[command]
+pub async fn list_search_tokens() -> Result<Vec<SearchToken>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.mount_manager().list_search_tokens())
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
+/// 吊销搜索令牌
+///
+/// 令牌加入吊销列表后，即使尚未过期也无法再通过验证。
+#[command]
+pub async fn revoke_search_token(token_id: String) -> Result<bool, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.mount_manager().revoke_search_token(&token_id)
+            .map_err(|e| format!("吊销搜索令牌失败: {e}"))
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
 /// 文件授权
 #[command]
 pub async fn authorize_file(
@@ -723,7 +967,7 @@ pub async fn authorize_file(
 
 /// 通过搜索令牌获取元数据
 #[command]
-pub async fn get_metadata_by_token(token_id: String) -> Result<Vec<std::collections::HashMap<String, String>>, String> {
+pub async fn get_metadata_by_token(token: String) -> Result<Vec<std::collections::HashMap<String, String>>, String> {
     ensure_global_state().await?;
     
     let global_state = GLOBAL_STATE.lock().await;
@@ -731,7 +975,7 @@ pub async fn get_metadata_by_token(token_id: String) -> Result<Vec<std::collecti
     
     let gateway_lock = state.gateway.read().await;
     if let Some(gateway) = gateway_lock.as_ref() {
-        let metadata = gateway.mount_manager().get_metadata_by_token(&token_id)
+        let metadata = gateway.mount_manager().get_metadata_by_token(&token)
             .await
             .map_err(|e| format!("获取元数据失败: {e}"))?;
         Ok(metadata)
@@ -740,6 +984,91 @@ pub async fn get_metadata_by_token(token_id: String) -> Result<Vec<std::collecti
     }
 }
 
+/// 将文件授权转换为分享链接
+///
+/// 分享为只读，可设置有效期、最大下载次数和访问密码。
+#[command]
+pub async fn create_share(
+    auth_id: String,
+    options: Option<ShareOptions>,
+) -> Result<ShareSummary, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        let authorization = gateway.mount_manager().get_file_authorization(&auth_id)
+            .ok_or_else(|| format!("文件授权不存在: {auth_id}"))?;
+        let share = gateway.share_manager()
+            .create_share(&authorization, options.unwrap_or_default())
+            .map_err(|e| format!("创建分享失败: {e}"))?;
+        Ok(gateway.share_manager().summary(&share))
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
+/// 获取仍然有效的分享及其使用计数
+#[command]
+pub async fn list_shares() -> Result<Vec<ShareSummary>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.share_manager().list_active())
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
+/// 撤销分享
+#[command]
+pub async fn revoke_share(share_id: String) -> Result<ShareSummary, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        let share = gateway.share_manager().revoke(&share_id)
+            .map_err(|e| format!("撤销分享失败: {e}"))?;
+        Ok(gateway.share_manager().summary(&share))
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
+/// 兑现其他网关的分享链接
+///
+/// 返回请求 ID，分享内容通过对应的文件或目录响应事件送达。
+#[command]
+pub async fn redeem_share(
+    link: String,
+    password: Option<String>,
+    path: Option<String>,
+) -> Result<String, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        let request_id = gateway.redeem_share(&link, password, path)
+            .await
+            .map_err(|e| format!("兑现分享失败: {e}"))?;
+        Ok(request_id.to_string())
+    } else {
+        Err("网关未初始化".to_string())
+    }
+}
+
 /// 数据传输请求信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataTransferRequest {
@@ -1193,14 +1522,69 @@ pub async fn connect_to_node(node_id: String, ip_address: String, port: u16) ->
     
     let global_state = GLOBAL_STATE.lock().await;
     let state = global_state.as_ref().unwrap();
-    
-    state.network_manager.connect_to_node(&node_id, &ip_address, port)
+    let gateway_lock = state.gateway.read().await;
+
+    // 网关运行时使用其网络管理器，握手才会出示网关的证书
+    let network_manager = match gateway_lock.as_ref() {
+        Some(gateway) => Arc::clone(gateway.network_manager()),
+        None => Arc::clone(&state.network_manager),
+    };
+
+    network_manager.connect_to_node(&node_id, &ip_address, port)
         .await
         .map_err(|e| format!("连接节点失败: {e}"))?;
 
     Ok(())
 }
 
+/// 获取已知网关的存活状态和 RTT
+#[command]
+pub async fn get_peer_health() -> Result<Vec<PeerHealth>, String> {
+    ensure_global_state().await?;
+    
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.heartbeat_scheduler().snapshot())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 获取中继转发统计
+#[command]
+pub async fn get_relay_stats() -> Result<RelayStats, String> {
+    ensure_global_state().await?;
+    
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.relay_stats())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 获取 NAT 穿透（UDP 打洞）统计
+#[command]
+pub async fn get_hole_punch_stats() -> Result<HolePunchStats, String> {
+    ensure_global_state().await?;
+    
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.hole_punch_stats())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
 /// 断开与节点的连接
 #[command]
 pub async fn disconnect_from_node(node_id: String) -> Result<(), String> {
@@ -1228,7 +1612,15 @@ pub async fn get_performance_report() -> Result<PerformanceReport, String> {
     let global_state = GLOBAL_STATE.lock().await;
     let state = global_state.as_ref().unwrap();
     
-    let report = state.performance_monitor.get_report().await;
+    let mut report = state.performance_monitor.get_report().await;
+
+    // 合并运行中网关的按网关延迟统计和入站限速统计
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        report.peer_latency = gateway.performance_monitor().get_peer_latency().await;
+        report.throttling = gateway.rate_limiter().stats();
+    }
+
     Ok(report)
 }
 
@@ -1426,11 +1818,9 @@ pub async fn get_service_logs(
                 let reader = BufReader::new(file);
                 let mut temp_entries = Vec::new();
                 
-                for line in reader.lines() {
-                    if let Ok(line_content) = line {
-                        if let Some(entry) = parse_log_line(&line_content, &level_filter) {
-                            temp_entries.push(entry);
-                        }
+                for line_content in reader.lines().map_while(Result::ok) {
+                    if let Some(entry) = parse_log_line(&line_content, &level_filter) {
+                        temp_entries.push(entry);
                     }
                 }
                 
@@ -1699,8 +2089,12 @@ pub async fn generate_tls_certificate(
     
     let global_state = GLOBAL_STATE.lock().await;
     let state = global_state.as_ref().unwrap();
-    
-    state.security_manager.generate_certificate(cert_info)
+
+    // 网关运行时用其 CA 签发证书，并把新证书加载到 TLS 管理器
+    let gateway_lock = state.gateway.read().await;
+    let tls_manager = gateway_lock.as_ref().map(|gateway| gateway.tls_manager().as_ref());
+
+    state.security_manager.generate_certificate(cert_info, tls_manager)
         .await
         .map_err(|e| format!("生成证书失败: {e}"))
 }
@@ -1716,8 +2110,26 @@ pub struct CertificateInfo {
     pub country: String,
     /// 有效期（天）
     pub validity_days: u32,
-    /// 主题备用名称
+    /// 主题备用名称（DNS 名称或 IP 地址）
     pub subject_alt_names: Vec<String>,
+    /// 密钥算法
+    #[serde(default)]
+    pub key_algorithm: KeyAlgorithm,
+    /// 是否由网关 CA 签发（否则生成自签名证书）
+    #[serde(default)]
+    pub sign_with_ca: bool,
+}
+
+/// 证书密钥算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAlgorithm {
+    /// ECDSA P-256 + SHA-256
+    #[default]
+    EcdsaP256,
+    /// ECDSA P-384 + SHA-384
+    EcdsaP384,
+    /// Ed25519
+    Ed25519,
 }
 
 /// 生成的证书
@@ -1801,6 +2213,69 @@ pub async fn validate_client_access(
         .map_err(|e| format!("验证访问权限失败: {e}"))
 }
 
+/// 解释访问决策
+///
+/// 按与实际访问相同的规则求值，返回决定结果的规则，不产生任何副作用。
+#[command]
+pub async fn explain_access(
+    client_ip: String,
+    device_id: Option<String>,
+    requested_path: String,
+    operation: String,
+) -> Result<AccessDecision, String> {
+    ensure_global_state().await?;
+
+    let device_id = device_id.as_deref().map(parse_uuid).transpose()?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    Ok(state
+        .security_manager
+        .explain_access(&client_ip, device_id, &requested_path, &operation))
+}
+
+/// 设置设备分组
+///
+/// 访问规则可以通过 `group:<分组名>` 引用分组，成员为网关 ID。
+#[command]
+pub async fn set_access_group(name: String, members: Vec<String>) -> Result<AccessGroup, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    state
+        .security_manager
+        .set_access_group(&name, &members)
+        .map_err(|e| format!("设置设备分组失败: {e}"))
+}
+
+/// 删除设备分组
+#[command]
+pub async fn remove_access_group(name: String) -> Result<(), String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    state
+        .security_manager
+        .remove_access_group(&name)
+        .map_err(|e| format!("删除设备分组失败: {e}"))
+}
+
+/// 获取设备分组列表
+#[command]
+pub async fn get_access_groups() -> Result<Vec<AccessGroup>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+
+    Ok(state.security_manager.get_access_groups())
+}
+
 /// 获取活跃会话列表
 #[command]
 pub async fn get_active_sessions() -> Result<Vec<ActiveSession>, String> {
@@ -1831,6 +2306,21 @@ pub struct ActiveSession {
     pub bytes_transferred: u64,
     /// 连接状态
     pub status: String,
+    /// 握手协商出的 TLS 参数，非 TLS 会话为 `None`
+    #[serde(default)]
+    pub tls: Option<NegotiatedTls>,
+    /// 对端连接地址
+    #[serde(default)]
+    pub remote_addr: Option<std::net::SocketAddr>,
+    /// 对端证书指纹
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// 收到的字节数
+    #[serde(default)]
+    pub bytes_received: u64,
+    /// 发送的字节数
+    #[serde(default)]
+    pub bytes_sent: u64,
 }
 
 /// 强制断开会话
@@ -1843,13 +2333,328 @@ pub async fn disconnect_session(
     let global_state = GLOBAL_STATE.lock().await;
     let state = global_state.as_ref().unwrap();
     
-    state.security_manager.disconnect_session(&session_id)
-        .await
-        .map_err(|e| format!("断开会话失败: {e}"))?;
+    // 网关运行时同时关闭底层连接，并在冷却期内拒绝对端重连
+    let gateway_lock = state.gateway.read().await;
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.disconnect_session(&session_id)
+            .await
+            .map_err(|e| format!("断开会话失败: {e}"))?;
+    } else {
+        state.security_manager.disconnect_session(&session_id)
+            .await
+            .map_err(|e| format!("断开会话失败: {e}"))?;
+    }
     
     Ok(())
 }
 
+/// 解析网关或配对会话 ID
+fn parse_uuid(value: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(value).map_err(|e| format!("无效的 ID '{value}': {e}"))
+}
+
+/// 获取已配对的受信任设备
+#[command]
+pub async fn list_trusted_devices() -> Result<Vec<TrustedDevice>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.pairing().devices())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 获取进行中的配对会话及其短验证码
+#[command]
+pub async fn get_pairing_sessions() -> Result<Vec<PairingSession>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.pairing().sessions())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 向已发现的网关发起配对
+#[command]
+pub async fn pair_device(gateway_id: String) -> Result<PairingSession, String> {
+    ensure_global_state().await?;
+    let gateway_id = parse_uuid(&gateway_id)?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.start_pairing(&gateway_id)
+            .await
+            .map_err(|e| format!("发起配对失败: {e}"))
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 确认或拒绝配对（用户核对双方短验证码后调用）
+#[command]
+pub async fn confirm_pairing(
+    session_id: String,
+    accepted: bool,
+) -> Result<Option<TrustedDevice>, String> {
+    ensure_global_state().await?;
+    let session_id = parse_uuid(&session_id)?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.confirm_pairing(session_id, accepted)
+            .await
+            .map_err(|e| format!("确认配对失败: {e}"))
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 重命名受信任设备
+#[command]
+pub async fn rename_trusted_device(
+    gateway_id: String,
+    name: String,
+) -> Result<TrustedDevice, String> {
+    ensure_global_state().await?;
+    let gateway_id = parse_uuid(&gateway_id)?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.rename_trusted_device(&gateway_id, name)
+            .map_err(|e| format!("重命名设备失败: {e}"))
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 撤销受信任设备
+#[command]
+pub async fn revoke_trusted_device(gateway_id: String) -> Result<TrustedDevice, String> {
+    ensure_global_state().await?;
+    let gateway_id = parse_uuid(&gateway_id)?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.revoke_trusted_device(&gateway_id)
+            .map_err(|e| format!("撤销设备失败: {e}"))
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 吊销设备证书
+///
+/// 吊销记录会传播到其他已配对的网关，被吊销设备的连接和会话立即断开。
+#[command]
+pub async fn revoke_device(gateway_id: String, reason: String) -> Result<Revocation, String> {
+    ensure_global_state().await?;
+    let gateway_id = parse_uuid(&gateway_id)?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        gateway.revoke_device(gateway_id, reason).await
+            .map_err(|e| format!("吊销设备失败: {e}"))
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 获取证书吊销列表
+#[command]
+pub async fn get_revocations() -> Result<Vec<Revocation>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        Ok(gateway.revocations())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 选取当前生效的审计日志：网关运行时使用网关的日志，否则使用全局状态的日志
+async fn active_audit_log(state: &GlobalGatewayState) -> Arc<AuditLog> {
+    match state.gateway.read().await.as_ref() {
+        Some(gateway) => Arc::clone(gateway.audit_log()),
+        None => Arc::clone(&state.audit_log),
+    }
+}
+
+/// 查询审计日志
+///
+/// # 参数
+///
+/// * `filter` - 可选的过滤条件（类别、结果、对端、时间范围、关键字、条数上限）
+///
+/// # 返回值
+///
+/// 按时间顺序排列的审计条目
+#[command]
+pub async fn query_audit_log(filter: Option<AuditFilter>) -> Result<Vec<AuditEntry>, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let audit_log = active_audit_log(state).await;
+
+    audit_log
+        .query(&filter.unwrap_or_default())
+        .map_err(|e| format!("查询审计日志失败: {e}"))
+}
+
+/// 以 JSON Lines 格式导出审计日志
+///
+/// # 参数
+///
+/// * `output_path` - 导出文件路径
+/// * `filter` - 可选的过滤条件
+///
+/// # 返回值
+///
+/// 导出的条目数量
+#[command]
+pub async fn export_audit_log(
+    output_path: PathBuf,
+    filter: Option<AuditFilter>,
+) -> Result<usize, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let audit_log = active_audit_log(state).await;
+
+    let count = audit_log
+        .export_jsonl(&filter.unwrap_or_default(), &output_path)
+        .map_err(|e| format!("导出审计日志失败: {e}"))?;
+    info!("已导出 {count} 条审计记录到 {output_path:?}");
+    Ok(count)
+}
+
+/// 校验审计日志的哈希链，检测记录是否被篡改或删除
+#[command]
+pub async fn verify_audit_log() -> Result<AuditVerification, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let audit_log = active_audit_log(state).await;
+
+    audit_log
+        .verify()
+        .map_err(|e| format!("校验审计日志失败: {e}"))
+}
+
+/// 导出网关身份包（CA、服务器/客户端证书与私钥、受信任 CA 及配对设备）
+///
+/// # 参数
+///
+/// * `path` - 导出文件路径
+/// * `password` - 加密口令
+/// * `format` - 导出格式（PEM 加密包或 PKCS#12）
+///
+/// # 返回值
+///
+/// 导出的身份包摘要
+#[command]
+pub async fn export_identity_bundle(
+    path: String,
+    password: String,
+    format: BundleFormat,
+) -> Result<IdentityBundleSummary, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    let gateway_lock = state.gateway.read().await;
+
+    if let Some(gateway) = gateway_lock.as_ref() {
+        let bundle = IdentityBundle::collect(
+            gateway.tls_manager(),
+            Some(gateway.registry().local_entry().id),
+            gateway.config().name.clone(),
+            gateway.pairing().devices(),
+        )
+        .map_err(|e| format!("收集身份信息失败: {e}"))?;
+        bundle.export_to_file(&PathBuf::from(path), format, &password)
+            .map_err(|e| format!("导出身份包失败: {e}"))?;
+        info!("已导出网关身份包: {:?}", format);
+        Ok(bundle.summary())
+    } else {
+        Err("网关未运行".to_string())
+    }
+}
+
+/// 导入网关身份包
+///
+/// 导入会覆盖本机的 CA、证书、受信任 CA 和配对设备，必须在网关停止时执行，
+/// 重新启动网关后生效。
+///
+/// # 参数
+///
+/// * `path` - 身份包文件路径
+/// * `password` - 解密口令
+/// * `expected_ca_fingerprint` - 可选的 CA 指纹，用于带外确认身份包来源
+/// * `config` - 安装目标的网关配置，缺省时使用默认配置
+///
+/// # 返回值
+///
+/// 导入的身份包摘要
+#[command]
+pub async fn import_identity_bundle(
+    path: String,
+    password: String,
+    expected_ca_fingerprint: Option<String>,
+    config: Option<GatewayConfig>,
+) -> Result<IdentityBundleSummary, String> {
+    ensure_global_state().await?;
+
+    let global_state = GLOBAL_STATE.lock().await;
+    let state = global_state.as_ref().unwrap();
+    if state.gateway.read().await.is_some() {
+        return Err("导入身份前请先停止网关".to_string());
+    }
+
+    let data = std::fs::read(&path).map_err(|e| format!("读取身份包失败: {e}"))?;
+    let bundle = IdentityBundle::open(&data, &password)
+        .map_err(|e| format!("解密身份包失败: {e}"))?;
+    bundle.verify(expected_ca_fingerprint.as_deref())
+        .map_err(|e| format!("身份包校验失败: {e}"))?;
+    bundle.install(&config.unwrap_or_default())
+        .map_err(|e| format!("安装身份包失败: {e}"))?;
+
+    info!("已导入网关身份包，CA 指纹: {}", bundle.ca.fingerprint);
+    Ok(bundle.summary())
+}
+
 // ============================================================================
 // 导出所有命令函数
 // ============================================================================
@@ -1868,9 +2673,17 @@ pub fn get_all_commands() -> Vec<&'static str> {
         "mount_directory",
         "unmount_directory",
         "get_mount_points",
+        "revalidate_mounts",
+        "set_mount_rules",
         "list_directory",
         "create_search_token",
         "validate_search_token",
+        "list_search_tokens",
+        "revoke_search_token",
+        "create_share",
+        "list_shares",
+        "revoke_share",
+        "redeem_share",
         "authorize_file",
         "get_metadata_by_token",
         "confirm_data_transfer",
@@ -1890,6 +2703,9 @@ pub fn get_all_commands() -> Vec<&'static str> {
         "get_discovered_nodes",
         "connect_to_node",
         "disconnect_from_node",
+        "get_peer_health",
+        "get_relay_stats",
+        "get_hole_punch_stats",
         "get_performance_report",
         "get_compression_stats",
         "get_cache_stats",
@@ -1905,8 +2721,25 @@ pub fn get_all_commands() -> Vec<&'static str> {
         "remove_access_rule",
         "get_access_rules",
         "validate_client_access",
+        "explain_access",
+        "set_access_group",
+        "remove_access_group",
+        "get_access_groups",
         "get_active_sessions",
         "disconnect_session",
+        "list_trusted_devices",
+        "get_pairing_sessions",
+        "pair_device",
+        "confirm_pairing",
+        "rename_trusted_device",
+        "revoke_trusted_device",
+        "revoke_device",
+        "get_revocations",
+        "query_audit_log",
+        "export_audit_log",
+        "verify_audit_log",
+        "export_identity_bundle",
+        "import_identity_bundle",
     ]
 }
 
@@ -1931,7 +2764,8 @@ mod tests {
         assert!(!status.is_running);
         
         // 测试启动网关
-        let config = GatewayConfig::default();
+        let gateway_dir = tempfile::tempdir().unwrap();
+        let config = GatewayConfig::for_test(gateway_dir.path(), "生命周期网关");
         start_gateway(config).await.unwrap();
         
         let status = get_gateway_status().await.unwrap();
@@ -1983,7 +2817,8 @@ mod tests {
         *GLOBAL_STATE.lock().await = Some(state);
         
         // 启动网关以便进行目录操作
-        let config = GatewayConfig::default();
+        let gateway_dir = tempfile::tempdir().unwrap();
+        let config = GatewayConfig::for_test(gateway_dir.path(), "目录网关");
         start_gateway(config).await.unwrap();
         
         // 创建一个专用的测试目录
@@ -1995,6 +2830,7 @@ mod tests {
             test_dir.clone(),
             "测试挂载".to_string(),
             true,
+            None,
         ).await.unwrap();
         
         assert!(!mount_id.is_empty());
@@ -2062,6 +2898,8 @@ mod tests {
             allowed_paths: vec!["/test".to_string()],
             permissions: vec!["read".to_string()],
             enabled: true,
+            effect: RuleEffect::Allow,
+            priority: 100,
         };
         
         let rule_id = add_access_rule(rule).await.unwrap();
@@ -39,14 +39,32 @@ pub fn generate_api_documentation() -> String {
     docs.push_str("重置为默认配置。\n\n");
     
     docs.push_str("## 目录和文件操作接口 (Directory API)\n\n");
-    docs.push_str("### `mount_directory(local_path: PathBuf, mount_name: String, read_only: bool) -> Result<String, String>`\n");
-    docs.push_str("挂载本地目录。\n\n");
+    docs.push_str("### `mount_directory(local_path: PathBuf, mount_name: String, read_only: bool, rules: Option<MountRules>) -> Result<String, String>`\n");
+    docs.push_str("挂载本地目录，可以指定包含/排除模式、大小和扩展名等过滤规则。\n\n");
     docs.push_str("### `unmount_directory(mount_id: String) -> Result<(), String>`\n");
     docs.push_str("卸载目录。\n\n");
     docs.push_str("### `get_mount_points() -> Result<Vec<MountPoint>, String>`\n");
     docs.push_str("获取所有挂载点。\n\n");
+    docs.push_str("### `revalidate_mounts() -> Result<Vec<MountPoint>, String>`\n");
+    docs.push_str("重新校验挂载点，路径已不存在的挂载点标记为 `Missing`。\n\n");
+    docs.push_str("### `set_mount_rules(mount_id: String, rules: MountRules) -> Result<MountPoint, String>`\n");
+    docs.push_str("修改挂载点的过滤规则并按新规则重建目录索引。\n\n");
     docs.push_str("### `list_directory(mount_id: String, path: String) -> Result<Vec<DirectoryEntry>, String>`\n");
     docs.push_str("列出目录内容。\n\n");
+    docs.push_str("### `create_search_token(mount_id: String, patterns: Vec<String>, permissions: Vec<String>, ttl_seconds: u64, grantee_id: Option<String>) -> Result<String, String>`\n");
+    docs.push_str("签发签名搜索令牌，交给其他网关在搜索、列目录和读取请求中出示；指定 `grantee_id` 时只有该网关可以使用。\n\n");
+    docs.push_str("### `list_search_tokens() -> Result<Vec<SearchToken>, String>`\n");
+    docs.push_str("获取本网关签发且仍然有效的搜索令牌。\n\n");
+    docs.push_str("### `revoke_search_token(token_id: String) -> Result<bool, String>`\n");
+    docs.push_str("将搜索令牌加入吊销列表，令牌立即失效。\n\n");
+    docs.push_str("### `create_share(auth_id: String, options: Option<ShareOptions>) -> Result<ShareSummary, String>`\n");
+    docs.push_str("将文件授权转换为只读分享链接，可设置有效期、最大下载次数和访问密码。\n\n");
+    docs.push_str("### `list_shares() -> Result<Vec<ShareSummary>, String>`\n");
+    docs.push_str("获取仍然有效的分享及其下载计数。\n\n");
+    docs.push_str("### `revoke_share(share_id: String) -> Result<ShareSummary, String>`\n");
+    docs.push_str("撤销分享，链接立即失效。\n\n");
+    docs.push_str("### `redeem_share(link: String, password: Option<String>, path: Option<String>) -> Result<String, String>`\n");
+    docs.push_str("向分享者兑现分享链接，返回请求 ID。\n\n");
     
     docs.push_str("## 网络通信接口 (Network API)\n\n");
     docs.push_str("### `get_network_status() -> Result<NetworkStatus, String>`\n");
@@ -57,6 +75,12 @@ pub fn generate_api_documentation() -> String {
     docs.push_str("停止 P2P 节点发现。\n\n");
     docs.push_str("### `get_discovered_nodes() -> Result<Vec<DiscoveredNode>, String>`\n");
     docs.push_str("获取已发现的节点列表。\n\n");
+    docs.push_str("### `get_peer_health() -> Result<Vec<PeerHealth>, String>`\n");
+    docs.push_str("获取已知网关的存活状态（Alive/Suspect/Dead）、平滑 RTT 和抖动。状态变化时发送 `peer-state-changed` 事件。\n\n");
+    docs.push_str("### `get_relay_stats() -> Result<RelayStats, String>`\n");
+    docs.push_str("获取中继转发统计（已转发消息数与字节数、限速丢弃数、拒绝数）。中继需在配置中通过 `enable_relay` 启用。\n\n");
+    docs.push_str("### `get_hole_punch_stats() -> Result<HolePunchStats, String>`\n");
+    docs.push_str("获取 NAT 穿透统计（会合网关观察到的公网端点、进行中的打洞会话数、尝试/成功/失败次数）。经中继通信时通过 `enable_hole_punching` 自动尝试打洞。\n\n");
     
     docs.push_str("## 性能监控接口 (Performance API)\n\n");
     docs.push_str("### `get_performance_report() -> Result<PerformanceReport, String>`\n");
@@ -85,6 +109,38 @@ pub fn generate_api_documentation() -> String {
     docs.push_str("添加访问控制规则。\n\n");
     docs.push_str("### `remove_access_rule(rule_id: String) -> Result<(), String>`\n");
     docs.push_str("删除访问控制规则。\n\n");
+    docs.push_str("### `explain_access(client_ip: String, device_id?: String, requested_path: String, operation: String) -> Result<AccessDecision, String>`\n");
+    docs.push_str("按访问规则求值但不执行访问，返回做出决定的规则及原因。\n\n");
+    docs.push_str("### `set_access_group(name: String, members: Vec<String>) -> Result<AccessGroup, String>`\n");
+    docs.push_str("设置设备分组，访问规则通过 `group:<分组名>` 引用。\n\n");
+    docs.push_str("### `remove_access_group(name: String) -> Result<(), String>`\n");
+    docs.push_str("删除设备分组。\n\n");
+    docs.push_str("### `get_access_groups() -> Result<Vec<AccessGroup>, String>`\n");
+    docs.push_str("获取设备分组列表。\n\n");
+    docs.push_str("### `list_trusted_devices() -> Result<Vec<TrustedDevice>, String>`\n");
+    docs.push_str("获取已配对的受信任设备。\n\n");
+    docs.push_str("### `pair_device(gateway_id: String) -> Result<PairingSession, String>`\n");
+    docs.push_str("向已发现的网关发起配对，双方显示 6 位短验证码。\n\n");
+    docs.push_str("### `confirm_pairing(session_id: String, accepted: bool) -> Result<Option<TrustedDevice>, String>`\n");
+    docs.push_str("核对短验证码后确认或拒绝配对，双方都确认后返回新配对的设备。\n\n");
+    docs.push_str("### `rename_trusted_device(gateway_id: String, name: String) -> Result<TrustedDevice, String>`\n");
+    docs.push_str("重命名受信任设备。\n\n");
+    docs.push_str("### `revoke_trusted_device(gateway_id: String) -> Result<TrustedDevice, String>`\n");
+    docs.push_str("撤销受信任设备。\n\n");
+    docs.push_str("### `revoke_device(gateway_id: String, reason: String) -> Result<Revocation, String>`\n");
+    docs.push_str("吊销设备证书，传播到其他已配对网关并立即断开该设备的连接和会话。\n\n");
+    docs.push_str("### `get_revocations() -> Result<Vec<Revocation>, String>`\n");
+    docs.push_str("获取证书吊销列表。\n\n");
+    docs.push_str("### `query_audit_log(filter: Option<AuditFilter>) -> Result<Vec<AuditEntry>, String>`\n");
+    docs.push_str("按类别、结果、对端、时间范围和关键字查询审计日志。\n\n");
+    docs.push_str("### `export_audit_log(output_path: PathBuf, filter: Option<AuditFilter>) -> Result<usize, String>`\n");
+    docs.push_str("以 JSON Lines 格式导出审计日志，返回导出条数。\n\n");
+    docs.push_str("### `verify_audit_log() -> Result<AuditVerification, String>`\n");
+    docs.push_str("校验审计日志哈希链，报告第一处被篡改或删除的位置。\n\n");
+    docs.push_str("### `export_identity_bundle(path: String, password: String, format: BundleFormat) -> Result<IdentityBundleSummary, String>`\n");
+    docs.push_str("导出加密的网关身份包（PEM 包或 PKCS#12），包含 CA、证书私钥、受信任 CA 和配对设备。\n\n");
+    docs.push_str("### `import_identity_bundle(path: String, password: String, expected_ca_fingerprint: Option<String>, config: Option<GatewayConfig>) -> Result<IdentityBundleSummary, String>`\n");
+    docs.push_str("在网关停止时导入身份包，校验证书链和 CA 指纹后安装，重启网关后生效。\n\n");
     
     docs.push_str("## 使用示例\n\n");
     docs.push_str("``typescript\n");
@@ -128,6 +184,8 @@ pub fn generate_api_documentation() -> String {
 mod tests {
     use tempfile::tempdir;
     
+    use crate::gateway::access_control::RuleEffect;
+    use crate::gateway::gateway::GatewayConfig;
     use crate::gateway::tauri_api::*;
     use crate::gateway::tauri_api_tests::create_test_global_state;
 
@@ -155,8 +213,11 @@ mod tests {
         assert!(is_valid);
         println!("✓ 配置管理API测试通过");
 
-        // 3.1. 启动网关以便测试需要网关运行的功能
-        start_gateway(default_config).await.unwrap();
+        // 3.1. 启动网关以便测试需要网关运行的功能，持久化文件写入临时目录
+        let gateway_dir = tempdir().unwrap();
+        start_gateway(GatewayConfig::for_test(gateway_dir.path(), &default_config.name))
+            .await
+            .unwrap();
 
         // 4. 测试性能监控API
         let _perf_report = get_performance_report().await.unwrap();
@@ -211,6 +272,7 @@ mod tests {
             temp_dir.path().to_path_buf(),
             "测试挂载".to_string(),
             true,
+            None,
         ).await.unwrap();
         
         let mount_points = get_mount_points().await.unwrap();
@@ -286,6 +348,8 @@ mod tests {
             allowed_paths: vec!["/test".to_string(), "/data".to_string()],
             permissions: vec!["read".to_string(), "write".to_string()],
             enabled: true,
+            effect: RuleEffect::Allow,
+            priority: 100,
         };
 
         let rule_id = add_access_rule(rule.clone()).await.unwrap();
@@ -383,6 +447,8 @@ mod tests {
             country: "CN".to_string(),
             validity_days: 365,
             subject_alt_names: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+            key_algorithm: KeyAlgorithm::EcdsaP256,
+            sign_with_ca: false,
         };
 
         let generated_cert = generate_tls_certificate(
@@ -432,6 +498,7 @@ mod tests {
             std::path::PathBuf::from("/nonexistent/path"),
             "无效挂载".to_string(),
             false,
+            None,
         ).await;
         assert!(mount_result.is_err());
 
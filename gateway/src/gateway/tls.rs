@@ -3,7 +3,7 @@
 //! # 功能特性
 //!
 //! * **双向认证 (mTLS)**: 支持 TLS 1.3 双向认证，确保客户端和服务端身份验证
-//! * **证书生成**: 自动生成 CA、服务端、客户端证书，支持开发和生产环境
+//! * **证书生成**: 自动生成本地 CA，并由其签发服务端、客户端证书
 //! * **证书验证**: 完整的 X.509 证书链验证，包括有效期、签名、用途验证  
 //! * **安全存储**: 安全的证书和私钥文件管理
 //! * **灵活配置**: 支持多种验证模式和密码套件配置
@@ -29,15 +29,126 @@
 
 use anyhow::{Context, Result};
 use base64::prelude::*;
+use chrono::{DateTime, Utc};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, CertifiedKey, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair, KeyUsagePurpose, SanType,
+};
+use ring::signature;
 use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::time::SystemTime;
+use uuid::Uuid;
+use x509_parser::oid_registry::{
+    Oid, OID_PKCS1_SHA256WITHRSA, OID_PKCS1_SHA384WITHRSA, OID_PKCS1_SHA512WITHRSA,
+    OID_SIG_ECDSA_WITH_SHA256, OID_SIG_ECDSA_WITH_SHA384, OID_SIG_ED25519,
+};
 use x509_parser::prelude::*;
 
+/// 证书组织名称
+const CERT_ORGANIZATION: &str = "WDIC Gateway";
+
+/// 本地 CA 的通用名称
+const CA_COMMON_NAME: &str = "WDIC Gateway Local CA";
+
+/// CA 证书有效期（天）
+const CA_VALIDITY_DAYS: i64 = 3650;
+
+/// 服务端和客户端证书有效期（天）
+const LEAF_VALIDITY_DAYS: i64 = 365;
+
+/// 吊销列表文件名，与 CA 证书位于同一目录
+const REVOCATIONS_FILE: &str = "revocations.json";
+
+/// 吊销通知签名的域分隔前缀
+const REVOCATION_SIGNATURE_CONTEXT: &[u8] = b"WDIC-REVOCATION";
+
+/// QUIC 握手可能协商出的 TLS 1.3 密码套件
+///
+/// QUIC 固定使用 TLS 1.3（RFC 9001），quiche 不支持禁用其中任何一个套件。
+pub const QUIC_CIPHER_SUITES: [&str; 3] = [
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+];
+
+/// 可用于 TLS 1.2 监听器的密码套件
+const TLS12_CIPHER_SUITES: [&str; 6] = [
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+];
+
+/// 证书主体身份
+///
+/// 服务端和客户端证书的主题名称与 SAN 由此派生。
+#[derive(Debug, Clone)]
+pub struct CertificateIdentity {
+    /// 网关名称，作为证书通用名称
+    pub name: String,
+    /// 网关唯一标识，以 `urn:uuid:` URI 的形式写入 SAN
+    pub gateway_id: Option<Uuid>,
+    /// 服务端证书的 DNS 名称
+    pub dns_names: Vec<String>,
+    /// 服务端证书的 IP 地址
+    pub ip_addresses: Vec<IpAddr>,
+}
+
+impl Default for CertificateIdentity {
+    fn default() -> Self {
+        Self {
+            name: CERT_ORGANIZATION.to_string(),
+            gateway_id: None,
+            dns_names: vec!["localhost".to_string()],
+            ip_addresses: vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ],
+        }
+    }
+}
+
+impl CertificateIdentity {
+    /// 根据网关身份和接口地址创建证书主体身份
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 网关名称
+    /// * `gateway_id` - 网关唯一标识
+    /// * `interface_ips` - 参与监听的接口地址
+    ///
+    /// # 返回值
+    ///
+    /// 证书主体身份，始终包含 localhost 和回环地址
+    pub fn for_gateway(name: &str, gateway_id: Uuid, interface_ips: &[IpAddr]) -> Self {
+        let mut identity = Self {
+            name: name.to_string(),
+            gateway_id: Some(gateway_id),
+            ..Default::default()
+        };
+        for ip in interface_ips {
+            if !identity.ip_addresses.contains(ip) {
+                identity.ip_addresses.push(*ip);
+            }
+        }
+        identity
+    }
+
+    /// 网关唯一标识对应的 URI
+    pub fn uri(&self) -> Option<String> {
+        self.gateway_id.map(|id| format!("urn:uuid:{id}"))
+    }
+}
+
 /// TLS 证书信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CertificateInfo {
@@ -70,6 +181,8 @@ pub struct MtlsConfig {
     pub client_cert_path: PathBuf,
     /// 客户端私钥路径
     pub client_key_path: PathBuf,
+    /// 受信任的其他 CA 证书目录，目录中的 `.crt`/`.pem` 文件与本地 CA 一起用于验证对端
+    pub trusted_ca_dir: PathBuf,
     /// 证书验证模式
     pub verify_mode: VerifyMode,
     /// 支持的 TLS 版本
@@ -92,7 +205,7 @@ pub enum VerifyMode {
 }
 
 /// TLS 版本
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TlsVersion {
     /// TLS 1.2
     Tls12,
@@ -100,6 +213,46 @@ pub enum TlsVersion {
     Tls13,
 }
 
+impl TlsVersion {
+    /// 协议版本名称，如 `TLSv1.3`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlsVersion::Tls12 => "TLSv1.2",
+            TlsVersion::Tls13 => "TLSv1.3",
+        }
+    }
+
+    /// 密码套件所属的 TLS 版本，未知套件返回 `None`
+    fn of_cipher_suite(suite: &str) -> Option<Self> {
+        if QUIC_CIPHER_SUITES.contains(&suite) {
+            Some(TlsVersion::Tls13)
+        } else if TLS12_CIPHER_SUITES.contains(&suite) {
+            Some(TlsVersion::Tls12)
+        } else {
+            None
+        }
+    }
+}
+
+/// 一次握手实际协商出的 TLS 参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NegotiatedTls {
+    /// 传输协议，如 `QUIC`
+    pub transport: String,
+    /// 协商出的 TLS 版本
+    pub tls_version: TlsVersion,
+    /// 协商出的密码套件，协议栈不公开时为 `None`
+    pub cipher_suite: Option<String>,
+    /// 本次握手可能使用的密码套件
+    ///
+    /// 协议栈不公开最终选择的套件时，会话只能确认落在此集合内。
+    pub permitted_cipher_suites: Vec<String>,
+    /// 协商出的应用层协议（ALPN）
+    pub application_protocol: Option<String>,
+    /// 是否为会话恢复
+    pub resumed: bool,
+}
+
 impl Default for MtlsConfig {
     fn default() -> Self {
         Self {
@@ -108,6 +261,7 @@ impl Default for MtlsConfig {
             server_key_path: PathBuf::from("certs/server.key"),
             client_cert_path: PathBuf::from("certs/client.crt"),
             client_key_path: PathBuf::from("certs/client.key"),
+            trusted_ca_dir: PathBuf::from("certs/trusted"),
             verify_mode: VerifyMode::MutualAuth,
             tls_versions: vec![TlsVersion::Tls13],
             cipher_suites: vec![
@@ -119,6 +273,108 @@ impl Default for MtlsConfig {
     }
 }
 
+impl MtlsConfig {
+    /// 校验 TLS 版本与密码套件策略
+    ///
+    /// # 返回值
+    ///
+    /// 版本或套件为空、套件名称未知、套件所属版本未启用时返回错误
+    pub fn validate_policy(&self) -> Result<()> {
+        if self.tls_versions.is_empty() {
+            return Err(anyhow::anyhow!("至少需要启用一个 TLS 版本"));
+        }
+        if self.cipher_suites.is_empty() {
+            return Err(anyhow::anyhow!("至少需要启用一个密码套件"));
+        }
+        for suite in &self.cipher_suites {
+            let version = TlsVersion::of_cipher_suite(suite)
+                .ok_or_else(|| anyhow::anyhow!("不支持的密码套件: {suite}"))?;
+            if !self.tls_versions.contains(&version) {
+                return Err(anyhow::anyhow!(
+                    "密码套件 {suite} 属于 {}，但该版本未启用",
+                    version.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// QUIC 连接中的角色，决定出示服务端证书还是客户端证书
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuicRole {
+    /// 发起连接的客户端
+    Client,
+    /// 接受连接的服务端
+    Server,
+}
+
+/// 对端证书身份
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerCertificate {
+    /// 证书指纹（SHA-256，小写十六进制）
+    pub fingerprint: String,
+    /// 证书 SAN 中声明的网关唯一标识
+    pub gateway_id: Option<Uuid>,
+    /// 证书通用名称
+    pub common_name: Option<String>,
+    /// 签发该证书的 CA 的指纹，不是由本地 CA 或受信任 CA 签发时为 `None`
+    pub issuer_fingerprint: Option<String>,
+    /// 证书是否通过了链验证
+    pub verified: bool,
+}
+
+/// 证书吊销记录
+///
+/// 覆盖被吊销设备的网关唯一标识以及它的 CA 和终端证书指纹。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revocation {
+    /// 被吊销设备的网关唯一标识
+    pub gateway_id: Option<Uuid>,
+    /// 被吊销的证书指纹（CA 与终端证书）
+    pub fingerprints: Vec<String>,
+    /// 吊销原因
+    pub reason: String,
+    /// 吊销时间
+    pub revoked_at: DateTime<Utc>,
+    /// 发起吊销的网关
+    pub revoked_by: Uuid,
+}
+
+impl Revocation {
+    /// 检查吊销记录是否覆盖指定的证书身份
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 证书声明的网关唯一标识
+    /// * `fingerprints` - 证书及其签发 CA 的指纹
+    pub fn covers(&self, gateway_id: Option<&Uuid>, fingerprints: &[&str]) -> bool {
+        gateway_id.is_some_and(|id| self.gateway_id.as_ref() == Some(id))
+            || fingerprints
+                .iter()
+                .any(|fingerprint| self.fingerprints.iter().any(|revoked| revoked == fingerprint))
+    }
+
+    /// 吊销通知的签名内容
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 转发吊销记录的网关 ID
+    pub fn signing_payload(&self, sender_id: &Uuid) -> Result<Vec<u8>> {
+        let mut payload = REVOCATION_SIGNATURE_CONTEXT.to_vec();
+        payload.extend_from_slice(sender_id.as_bytes());
+        payload.extend(serde_json::to_vec(self).context("序列化吊销记录失败")?);
+        Ok(payload)
+    }
+
+    /// 检查吊销记录是否覆盖对端证书
+    pub fn covers_peer(&self, certificate: &PeerCertificate) -> bool {
+        let mut fingerprints = vec![certificate.fingerprint.as_str()];
+        fingerprints.extend(certificate.issuer_fingerprint.as_deref());
+        self.covers(certificate.gateway_id.as_ref(), &fingerprints)
+    }
+}
+
 /// TLS 管理器
 #[derive(Debug)]
 pub struct TlsManager {
@@ -127,10 +383,16 @@ pub struct TlsManager {
     /// 信任的证书存储（保留用于未来扩展）
     #[allow(dead_code)]
     trusted_certs: HashMap<String, CertificateInfo>,
-    /// 证书缓存
-    cert_cache: HashMap<String, Vec<u8>>,
+    /// 证书缓存，运行时签发的证书也会加入其中
+    cert_cache: RwLock<HashMap<String, Vec<u8>>>,
     /// 私钥缓存
-    key_cache: HashMap<String, Vec<u8>>,
+    key_cache: RwLock<HashMap<String, Vec<u8>>>,
+    /// 受信任的其他 CA 证书（DER），配对或撤销设备后重新加载
+    trusted_cas: RwLock<Vec<Vec<u8>>>,
+    /// 证书吊销列表，持久化在 CA 证书旁的 `revocations.json`
+    revocations: RwLock<Vec<Revocation>>,
+    /// 证书主体身份
+    identity: CertificateIdentity,
 }
 
 impl TlsManager {
@@ -161,11 +423,30 @@ impl TlsManager {
     /// let manager = TlsManager::new(config)?;
     /// ```
     pub fn new(config: MtlsConfig) -> Result<Self> {
+        Self::with_identity(config, CertificateIdentity::default())
+    }
+
+    /// 使用指定的证书主体身份创建 TLS 管理器
+    ///
+    /// 需要生成证书时，服务端和客户端证书的主题名称与 SAN 由 `identity` 派生。
+    ///
+    /// # 参数
+    ///
+    /// * `config` - mTLS 配置
+    /// * `identity` - 证书主体身份
+    ///
+    /// # 返回值
+    ///
+    /// TLS 管理器实例
+    pub fn with_identity(config: MtlsConfig, identity: CertificateIdentity) -> Result<Self> {
         let mut manager = Self {
             config,
             trusted_certs: HashMap::new(),
-            cert_cache: HashMap::new(),
-            key_cache: HashMap::new(),
+            cert_cache: RwLock::new(HashMap::new()),
+            key_cache: RwLock::new(HashMap::new()),
+            trusted_cas: RwLock::new(Vec::new()),
+            revocations: RwLock::new(Vec::new()),
+            identity,
         };
 
         // 确保证书目录存在
@@ -180,6 +461,8 @@ impl TlsManager {
         manager
             .initialize_certificates()
             .context("初始化证书失败")?;
+        let revocations = manager.load_revocations();
+        *manager.revocations.get_mut().unwrap_or_else(|e| e.into_inner()) = revocations;
 
         info!(
             "TLS 管理器初始化完成，验证模式: {:?}",
@@ -201,23 +484,48 @@ impl TlsManager {
         // 加载证书到缓存
         self.load_certificates().context("加载证书失败")?;
 
+        // 旧版本生成的是互不相关的自签名证书，无法通过链验证，需要重新签发
+        if !self.is_chain_consistent() {
+            warn!("现有证书不是由本地 CA 签发，重新生成证书");
+            self.generate_self_signed_certificates()
+                .context("重新生成证书失败")?;
+            self.load_certificates().context("加载证书失败")?;
+        }
+
         Ok(())
     }
 
-    /// 生成自签名证书（用于开发和测试）
+    /// 检查服务端和客户端证书是否由当前 CA 签发
+    fn is_chain_consistent(&self) -> bool {
+        let Ok(Some(ca_der)) = self.certificate_der("ca") else {
+            return false;
+        };
+        let Ok((_, ca)) = X509Certificate::from_der(&ca_der) else {
+            return false;
+        };
+
+        ["server", "client"].iter().all(|name| {
+            matches!(self.certificate_der(name), Ok(Some(der))
+                if X509Certificate::from_der(&der)
+                    .map(|(_, cert)| Self::is_issued_by(&cert, &ca))
+                    .unwrap_or(false))
+        })
+    }
+
+    /// 生成本地 CA 及其签发的服务端、客户端证书
     fn generate_self_signed_certificates(&mut self) -> Result<()> {
-        info!("生成专业级自签名证书");
+        info!("生成本地 CA 及其签发的证书");
 
         // 生成 CA 证书
         let ca_cert = self.create_ca_certificate()
             .context("创建 CA 证书失败")?;
         
         // 生成服务端证书
-        let server_cert = self.create_server_certificate(&ca_cert)
+        let server_cert = self.create_server_certificate(&ca_cert.signing_key)
             .context("创建服务端证书失败")?;
         
         // 生成客户端证书
-        let client_cert = self.create_client_certificate(&ca_cert)
+        let client_cert = self.create_client_certificate(&ca_cert.signing_key)
             .context("创建客户端证书失败")?;
 
         // 保存证书文件
@@ -234,75 +542,154 @@ impl TlsManager {
             &self.config.client_key_path)?;
 
         // 存储证书数据到内存
-        self.cert_cache.insert("ca".to_string(), ca_cert.cert.pem().into_bytes());
-        self.cert_cache.insert("server".to_string(), server_cert.cert.pem().into_bytes());
-        self.cert_cache.insert("client".to_string(), client_cert.cert.pem().into_bytes());
-
-        self.key_cache.insert("ca".to_string(), ca_cert.signing_key.serialize_pem().into_bytes());
-        self.key_cache.insert("server".to_string(), server_cert.signing_key.serialize_pem().into_bytes());
-        self.key_cache.insert("client".to_string(), client_cert.signing_key.serialize_pem().into_bytes());
+        for (name, cert) in [("ca", &ca_cert), ("server", &server_cert), ("client", &client_cert)] {
+            self.install_certificate(name, cert.cert.pem(), cert.signing_key.serialize_pem());
+        }
 
-        info!("专业级自签名证书生成完成");
+        info!("本地 CA 证书链生成完成");
 
         Ok(())
     }
 
+    /// 本地 CA 的证书参数
+    ///
+    /// 签发证书时据此重建颁发者信息，因此主题名称和密钥用途必须由 CA 密钥唯一确定。
+    /// 组织单位取自 CA 公钥的指纹，使不同网关的 CA 主题名称互不相同。
+    fn ca_params(ca_key: &KeyPair) -> CertificateParams {
+        let key_fingerprint = Self::fingerprint(ca_key.public_key_raw());
+        let mut params = CertificateParams::default();
+        let mut name = DistinguishedName::new();
+        name.push(DnType::OrganizationName, CERT_ORGANIZATION);
+        name.push(DnType::OrganizationalUnitName, &key_fingerprint[..16]);
+        name.push(DnType::CommonName, CA_COMMON_NAME);
+        params.distinguished_name = name;
+        params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        let now = ::time::OffsetDateTime::now_utc();
+        params.not_before = now - ::time::Duration::days(1);
+        params.not_after = now + ::time::Duration::days(CA_VALIDITY_DAYS);
+        params
+    }
+
+    /// 终端证书的公共参数
+    ///
+    /// # 参数
+    ///
+    /// * `common_name` - 通用名称
+    /// * `usage` - 扩展密钥用途
+    fn leaf_params(&self, common_name: &str, usage: ExtendedKeyUsagePurpose) -> CertificateParams {
+        let mut params = CertificateParams::default();
+        let mut name = DistinguishedName::new();
+        name.push(DnType::OrganizationName, CERT_ORGANIZATION);
+        name.push(DnType::CommonName, common_name);
+        params.distinguished_name = name;
+        params.is_ca = IsCa::ExplicitNoCa;
+        // ECDSA 密钥只用于握手签名，密钥交换由临时 ECDHE 完成
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![usage];
+        params.use_authority_key_identifier_extension = true;
+        let now = ::time::OffsetDateTime::now_utc();
+        params.not_before = now - ::time::Duration::days(1);
+        params.not_after = now + ::time::Duration::days(LEAF_VALIDITY_DAYS);
+        params
+    }
+
+    /// 网关 URI 对应的 SAN
+    fn identity_uri_san(&self) -> Result<Option<SanType>> {
+        self.identity
+            .uri()
+            .map(|uri| {
+                uri.try_into()
+                    .map(SanType::URI)
+                    .map_err(|e| anyhow::anyhow!("无效的网关 URI: {e}"))
+            })
+            .transpose()
+    }
+
+    /// 用 CA 签发证书
+    fn sign_with_ca(params: &CertificateParams, ca_key: &KeyPair) -> Result<CertifiedKey<KeyPair>> {
+        let signing_key = KeyPair::generate().context("生成证书密钥失败")?;
+        let issuer = Issuer::new(Self::ca_params(ca_key), ca_key);
+        let cert = params
+            .signed_by(&signing_key, &issuer)
+            .context("CA 签发证书失败")?;
+        Ok(CertifiedKey { cert, signing_key })
+    }
+
     /// 创建 CA 证书
     ///
     /// # 返回值
     ///
     /// 生成的 CA 证书和密钥对
-    fn create_ca_certificate(&self) -> Result<rcgen::CertifiedKey> {
+    fn create_ca_certificate(&self) -> Result<CertifiedKey<KeyPair>> {
         info!("生成 CA 根证书");
-        
-        // 使用 rcgen 的简化 API 生成证书
-        let cert = rcgen::generate_simple_self_signed(vec!["WDIC Gateway CA".to_string()])
+
+        let signing_key = KeyPair::generate().context("生成 CA 密钥失败")?;
+        let cert = Self::ca_params(&signing_key)
+            .self_signed(&signing_key)
             .context("生成 CA 证书失败")?;
-        
-        debug!("CA 证书生成成功，PEM 长度: {} 字节", cert.cert.pem().len());
-        Ok(cert)
+
+        debug!("CA 证书生成成功，PEM 长度: {} 字节", cert.pem().len());
+        Ok(CertifiedKey { cert, signing_key })
     }
 
     /// 创建服务端证书
     ///
+    /// SAN 包含 localhost、回环地址、网关接口地址和网关 URI。
+    ///
     /// # 参数
     ///
-    /// * `ca_cert` - CA 证书用于签名
+    /// * `ca_key` - 用于签名的 CA 密钥
     ///
     /// # 返回值
     ///
     /// 生成的服务端证书和密钥对
-    fn create_server_certificate(&self, _ca_cert: &rcgen::CertifiedKey) -> Result<rcgen::CertifiedKey> {
+    fn create_server_certificate(&self, ca_key: &KeyPair) -> Result<CertifiedKey<KeyPair>> {
         info!("生成服务端证书");
-        
-        // 使用 rcgen 的简化 API 生成服务端证书
-        let cert = rcgen::generate_simple_self_signed(vec![
-            "WDIC Gateway Server".to_string(),
-            "localhost".to_string(),
-            "127.0.0.1".to_string(),
-            "::1".to_string()
-        ]).context("生成服务端证书失败")?;
-        
+
+        let mut params = self.leaf_params(&self.identity.name, ExtendedKeyUsagePurpose::ServerAuth);
+        for dns_name in &self.identity.dns_names {
+            let dns_name = dns_name
+                .clone()
+                .try_into()
+                .map_err(|e| anyhow::anyhow!("无效的 DNS 名称 {dns_name}: {e}"))?;
+            params.subject_alt_names.push(SanType::DnsName(dns_name));
+        }
+        params.subject_alt_names.extend(
+            self.identity
+                .ip_addresses
+                .iter()
+                .map(|ip| SanType::IpAddress(*ip)),
+        );
+        params.subject_alt_names.extend(self.identity_uri_san()?);
+
+        let cert = Self::sign_with_ca(&params, ca_key).context("生成服务端证书失败")?;
         debug!("服务端证书生成成功，PEM 长度: {} 字节", cert.cert.pem().len());
         Ok(cert)
     }
 
     /// 创建客户端证书
     ///
+    /// SAN 只包含网关 URI，对端据此识别网关身份。
+    ///
     /// # 参数
     ///
-    /// * `ca_cert` - CA 证书用于签名
+    /// * `ca_key` - 用于签名的 CA 密钥
     ///
     /// # 返回值
     ///
     /// 生成的客户端证书和密钥对
-    fn create_client_certificate(&self, _ca_cert: &rcgen::CertifiedKey) -> Result<rcgen::CertifiedKey> {
+    fn create_client_certificate(&self, ca_key: &KeyPair) -> Result<CertifiedKey<KeyPair>> {
         info!("生成客户端证书");
-        
-        // 使用 rcgen 的简化 API 生成客户端证书
-        let cert = rcgen::generate_simple_self_signed(vec!["WDIC Gateway Client".to_string()])
-            .context("生成客户端证书失败")?;
-        
+
+        let mut params = self.leaf_params(&self.identity.name, ExtendedKeyUsagePurpose::ClientAuth);
+        params.subject_alt_names.extend(self.identity_uri_san()?);
+
+        let cert = Self::sign_with_ca(&params, ca_key).context("生成客户端证书失败")?;
         debug!("客户端证书生成成功，PEM 长度: {} 字节", cert.cert.pem().len());
         Ok(cert)
     }
@@ -322,15 +709,7 @@ impl TlsManager {
 
     /// 保存私钥到文件
     fn save_private_key_to_file(&self, key_data: &[u8], path: &Path) -> Result<()> {
-        let mut file =
-            File::create(path).with_context(|| format!("创建私钥文件失败: {path:?}"))?;
-
-        file.write_all(key_data)
-            .with_context(|| format!("写入私钥文件失败: {path:?}"))?;
-
-        debug!("私钥已保存到: {path:?}");
-
-        Ok(())
+        write_private_key_file(path, key_data)
     }
 
     /// 加载证书到缓存
@@ -355,15 +734,77 @@ impl TlsManager {
         self.load_private_key_file(&server_key_path, "server")?;
         self.load_private_key_file(&client_key_path, "client")?;
 
-        info!(
-            "证书加载完成，缓存了 {} 个证书和 {} 个私钥",
-            self.cert_cache.len(),
-            self.key_cache.len()
-        );
+        // CA 私钥用于后续签发证书，缺失时只影响签发
+        let ca_key_path = ca_cert_path.with_extension("key");
+        if ca_key_path.exists() {
+            self.load_private_key_file(&ca_key_path, "ca")?;
+        }
+
+        let trusted_cas = self.load_trusted_cas();
+        *self.trusted_cas.get_mut().unwrap_or_else(|e| e.into_inner()) = trusted_cas;
+
+        let (cert_count, key_count, _) = self.get_certificate_stats();
+        info!("证书加载完成，缓存了 {cert_count} 个证书和 {key_count} 个私钥");
 
         Ok(())
     }
 
+    /// 受信任 CA 目录中的证书文件
+    fn trusted_ca_files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.config.trusted_ca_dir) else {
+            return Vec::new();
+        };
+
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("crt") | Some("pem")
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// 获取受信任 CA 目录中的证书
+    ///
+    /// # 返回值
+    ///
+    /// 文件名（不含扩展名）与 PEM 编码的 CA 证书
+    pub fn trusted_ca_certificates(&self) -> Result<Vec<(String, String)>> {
+        self.trusted_ca_files()
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .ok_or_else(|| anyhow::anyhow!("受信任 CA 文件名无效: {path:?}"))?
+                    .to_string();
+                let pem = std::fs::read_to_string(&path)
+                    .with_context(|| format!("读取受信任 CA 证书失败: {path:?}"))?;
+                Ok((name, pem))
+            })
+            .collect()
+    }
+
+    /// 加载受信任 CA 目录中的证书，无法解析的文件会被跳过
+    fn load_trusted_cas(&self) -> Vec<Vec<u8>> {
+        self.trusted_ca_files()
+            .into_iter()
+            .filter_map(|path| {
+                let der = std::fs::read(&path)
+                    .ok()
+                    .and_then(|data| Self::pem_to_der(&data).ok().flatten());
+                if der.is_none() {
+                    warn!("跳过无法解析的受信任 CA 证书: {path:?}");
+                }
+                der
+            })
+            .collect()
+    }
+
     /// 从文件加载证书
     fn load_certificate_file(&mut self, path: &Path, name: &str) -> Result<()> {
         if !path.exists() {
@@ -376,7 +817,10 @@ impl TlsManager {
         file.read_to_end(&mut cert_data)
             .with_context(|| format!("读取证书文件失败: {path:?}"))?;
 
-        self.cert_cache.insert(name.to_string(), cert_data);
+        self.cert_cache
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), cert_data);
 
         debug!("证书已加载: {name} -> {path:?}");
 
@@ -395,7 +839,10 @@ impl TlsManager {
         file.read_to_end(&mut key_data)
             .with_context(|| format!("读取私钥文件失败: {path:?}"))?;
 
-        self.key_cache.insert(name.to_string(), key_data);
+        self.key_cache
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), key_data);
 
         debug!("私钥已加载: {name} -> {path:?}");
 
@@ -403,27 +850,261 @@ impl TlsManager {
     }
 
     /// 获取证书数据
-    pub fn get_certificate(&self, name: &str) -> Option<&[u8]> {
-        self.cert_cache.get(name).map(|data| data.as_slice())
+    pub fn get_certificate(&self, name: &str) -> Option<Vec<u8>> {
+        self.cert_cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
     }
 
     /// 获取私钥数据
-    pub fn get_private_key(&self, name: &str) -> Option<&[u8]> {
-        self.key_cache.get(name).map(|data| data.as_slice())
+    pub fn get_private_key(&self, name: &str) -> Option<Vec<u8>> {
+        self.key_cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
     }
 
-    /// 验证证书
-    pub fn verify_certificate(&self, cert_data: &[u8]) -> Result<bool> {
-        if cert_data.is_empty() {
-            return Ok(false);
+    /// 将证书和私钥加入缓存，无需重启即可通过名称获取和验证
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 缓存名称
+    /// * `cert_pem` - 证书（PEM）
+    /// * `key_pem` - 私钥（PEM）
+    pub fn install_certificate(&self, name: &str, cert_pem: String, key_pem: String) {
+        self.cert_cache
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), cert_pem.into_bytes());
+        self.key_cache
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), key_pem.into_bytes());
+        debug!("证书已加入缓存: {name}");
+    }
+
+    /// 用本地 CA 签发证书
+    ///
+    /// # 参数
+    ///
+    /// * `params` - 证书参数
+    /// * `signing_key` - 证书主体的密钥对
+    ///
+    /// # 返回值
+    ///
+    /// 由本地 CA 签发的证书
+    pub fn sign_certificate(
+        &self,
+        params: &CertificateParams,
+        signing_key: &KeyPair,
+    ) -> Result<Certificate> {
+        let ca_key = self.ca_key()?;
+        let issuer = Issuer::new(Self::ca_params(&ca_key), &ca_key);
+        params
+            .signed_by(signing_key, &issuer)
+            .context("CA 签发证书失败")
+    }
+
+    /// 从缓存加载本地 CA 私钥
+    fn ca_key(&self) -> Result<KeyPair> {
+        let ca_key_pem = self
+            .get_private_key("ca")
+            .ok_or_else(|| anyhow::anyhow!("未找到 CA 私钥，无法签发证书"))?;
+        KeyPair::from_pem(&String::from_utf8_lossy(&ca_key_pem)).context("解析 CA 私钥失败")
+    }
+
+    /// 用本地 CA 重新签发服务端和客户端证书
+    ///
+    /// 新证书和私钥先全部写入临时文件，再依次用证书、私钥替换原文件，全部替换后才更新缓存。
+    /// QUIC 每次建立连接时从文件加载证书，因此新连接立即使用新证书，已有连接不受影响。
+    ///
+    /// # 返回值
+    ///
+    /// 新服务端和客户端证书的指纹
+    pub fn rotate_leaf_certificates(&self) -> Result<Vec<String>> {
+        let ca_key = self.ca_key()?;
+        let server_cert = self
+            .create_server_certificate(&ca_key)
+            .context("重新签发服务端证书失败")?;
+        let client_cert = self
+            .create_client_certificate(&ca_key)
+            .context("重新签发客户端证书失败")?;
+
+        let leaves = [
+            ("server", &server_cert, &self.config.server_cert_path, &self.config.server_key_path),
+            ("client", &client_cert, &self.config.client_cert_path, &self.config.client_key_path),
+        ];
+        // 先写好全部临时文件，任一写入失败时原有证书和私钥都保持不变
+        let mut staged = Vec::with_capacity(leaves.len());
+        for (name, cert, cert_path, key_path) in leaves {
+            let cert_pem = cert.cert.pem();
+            let key_pem = cert.signing_key.serialize_pem();
+            let cert_tmp = cert_path.with_extension("crt.tmp");
+            self.save_certificate_to_file(cert_pem.as_bytes(), &cert_tmp)?;
+            write_private_key_file(&key_path.with_extension("key.tmp"), key_pem.as_bytes())?;
+            staged.push((name, cert, cert_path, key_path, cert_pem, key_pem));
         }
 
-        // 尝试解析 PEM 格式的证书
+        // 先替换证书再替换私钥，全部就位后才更新缓存
+        for (_, _, cert_path, key_path, _, _) in &staged {
+            std::fs::rename(cert_path.with_extension("crt.tmp"), cert_path)
+                .with_context(|| format!("替换证书文件失败: {cert_path:?}"))?;
+            std::fs::rename(key_path.with_extension("key.tmp"), key_path)
+                .with_context(|| format!("替换私钥文件失败: {key_path:?}"))?;
+        }
+
+        let mut fingerprints = Vec::with_capacity(staged.len());
+        for (name, cert, _, _, cert_pem, key_pem) in staged {
+            fingerprints.push(Self::fingerprint(cert.cert.der()));
+            self.install_certificate(name, cert_pem, key_pem);
+        }
+
+        info!("服务端和客户端证书已由本地 CA 重新签发");
+        Ok(fingerprints)
+    }
+
+    /// 获取缓存中证书的详细信息
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 缓存名称
+    ///
+    /// # 返回值
+    ///
+    /// 证书信息，名称不存在时返回 None
+    pub fn certificate_info(&self, name: &str) -> Result<Option<CertificateInfo>> {
+        let Some(der_data) = self.certificate_der(name)? else {
+            return Ok(None);
+        };
+        let (_, cert) = X509Certificate::from_der(&der_data)
+            .map_err(|e| anyhow::anyhow!("X.509 证书解析失败: {}", e))?;
+
+        let to_system_time = |time: ASN1Time| {
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(time.timestamp().max(0) as u64)
+        };
+        let mut key_usage = Vec::new();
+        if let Ok(Some(usage)) = cert.key_usage() {
+            key_usage.extend(usage.value.to_string().split(", ").map(str::to_string));
+        }
+        if let Ok(Some(usage)) = cert.extended_key_usage() {
+            if usage.value.server_auth {
+                key_usage.push("Server Authentication".to_string());
+            }
+            if usage.value.client_auth {
+                key_usage.push("Client Authentication".to_string());
+            }
+        }
+
+        Ok(Some(CertificateInfo {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial_number: cert.raw_serial_as_string(),
+            not_before: to_system_time(cert.validity().not_before),
+            not_after: to_system_time(cert.validity().not_after),
+            fingerprint: Self::fingerprint(&der_data),
+            key_usage,
+        }))
+    }
+
+    /// 获取缓存中所有证书的名称
+    pub fn certificate_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .cert_cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 从本地 CA 私钥派生对称密钥
+    ///
+    /// 同一 CA 私钥和用途标签总是得到相同的密钥，因此派生密钥在重启后保持不变，
+    /// 更换网关身份后随之失效。
+    ///
+    /// # 参数
+    ///
+    /// * `label` - 用途标签，不同用途使用不同标签
+    ///
+    /// # 返回值
+    ///
+    /// 32 字节密钥
+    pub fn derive_key(&self, label: &[u8]) -> Result<[u8; 32]> {
+        use ring::hkdf;
+
+        let ca_key = self.ca_key()?;
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, label).extract(&ca_key.serialize_der());
+        let mut key = [0u8; 32];
+        prk.expand(&[label], hkdf::HKDF_SHA256)
+            .and_then(|okm| okm.fill(&mut key))
+            .map_err(|_| anyhow::anyhow!("派生密钥失败"))?;
+        Ok(key)
+    }
+
+    /// 用本地 CA 私钥签名数据
+    ///
+    /// 对端用配对时固定的 CA 证书验证签名，用于证书轮换通知等需要证明网关身份的消息。
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 待签名数据
+    ///
+    /// # 返回值
+    ///
+    /// 签名
+    pub fn sign_with_ca_key(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use rcgen::SigningKey;
+
+        self.ca_key()?
+            .sign(data)
+            .map_err(|e| anyhow::anyhow!("CA 签名失败: {e}"))
+    }
+
+    /// 用 CA 证书中的公钥验证签名
+    ///
+    /// # 参数
+    ///
+    /// * `ca_pem` - CA 证书（PEM）
+    /// * `data` - 被签名数据
+    /// * `signature_data` - 签名
+    ///
+    /// # 返回值
+    ///
+    /// 签名是否有效
+    pub fn verify_ca_signature(ca_pem: &str, data: &[u8], signature_data: &[u8]) -> Result<bool> {
+        let ca_der = Self::pem_to_der(ca_pem.as_bytes())?
+            .ok_or_else(|| anyhow::anyhow!("CA 证书不是 PEM 格式"))?;
+        let (_, ca_cert) = X509Certificate::from_der(&ca_der)
+            .map_err(|e| anyhow::anyhow!("CA 证书解析失败: {}", e))?;
+
+        // CA 证书是自签名的，其签名算法即 CA 密钥的算法
+        let Some(algorithm) = Self::verification_algorithm(&ca_cert.signature_algorithm.algorithm)
+        else {
+            return Ok(false);
+        };
+        Ok(
+            signature::UnparsedPublicKey::new(algorithm, &ca_cert.public_key().subject_public_key.data)
+                .verify(data, signature_data)
+                .is_ok(),
+        )
+    }
+
+    /// 将 PEM 格式的证书解码为 DER
+    ///
+    /// # 返回值
+    ///
+    /// DER 数据，不是 PEM 格式时返回 None
+    pub(crate) fn pem_to_der(cert_data: &[u8]) -> Result<Option<Vec<u8>>> {
         let cert_str = String::from_utf8_lossy(cert_data);
         if !cert_str.contains("-----BEGIN CERTIFICATE-----")
             || !cert_str.contains("-----END CERTIFICATE-----")
         {
-            return Ok(false);
+            return Ok(None);
         }
 
         // 提取 PEM 内容（去掉头尾标记）
@@ -433,15 +1114,54 @@ impl TlsManager {
             .collect::<Vec<_>>()
             .join("");
 
-        // 解码 Base64
         let der_data = BASE64_STANDARD
             .decode(pem_content.trim())
             .context("Base64 解码失败")?;
+        Ok(Some(der_data))
+    }
+
+    /// 获取缓存中证书的 DER 数据
+    fn certificate_der(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.get_certificate(name) {
+            Some(data) => Self::pem_to_der(&data),
+            None => Ok(None),
+        }
+    }
 
+    /// 验证证书
+    pub fn verify_certificate(&self, cert_data: &[u8]) -> Result<bool> {
+        if cert_data.is_empty() {
+            return Ok(false);
+        }
+
+        let Some(der_data) = Self::pem_to_der(cert_data)? else {
+            return Ok(false);
+        };
+
+        self.verify_certificate_der(&der_data)
+    }
+
+    /// 验证 DER 格式的证书
+    ///
+    /// 先检查吊销列表，再按验证模式检查有效期、由本地 CA 或受信任 CA 签发以及证书用途。
+    ///
+    /// # 参数
+    ///
+    /// * `der_data` - DER 编码的证书
+    ///
+    /// # 返回值
+    ///
+    /// 是否通过验证
+    pub fn verify_certificate_der(&self, der_data: &[u8]) -> Result<bool> {
         // 使用 x509-parser 解析证书
-        let (_, x509_cert) = X509Certificate::from_der(&der_data)
+        let (_, x509_cert) = X509Certificate::from_der(der_data)
             .map_err(|e| anyhow::anyhow!("X.509 证书解析失败: {}", e))?;
 
+        if self.is_certificate_revoked(der_data, &x509_cert)? {
+            warn!("证书已被吊销");
+            return Ok(false);
+        }
+
         match self.config.verify_mode {
             VerifyMode::None => Ok(true),
             VerifyMode::VerifyPeer => {
@@ -449,36 +1169,23 @@ impl TlsManager {
                 self.verify_certificate_validity(&x509_cert)
             }
             VerifyMode::MutualAuth => {
-                // 双向认证：检查有效期和签名
-                self.verify_certificate_validity(&x509_cert)?;
-                self.verify_certificate_signature(&x509_cert, &der_data)
+                // 双向认证：检查有效期和 CA 签名
+                Ok(self.verify_certificate_validity(&x509_cert)?
+                    && self.verify_certificate_signature(&x509_cert)?)
             }
             VerifyMode::Strict => {
                 // 严格模式：完整验证
-                self.verify_certificate_validity(&x509_cert)?;
-                self.verify_certificate_signature(&x509_cert, &der_data)?;
-                self.verify_certificate_chain(&x509_cert)
+                Ok(self.verify_certificate_validity(&x509_cert)?
+                    && self.verify_certificate_signature(&x509_cert)?
+                    && self.verify_certificate_chain(&x509_cert)?)
             }
         }
     }
 
     /// 验证证书有效期
     fn verify_certificate_validity(&self, cert: &X509Certificate) -> Result<bool> {
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
-
-        let not_before = cert.validity().not_before.timestamp();
-        let not_after = cert.validity().not_after.timestamp();
-
-        if now < not_before {
-            warn!("证书尚未生效");
-            return Ok(false);
-        }
-
-        if now > not_after {
-            warn!("证书已过期");
+        if !cert.validity().is_valid() {
+            warn!("证书不在有效期内");
             return Ok(false);
         }
 
@@ -486,74 +1193,518 @@ impl TlsManager {
         Ok(true)
     }
 
-    /// 验证证书签名
-    fn verify_certificate_signature(&self, cert: &X509Certificate, _cert_der: &[u8]) -> Result<bool> {
-        // 获取 CA 证书进行签名验证
-        if let Some(ca_cert_data) = self.cert_cache.get("ca") {
-            let ca_cert_str = String::from_utf8_lossy(ca_cert_data);
-            let ca_pem_content = ca_cert_str
-                .lines()
-                .filter(|line| !line.contains("-----"))
-                .collect::<Vec<_>>()
-                .join("");
-            
-            let ca_der_data = BASE64_STANDARD
-                .decode(ca_pem_content.trim())
-                .context("CA 证书 Base64 解码失败")?;
-
-            let (_, ca_x509_cert) = X509Certificate::from_der(&ca_der_data)
+    /// 验证证书由本地 CA 或受信任的 CA 签发
+    fn verify_certificate_signature(&self, cert: &X509Certificate) -> Result<bool> {
+        if self.issuing_ca(cert)?.is_some() {
+            debug!("证书签名验证通过");
+            return Ok(true);
+        }
+
+        warn!("证书不是由本地 CA 或受信任的 CA 签发");
+        Ok(false)
+    }
+
+    /// 查找签发证书的本地 CA 或受信任 CA
+    ///
+    /// # 返回值
+    ///
+    /// 签发该证书的 CA 证书（DER），没有匹配的 CA 时为 `None`
+    fn issuing_ca(&self, cert: &X509Certificate) -> Result<Option<Vec<u8>>> {
+        let Some(ca_der_data) = self.certificate_der("ca").context("CA 证书解码失败")? else {
+            warn!("未找到 CA 证书，无法验证签名");
+            return Ok(None);
+        };
+
+        let trusted_cas = self.trusted_cas.read().unwrap_or_else(|e| e.into_inner());
+        for ca_der in std::iter::once(&ca_der_data).chain(trusted_cas.iter()) {
+            let (_, ca_x509_cert) = X509Certificate::from_der(ca_der)
                 .map_err(|e| anyhow::anyhow!("CA 证书解析失败: {}", e))?;
 
-            // 验证签名算法
-            if cert.signature_algorithm != ca_x509_cert.signature_algorithm {
-                warn!("证书签名算法不匹配");
-                return Ok(false);
+            // 只用颁发者名称匹配的 CA 验证，避免对每个 CA 都记录不匹配警告
+            if cert.issuer() != ca_x509_cert.subject() {
+                continue;
             }
 
-            // 验证颁发者
-            if cert.issuer() != ca_x509_cert.subject() {
-                warn!("证书颁发者不匹配");
-                return Ok(false);
+            if !ca_x509_cert.validity().is_valid() {
+                warn!("CA 证书不在有效期内");
+                continue;
             }
 
-            debug!("证书签名验证通过");
-            Ok(true)
-        } else {
-            warn!("未找到 CA 证书，无法验证签名");
-            Ok(false)
+            if Self::is_issued_by(cert, &ca_x509_cert) {
+                return Ok(Some(ca_der.clone()));
+            }
         }
+
+        Ok(None)
     }
 
-    /// 验证证书链
+    /// 检查证书是否由指定 CA 签发
+    ///
+    /// 依次检查颁发者名称、CA 基本约束与密钥用途，并用 CA 公钥验证签名。
+    pub(crate) fn is_issued_by(cert: &X509Certificate, ca: &X509Certificate) -> bool {
+        if cert.issuer() != ca.subject() {
+            warn!("证书颁发者不匹配");
+            return false;
+        }
+
+        let is_ca = matches!(ca.basic_constraints(), Ok(Some(constraints)) if constraints.value.ca);
+        if !is_ca {
+            warn!("颁发者证书不是 CA 证书");
+            return false;
+        }
+
+        if let Ok(Some(key_usage)) = ca.key_usage() {
+            if !key_usage.value.key_cert_sign() {
+                warn!("颁发者证书不允许签发证书");
+                return false;
+            }
+        }
+
+        if !Self::verify_signature_with(cert, ca.public_key()) {
+            warn!("证书签名无效");
+            return false;
+        }
+
+        true
+    }
+
+    /// 使用颁发者公钥验证证书签名
+    fn verify_signature_with(cert: &X509Certificate, issuer_key: &SubjectPublicKeyInfo) -> bool {
+        let Some(algorithm) = Self::verification_algorithm(&cert.signature_algorithm.algorithm)
+        else {
+            return false;
+        };
+
+        signature::UnparsedPublicKey::new(algorithm, &issuer_key.subject_public_key.data)
+            .verify(cert.tbs_certificate.as_ref(), &cert.signature_value.data)
+            .is_ok()
+    }
+
+    /// 签名算法 OID 对应的验证算法
+    fn verification_algorithm(oid: &Oid<'_>) -> Option<&'static dyn signature::VerificationAlgorithm> {
+        let algorithm: &'static dyn signature::VerificationAlgorithm =
+            if *oid == OID_SIG_ECDSA_WITH_SHA256 {
+                &signature::ECDSA_P256_SHA256_ASN1
+            } else if *oid == OID_SIG_ECDSA_WITH_SHA384 {
+                &signature::ECDSA_P384_SHA384_ASN1
+            } else if *oid == OID_SIG_ED25519 {
+                &signature::ED25519
+            } else if *oid == OID_PKCS1_SHA256WITHRSA {
+                &signature::RSA_PKCS1_2048_8192_SHA256
+            } else if *oid == OID_PKCS1_SHA384WITHRSA {
+                &signature::RSA_PKCS1_2048_8192_SHA384
+            } else if *oid == OID_PKCS1_SHA512WITHRSA {
+                &signature::RSA_PKCS1_2048_8192_SHA512
+            } else {
+                warn!("不支持的签名算法: {oid}");
+                return None;
+            };
+        Some(algorithm)
+    }
+
+    /// 验证终端证书的用途
+    ///
+    /// 终端证书不能是 CA，必须允许数字签名并声明服务端或客户端认证用途。
     fn verify_certificate_chain(&self, cert: &X509Certificate) -> Result<bool> {
-        // 检查基本约束
         if let Ok(Some(basic_constraints)) = cert.basic_constraints() {
-            if basic_constraints.value.ca && basic_constraints.value.path_len_constraint.is_some() {
-                debug!("发现 CA 证书，路径约束: {:?}", basic_constraints.value.path_len_constraint);
+            if basic_constraints.value.ca {
+                warn!("终端证书不能是 CA 证书");
+                return Ok(false);
             }
         }
 
-        // 检查密钥用途
-        if let Ok(Some(key_usage)) = cert.key_usage() {
-            debug!("密钥用途: {:?}", key_usage);
+        match cert.key_usage() {
+            Ok(Some(key_usage)) if key_usage.value.digital_signature() => {}
+            _ => {
+                warn!("证书缺少数字签名用途");
+                return Ok(false);
+            }
         }
 
-        // 检查扩展密钥用途
-        if let Ok(Some(ext_key_usage)) = cert.extended_key_usage() {
-            debug!("扩展密钥用途: {:?}", ext_key_usage);
+        match cert.extended_key_usage() {
+            Ok(Some(ext_key_usage))
+                if ext_key_usage.value.server_auth || ext_key_usage.value.client_auth => {}
+            _ => {
+                warn!("证书缺少服务端或客户端认证用途");
+                return Ok(false);
+            }
         }
 
-        // 在实际生产环境中，这里应该实现完整的证书链验证
-        // 包括递归验证到根 CA，检查 CRL，OCSP 等
-        
-        debug!("证书链验证完成");
+        debug!("证书用途验证通过");
+        Ok(true)
+    }
+
+    /// 计算证书指纹
+    ///
+    /// # 参数
+    ///
+    /// * `der_data` - DER 编码的证书
+    ///
+    /// # 返回值
+    ///
+    /// SHA-256 指纹（小写十六进制）
+    pub fn fingerprint(der_data: &[u8]) -> String {
+        ring::digest::digest(&ring::digest::SHA256, der_data)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// 解析对端证书身份
+    ///
+    /// 网关唯一标识取自 SAN 中的 `urn:uuid:` URI。
+    ///
+    /// # 参数
+    ///
+    /// * `der_data` - 对端出示的 DER 编码证书
+    ///
+    /// # 返回值
+    ///
+    /// 对端证书身份，`verified` 表示是否按当前验证模式通过验证
+    pub fn peer_certificate(&self, der_data: &[u8]) -> Result<PeerCertificate> {
+        let (_, cert) = X509Certificate::from_der(der_data)
+            .map_err(|e| anyhow::anyhow!("X.509 证书解析失败: {}", e))?;
+
+        let gateway_id = Self::certificate_gateway_id(&cert);
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|name| name.as_str().ok())
+            .map(str::to_string);
+
+        Ok(PeerCertificate {
+            fingerprint: Self::fingerprint(der_data),
+            gateway_id,
+            common_name,
+            issuer_fingerprint: self
+                .issuing_ca(&cert)?
+                .map(|ca_der| Self::fingerprint(&ca_der)),
+            verified: self.verify_certificate_der(der_data)?,
+        })
+    }
+
+    /// 证书 SAN 中 `urn:uuid:` URI 声明的网关唯一标识
+    pub(crate) fn certificate_gateway_id(cert: &X509Certificate) -> Option<Uuid> {
+        cert.subject_alternative_name()
+            .ok()
+            .flatten()
+            .and_then(|san| {
+                san.value.general_names.iter().find_map(|name| match name {
+                    GeneralName::URI(uri) => uri
+                        .strip_prefix("urn:uuid:")
+                        .and_then(|id| Uuid::parse_str(id).ok()),
+                    _ => None,
+                })
+            })
+    }
+
+    /// 检查证书是否被吊销
+    ///
+    /// 网关唯一标识、证书指纹或签发 CA 的指纹出现在吊销列表中即视为吊销。
+    fn is_certificate_revoked(&self, der_data: &[u8], cert: &X509Certificate) -> Result<bool> {
+        let revocations = self.revocations.read().unwrap_or_else(|e| e.into_inner());
+        if revocations.is_empty() {
+            return Ok(false);
+        }
+
+        let gateway_id = Self::certificate_gateway_id(cert);
+        let fingerprint = Self::fingerprint(der_data);
+        let issuer_fingerprint = self
+            .issuing_ca(cert)?
+            .map(|ca_der| Self::fingerprint(&ca_der));
+        let mut fingerprints = vec![fingerprint.as_str()];
+        fingerprints.extend(issuer_fingerprint.as_deref());
+
+        Ok(revocations
+            .iter()
+            .any(|revocation| revocation.covers(gateway_id.as_ref(), &fingerprints)))
+    }
+
+    /// 吊销列表文件路径
+    fn revocations_path(&self) -> PathBuf {
+        self.config.ca_cert_path.with_file_name(REVOCATIONS_FILE)
+    }
+
+    /// 加载吊销列表，文件不存在或无法解析时为空
+    fn load_revocations(&self) -> Vec<Revocation> {
+        let path = self.revocations_path();
+        let Ok(data) = std::fs::read(&path) else {
+            return Vec::new();
+        };
+
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            warn!("无法解析吊销列表 {path:?}: {e}");
+            Vec::new()
+        })
+    }
+
+    /// 吊销设备证书
+    ///
+    /// 吊销记录写入吊销列表文件，此后覆盖的证书都无法通过验证。
+    /// 不能吊销本地网关自身。
+    ///
+    /// # 参数
+    ///
+    /// * `revocation` - 吊销记录
+    ///
+    /// # 返回值
+    ///
+    /// 是否为新的吊销记录，已覆盖相同网关和指纹时返回 `false`
+    pub fn revoke(&self, revocation: Revocation) -> Result<bool> {
+        if revocation.gateway_id.is_none() && revocation.fingerprints.is_empty() {
+            return Err(anyhow::anyhow!("吊销记录没有指定网关或证书指纹"));
+        }
+
+        let mut local_fingerprints = Vec::new();
+        for name in ["ca", "server", "client"] {
+            if let Some(der) = self.certificate_der(name)? {
+                local_fingerprints.push(Self::fingerprint(&der));
+            }
+        }
+        let local_fingerprints: Vec<&str> = local_fingerprints.iter().map(String::as_str).collect();
+        if revocation.covers(self.identity.gateway_id.as_ref(), &local_fingerprints) {
+            return Err(anyhow::anyhow!("不能吊销本地网关自身的证书"));
+        }
+
+        let mut revocations = self.revocations.write().unwrap_or_else(|e| e.into_inner());
+        let known = revocations.iter().any(|existing| {
+            existing.gateway_id == revocation.gateway_id
+                && revocation
+                    .fingerprints
+                    .iter()
+                    .all(|fingerprint| existing.fingerprints.contains(fingerprint))
+        });
+        if known {
+            return Ok(false);
+        }
+
+        let mut updated = revocations.clone();
+        updated.push(revocation);
+        let path = self.revocations_path();
+        let data = serde_json::to_vec_pretty(&updated).context("序列化吊销列表失败")?;
+        std::fs::write(&path, data).with_context(|| format!("写入吊销列表失败: {path:?}"))?;
+        *revocations = updated;
+
+        info!("已更新吊销列表 {path:?}");
+        Ok(true)
+    }
+
+    /// 获取吊销列表
+    pub fn revocations(&self) -> Vec<Revocation> {
+        self.revocations
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 检查对端证书是否被吊销
+    pub fn is_revoked(&self, certificate: &PeerCertificate) -> bool {
+        self.revocations
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|revocation| revocation.covers_peer(certificate))
+    }
+
+    /// 检查网关是否被吊销
+    pub fn is_gateway_revoked(&self, gateway_id: &Uuid) -> bool {
+        self.revocations
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|revocation| revocation.gateway_id.as_ref() == Some(gateway_id))
+    }
+
+    /// 获取本地 CA 证书（PEM）
+    pub fn ca_certificate_pem(&self) -> Option<String> {
+        self.get_certificate("ca")
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+
+    /// 获取本地 CA 证书指纹
+    ///
+    /// # 返回值
+    ///
+    /// SHA-256 指纹（小写十六进制）
+    pub fn ca_fingerprint(&self) -> Result<String> {
+        let ca_der = self
+            .certificate_der("ca")
+            .context("CA 证书解码失败")?
+            .ok_or_else(|| anyhow::anyhow!("未找到 CA 证书"))?;
+        Ok(Self::fingerprint(&ca_der))
+    }
+
+    /// 解析 PEM 格式的 CA 证书并计算指纹
+    ///
+    /// # 参数
+    ///
+    /// * `ca_pem` - PEM 编码的 CA 证书
+    ///
+    /// # 返回值
+    ///
+    /// SHA-256 指纹，内容不是有效的 CA 证书时返回错误
+    pub fn ca_pem_fingerprint(ca_pem: &str) -> Result<String> {
+        let ca_der = Self::pem_to_der(ca_pem.as_bytes())?
+            .ok_or_else(|| anyhow::anyhow!("CA 证书不是 PEM 格式"))?;
+        let (_, ca_cert) = X509Certificate::from_der(&ca_der)
+            .map_err(|e| anyhow::anyhow!("CA 证书解析失败: {}", e))?;
+        if !ca_cert.is_ca() {
+            return Err(anyhow::anyhow!("证书不是 CA 证书"));
+        }
+        Ok(Self::fingerprint(&ca_der))
+    }
+
+    /// 将对端 CA 证书加入受信任目录
+    ///
+    /// 由该 CA 签发的证书随即可以通过验证，新建的 QUIC 连接也会信任它。
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 受信任 CA 的文件名（不含扩展名）
+    /// * `ca_pem` - PEM 编码的 CA 证书
+    ///
+    /// # 返回值
+    ///
+    /// CA 证书指纹
+    pub fn trust_ca(&self, name: &str, ca_pem: &str) -> Result<String> {
+        let fingerprint = Self::ca_pem_fingerprint(ca_pem)?;
+        create_dir_all(&self.config.trusted_ca_dir)
+            .with_context(|| format!("创建受信任 CA 目录失败: {:?}", self.config.trusted_ca_dir))?;
+
+        let path = self.config.trusted_ca_dir.join(format!("{name}.crt"));
+        std::fs::write(&path, ca_pem)
+            .with_context(|| format!("写入受信任 CA 证书失败: {path:?}"))?;
+        self.reload_trusted_cas();
+
+        info!("信任 CA 证书 {path:?}，指纹 {fingerprint}");
+        Ok(fingerprint)
+    }
+
+    /// 将 CA 证书移出受信任目录
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 受信任 CA 的文件名（不含扩展名）
+    ///
+    /// # 返回值
+    ///
+    /// 是否移除了证书
+    pub fn untrust_ca(&self, name: &str) -> Result<bool> {
+        let path = self.config.trusted_ca_dir.join(format!("{name}.crt"));
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        std::fs::remove_file(&path)
+            .with_context(|| format!("删除受信任 CA 证书失败: {path:?}"))?;
+        self.reload_trusted_cas();
+
+        info!("不再信任 CA 证书 {path:?}");
         Ok(true)
     }
 
+    /// 重新加载受信任 CA 目录
+    pub fn reload_trusted_cas(&self) {
+        let trusted_cas = self.load_trusted_cas();
+        *self.trusted_cas.write().unwrap_or_else(|e| e.into_inner()) = trusted_cas;
+    }
+
+    /// 检查 TLS 策略能否在 QUIC 上落实
+    ///
+    /// QUIC 只使用 TLS 1.3，且协议栈不支持禁用任何 TLS 1.3 密码套件，
+    /// 因此策略必须启用 TLS 1.3 并包含 [`QUIC_CIPHER_SUITES`] 中的全部套件，
+    /// 否则拒绝建立 QUIC 连接，而不是以与配置不符的参数运行。
+    ///
+    /// quiche 不公开握手实际协商出的版本和密码套件，握手完成后无法再逐个连接核对，
+    /// 因此 TLS 策略只在此处落实：[`Self::configure_quic`] 生成每个连接配置前都会调用本方法。
+    ///
+    /// # 返回值
+    ///
+    /// 策略无法在 QUIC 上落实时返回错误
+    pub fn check_quic_policy(&self) -> Result<()> {
+        self.config.validate_policy()?;
+        if !self.config.tls_versions.contains(&TlsVersion::Tls13) {
+            return Err(anyhow::anyhow!("QUIC 要求 TLS 1.3，但 TLS 策略未启用 TLSv1.3"));
+        }
+        let missing: Vec<&str> = QUIC_CIPHER_SUITES
+            .into_iter()
+            .filter(|suite| !self.config.cipher_suites.iter().any(|s| s == suite))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "QUIC 无法禁用密码套件 {}，请将其加入 cipher_suites 或关闭 QUIC",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// 将证书配置到 QUIC 连接配置中
+    ///
+    /// 服务端出示服务端证书，客户端出示客户端证书；本地 CA 和受信任 CA 用于验证对端。
+    ///
+    /// # 参数
+    ///
+    /// * `config` - QUIC 连接配置
+    /// * `role` - 连接中的角色
+    /// * `verify_peer` - 是否要求并验证对端证书
+    ///
+    /// # 返回值
+    ///
+    /// 配置结果，TLS 策略无法在 QUIC 上落实时返回错误
+    pub fn configure_quic(
+        &self,
+        config: &mut quiche::Config,
+        role: QuicRole,
+        verify_peer: bool,
+    ) -> Result<()> {
+        self.check_quic_policy()?;
+
+        let (cert_path, key_path) = match role {
+            QuicRole::Server => (&self.config.server_cert_path, &self.config.server_key_path),
+            QuicRole::Client => (&self.config.client_cert_path, &self.config.client_key_path),
+        };
+
+        config
+            .load_cert_chain_from_pem_file(Self::path_str(cert_path)?)
+            .map_err(|e| anyhow::anyhow!("加载证书链失败 {cert_path:?}: {e}"))?;
+        config
+            .load_priv_key_from_pem_file(Self::path_str(key_path)?)
+            .map_err(|e| anyhow::anyhow!("加载私钥失败 {key_path:?}: {e}"))?;
+
+        let ca_files =
+            std::iter::once(self.config.ca_cert_path.clone()).chain(self.trusted_ca_files());
+        for ca_file in ca_files {
+            config
+                .load_verify_locations_from_file(Self::path_str(&ca_file)?)
+                .map_err(|e| anyhow::anyhow!("加载 CA 证书失败 {ca_file:?}: {e}"))?;
+        }
+
+        config.verify_peer(verify_peer);
+        debug!("QUIC {role:?} 证书配置完成，验证对端: {verify_peer}");
+
+        Ok(())
+    }
+
+    /// 将路径转换为 quiche 接受的字符串
+    fn path_str(path: &Path) -> Result<&str> {
+        path.to_str()
+            .ok_or_else(|| anyhow::anyhow!("证书路径包含无效字符: {path:?}"))
+    }
+
     /// 验证对等证书
+    ///
+    /// 即使验证模式为 `None`，被吊销的证书也不会通过。
     pub fn verify_peer_certificate(&self, peer_cert: &[u8]) -> Result<bool> {
         if self.config.verify_mode == VerifyMode::None {
-            return Ok(true);
+            let Some(der_data) = Self::pem_to_der(peer_cert)? else {
+                return Ok(true);
+            };
+            let (_, x509_cert) = X509Certificate::from_der(&der_data)
+                .map_err(|e| anyhow::anyhow!("X.509 证书解析失败: {}", e))?;
+            return Ok(!self.is_certificate_revoked(&der_data, &x509_cert)?);
         }
 
         self.verify_certificate(peer_cert)
@@ -564,10 +1715,7 @@ impl TlsManager {
         self.config
             .tls_versions
             .iter()
-            .map(|v| match v {
-                TlsVersion::Tls12 => "TLSv1.2",
-                TlsVersion::Tls13 => "TLSv1.3",
-            })
+            .map(TlsVersion::as_str)
             .collect::<Vec<_>>()
             .join(",")
     }
@@ -592,8 +1740,8 @@ impl TlsManager {
 
     /// 获取证书统计信息
     pub fn get_certificate_stats(&self) -> (usize, usize, bool) {
-        let cert_count = self.cert_cache.len();
-        let key_count = self.key_cache.len();
+        let cert_count = self.cert_cache.read().unwrap_or_else(|e| e.into_inner()).len();
+        let key_count = self.key_cache.read().unwrap_or_else(|e| e.into_inner()).len();
         let mtls_ready = self.is_mutual_auth_enabled() && cert_count >= 3 && key_count >= 2;
 
         (cert_count, key_count, mtls_ready)
@@ -605,13 +1753,53 @@ impl TlsManager {
     }
 }
 
+/// 保存私钥到文件，Unix 上只允许所有者读写
+///
+/// # 参数
+///
+/// * `path` - 私钥文件路径
+/// * `key_data` - 私钥数据（PEM）
+///
+/// # 返回值
+///
+/// 写入结果
+pub(crate) fn write_private_key_file(path: &Path, key_data: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("创建私钥文件失败: {path:?}"))?;
+
+    // 文件已存在时创建模式不生效，需要显式收紧权限
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("设置私钥文件权限失败: {path:?}"))?;
+    }
+
+    file.write_all(key_data)
+        .with_context(|| format!("写入私钥文件失败: {path:?}"))?;
+
+    debug!("私钥已保存到: {path:?}");
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use tempfile::tempdir;
     use crate::gateway::VerifyMode;
     use crate::gateway::TlsVersion;
     
-    use crate::gateway::tls::{MtlsConfig, TlsManager};
+    use crate::gateway::tls::{write_private_key_file, MtlsConfig, TlsManager};
+    use rcgen::{CertificateParams, ExtendedKeyUsagePurpose, KeyPair, KeyUsagePurpose};
 
     #[test]
     fn test_mtls_config_default() {
@@ -748,13 +1936,13 @@ mod tests {
         println!("✓ CA 证书创建测试通过");
 
         // 测试服务端证书创建
-        let server_cert = manager.create_server_certificate(&ca_cert)?;
+        let server_cert = manager.create_server_certificate(&ca_cert.signing_key)?;
         assert!(server_cert.cert.pem().len() > 100);
         assert!(server_cert.signing_key.serialize_pem().len() > 100);
         println!("✓ 服务端证书创建测试通过");
 
         // 测试客户端证书创建
-        let client_cert = manager.create_client_certificate(&ca_cert)?;
+        let client_cert = manager.create_client_certificate(&ca_cert.signing_key)?;
         assert!(client_cert.cert.pem().len() > 100);
         assert!(client_cert.signing_key.serialize_pem().len() > 100);
         println!("✓ 客户端证书创建测试通过");
@@ -839,4 +2027,248 @@ mod tests {
         println!("✓ 证书加载和验证测试通过");
         Ok(())
     }
+
+    #[test]
+    fn test_ca_signed_certificate_chain() -> anyhow::Result<()> {
+        use crate::gateway::tls::CertificateIdentity;
+        use x509_parser::prelude::*;
+
+        let config_in = |dir: &std::path::Path| MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            verify_mode: VerifyMode::Strict,
+            ..Default::default()
+        };
+        let gateway_id = uuid::Uuid::new_v4();
+        let lan_ip: std::net::IpAddr = "192.168.1.20".parse()?;
+        let identity = CertificateIdentity::for_gateway("测试网关", gateway_id, &[lan_ip]);
+
+        let temp_dir = tempdir()?;
+        let manager = TlsManager::with_identity(config_in(temp_dir.path()), identity)?;
+        let server_pem = manager.get_certificate("server").unwrap();
+        let client_pem = manager.get_certificate("client").unwrap();
+
+        // 服务端和客户端证书都能通过本地 CA 的链验证，CA 证书本身不能作为终端证书
+        assert!(manager.verify_certificate(&server_pem)?);
+        assert!(manager.verify_certificate(&client_pem)?);
+        assert!(!manager.verify_certificate(&manager.get_certificate("ca").unwrap())?);
+
+        // SAN 和扩展密钥用途由网关身份派生
+        let server_der = TlsManager::pem_to_der(&server_pem)?.unwrap();
+        let (_, server) = X509Certificate::from_der(&server_der)?;
+        let names = &server.subject_alternative_name()?.unwrap().value.general_names;
+        let uri = format!("urn:uuid:{gateway_id}");
+        assert!(names.contains(&GeneralName::DNSName("localhost")));
+        assert!(names.contains(&GeneralName::URI(&uri)));
+        assert!(names.contains(&GeneralName::IPAddress(&[192, 168, 1, 20])));
+        assert!(names.contains(&GeneralName::IPAddress(&[127, 0, 0, 1])));
+        let server_eku = server.extended_key_usage()?.unwrap().value;
+        assert!(server_eku.server_auth && !server_eku.client_auth);
+        let server_ku = server.key_usage()?.unwrap().value;
+        assert!(server_ku.digital_signature() && !server_ku.key_encipherment());
+
+        let client_der = TlsManager::pem_to_der(&client_pem)?.unwrap();
+        let (_, client) = X509Certificate::from_der(&client_der)?;
+        let client_eku = client.extended_key_usage()?.unwrap().value;
+        assert!(client_eku.client_auth && !client_eku.server_auth);
+
+        // 另一个 CA 签发的证书无法通过验证
+        let other_dir = tempdir()?;
+        let other = TlsManager::new(config_in(other_dir.path()))?;
+        assert!(!manager.verify_certificate(&other.get_certificate("server").unwrap())?);
+
+        // 旧版本遗留的自签名服务端证书会在初始化时被重新签发
+        let legacy = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        std::fs::write(temp_dir.path().join("server.crt"), legacy.cert.pem())?;
+        let reloaded = TlsManager::new(config_in(temp_dir.path()))?;
+        assert!(reloaded.verify_certificate(&reloaded.get_certificate("server").unwrap())?);
+
+        // 从 CA 私钥派生的密钥在重启后保持不变，不同用途和不同 CA 得到不同密钥
+        let restarted = TlsManager::new(config_in(temp_dir.path()))?;
+        assert_eq!(restarted.derive_key(b"label")?, reloaded.derive_key(b"label")?);
+        assert_ne!(reloaded.derive_key(b"label")?, reloaded.derive_key(b"other")?);
+        assert_ne!(reloaded.derive_key(b"label")?, other.derive_key(b"label")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_and_install_certificate() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let config = MtlsConfig {
+            ca_cert_path: temp_dir.path().join("ca.crt"),
+            server_cert_path: temp_dir.path().join("server.crt"),
+            server_key_path: temp_dir.path().join("server.key"),
+            client_cert_path: temp_dir.path().join("client.crt"),
+            client_key_path: temp_dir.path().join("client.key"),
+            verify_mode: VerifyMode::Strict,
+            ..Default::default()
+        };
+        let manager = TlsManager::new(config)?;
+
+        let mut params = CertificateParams::new(vec!["nas.local".to_string()])?;
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let signing_key = KeyPair::generate_for(&rcgen::PKCS_ED25519)?;
+        let cert = manager.sign_certificate(&params, &signing_key)?;
+
+        // 运行时签发的证书可以直接通过本地 CA 的链验证，并按名称从缓存获取
+        assert!(manager.verify_certificate(cert.pem().as_bytes())?);
+        assert!(manager.get_certificate("nas.local").is_none());
+        manager.install_certificate("nas.local", cert.pem(), signing_key.serialize_pem());
+        assert_eq!(manager.get_certificate("nas.local"), Some(cert.pem().into_bytes()));
+        assert!(manager.get_private_key("nas.local").is_some());
+
+        // 私钥文件只允许所有者读写
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let key_path = temp_dir.path().join("nas.key");
+            write_private_key_file(&key_path, signing_key.serialize_pem().as_bytes())?;
+            let mode = std::fs::metadata(&key_path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_peer_certificate_identity_and_trusted_ca() -> anyhow::Result<()> {
+        use crate::gateway::tls::CertificateIdentity;
+
+        let config_in = |dir: &std::path::Path| MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            trusted_ca_dir: dir.join("trusted"),
+            ..Default::default()
+        };
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let id_b = uuid::Uuid::new_v4();
+        let manager_a = TlsManager::new(config_in(dir_a.path()))?;
+        let manager_b = TlsManager::with_identity(
+            config_in(dir_b.path()),
+            CertificateIdentity::for_gateway("网关 B", id_b, &[]),
+        )?;
+
+        // 对端证书身份来自 SAN 中的网关 URI，未受信任的 CA 签发时标记为未验证
+        let client_b = manager_b.get_certificate("client").unwrap();
+        let der_b = TlsManager::pem_to_der(&client_b)?.unwrap();
+        let identity = manager_a.peer_certificate(&der_b)?;
+        assert_eq!(identity.gateway_id, Some(id_b));
+        assert_eq!(identity.common_name.as_deref(), Some("网关 B"));
+        assert_eq!(identity.fingerprint, TlsManager::fingerprint(&der_b));
+        assert_eq!(identity.fingerprint.len(), 64);
+        assert!(!identity.verified);
+
+        // 将 B 的 CA 放入受信任目录后重新加载即可通过验证
+        std::fs::create_dir_all(dir_a.path().join("trusted"))?;
+        std::fs::copy(dir_b.path().join("ca.crt"), dir_a.path().join("trusted/b.crt"))?;
+        let manager_a = TlsManager::new(config_in(dir_a.path()))?;
+        assert!(manager_a.peer_certificate(&der_b)?.verified);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_revoked_certificates_fail_verification() -> anyhow::Result<()> {
+        use crate::gateway::tls::{CertificateIdentity, Revocation};
+        use chrono::Utc;
+
+        let config_in = |dir: &std::path::Path| MtlsConfig {
+            ca_cert_path: dir.join("ca.crt"),
+            server_cert_path: dir.join("server.crt"),
+            server_key_path: dir.join("server.key"),
+            client_cert_path: dir.join("client.crt"),
+            client_key_path: dir.join("client.key"),
+            trusted_ca_dir: dir.join("trusted"),
+            verify_mode: VerifyMode::None,
+            ..Default::default()
+        };
+        let (dir_a, dir_b) = (tempdir()?, tempdir()?);
+        let (id_a, id_b) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let manager_a = TlsManager::with_identity(
+            config_in(dir_a.path()),
+            CertificateIdentity::for_gateway("网关 A", id_a, &[]),
+        )?;
+        let manager_b = TlsManager::with_identity(
+            config_in(dir_b.path()),
+            CertificateIdentity::for_gateway("网关 B", id_b, &[]),
+        )?;
+        let client_b = manager_b.get_certificate("client").unwrap();
+        assert!(manager_a.verify_peer_certificate(&client_b)?);
+
+        let revocation = Revocation {
+            gateway_id: Some(id_b),
+            fingerprints: vec![manager_b.ca_fingerprint()?],
+            reason: "设备丢失".to_string(),
+            revoked_at: Utc::now(),
+            revoked_by: id_a,
+        };
+        assert!(manager_a.revoke(revocation.clone())?);
+        assert!(!manager_a.revoke(revocation.clone())?);
+
+        // 即使不验证对端，被吊销的证书也会被拒绝
+        assert!(!manager_a.verify_peer_certificate(&client_b)?);
+        let der_b = TlsManager::pem_to_der(&client_b)?.unwrap();
+        assert!(manager_a.is_revoked(&manager_a.peer_certificate(&der_b)?));
+        assert!(manager_a.is_gateway_revoked(&id_b));
+
+        // 吊销列表持久化，重新加载后依然生效
+        let manager_a = TlsManager::new(config_in(dir_a.path()))?;
+        assert_eq!(manager_a.revocations(), vec![revocation]);
+        assert!(!manager_a.verify_peer_certificate(&client_b)?);
+
+        // 不能吊销本地网关自身
+        let own = Revocation {
+            gateway_id: None,
+            fingerprints: vec![manager_a.ca_fingerprint()?],
+            reason: "误操作".to_string(),
+            revoked_at: Utc::now(),
+            revoked_by: id_a,
+        };
+        assert!(manager_a.revoke(own).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tls_policy_enforcement() -> anyhow::Result<()> {
+        // 默认策略可以在 QUIC 上落实
+        let default_config = MtlsConfig::default();
+        default_config.validate_policy()?;
+
+        // 未知套件、套件所属版本未启用都是无效策略
+        let mut unknown = MtlsConfig::default();
+        unknown.cipher_suites.push("TLS_RSA_WITH_RC4_128_SHA".to_string());
+        assert!(unknown.validate_policy().is_err());
+        let tls12_only = MtlsConfig {
+            tls_versions: vec![TlsVersion::Tls12],
+            ..Default::default()
+        };
+        assert!(tls12_only.validate_policy().is_err());
+
+        // 有效但无法在 QUIC 上落实的策略拒绝生成 QUIC 配置
+        let temp_dir = tempdir()?;
+        let manager = TlsManager::new(MtlsConfig {
+            ca_cert_path: temp_dir.path().join("ca.crt"),
+            server_cert_path: temp_dir.path().join("server.crt"),
+            server_key_path: temp_dir.path().join("server.key"),
+            client_cert_path: temp_dir.path().join("client.crt"),
+            client_key_path: temp_dir.path().join("client.key"),
+            trusted_ca_dir: temp_dir.path().join("trusted"),
+            cipher_suites: vec!["TLS_AES_256_GCM_SHA384".to_string()],
+            ..Default::default()
+        })?;
+        manager.config().validate_policy()?;
+        let error = manager.check_quic_policy().unwrap_err().to_string();
+        assert!(error.contains("TLS_CHACHA20_POLY1305_SHA256"));
+
+        Ok(())
+    }
 }
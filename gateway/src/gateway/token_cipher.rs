@@ -0,0 +1,493 @@
+//! UDP 令牌端到端加密模块
+//!
+//! UDP 令牌（目录搜索、文件响应、信息消息等）即使经过签名，内容仍以明文在局域网中传输。
+//! 本模块为已配对网关之间的令牌提供 ChaCha20-Poly1305 加密：
+//!
+//! - 点对点令牌使用双方配对时固定的 X25519 公钥经 HKDF 派生的密钥加密，
+//!   注册表中可被发现或中继消息更新的公钥不参与派生；
+//! - 广播令牌可选地使用发送者的群组密钥加密。每个网关持有自己的随机群组密钥，
+//!   已配对的网关在收到无法解密的广播后，以点对点加密的令牌向发送者索取该密钥。
+//!
+//! 解除配对或吊销设备后群组密钥会轮换，被移除的设备无法再解密后续广播。
+
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use dashmap::DashMap;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+use crate::gateway::crypto::{decode_public_key, AgreementKeyPair, SessionKey, SESSION_KEY_LEN};
+use crate::gateway::pairing::PairingManager;
+use crate::gateway::registry::Registry;
+
+/// 点对点令牌加密的密钥用途标签
+const TOKEN_KEY_CONTEXT: &[u8] = b"wdic-udp-token-v1";
+
+/// 群组令牌附加认证数据的域分隔前缀
+const GROUP_TOKEN_CONTEXT: &[u8] = b"wdic-udp-group-v1";
+
+/// 加密令牌的接收方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenRecipient {
+    /// 发给指定网关，使用点对点密钥加密
+    Peer(Uuid),
+    /// 广播给发送者的所有配对网关，使用指定 ID 的群组密钥加密
+    Group(Uuid),
+}
+
+/// 加密令牌
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SealedToken {
+    /// 发送者网关 ID
+    pub sender_id: Uuid,
+    /// 接收方
+    pub recipient: TokenRecipient,
+    /// 密文（Base64）
+    pub ciphertext: String,
+}
+
+impl SealedToken {
+    /// 是否为点对点加密的令牌
+    pub fn is_pairwise(&self) -> bool {
+        matches!(self.recipient, TokenRecipient::Peer(_))
+    }
+
+    /// 附加认证数据，绑定发送者和接收方，防止令牌被改投
+    fn aad(sender_id: &Uuid, recipient: &TokenRecipient) -> Vec<u8> {
+        let (context, recipient_id) = match recipient {
+            TokenRecipient::Peer(peer_id) => (TOKEN_KEY_CONTEXT, peer_id),
+            TokenRecipient::Group(key_id) => (GROUP_TOKEN_CONTEXT, key_id),
+        };
+        let mut aad = Vec::with_capacity(context.len() + 32);
+        aad.extend_from_slice(context);
+        aad.extend_from_slice(sender_id.as_bytes());
+        aad.extend_from_slice(recipient_id.as_bytes());
+        aad
+    }
+}
+
+/// 群组密钥
+#[derive(Clone)]
+struct GroupKey {
+    /// 密钥 ID，每次轮换重新生成
+    key_id: Uuid,
+    /// 密钥字节
+    secret: [u8; SESSION_KEY_LEN],
+}
+
+impl GroupKey {
+    /// 生成新的随机群组密钥
+    fn generate() -> Result<Self> {
+        let mut secret = [0u8; SESSION_KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| anyhow!("生成群组密钥失败"))?;
+        Ok(Self {
+            key_id: Uuid::new_v4(),
+            secret,
+        })
+    }
+
+    fn session_key(&self) -> Result<SessionKey> {
+        SessionKey::from_bytes(&self.secret)
+    }
+}
+
+/// UDP 令牌加密器
+///
+/// 只有已配对且在注册表中发布了密钥协商公钥的网关才能收发加密令牌。
+pub struct TokenCipher {
+    /// 注册表，提供本网关 ID
+    registry: Arc<Registry>,
+    /// 配对管理器，只有受信任设备参与加密通信，并提供配对时固定的密钥协商公钥
+    pairing: Arc<PairingManager>,
+    /// 本网关密钥协商密钥对
+    agreement_key: Arc<AgreementKeyPair>,
+    /// 是否加密广播令牌
+    encrypt_broadcasts: bool,
+    /// 本网关的群组密钥
+    group_key: RwLock<GroupKey>,
+    /// 已配对网关的群组密钥，以网关 ID 为键
+    peer_group_keys: DashMap<Uuid, GroupKey>,
+}
+
+impl TokenCipher {
+    /// 创建令牌加密器
+    ///
+    /// # 参数
+    ///
+    /// * `registry` - 注册表
+    /// * `pairing` - 配对管理器
+    /// * `agreement_key` - 本网关密钥协商密钥对
+    /// * `encrypt_broadcasts` - 是否使用群组密钥加密广播令牌
+    ///
+    /// # 返回值
+    ///
+    /// 加密器实例
+    pub fn new(
+        registry: Arc<Registry>,
+        pairing: Arc<PairingManager>,
+        agreement_key: Arc<AgreementKeyPair>,
+        encrypt_broadcasts: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            registry,
+            pairing,
+            agreement_key,
+            encrypt_broadcasts,
+            group_key: RwLock::new(GroupKey::generate()?),
+            peer_group_keys: DashMap::new(),
+        })
+    }
+
+    /// 获取本网关 ID
+    pub fn local_id(&self) -> Uuid {
+        self.registry.local_entry().id
+    }
+
+    /// 是否使用群组密钥加密广播令牌
+    pub fn encrypts_broadcasts(&self) -> bool {
+        self.encrypt_broadcasts
+    }
+
+    /// 检查网关是否为可以接收加密令牌的受信任设备
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    pub fn is_trusted(&self, gateway_id: &Uuid) -> bool {
+        self.pairing.is_trusted(gateway_id)
+    }
+
+    /// 获取本网关当前群组密钥的 ID
+    pub fn group_key_id(&self) -> Uuid {
+        self.group_key
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .key_id
+    }
+
+    /// 派生与受信任设备之间的点对点密钥
+    ///
+    /// 只使用配对时固定的公钥，旧版本配对记录没有固定公钥时需要重新配对。
+    fn pairwise_key(&self, peer_id: &Uuid) -> Result<SessionKey> {
+        if !self.pairing.is_trusted(peer_id) {
+            return Err(anyhow!("网关 {peer_id} 不是受信任设备"));
+        }
+        let public_key = self
+            .pairing
+            .pinned_agreement_key(peer_id)
+            .ok_or_else(|| anyhow!("网关 {peer_id} 的配对记录没有固定密钥协商公钥，请重新配对"))?;
+        self.agreement_key
+            .derive_session_key(&decode_public_key(&public_key)?, TOKEN_KEY_CONTEXT)
+    }
+
+    /// 使用点对点密钥加密令牌
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 接收方网关 ID，必须是受信任设备
+    /// * `plaintext` - 序列化后的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 加密令牌，接收方未配对或没有公钥时返回错误
+    pub fn seal_for_peer(&self, peer_id: Uuid, plaintext: &[u8]) -> Result<SealedToken> {
+        let sender_id = self.local_id();
+        let recipient = TokenRecipient::Peer(peer_id);
+        let ciphertext = self
+            .pairwise_key(&peer_id)?
+            .seal(plaintext, &SealedToken::aad(&sender_id, &recipient))?;
+
+        Ok(SealedToken {
+            sender_id,
+            recipient,
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// 使用本网关的群组密钥加密令牌
+    ///
+    /// # 参数
+    ///
+    /// * `plaintext` - 序列化后的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 加密令牌
+    pub fn seal_for_group(&self, plaintext: &[u8]) -> Result<SealedToken> {
+        let sender_id = self.local_id();
+        let group_key = self
+            .group_key
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let recipient = TokenRecipient::Group(group_key.key_id);
+        let ciphertext = group_key
+            .session_key()?
+            .seal(plaintext, &SealedToken::aad(&sender_id, &recipient))?;
+
+        Ok(SealedToken {
+            sender_id,
+            recipient,
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// 解密令牌
+    ///
+    /// # 参数
+    ///
+    /// * `sealed` - 加密令牌
+    ///
+    /// # 返回值
+    ///
+    /// 序列化后的令牌；尚未获得发送者的群组密钥时返回 `None`，
+    /// 发送者不受信任、令牌不是发给本网关或密文无效时返回错误
+    pub fn open(&self, sealed: &SealedToken) -> Result<Option<Vec<u8>>> {
+        let local_id = self.local_id();
+        let key = match sealed.recipient {
+            TokenRecipient::Peer(peer_id) => {
+                if peer_id != local_id {
+                    return Err(anyhow!("加密令牌的接收方不是本网关"));
+                }
+                self.pairwise_key(&sealed.sender_id)?
+            }
+            TokenRecipient::Group(key_id) => {
+                let group_key = if sealed.sender_id == local_id {
+                    Some(
+                        self.group_key
+                            .read()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .clone(),
+                    )
+                } else if self.pairing.is_trusted(&sealed.sender_id) {
+                    self.peer_group_keys
+                        .get(&sealed.sender_id)
+                        .map(|entry| entry.clone())
+                } else {
+                    return Err(anyhow!("网关 {} 不是受信任设备", sealed.sender_id));
+                };
+                match group_key.filter(|group_key| group_key.key_id == key_id) {
+                    Some(group_key) => group_key.session_key()?,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let ciphertext = BASE64_STANDARD
+            .decode(&sealed.ciphertext)
+            .map_err(|e| anyhow!("加密令牌编码无效: {e}"))?;
+        let plaintext = key.open(
+            &ciphertext,
+            &SealedToken::aad(&sealed.sender_id, &sealed.recipient),
+        )?;
+        Ok(Some(plaintext))
+    }
+
+    /// 导出本网关当前的群组密钥
+    ///
+    /// 只能放入点对点加密的令牌中发送给受信任设备。
+    ///
+    /// # 返回值
+    ///
+    /// 群组密钥 ID 与 Base64 编码的密钥
+    pub fn export_group_key(&self) -> (Uuid, String) {
+        let group_key = self.group_key.read().unwrap_or_else(|e| e.into_inner());
+        (group_key.key_id, BASE64_STANDARD.encode(group_key.secret))
+    }
+
+    /// 保存受信任设备发来的群组密钥
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 群组密钥所属的网关 ID
+    /// * `key_id` - 群组密钥 ID
+    /// * `key` - Base64 编码的密钥
+    ///
+    /// # 返回值
+    ///
+    /// 发送者不受信任或密钥格式无效时返回错误
+    pub fn install_group_key(&self, sender_id: Uuid, key_id: Uuid, key: &str) -> Result<()> {
+        if !self.pairing.is_trusted(&sender_id) {
+            return Err(anyhow!("网关 {sender_id} 不是受信任设备，拒绝其群组密钥"));
+        }
+        let secret: [u8; SESSION_KEY_LEN] = BASE64_STANDARD
+            .decode(key)
+            .map_err(|e| anyhow!("群组密钥编码无效: {e}"))?
+            .try_into()
+            .map_err(|_| anyhow!("群组密钥长度必须为 {SESSION_KEY_LEN} 字节"))?;
+
+        self.peer_group_keys
+            .insert(sender_id, GroupKey { key_id, secret });
+        Ok(())
+    }
+
+    /// 轮换本网关的群组密钥
+    ///
+    /// 解除配对后调用，受信任设备会在收到新密钥加密的广播后重新索取密钥。
+    pub fn rotate_group_key(&self) -> Result<()> {
+        let group_key = GroupKey::generate()?;
+        *self.group_key.write().unwrap_or_else(|e| e.into_inner()) = group_key;
+        Ok(())
+    }
+
+    /// 忘记网关的群组密钥
+    ///
+    /// # 参数
+    ///
+    /// * `gateway_id` - 网关 ID
+    pub fn forget(&self, gateway_id: &Uuid) {
+        self.peer_group_keys.remove(gateway_id);
+    }
+}
+
+impl std::fmt::Debug for TokenCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenCipher")
+            .field("encrypt_broadcasts", &self.encrypt_broadcasts)
+            .field("group_key_id", &self.group_key_id())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::pairing::TrustedDevice;
+    use chrono::Utc;
+    use std::net::SocketAddr;
+
+    struct Peer {
+        id: Uuid,
+        registry: Arc<Registry>,
+        pairing: Arc<PairingManager>,
+        cipher: TokenCipher,
+    }
+
+    fn peer(name: &str, port: u16) -> Peer {
+        let address: SocketAddr = format!("127.0.0.1:{port}").parse().unwrap();
+        let registry = Arc::new(Registry::new(name.to_string(), address));
+        let agreement_key = Arc::new(AgreementKeyPair::generate().unwrap());
+        registry.set_local_public_key(agreement_key.public_key_base64());
+        let pairing = Arc::new(PairingManager::new(
+            std::env::temp_dir().join(format!("wdic_token_cipher_{}.json", Uuid::new_v4())),
+            String::new(),
+        ));
+        let cipher = TokenCipher::new(
+            Arc::clone(&registry),
+            Arc::clone(&pairing),
+            agreement_key,
+            true,
+        )
+        .unwrap();
+        Peer {
+            id: registry.local_entry().id,
+            registry,
+            pairing,
+            cipher,
+        }
+    }
+
+    /// 让 `a` 认识并信任 `b`
+    fn trust(a: &Peer, b: &Peer) {
+        a.registry.add_or_update(b.registry.local_entry());
+        let mut devices = a.pairing.devices();
+        devices.push(TrustedDevice {
+            gateway_id: b.id,
+            name: b.registry.local_entry().name,
+            fingerprint: String::new(),
+            certificate: String::new(),
+            paired_at: Utc::now(),
+            leaf_fingerprints: Vec::new(),
+            rotated_at: None,
+            agreement_key: b.registry.local_entry().public_key,
+        });
+        a.pairing.restore(devices).unwrap();
+    }
+
+    fn untrust(a: &Peer, b: &Peer) {
+        a.pairing.revoke(&b.id).unwrap();
+    }
+
+    #[test]
+    fn test_pairwise_tokens_only_open_for_recipient() {
+        let (alice, bob, eve) = (peer("A", 1), peer("B", 2), peer("E", 3));
+        trust(&alice, &bob);
+        trust(&bob, &alice);
+        trust(&eve, &alice);
+        eve.registry.add_or_update(bob.registry.local_entry());
+
+        let plaintext = b"{\"FileResponse\":{}}";
+        let sealed = alice.cipher.seal_for_peer(bob.id, plaintext).unwrap();
+        assert!(!BASE64_STANDARD
+            .decode(&sealed.ciphertext)
+            .unwrap()
+            .windows(plaintext.len())
+            .any(|window| window == plaintext));
+        assert_eq!(
+            bob.cipher.open(&sealed).unwrap().as_deref(),
+            Some(&plaintext[..])
+        );
+
+        // 第三方即使信任发送者也无法解密，改投后的令牌同样无效
+        assert!(eve.cipher.open(&sealed).is_err());
+        let mut redirected = sealed.clone();
+        redirected.recipient = TokenRecipient::Peer(eve.id);
+        assert!(eve.cipher.open(&redirected).is_err());
+
+        // 注册表中的公钥被替换后，加密仍使用配对时固定的公钥
+        let mut forged = bob.registry.local_entry();
+        forged.public_key = eve.registry.local_entry().public_key;
+        alice.registry.add_or_update(forged);
+        let sealed = alice.cipher.seal_for_peer(bob.id, plaintext).unwrap();
+        assert_eq!(
+            bob.cipher.open(&sealed).unwrap().as_deref(),
+            Some(&plaintext[..])
+        );
+
+        // 无法向未配对的网关发送加密令牌
+        assert!(alice.cipher.seal_for_peer(eve.id, plaintext).is_err());
+    }
+
+    #[test]
+    fn test_group_key_distribution_and_rotation() {
+        let (alice, bob) = (peer("A", 1), peer("B", 2));
+        trust(&alice, &bob);
+        trust(&bob, &alice);
+
+        let sealed = alice.cipher.seal_for_group(b"search").unwrap();
+        assert_eq!(
+            alice.cipher.open(&sealed).unwrap().as_deref(),
+            Some(&b"search"[..])
+        );
+        // 尚未获得群组密钥
+        assert_eq!(bob.cipher.open(&sealed).unwrap(), None);
+
+        let (key_id, key) = alice.cipher.export_group_key();
+        bob.cipher
+            .install_group_key(alice.id, key_id, &key)
+            .unwrap();
+        assert_eq!(
+            bob.cipher.open(&sealed).unwrap().as_deref(),
+            Some(&b"search"[..])
+        );
+
+        // 解除配对后轮换密钥，旧密钥无法解密新广播
+        untrust(&alice, &bob);
+        alice.cipher.rotate_group_key().unwrap();
+        let rotated = alice.cipher.seal_for_group(b"search").unwrap();
+        assert_eq!(bob.cipher.open(&rotated).unwrap(), None);
+
+        // 不接受未受信任网关的群组密钥
+        untrust(&bob, &alice);
+        let (key_id, key) = alice.cipher.export_group_key();
+        assert!(bob
+            .cipher
+            .install_group_key(alice.id, key_id, &key)
+            .is_err());
+        assert!(bob.cipher.open(&rotated).is_err());
+    }
+}
@@ -0,0 +1,536 @@
+//! 数据报传输层模块
+//!
+//! 抽象网络管理器底层的数据报收发，默认使用 UDP 套接字，
+//! 同时提供进程内的内存网络，便于在测试中模拟多个网关、受限的网络拓扑和 NAT。
+
+use dashmap::{DashMap, DashSet};
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 数据报传输接口
+///
+/// 所有方法均为非阻塞：没有可读数据时 `recv_from` 返回 `WouldBlock`。
+pub trait DatagramTransport: Send + Sync + std::fmt::Debug {
+    /// 获取本地绑定地址
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    /// 向目标地址发送数据报
+    ///
+    /// # 参数
+    ///
+    /// * `buf` - 数据内容
+    /// * `target` - 目标地址
+    ///
+    /// # 返回值
+    ///
+    /// 发送的字节数
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize>;
+
+    /// 接收一个数据报
+    ///
+    /// # 参数
+    ///
+    /// * `buf` - 接收缓冲区
+    ///
+    /// # 返回值
+    ///
+    /// 接收的字节数和发送者地址
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
+}
+
+/// UDP 数据报传输
+#[derive(Debug)]
+pub struct UdpTransport {
+    /// 非阻塞 UDP 套接字
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    /// 绑定 UDP 传输
+    ///
+    /// # 参数
+    ///
+    /// * `local_addr` - 本地监听地址
+    ///
+    /// # 返回值
+    ///
+    /// 已启用广播且处于非阻塞模式的 UDP 传输
+    pub fn bind(local_addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local_addr)?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+}
+
+impl DatagramTransport for UdpTransport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+        self.socket.send_to(buf, target)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.socket.recv_from(buf)
+    }
+}
+
+/// 可替换底层实现的数据报传输
+///
+/// 网络接口变化需要重新绑定时，替换底层传输即可让所有持有者切换到新的套接字。
+#[derive(Debug)]
+pub struct SwappableTransport {
+    /// 当前底层传输
+    inner: RwLock<Arc<dyn DatagramTransport>>,
+}
+
+impl SwappableTransport {
+    /// 创建可替换的传输
+    ///
+    /// # 参数
+    ///
+    /// * `inner` - 初始底层传输
+    ///
+    /// # 返回值
+    ///
+    /// 可替换传输实例
+    pub fn new(inner: Arc<dyn DatagramTransport>) -> Self {
+        Self {
+            inner: RwLock::new(inner),
+        }
+    }
+
+    /// 替换底层传输
+    ///
+    /// # 参数
+    ///
+    /// * `inner` - 新的底层传输
+    ///
+    /// # 返回值
+    ///
+    /// 被替换的底层传输
+    pub fn replace(&self, inner: Arc<dyn DatagramTransport>) -> Arc<dyn DatagramTransport> {
+        let mut guard = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *guard, inner)
+    }
+
+    /// 获取当前底层传输
+    fn current(&self) -> Arc<dyn DatagramTransport> {
+        Arc::clone(&self.inner.read().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl DatagramTransport for SwappableTransport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.current().local_addr()
+    }
+
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+        self.current().send_to(buf, target)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.current().recv_from(buf)
+    }
+}
+
+/// 内存网络中单个端点的接收队列
+type MemoryQueue = Arc<Mutex<VecDeque<(Vec<u8>, SocketAddr)>>>;
+
+/// 模拟 NAT 的映射状态
+///
+/// 采用端点无关映射和地址端口受限过滤（端口受限锥形 NAT）：
+/// 所有出站数据报使用同一个公网端点，入站数据报只有来自内部曾发送过的远端地址时才放行。
+#[derive(Debug)]
+struct NatMapping {
+    /// 已放行的远端地址
+    permitted: DashSet<SocketAddr>,
+}
+
+/// 内存网络中的端点
+#[derive(Debug, Clone)]
+struct MemoryEndpoint {
+    /// 接收队列
+    queue: MemoryQueue,
+    /// NAT 映射（位于 NAT 之后时）
+    nat: Option<Arc<NatMapping>>,
+}
+
+/// 内存网络共享状态
+#[derive(Debug)]
+struct MemoryNetworkInner {
+    /// 按可达地址（NAT 之后的端点为公网映射地址）索引的端点
+    endpoints: DashMap<SocketAddr, MemoryEndpoint>,
+    /// 被阻断的地址对（双向）
+    blocked: DashSet<(SocketAddr, SocketAddr)>,
+    /// 下一个自动分配的端口
+    next_port: AtomicU16,
+    /// 成功投递的数据报数量
+    delivered: AtomicU64,
+    /// 被丢弃的数据报数量
+    dropped: AtomicU64,
+}
+
+impl MemoryNetworkInner {
+    /// 规范化地址对，使阻断规则与方向无关
+    fn pair(a: SocketAddr, b: SocketAddr) -> (SocketAddr, SocketAddr) {
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+/// 进程内内存网络
+///
+/// 多个 [`MemoryTransport`] 通过同一个内存网络互相收发数据报，
+/// 可以阻断任意两个端点之间的直接通信以模拟不可达的网络拓扑，
+/// 也可以将端点置于模拟 NAT 之后以验证 UDP 打洞。
+/// 与 UDP 一样，发往未绑定、被阻断或被 NAT 过滤的地址的数据报会被静默丢弃。
+#[derive(Debug, Clone)]
+pub struct MemoryNetwork {
+    inner: Arc<MemoryNetworkInner>,
+}
+
+impl MemoryNetwork {
+    /// 创建新的内存网络
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(MemoryNetworkInner {
+                endpoints: DashMap::new(),
+                blocked: DashSet::new(),
+                next_port: AtomicU16::new(40000),
+                delivered: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// 在内存网络上绑定端点
+    ///
+    /// # 参数
+    ///
+    /// * `local_addr` - 本地地址，端口为 0 时自动分配
+    ///
+    /// # 返回值
+    ///
+    /// 内存传输实例，地址已被占用时返回错误
+    pub fn bind(&self, mut local_addr: SocketAddr) -> io::Result<Arc<MemoryTransport>> {
+        if local_addr.port() == 0 {
+            local_addr.set_port(self.allocate_port());
+        }
+
+        self.register(local_addr, local_addr, None)
+    }
+
+    /// 在模拟 NAT 之后绑定端点
+    ///
+    /// 端点对外使用 `public_ip` 上自动分配的端口作为公网映射地址，
+    /// 其他端点只能看到并回复该映射地址。
+    ///
+    /// # 参数
+    ///
+    /// * `local_addr` - NAT 内部的本地地址
+    /// * `public_ip` - NAT 的公网 IP
+    ///
+    /// # 返回值
+    ///
+    /// 内存传输实例
+    pub fn bind_behind_nat(
+        &self,
+        local_addr: SocketAddr,
+        public_ip: IpAddr,
+    ) -> io::Result<Arc<MemoryTransport>> {
+        let public_addr = SocketAddr::new(public_ip, self.allocate_port());
+        let nat = Arc::new(NatMapping {
+            permitted: DashSet::new(),
+        });
+
+        self.register(local_addr, public_addr, Some(nat))
+    }
+
+    /// 分配一个自动端口
+    fn allocate_port(&self) -> u16 {
+        self.inner.next_port.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 在可达地址上注册端点
+    fn register(
+        &self,
+        local_addr: SocketAddr,
+        reachable_addr: SocketAddr,
+        nat: Option<Arc<NatMapping>>,
+    ) -> io::Result<Arc<MemoryTransport>> {
+        let queue: MemoryQueue = Arc::new(Mutex::new(VecDeque::new()));
+        match self.inner.endpoints.entry(reachable_addr) {
+            dashmap::mapref::entry::Entry::Occupied(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("内存网络地址 {reachable_addr} 已被占用"),
+                ));
+            }
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                slot.insert(MemoryEndpoint {
+                    queue: Arc::clone(&queue),
+                    nat: nat.clone(),
+                });
+            }
+        }
+
+        Ok(Arc::new(MemoryTransport {
+            local_addr,
+            reachable_addr,
+            queue,
+            nat,
+            network: Arc::clone(&self.inner),
+        }))
+    }
+
+    /// 阻断两个端点之间的直接通信（双向）
+    pub fn block(&self, a: SocketAddr, b: SocketAddr) {
+        self.inner.blocked.insert(MemoryNetworkInner::pair(a, b));
+    }
+
+    /// 恢复两个端点之间的直接通信
+    pub fn unblock(&self, a: SocketAddr, b: SocketAddr) {
+        self.inner.blocked.remove(&MemoryNetworkInner::pair(a, b));
+    }
+
+    /// 检查两个端点之间是否被阻断
+    pub fn is_blocked(&self, a: SocketAddr, b: SocketAddr) -> bool {
+        self.inner.blocked.contains(&MemoryNetworkInner::pair(a, b))
+    }
+
+    /// 获取成功投递的数据报数量
+    pub fn delivered_count(&self) -> u64 {
+        self.inner.delivered.load(Ordering::Relaxed)
+    }
+
+    /// 获取被丢弃的数据报数量
+    pub fn dropped_count(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for MemoryNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 内存网络上的数据报传输端点
+#[derive(Debug)]
+pub struct MemoryTransport {
+    /// 本地地址
+    local_addr: SocketAddr,
+    /// 其他端点看到的地址（位于 NAT 之后时为公网映射地址）
+    reachable_addr: SocketAddr,
+    /// 本端点的接收队列
+    queue: MemoryQueue,
+    /// NAT 映射（位于 NAT 之后时）
+    nat: Option<Arc<NatMapping>>,
+    /// 所属内存网络
+    network: Arc<MemoryNetworkInner>,
+}
+
+impl MemoryTransport {
+    /// 获取其他端点看到的地址
+    ///
+    /// 位于模拟 NAT 之后时为公网映射地址，否则与本地地址相同。
+    pub fn public_addr(&self) -> SocketAddr {
+        self.reachable_addr
+    }
+}
+
+impl DatagramTransport for MemoryTransport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+        let source = self.reachable_addr;
+
+        // 出站数据报在本端 NAT 上为目标地址开放入站过滤
+        if let Some(nat) = &self.nat {
+            nat.permitted.insert(target);
+        }
+
+        let blocked = self
+            .network
+            .blocked
+            .contains(&MemoryNetworkInner::pair(source, target));
+        let endpoint = self
+            .network
+            .endpoints
+            .get(&target)
+            .map(|entry| entry.value().clone());
+
+        // 对端位于 NAT 之后时，只接受其内部曾主动联系过的地址
+        let deliverable = endpoint.filter(|endpoint| {
+            !blocked
+                && endpoint
+                    .nat
+                    .as_ref()
+                    .is_none_or(|nat| nat.permitted.contains(&source))
+        });
+
+        match deliverable {
+            Some(endpoint) => {
+                endpoint
+                    .queue
+                    .lock()
+                    .map_err(|_| io::Error::other("内存网络队列锁已损坏"))?
+                    .push_back((buf.to_vec(), source));
+                self.network.delivered.fetch_add(1, Ordering::Relaxed);
+            }
+            None => {
+                self.network.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let next = self
+            .queue
+            .lock()
+            .map_err(|_| io::Error::other("内存网络队列锁已损坏"))?
+            .pop_front();
+
+        match next {
+            Some((data, sender)) => {
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                Ok((len, sender))
+            }
+            None => Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        }
+    }
+}
+
+impl Drop for MemoryTransport {
+    fn drop(&mut self) {
+        self.network.endpoints.remove(&self.reachable_addr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn test_memory_network_delivery_and_blocking() {
+        let network = MemoryNetwork::new();
+        let a = network.bind(addr(41001)).unwrap();
+        let b = network.bind(addr(41002)).unwrap();
+        let c = network.bind(addr(41003)).unwrap();
+        network.block(addr(41001), addr(41003));
+
+        a.send_to(b"hello", addr(41002)).unwrap();
+        a.send_to(b"blocked", addr(41003)).unwrap();
+        c.send_to(b"blocked", addr(41001)).unwrap();
+
+        let mut buf = [0u8; 64];
+        let (len, sender) = b.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"hello");
+        assert_eq!(sender, addr(41001));
+
+        assert_eq!(
+            c.recv_from(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(
+            a.recv_from(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(network.delivered_count(), 1);
+        assert_eq!(network.dropped_count(), 2);
+
+        // 重复绑定同一地址应失败，释放后可重新绑定
+        assert!(network.bind(addr(41002)).is_err());
+        drop(b);
+        assert!(network.bind(addr(41002)).is_ok());
+    }
+
+    #[test]
+    fn test_memory_network_nat_filtering() {
+        let network = MemoryNetwork::new();
+        let server = network.bind(addr(41101)).unwrap();
+        let nat_a = network
+            .bind_behind_nat(
+                "10.0.1.2:41102".parse().unwrap(),
+                "203.0.113.1".parse().unwrap(),
+            )
+            .unwrap();
+        let nat_b = network
+            .bind_behind_nat(
+                "10.0.2.2:41103".parse().unwrap(),
+                "203.0.113.2".parse().unwrap(),
+            )
+            .unwrap();
+        let (public_a, public_b) = (nat_a.public_addr(), nat_b.public_addr());
+        let mut buf = [0u8; 64];
+
+        // 对外只暴露公网映射地址，本地地址保持不变
+        assert_eq!(
+            nat_a.local_addr().unwrap(),
+            "10.0.1.2:41102".parse().unwrap()
+        );
+        nat_a.send_to(b"hello", addr(41101)).unwrap();
+        let (_, sender) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(sender, public_a);
+
+        // 服务器可以回复，但未联系过的 NAT 端点会过滤入站数据报
+        server.send_to(b"reply", public_a).unwrap();
+        assert!(nat_a.recv_from(&mut buf).is_ok());
+        server.send_to(b"unsolicited", public_b).unwrap();
+        assert!(nat_b.recv_from(&mut buf).is_err());
+
+        // 双方同时向对方发送后形成打洞路径
+        nat_a.send_to(b"probe", public_b).unwrap();
+        assert!(nat_b.recv_from(&mut buf).is_err());
+        nat_b.send_to(b"probe", public_a).unwrap();
+        let (_, sender) = nat_a.recv_from(&mut buf).unwrap();
+        assert_eq!(sender, public_b);
+        nat_a.send_to(b"ack", public_b).unwrap();
+        let (_, sender) = nat_b.recv_from(&mut buf).unwrap();
+        assert_eq!(sender, public_a);
+    }
+
+    #[test]
+    fn test_swappable_transport_replace() {
+        let network = MemoryNetwork::new();
+        let peer = network.bind(addr(41201)).unwrap();
+        let first = network.bind(addr(41202)).unwrap();
+        let second = network.bind(addr(41203)).unwrap();
+        let swappable = SwappableTransport::new(first);
+        let mut buf = [0u8; 64];
+
+        swappable.send_to(b"before", addr(41201)).unwrap();
+        let (_, sender) = peer.recv_from(&mut buf).unwrap();
+        assert_eq!(sender, addr(41202));
+
+        // 替换后收发都走新的底层传输
+        let previous = swappable.replace(second);
+        assert_eq!(previous.local_addr().unwrap(), addr(41202));
+        assert_eq!(swappable.local_addr().unwrap(), addr(41203));
+        swappable.send_to(b"after", addr(41201)).unwrap();
+        let (_, sender) = peer.recv_from(&mut buf).unwrap();
+        assert_eq!(sender, addr(41203));
+
+        peer.send_to(b"reply", addr(41203)).unwrap();
+        let (len, _) = swappable.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"reply");
+    }
+}
@@ -1,23 +1,26 @@
 //! UDP 广播协议模块
 //!
 //! 实现基于 UDP 的 WDIC 协议自主广播功能，支持 IPv4/IPv6 双栈网络，所有网关都是一等公民。
-//! 性能优化版本：使用 SmallVec 减少堆分配，使用 AHash 提升 HashMap 性能。
+//! 性能优化版本：使用 SmallVec 减少堆分配。
 
-use ahash::AHashMap;
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::borrow::Cow;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock as StdRwLock};
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::time::Duration;
 use uuid::Uuid;
 
+use crate::gateway::envelope::MessageAuthenticator;
+use crate::gateway::mount_rules::{MountFilter, MountRules};
 use crate::gateway::protocol::WdicMessage;
-use crate::gateway::security::{PathValidator, SecureFileReader, SearchResultFilter};
+use crate::gateway::rate_limit::{InboundRateLimiter, MessageClass, RateVerdict};
+use crate::gateway::security::{PathValidator, SearchResultFilter};
+use crate::gateway::tauri_api::DirectoryEntry as TauriDirectoryEntry;
+use crate::gateway::token_cipher::{SealedToken, TokenCipher};
 
 /// UDP 广播令牌类型
 /// 性能优化：使用 SmallVec 减少小集合的堆分配
@@ -31,6 +34,9 @@ pub enum UdpToken {
         keywords: SmallVec<[String; 4]>,
         /// 搜索 ID
         search_id: Uuid,
+        /// 出示的搜索令牌
+        #[serde(default)]
+        search_token: Option<String>,
     },
     /// 目录搜索响应令牌
     DirectorySearchResponse {
@@ -49,6 +55,9 @@ pub enum UdpToken {
         file_path: String,
         /// 请求 ID
         request_id: Uuid,
+        /// 出示的搜索令牌
+        #[serde(default)]
+        search_token: Option<String>,
     },
     /// 文件响应令牌
     FileResponse {
@@ -61,6 +70,56 @@ pub enum UdpToken {
         /// 错误信息
         error: Option<String>,
     },
+    /// 列目录请求令牌
+    DirectoryList {
+        /// 请求者 ID
+        requester_id: Uuid,
+        /// 目录路径
+        path: String,
+        /// 请求 ID
+        request_id: Uuid,
+        /// 出示的搜索令牌
+        #[serde(default)]
+        search_token: Option<String>,
+    },
+    /// 列目录响应令牌
+    DirectoryListResponse {
+        /// 响应者 ID
+        responder_id: Uuid,
+        /// 请求 ID
+        request_id: Uuid,
+        /// 请求者有权读取的目录条目
+        entries: Vec<TauriDirectoryEntry>,
+    },
+    /// 分享兑现请求令牌
+    ///
+    /// 文件分享返回 `FileResponse`，文件夹分享返回路径相对于分享根目录的 `DirectoryListResponse`。
+    /// 发给受信任设备时连同密码一起以点对点密钥加密。
+    ShareRequest {
+        /// 请求者 ID
+        requester_id: Uuid,
+        /// 分享码
+        code: String,
+        /// 访问密码
+        #[serde(default)]
+        password: Option<String>,
+        /// 文件夹分享中的相对路径，为空时指分享本身
+        #[serde(default)]
+        path: Option<String>,
+        /// 请求 ID
+        request_id: Uuid,
+    },
+    /// 错误令牌，请求被拒绝时返回稳定的错误代码
+    Error {
+        /// 响应者 ID
+        responder_id: Uuid,
+        /// 被拒绝的请求 ID
+        request_id: Uuid,
+        /// 错误代码
+        code: u32,
+        /// 错误描述
+        message: String,
+    },
     /// 信息发送令牌
     InfoMessage {
         /// 发送者 ID
@@ -81,6 +140,70 @@ pub enum UdpToken {
         /// 测试开始时间
         start_time: chrono::DateTime<chrono::Utc>,
     },
+    /// 群组密钥请求令牌，只在受信任设备之间以点对点加密的方式发送
+    GroupKeyRequest {
+        /// 请求者 ID
+        requester_id: Uuid,
+    },
+    /// 群组密钥令牌，只在受信任设备之间以点对点加密的方式发送
+    GroupKey {
+        /// 群组密钥所属的网关 ID
+        sender_id: Uuid,
+        /// 群组密钥 ID
+        key_id: Uuid,
+        /// 群组密钥（Base64 编码）
+        key: String,
+    },
+}
+
+/// 令牌的发送方式，决定使用哪种密钥加密
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenDelivery {
+    /// 发往指定地址，不加密
+    Plain,
+    /// 发给指定网关，对方是受信任设备时使用点对点密钥加密
+    Peer(Uuid),
+    /// 广播，启用广播加密时使用群组密钥加密
+    Group,
+}
+
+impl UdpToken {
+    /// 获取令牌发送者 ID
+    ///
+    /// # 返回值
+    ///
+    /// 令牌中声明的发送者 ID
+    pub fn sender_id(&self) -> Uuid {
+        match self {
+            Self::DirectorySearch { searcher_id, .. } => *searcher_id,
+            Self::DirectorySearchResponse { responder_id, .. } => *responder_id,
+            Self::FileRequest { requester_id, .. } => *requester_id,
+            Self::FileResponse { responder_id, .. } => *responder_id,
+            Self::DirectoryList { requester_id, .. } => *requester_id,
+            Self::DirectoryListResponse { responder_id, .. } => *responder_id,
+            Self::ShareRequest { requester_id, .. } => *requester_id,
+            Self::Error { responder_id, .. } => *responder_id,
+            Self::InfoMessage { sender_id, .. } => *sender_id,
+            Self::PerformanceTest { tester_id, .. } => *tester_id,
+            Self::GroupKeyRequest { requester_id, .. } => *requester_id,
+            Self::GroupKey { sender_id, .. } => *sender_id,
+        }
+    }
+
+    /// 获取令牌的入站限速类别
+    pub fn rate_class(&self) -> MessageClass {
+        match self {
+            Self::DirectorySearch { .. } | Self::DirectoryList { .. } => MessageClass::Search,
+            Self::FileRequest { .. } | Self::ShareRequest { .. } => MessageClass::FileRequest,
+            Self::GroupKeyRequest { .. } | Self::GroupKey { .. } => MessageClass::Pairing,
+            Self::DirectorySearchResponse { .. }
+            | Self::FileResponse { .. }
+            | Self::DirectoryListResponse { .. }
+            | Self::Error { .. }
+            | Self::InfoMessage { .. }
+            | Self::PerformanceTest { .. } => MessageClass::Control,
+        }
+    }
 }
 
 /// UDP 广播事件
@@ -92,6 +215,8 @@ pub enum UdpBroadcastEvent {
         token: UdpToken,
         /// 发送者地址
         sender: SocketAddr,
+        /// 经签名信封确认的发送者，未签名令牌为 `None`
+        signer: Option<Uuid>,
     },
     /// 广播发送完成
     BroadcastSent {
@@ -129,10 +254,13 @@ pub struct DirectoryIndex {
     pub entries: Vec<DirectoryEntry>,
     /// 生成时间
     pub generated_at: chrono::DateTime<chrono::Utc>,
+    /// 生成索引时使用的挂载点过滤规则
+    #[serde(default)]
+    pub rules: MountRules,
 }
 
 impl DirectoryIndex {
-    /// 生成目录索引
+    /// 使用默认过滤规则生成目录索引
     ///
     /// # 参数
     ///
@@ -142,6 +270,20 @@ impl DirectoryIndex {
     ///
     /// 目录索引实例
     pub fn generate(path: &str) -> Result<Self> {
+        Self::generate_with_rules(path, &MountRules::default())
+    }
+
+    /// 按挂载点过滤规则生成目录索引
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 目录路径
+    /// * `rules` - 挂载点过滤规则
+    ///
+    /// # 返回值
+    ///
+    /// 目录索引实例
+    pub fn generate_with_rules(path: &str, rules: &MountRules) -> Result<Self> {
         let mut entries = Vec::new();
 
         // 创建路径验证器，只允许访问指定的根目录
@@ -152,6 +294,7 @@ impl DirectoryIndex {
             dir_path: &std::path::Path,
             entries: &mut Vec<DirectoryEntry>,
             validator: &PathValidator,
+            filter: &MountFilter,
             current_depth: usize,
         ) -> Result<()> {
             // 检查目录深度，防止无限递归
@@ -173,12 +316,12 @@ impl DirectoryIndex {
             }
 
             // 限制每个目录的最大条目数，防止内存耗尽
-            const MAX_ENTRIES_PER_DIR: usize = 10000;
+            let max_entries_per_dir = filter.rules().max_entries_per_dir;
             let mut dir_entry_count = 0;
 
             for entry in std::fs::read_dir(dir_path)? {
-                if dir_entry_count >= MAX_ENTRIES_PER_DIR {
-                    warn!("目录 {} 包含过多文件，已达到限制 {} 个", dir_path.display(), MAX_ENTRIES_PER_DIR);
+                if dir_entry_count >= max_entries_per_dir {
+                    warn!("目录 {} 包含过多文件，已达到限制 {} 个", dir_path.display(), max_entries_per_dir);
                     break;
                 }
 
@@ -192,32 +335,10 @@ impl DirectoryIndex {
                     continue;
                 }
 
-                // 检查是否为隐藏目录或文件，如果是则跳过
-                if let Some(name) = path.file_name() {
-                    let name_str = name.to_string_lossy();
-                    if name_str.starts_with('.') {
-                        if metadata.is_dir() {
-                            debug!("跳过隐藏目录: {}", path.display());
-                            continue;
-                        } else {
-                            // 对于隐藏文件，检查是否在允许列表中
-                            let allowed_hidden = [".gitignore", ".env.example", ".dockerignore"];
-                            if !allowed_hidden.iter().any(|&allowed| name_str == allowed) {
-                                debug!("跳过隐藏文件: {}", path.display());
-                                continue;
-                            }
-                        }
-                    }
-                    
-                    // 跳过系统目录
-                    if metadata.is_dir() && (
-                        name_str == "System Volume Information" ||
-                        name_str == "$RECYCLE.BIN" ||
-                        name_str == "Thumbs.db"
-                    ) {
-                        debug!("跳过系统目录: {}", path.display());
-                        continue;
-                    }
+                // 按挂载点规则跳过隐藏文件、系统目录以及被排除或忽略的路径
+                if !filter.allows_entry(&path, &metadata) {
+                    debug!("按挂载规则跳过: {}", path.display());
+                    continue;
                 }
 
                 let dir_entry = DirectoryEntry {
@@ -238,7 +359,7 @@ impl DirectoryIndex {
 
                 // 递归扫描子目录
                 if metadata.is_dir() {
-                    if let Err(e) = scan_directory(&path, entries, validator, current_depth + 1) {
+                    if let Err(e) = scan_directory(&path, entries, validator, filter, current_depth + 1) {
                         warn!("扫描子目录失败 {}: {}", path.display(), e);
                         // 继续扫描其他目录，不中断整个过程
                     }
@@ -249,7 +370,8 @@ impl DirectoryIndex {
         }
 
         let root_path = normalized_path.as_path();
-        scan_directory(root_path, &mut entries, &validator, 0)?;
+        let filter = MountFilter::new(root_path, rules);
+        scan_directory(root_path, &mut entries, &validator, &filter, 0)?;
 
         info!("目录索引生成完成，共扫描 {} 个条目", entries.len());
 
@@ -257,6 +379,7 @@ impl DirectoryIndex {
             root_path: path.to_string(),
             entries,
             generated_at: chrono::Utc::now(),
+            rules: rules.clone(),
         })
     }
 
@@ -299,6 +422,25 @@ impl DirectoryIndex {
         filtered_results.into_iter().collect()
     }
 
+    /// 检查索引是否仍可直接使用
+    ///
+    /// 索引生成时间超过 `max_age_seconds`，或根目录在生成后被修改过时视为过期。
+    ///
+    /// # 参数
+    ///
+    /// * `max_age_seconds` - 索引最长有效时间（秒）
+    pub fn is_fresh(&self, max_age_seconds: u64) -> bool {
+        let age = chrono::Utc::now() - self.generated_at;
+        if age.num_seconds() < 0 || age.num_seconds() as u64 > max_age_seconds {
+            return false;
+        }
+
+        std::fs::metadata(&self.root_path)
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified) <= self.generated_at)
+            .unwrap_or(false)
+    }
+
     /// 保存索引到文件 - 性能优化版本（使用JSON以确保兼容性）
     ///
     /// # 参数
@@ -340,8 +482,7 @@ impl DirectoryIndex {
 
 /// UDP 广播管理器
 ///
-/// 负责处理基于 UDP 的 WDIC 协议广播功能。
-/// 性能优化版本：使用 AHashMap 提升哈希表性能。
+/// 负责处理基于 UDP 的 WDIC 协议广播功能。挂载目录与索引由 [`MountManager`](crate::gateway::mount::MountManager) 管理。
 #[derive(Debug)]
 pub struct UdpBroadcastManager {
     /// 本地地址
@@ -352,10 +493,14 @@ pub struct UdpBroadcastManager {
     event_sender: mpsc::UnboundedSender<UdpBroadcastEvent>,
     /// 事件接收通道
     event_receiver: Arc<Mutex<Option<mpsc::UnboundedReceiver<UdpBroadcastEvent>>>>,
-    /// 广播地址列表 - 使用 SmallVec 减少堆分配
-    broadcast_addresses: SmallVec<[SocketAddr; 8]>,
-    /// 目录挂载点 - 使用 AHashMap 提升性能
-    mounted_directories: Arc<RwLock<AHashMap<String, DirectoryIndex>>>,
+    /// 广播地址列表 - 使用 SmallVec 减少堆分配，接口变化时由网关更新
+    broadcast_addresses: RwLock<SmallVec<[SocketAddr; 8]>>,
+    /// 消息签名与验证器
+    authenticator: Arc<StdRwLock<Arc<MessageAuthenticator>>>,
+    /// 令牌加密器，未设置时令牌以明文发送
+    cipher: Arc<StdRwLock<Option<Arc<TokenCipher>>>>,
+    /// 入站限速器
+    rate_limiter: Arc<StdRwLock<Arc<InboundRateLimiter>>>,
     /// 运行状态
     running: Arc<Mutex<bool>>,
 }
@@ -385,17 +530,176 @@ impl UdpBroadcastManager {
             udp_socket: Arc::new(udp_socket),
             event_sender,
             event_receiver: Arc::new(Mutex::new(Some(event_receiver))),
-            broadcast_addresses,
-            mounted_directories: Arc::new(RwLock::new(AHashMap::new())),
+            broadcast_addresses: RwLock::new(broadcast_addresses),
+            authenticator: Arc::new(StdRwLock::new(Arc::new(MessageAuthenticator::disabled()))),
+            cipher: Arc::new(StdRwLock::new(None)),
+            rate_limiter: Arc::new(StdRwLock::new(Arc::new(InboundRateLimiter::disabled()))),
             running: Arc::new(Mutex::new(false)),
         })
     }
 
+    /// 设置消息签名与验证器
+    ///
+    /// 设置后发出的令牌都会签名，收到的令牌按签名策略验证。
+    ///
+    /// # 参数
+    ///
+    /// * `authenticator` - 与网络管理器共享的签名与验证器
+    pub fn set_message_authenticator(&self, authenticator: Arc<MessageAuthenticator>) {
+        *self.authenticator.write().unwrap_or_else(|e| e.into_inner()) = authenticator;
+    }
+
+    /// 设置入站限速器
+    ///
+    /// # 参数
+    ///
+    /// * `rate_limiter` - 与网络管理器共享的限速器
+    pub fn set_rate_limiter(&self, rate_limiter: Arc<InboundRateLimiter>) {
+        *self.rate_limiter.write().unwrap_or_else(|e| e.into_inner()) = rate_limiter;
+    }
+
+    /// 设置令牌加密器
+    ///
+    /// 设置后发给受信任设备的令牌使用点对点密钥加密，
+    /// 加密器启用广播加密时广播令牌使用群组密钥加密。
+    ///
+    /// # 参数
+    ///
+    /// * `cipher` - 令牌加密器
+    pub fn set_token_cipher(&self, cipher: Arc<TokenCipher>) {
+        *self.cipher.write().unwrap_or_else(|e| e.into_inner()) = Some(cipher);
+    }
+
+    /// 获取令牌加密器
+    pub fn token_cipher(&self) -> Option<Arc<TokenCipher>> {
+        self.cipher.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 序列化、按需加密并签名令牌
+    fn encode_token(&self, token: &UdpToken, delivery: TokenDelivery) -> Result<Vec<u8>> {
+        let authenticator = Arc::clone(&self.authenticator.read().unwrap_or_else(|e| e.into_inner()));
+        Self::encode_token_with(&authenticator, self.token_cipher().as_deref(), token, delivery)
+    }
+
+    /// 使用给定的签名器和加密器编码令牌
+    fn encode_token_with(
+        authenticator: &MessageAuthenticator,
+        cipher: Option<&TokenCipher>,
+        token: &UdpToken,
+        delivery: TokenDelivery,
+    ) -> Result<Vec<u8>> {
+        let data =
+            serde_json::to_vec(token).map_err(|e| anyhow::anyhow!("序列化令牌失败: {}", e))?;
+        let sealed = match (cipher, delivery) {
+            (Some(cipher), TokenDelivery::Peer(peer_id)) if cipher.is_trusted(&peer_id) => {
+                Some(cipher.seal_for_peer(peer_id, &data)?)
+            }
+            (Some(cipher), TokenDelivery::Group) if cipher.encrypts_broadcasts() => {
+                Some(cipher.seal_for_group(&data)?)
+            }
+            _ => None,
+        };
+        let data = match sealed {
+            Some(sealed) => serde_json::to_vec(&sealed)
+                .map_err(|e| anyhow::anyhow!("序列化加密令牌失败: {}", e))?,
+            None => data,
+        };
+        authenticator.seal(&data)
+    }
+
+    /// 以点对点加密的方式向受信任设备发送令牌
+    ///
+    /// 与 [`Self::encode_token_with`] 不同，接收方不受信任时返回错误而不是回退到明文。
+    fn send_sealed_token(
+        socket: &UdpSocket,
+        authenticator: &MessageAuthenticator,
+        cipher: &TokenCipher,
+        token: &UdpToken,
+        peer_id: Uuid,
+        target: SocketAddr,
+    ) -> Result<()> {
+        let data =
+            serde_json::to_vec(token).map_err(|e| anyhow::anyhow!("序列化令牌失败: {}", e))?;
+        let sealed = serde_json::to_vec(&cipher.seal_for_peer(peer_id, &data)?)
+            .map_err(|e| anyhow::anyhow!("序列化加密令牌失败: {}", e))?;
+        socket
+            .send_to(&authenticator.seal(&sealed)?, target)
+            .map_err(|e| anyhow::anyhow!("发送令牌到 {target} 失败: {e}"))?;
+        Ok(())
+    }
+
+    /// 处理群组密钥的请求与分发令牌
+    ///
+    /// 群组密钥只在受信任设备之间以点对点加密的方式交换，其他方式收到的一律丢弃。
+    ///
+    /// # 返回值
+    ///
+    /// 令牌是否为群组密钥令牌，是则已在此处理完毕
+    fn handle_group_key_token(
+        socket: &UdpSocket,
+        authenticator: &MessageAuthenticator,
+        cipher: Option<&TokenCipher>,
+        token: &UdpToken,
+        pairwise: bool,
+        sender_addr: SocketAddr,
+    ) -> bool {
+        if !matches!(token, UdpToken::GroupKeyRequest { .. } | UdpToken::GroupKey { .. }) {
+            return false;
+        }
+        let Some(cipher) = cipher.filter(|_| pairwise) else {
+            warn!("丢弃来自 {sender_addr} 的未加密群组密钥令牌");
+            return true;
+        };
+
+        match token {
+            UdpToken::GroupKeyRequest { requester_id } => {
+                let (key_id, key) = cipher.export_group_key();
+                let reply = UdpToken::GroupKey {
+                    sender_id: cipher.local_id(),
+                    key_id,
+                    key,
+                };
+                match Self::send_sealed_token(
+                    socket,
+                    authenticator,
+                    cipher,
+                    &reply,
+                    *requester_id,
+                    sender_addr,
+                ) {
+                    Ok(()) => debug!("已向网关 {requester_id} 发送群组密钥"),
+                    Err(e) => warn!("向网关 {requester_id} 发送群组密钥失败: {e}"),
+                }
+            }
+            UdpToken::GroupKey {
+                sender_id,
+                key_id,
+                key,
+            } => match cipher.install_group_key(*sender_id, *key_id, key) {
+                Ok(()) => debug!("已获得网关 {sender_id} 的群组密钥 {key_id}"),
+                Err(e) => warn!("拒绝来自 {sender_addr} 的群组密钥: {e}"),
+            },
+            _ => {}
+        }
+        true
+    }
+
     /// 获取本地地址
     pub fn local_addr(&self) -> SocketAddr {
         self.local_addr
     }
 
+    /// 替换广播地址列表
+    ///
+    /// 网关按接口策略生成地址后调用，避免令牌广播到被排除的接口上。
+    ///
+    /// # 参数
+    ///
+    /// * `addresses` - 新的广播地址列表
+    pub async fn set_broadcast_addresses(&self, addresses: &[SocketAddr]) {
+        *self.broadcast_addresses.write().await = SmallVec::from_slice(addresses);
+    }
+
     /// 获取事件接收器
     pub async fn take_event_receiver(&self) -> Option<mpsc::UnboundedReceiver<UdpBroadcastEvent>> {
         self.event_receiver.lock().await.take()
@@ -605,10 +909,21 @@ impl UdpBroadcastManager {
         // 启动 UDP 监听任务
         let socket = Arc::clone(&self.udp_socket);
         let event_sender = self.event_sender.clone();
+        let authenticator = Arc::clone(&self.authenticator);
+        let cipher = Arc::clone(&self.cipher);
+        let rate_limiter = Arc::clone(&self.rate_limiter);
         let running = Arc::clone(&self.running);
 
         tokio::spawn(async move {
-            Self::udp_listener_task(socket, event_sender, running).await;
+            Self::udp_listener_task(
+                socket,
+                event_sender,
+                authenticator,
+                cipher,
+                rate_limiter,
+                running,
+            )
+            .await;
         });
 
         Ok(())
@@ -618,6 +933,9 @@ impl UdpBroadcastManager {
     async fn udp_listener_task(
         socket: Arc<UdpSocket>,
         event_sender: mpsc::UnboundedSender<UdpBroadcastEvent>,
+        authenticator: Arc<StdRwLock<Arc<MessageAuthenticator>>>,
+        cipher: Arc<StdRwLock<Option<Arc<TokenCipher>>>>,
+        rate_limiter: Arc<StdRwLock<Arc<InboundRateLimiter>>>,
         running: Arc<Mutex<bool>>,
     ) {
         let mut buffer = [0u8; 65536];
@@ -625,22 +943,110 @@ impl UdpBroadcastManager {
         while *running.lock().await {
             match socket.recv_from(&mut buffer) {
                 Ok((size, sender_addr)) => {
+                    // 解析和验签之前先按来源地址限速，被封禁的来源直接丢弃
+                    let rate_limiter =
+                        Arc::clone(&rate_limiter.read().unwrap_or_else(|e| e.into_inner()));
+                    if rate_limiter.check_datagram(sender_addr.ip()) != RateVerdict::Allowed {
+                        continue;
+                    }
+
                     debug!("收到来自 {sender_addr} 的 {size} 字节 UDP 数据");
 
+                    // 拆开签名信封，签名无效或重放的数据直接丢弃
+                    let authenticator =
+                        Arc::clone(&authenticator.read().unwrap_or_else(|e| e.into_inner()));
+                    let opened = match authenticator.open(&buffer[..size]) {
+                        Ok(opened) => opened,
+                        Err(e) => {
+                            warn!("丢弃来自 {sender_addr} 的 UDP 数据: {e}");
+                            continue;
+                        }
+                    };
+                    let signer = opened.signer;
+
+                    // 解密加密令牌，尚未获得发送者的群组密钥时向其索取
+                    let cipher = cipher.read().unwrap_or_else(|e| e.into_inner()).clone();
+                    let (payload, sealed) =
+                        match serde_json::from_slice::<SealedToken>(&opened.payload) {
+                            Ok(sealed) => {
+                                let Some(cipher) = cipher.as_deref() else {
+                                    debug!("未启用令牌加密，丢弃来自 {sender_addr} 的加密令牌");
+                                    continue;
+                                };
+                                match cipher.open(&sealed) {
+                                    Ok(Some(plaintext)) => (Cow::Owned(plaintext), Some(sealed)),
+                                    Ok(None) => {
+                                        let request = UdpToken::GroupKeyRequest {
+                                            requester_id: cipher.local_id(),
+                                        };
+                                        if let Err(e) = Self::send_sealed_token(
+                                            &socket,
+                                            &authenticator,
+                                            cipher,
+                                            &request,
+                                            sealed.sender_id,
+                                            sender_addr,
+                                        ) {
+                                            warn!("向网关 {} 索取群组密钥失败: {e}", sealed.sender_id);
+                                        }
+                                        continue;
+                                    }
+                                    Err(e) => {
+                                        warn!("丢弃来自 {sender_addr} 的加密令牌: {e}");
+                                        continue;
+                                    }
+                                }
+                            }
+                            Err(_) => (opened.payload, None),
+                        };
+
                     // 尝试解析为 UDP 令牌
-                    match serde_json::from_slice::<UdpToken>(&buffer[..size]) {
+                    match serde_json::from_slice::<UdpToken>(&payload) {
                         Ok(token) => {
+                            if let Err(e) = authenticator.authorize(signer, Some(token.sender_id())) {
+                                warn!("丢弃来自 {sender_addr} 的 UDP 令牌: {e}");
+                                continue;
+                            }
+                            if sealed
+                                .as_ref()
+                                .is_some_and(|sealed| sealed.sender_id != token.sender_id())
+                            {
+                                warn!(
+                                    "丢弃来自 {sender_addr} 的 UDP 令牌: 加密令牌的发送者与令牌内容不一致"
+                                );
+                                continue;
+                            }
+                            let verdict = rate_limiter.check_message(
+                                sender_addr.ip(),
+                                Some(token.sender_id()),
+                                token.rate_class(),
+                            );
+                            if verdict != RateVerdict::Allowed {
+                                debug!("丢弃来自 {sender_addr} 的 UDP 令牌: {verdict:?}");
+                                continue;
+                            }
+                            if Self::handle_group_key_token(
+                                &socket,
+                                &authenticator,
+                                cipher.as_deref(),
+                                &token,
+                                sealed.as_ref().is_some_and(SealedToken::is_pairwise),
+                                sender_addr,
+                            ) {
+                                continue;
+                            }
                             debug!("解析 UDP 令牌成功: {token:?}");
                             let _ = event_sender.send(UdpBroadcastEvent::TokenReceived {
                                 token,
                                 sender: sender_addr,
+                                signer,
                             });
                         }
                         Err(e) => {
                             debug!("解析 UDP 令牌失败，尝试解析为 WDIC 消息: {e}");
                             // 尝试解析为 WDIC 消息（向后兼容）
                             if let Ok(_message) =
-                                serde_json::from_slice::<WdicMessage>(&buffer[..size])
+                                serde_json::from_slice::<WdicMessage>(&payload)
                             {
                                 debug!("解析为 WDIC 消息成功，但在 UDP 广播管理器中忽略");
                             }
@@ -673,12 +1079,12 @@ impl UdpBroadcastManager {
     ///
     /// 成功发送的地址数量
     pub async fn broadcast_token(&self, token: &UdpToken) -> Result<usize> {
-        let data =
-            serde_json::to_vec(token).map_err(|e| anyhow::anyhow!("序列化令牌失败: {}", e))?;
+        let data = self.encode_token(token, TokenDelivery::Group)?;
 
         let mut success_count = 0;
+        let broadcast_addresses = self.broadcast_addresses.read().await.clone();
 
-        for &broadcast_addr in &self.broadcast_addresses {
+        for &broadcast_addr in &broadcast_addresses {
             match self.udp_socket.send_to(&data, broadcast_addr) {
                 Ok(_) => {
                     success_count += 1;
@@ -711,193 +1117,49 @@ impl UdpBroadcastManager {
     ///
     /// 发送结果
     pub async fn send_token_to(&self, token: &UdpToken, target: SocketAddr) -> Result<()> {
-        let data =
-            serde_json::to_vec(token).map_err(|e| anyhow::anyhow!("序列化令牌失败: {}", e))?;
-
-        debug!("发送令牌到 {target}");
-
-        self.udp_socket.send_to(&data, target).map_err(|e| {
-            // 隐蔽 OS 异常
-            debug!("发送令牌到 {target} 时出现 OS 异常: {e}");
-            anyhow::anyhow!("网络通信失败")
-        })?;
-
-        Ok(())
-    }
-
-    /// 挂载目录
-    ///
-    /// # 参数
-    ///
-    /// * `name` - 挂载点名称
-    /// * `path` - 目录路径
-    ///
-    /// # 返回值
-    ///
-    /// 挂载结果
-    pub async fn mount_directory(&self, name: String, path: String) -> Result<()> {
-        info!("开始挂载目录: {name} -> {path}");
-
-        // 验证挂载点名称
-        if name.is_empty() || name.len() > 255 {
-            return Err(anyhow::anyhow!("挂载点名称无效: 长度必须在 1-255 字符之间"));
-        }
-
-        // 检查挂载点名称是否包含非法字符
-        if name.contains('/') || name.contains('\\') || name.contains(':') || name.contains('<') ||
-           name.contains('>') || name.contains('|') || name.contains('?') || name.contains('*') {
-            return Err(anyhow::anyhow!("挂载点名称包含非法字符: {}", name));
-        }
-
-        // 验证路径安全性
-        let validator = PathValidator::new(vec![]);
-        let normalized_path = validator.validate_and_normalize(&path)?;
-
-        // 检查路径是否存在且为目录
-        if !normalized_path.exists() {
-            return Err(anyhow::anyhow!("目录不存在: {}", normalized_path.display()));
-        }
-
-        if !normalized_path.is_dir() {
-            return Err(anyhow::anyhow!("路径不是目录: {}", normalized_path.display()));
-        }
-
-        // 检查是否已经挂载了同名的挂载点
-        {
-            let mounted = self.mounted_directories.read().await;
-            if mounted.contains_key(&name) {
-                return Err(anyhow::anyhow!("挂载点已存在: {}", name));
-            }
-        }
-
-        // 生成目录索引
-        let index = DirectoryIndex::generate(&path)?;
-
-        // 保存索引文件到安全位置
-        let index_dir = PathBuf::from("./indices");
-        if !index_dir.exists() {
-            std::fs::create_dir_all(&index_dir)
-                .map_err(|e| anyhow::anyhow!("创建索引目录失败: {}", e))?;
-        }
-
-        let index_file = index_dir.join(format!("{name}.index"));
-        index.save_to_file(&index_file.to_string_lossy())?;
-
-        // 添加到挂载点
-        {
-            let mut mounted = self.mounted_directories.write().await;
-            mounted.insert(name.clone(), index);
-        }
-
-        // 更新安全文件读取器的允许根目录列表
-        let mounted_dirs = self.get_mounted_directories().await;
-        let mut allowed_roots = Vec::new();
-        for mount_name in mounted_dirs {
-            if let Some(index) = self.mounted_directories.read().await.get(&mount_name) {
-                allowed_roots.push(PathBuf::from(&index.root_path));
-            }
-        }
-
-        // 由于 SecureFileReader 不可变，我们在读取文件时会重新创建
-        // 这是一个权衡，确保每次文件访问都使用最新的安全配置
-
-        info!("目录挂载成功: {name}");
-        Ok(())
-    }
-
-    /// 卸载目录
-    ///
-    /// # 参数
-    ///
-    /// * `name` - 挂载点名称
-    ///
-    /// # 返回值
-    ///
-    /// 是否成功卸载
-    pub async fn unmount_directory(&self, name: &str) -> bool {
-        let mut mounted = self.mounted_directories.write().await;
-        mounted.remove(name).is_some()
+        self.send_encoded_token(token, target, TokenDelivery::Plain)
     }
 
-    /// 获取已挂载目录列表
+    /// 向指定网关发送令牌
     ///
-    /// # 返回值
-    ///
-    /// 挂载点名称列表
-    pub async fn get_mounted_directories(&self) -> Vec<String> {
-        let mounted = self.mounted_directories.read().await;
-        mounted.keys().cloned().collect()
-    }
-
-    /// 搜索文件
+    /// 接收方是受信任设备且启用了令牌加密时，令牌使用点对点密钥加密。
     ///
     /// # 参数
     ///
-    /// * `keywords` - 搜索关键词
+    /// * `token` - 要发送的令牌
+    /// * `target` - 目标地址
+    /// * `peer_id` - 接收方网关 ID
     ///
     /// # 返回值
     ///
-    /// 匹配的文件路径列表
-    pub async fn search_files(&self, keywords: &[String]) -> Vec<String> {
-        let mounted = self.mounted_directories.read().await;
-        let mut results = Vec::new();
-
-        for index in mounted.values() {
-            results.extend(index.search(keywords));
-        }
-
-        results
+    /// 发送结果
+    pub async fn send_token_to_peer(
+        &self,
+        token: &UdpToken,
+        target: SocketAddr,
+        peer_id: Uuid,
+    ) -> Result<()> {
+        self.send_encoded_token(token, target, TokenDelivery::Peer(peer_id))
     }
 
-    /// 读取文件内容
-    ///
-    /// # 参数
-    ///
-    /// * `file_path` - 文件路径
-    ///
-    /// # 返回值
-    ///
-    /// 文件内容（Base64 编码）
-    pub async fn read_file(&self, file_path: &str) -> Result<String> {
-        // 获取当前挂载的根目录列表
-        let mounted_dirs = self.get_mounted_directories().await;
-        let mut allowed_roots = Vec::new();
-        
-        {
-            let mounted = self.mounted_directories.read().await;
-            for mount_name in &mounted_dirs {
-                if let Some(index) = mounted.get(mount_name) {
-                    allowed_roots.push(PathBuf::from(&index.root_path));
-                }
-            }
-        }
-
-        // 创建安全文件读取器
-        let secure_reader = SecureFileReader::new(
-            allowed_roots,
-            10 * 1024 * 1024, // 10MB 文件大小限制
-        );
+    /// 编码并发送令牌
+    fn send_encoded_token(
+        &self,
+        token: &UdpToken,
+        target: SocketAddr,
+        delivery: TokenDelivery,
+    ) -> Result<()> {
+        let data = self.encode_token(token, delivery)?;
 
-        // 安全地读取文件
-        let data = secure_reader.read_file(file_path)
-            .map_err(|e| anyhow::anyhow!("安全文件读取失败: {}", e))?;
-
-        // 检查文件是否在挂载的目录索引中
-        let file_found_in_index = {
-            let mounted = self.mounted_directories.read().await;
-            mounted.values().any(|index| {
-                index.entries.iter().any(|entry| {
-                    entry.path == file_path && !entry.is_dir
-                })
-            })
-        };
+        debug!("发送令牌到 {target}");
 
-        if !file_found_in_index {
-            warn!("尝试访问未在索引中的文件: {file_path}");
-            return Err(anyhow::anyhow!("文件访问被拒绝: 文件不在任何挂载的目录索引中"));
-        }
+        self.udp_socket.send_to(&data, target).map_err(|e| {
+            // 隐蔽 OS 异常
+            debug!("发送令牌到 {target} 时出现 OS 异常: {e}");
+            anyhow::anyhow!("网络通信失败")
+        })?;
 
-        Ok(general_purpose::STANDARD.encode(&data))
+        Ok(())
     }
 
     /// 发送信息消息
@@ -966,12 +1228,6 @@ impl UdpBroadcastManager {
             *running = false;
         }
 
-        // 清理挂载的目录
-        {
-            let mut mounted = self.mounted_directories.write().await;
-            mounted.clear();
-        }
-
         Ok(())
     }
 
@@ -1028,6 +1284,25 @@ mod tests {
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn test_directory_index_freshness() {
+        let temp_dir = std::env::temp_dir().join(format!("wdic_fresh_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&temp_dir).expect("创建测试目录失败");
+        std::fs::write(temp_dir.join("test.txt"), "测试内容").expect("创建测试文件失败");
+
+        let mut index = DirectoryIndex::generate(temp_dir.to_str().unwrap()).unwrap();
+        assert!(index.is_fresh(3600));
+
+        // 超过最长有效时间的索引需要重新生成
+        index.generated_at = chrono::Utc::now() - chrono::Duration::hours(2);
+        assert!(!index.is_fresh(3600));
+
+        // 根目录已不存在时索引不可用
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        index.generated_at = chrono::Utc::now();
+        assert!(!index.is_fresh(3600));
+    }
+
     #[test]
     fn test_directory_index_search() {
         let index = DirectoryIndex {
@@ -1047,6 +1322,7 @@ mod tests {
                 },
             ],
             generated_at: chrono::Utc::now(),
+            rules: MountRules::default(),
         };
 
         let results = index.search(&["txt".to_string()]);
@@ -1067,35 +1343,6 @@ mod tests {
         assert!(!manager.is_running().await);
     }
 
-    #[tokio::test]
-    async fn test_udp_broadcast_manager_directory_operations() {
-        let local_addr = create_test_addr(0);
-        let manager = UdpBroadcastManager::new(local_addr).expect("创建管理器失败");
-
-        // 测试目录挂载（使用当前目录）
-        let current_dir = std::env::current_dir().unwrap();
-        let mount_result = manager
-            .mount_directory(
-                "test_mount".to_string(),
-                current_dir.to_string_lossy().to_string(),
-            )
-            .await;
-
-        // 如果目录存在且可访问，挂载应该成功
-        if mount_result.is_ok() {
-            let mounted = manager.get_mounted_directories().await;
-            assert!(mounted.contains(&"test_mount".to_string()));
-
-            // 测试搜索功能
-            let _results = manager.search_files(&["rs".to_string()]).await;
-            // 应该能找到一些 .rs 文件
-
-            // 测试卸载
-            let unmounted = manager.unmount_directory("test_mount").await;
-            assert!(unmounted);
-        }
-    }
-
     #[tokio::test]
     async fn test_udp_broadcast_manager_info_message() {
         let local_addr = create_test_addr(0);
@@ -1124,4 +1371,53 @@ mod tests {
         let latency = result.unwrap();
         assert!(latency <= 1000); // 延迟应该在合理范围内（毫秒）
     }
+
+    #[tokio::test]
+    async fn test_flooding_peer_is_rate_limited_and_banned() {
+        use crate::gateway::rate_limit::{RateBudget, RateLimitConfig};
+
+        let manager = UdpBroadcastManager::new(create_test_addr(0)).expect("创建管理器失败");
+        let rate_limiter = Arc::new(InboundRateLimiter::new(RateLimitConfig {
+            search: RateBudget::new(5, 1),
+            ban_threshold: 10,
+            ..Default::default()
+        }));
+        manager.set_rate_limiter(Arc::clone(&rate_limiter));
+        let mut events = manager.take_event_receiver().await.unwrap();
+        manager.start().await.unwrap();
+        let target = SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            manager.udp_socket.local_addr().unwrap().port(),
+        );
+
+        // 模拟的洪泛对端连续发送目录搜索
+        let flooder = UdpSocket::bind(create_test_addr(0)).unwrap();
+        let token = UdpToken::DirectorySearch {
+            searcher_id: Uuid::new_v4(),
+            keywords: smallvec::smallvec!["secret".to_string()],
+            search_id: Uuid::new_v4(),
+            search_token: None,
+        };
+        let data = serde_json::to_vec(&token).unwrap();
+        for _ in 0..30 {
+            flooder.send_to(&data, target).unwrap();
+        }
+
+        let mut received = 0;
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(500), events.recv()).await
+        {
+            if matches!(event, UdpBroadcastEvent::TokenReceived { .. }) {
+                received += 1;
+            }
+        }
+        manager.stop().await.unwrap();
+
+        // 只有突发预算内的搜索被转发，洪泛者随后被封禁
+        assert_eq!(received, 5);
+        let stats = rate_limiter.stats();
+        assert_eq!(stats.throttled, 10);
+        assert_eq!(stats.bans_issued, 2);
+        assert_eq!(stats.dropped_banned, 15);
+    }
 }
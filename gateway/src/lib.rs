@@ -51,9 +51,17 @@ pub fn run() {
             gateway::tauri_api::mount_directory,
             gateway::tauri_api::unmount_directory,
             gateway::tauri_api::get_mount_points,
+            gateway::tauri_api::revalidate_mounts,
+            gateway::tauri_api::set_mount_rules,
             gateway::tauri_api::list_directory,
             gateway::tauri_api::create_search_token,
             gateway::tauri_api::validate_search_token,
+            gateway::tauri_api::list_search_tokens,
+            gateway::tauri_api::revoke_search_token,
+            gateway::tauri_api::create_share,
+            gateway::tauri_api::list_shares,
+            gateway::tauri_api::revoke_share,
+            gateway::tauri_api::redeem_share,
             gateway::tauri_api::authorize_file,
             gateway::tauri_api::get_metadata_by_token,
             gateway::tauri_api::confirm_data_transfer,
@@ -74,6 +82,9 @@ pub fn run() {
             gateway::tauri_api::get_discovered_nodes,
             gateway::tauri_api::connect_to_node,
             gateway::tauri_api::disconnect_from_node,
+            gateway::tauri_api::get_peer_health,
+            gateway::tauri_api::get_relay_stats,
+            gateway::tauri_api::get_hole_punch_stats,
             
             // Performance API
             gateway::tauri_api::get_performance_report,
@@ -95,8 +106,25 @@ pub fn run() {
             gateway::tauri_api::remove_access_rule,
             gateway::tauri_api::get_access_rules,
             gateway::tauri_api::validate_client_access,
+            gateway::tauri_api::explain_access,
+            gateway::tauri_api::set_access_group,
+            gateway::tauri_api::remove_access_group,
+            gateway::tauri_api::get_access_groups,
             gateway::tauri_api::get_active_sessions,
             gateway::tauri_api::disconnect_session,
+            gateway::tauri_api::list_trusted_devices,
+            gateway::tauri_api::get_pairing_sessions,
+            gateway::tauri_api::pair_device,
+            gateway::tauri_api::confirm_pairing,
+            gateway::tauri_api::rename_trusted_device,
+            gateway::tauri_api::revoke_trusted_device,
+            gateway::tauri_api::revoke_device,
+            gateway::tauri_api::get_revocations,
+            gateway::tauri_api::query_audit_log,
+            gateway::tauri_api::export_audit_log,
+            gateway::tauri_api::verify_audit_log,
+            gateway::tauri_api::export_identity_bundle,
+            gateway::tauri_api::import_identity_bundle,
         ])
         .setup(|app| {
             // Initialize event emitter
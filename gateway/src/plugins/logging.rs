@@ -14,10 +14,12 @@ pub fn tauri_plugin_logging() -> TauriPlugin<tauri::Wry> {
         .build()
 }
 
+#[cfg(dev)]
 fn dev_logging_target() -> tauri_plugin_log::Target {
     tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Stdout)
 }
 
+#[cfg(not(dev))]
 fn prod_logging_target() -> tauri_plugin_log::Target {
     tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::LogDir {
         file_name: Some("logs".to_string()),
@@ -229,6 +229,20 @@ export interface MountPoint {
   mount_time: string
   file_count: number
   total_size: number
+  status: 'Available' | 'Missing'
+  rules: MountRules
+}
+
+// 挂载点过滤规则，模式语法与 .gitignore 一致
+export interface MountRules {
+  include: string[]
+  exclude: string[]
+  respect_ignore_files: boolean
+  include_hidden: boolean
+  max_file_size: number | null
+  allowed_extensions: string[]
+  denied_extensions: string[]
+  max_entries_per_dir: number
 }
 
 // 目录条目信息
@@ -263,17 +277,20 @@ export type TransferStatus = 'Pending' | 'Transferring' | 'Completed' | 'Cancell
  * @param localPath 本地路径
  * @param mountName 挂载名称
  * @param readOnly 是否只读
+ * @param rules 过滤规则，为空时使用默认规则
  * @returns 挂载点ID
  */
 export async function mountDirectory(
   localPath: string,
   mountName: string,
   readOnly: boolean,
+  rules?: MountRules,
 ): Promise<string> {
   return await invoke('mount_directory', { 
     local_path: localPath, 
     mount_name: mountName, 
-    read_only: readOnly 
+    read_only: readOnly,
+    rules: rules ?? null
   })
 }
 
@@ -294,6 +311,23 @@ export async function getMountPoints(): Promise<MountPoint[]> {
   return await invoke('get_mount_points')
 }
 
+/**
+ * 重新校验挂载点，路径已不存在的挂载点标记为 Missing
+ */
+export async function revalidateMounts(): Promise<MountPoint[]> {
+  return await invoke('revalidate_mounts')
+}
+
+/**
+ * 修改挂载点的过滤规则，目录索引按新规则重建
+ * @param mountId 挂载点ID
+ * @param rules 过滤规则
+ * @returns 更新后的挂载点
+ */
+export async function setMountRules(mountId: string, rules: MountRules): Promise<MountPoint> {
+  return await invoke('set_mount_rules', { mount_id: mountId, rules })
+}
+
 /**
  * 列出目录内容
  * @param mountId 挂载点ID
@@ -305,30 +339,41 @@ export async function listDirectory(mountId: string, path: string): Promise<Dire
 }
 
 /**
- * 创建搜索令牌
+ * 创建签名搜索令牌
  * @param mountId 挂载点ID
  * @param patterns 搜索模式列表
  * @param permissions 权限列表
  * @param ttlSeconds 生存时间（秒）
- * @returns 搜索令牌ID
+ * @param granteeId 被授权的网关ID，为空时任何已配对网关都可以出示
+ * @returns 签名后的搜索令牌
  */
 export async function createSearchToken(
   mountId: string,
   patterns: string[],
   permissions: string[],
   ttlSeconds: number,
+  granteeId?: string,
 ): Promise<string> {
-  return await invoke('create_search_token', { mountId, patterns, permissions, ttlSeconds })
+  return await invoke('create_search_token', { mountId, patterns, permissions, ttlSeconds, granteeId })
 }
 
 /**
  * 验证搜索令牌
- * @param tokenId 令牌ID
+ * @param token 签名后的搜索令牌
  * @param path 要访问的路径
  * @returns 是否授权
  */
-export async function validateSearchToken(tokenId: string, path: string): Promise<boolean> {
-  return await invoke('validate_search_token', { tokenId, path })
+export async function validateSearchToken(token: string, path: string): Promise<boolean> {
+  return await invoke('validate_search_token', { token, path })
+}
+
+/**
+ * 吊销搜索令牌
+ * @param tokenId 令牌ID
+ * @returns 令牌此前未被吊销时返回 true
+ */
+export async function revokeSearchToken(tokenId: string): Promise<boolean> {
+  return await invoke('revoke_search_token', { tokenId })
 }
 
 /**
@@ -348,11 +393,11 @@ export async function authorizeFile(
 
 /**
  * 通过搜索令牌获取元数据
- * @param tokenId 搜索令牌ID
+ * @param token 签名后的搜索令牌
  * @returns 文件元数据列表
  */
-export async function getMetadataByToken(tokenId: string): Promise<Record<string, string>[]> {
-  return await invoke('get_metadata_by_token', { tokenId })
+export async function getMetadataByToken(token: string): Promise<Record<string, string>[]> {
+  return await invoke('get_metadata_by_token', { token })
 }
 
 /**